@@ -0,0 +1,265 @@
+//! 基于本地 anvil 节点的集成测试（`cargo test --features anvil-tests`）
+//!
+//! 每个测试启动一个独立的 anvil 进程（可通过 `ANVIL_BIN` 指定可执行文件路径），用 `anvil_setCode`
+//! 在配置的合约地址上安装最小的模拟合约：模拟合约对任意调用都返回一段固定的返回数据，
+//! 足以覆盖 Keeper 使用的 `latestRoundData`、`getSingleLeverageTokenNavV2`、`checkFreezeStatus`、
+//! `bark` 和 `resetAuction`。随后驱动 `LiquidationMonitor` / `AuctionResetMonitor`，
+//! 断言交易确实发送到对应合约并被打包，且数据库状态随之更新。
+
+use std::net::TcpListener;
+use std::process::{Child, Command, Stdio};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use web3::types::{Address, BlockId, BlockNumber, U256, U64};
+use web3::Transport;
+use crate::config::{AppConfig, PriceCurve};
+use crate::database::{AuctionInfo, Database, LeverageType, SystemParams, UserPosition};
+use crate::failover::FailoverTransport;
+use crate::liquidation::LiquidationMonitor;
+use crate::nav::NavMonitor;
+use crate::notify::NoopNotifier;
+use crate::reset::AuctionResetMonitor;
+use crate::status::{unix_now, KeeperStatus};
+use crate::tx::TransactionSender;
+
+/// anvil 默认助记词的第一个账户私钥（账户预置了测试 ETH）
+const ANVIL_PRIVATE_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+const ORACLE: &str = "0x0000000000000000000000000000000000001001";
+const LIQUIDATION_MANAGER: &str = "0x0000000000000000000000000000000000001002";
+const CUSTODIAN: &str = "0x0000000000000000000000000000000000001003";
+const AUCTION_MANAGER: &str = "0x0000000000000000000000000000000000001004";
+const INTEREST_MANAGER: &str = "0x0000000000000000000000000000000000001005";
+const TOKEN: &str = "0x0000000000000000000000000000000000001006";
+
+/// 测试结束时随 Drop 终止的 anvil 进程
+struct Anvil {
+    child: Child,
+    url: String,
+}
+
+impl Anvil {
+    async fn spawn() -> Anvil {
+        let port = TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .expect("无法分配本地端口")
+            .port();
+        let binary = std::env::var("ANVIL_BIN").unwrap_or_else(|_| "anvil".to_string());
+        let child = Command::new(&binary)
+            .args(["--port", &port.to_string(), "--silent"])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap_or_else(|e| panic!("无法启动 {}（需要安装 foundry 或设置 ANVIL_BIN）: {}", binary, e));
+        let anvil = Anvil { child, url: format!("http://127.0.0.1:{}", port) };
+
+        // 等待节点开始响应 RPC
+        let web3 = anvil.web3();
+        for _ in 0..100 {
+            if web3.eth().block_number().await.is_ok() {
+                return anvil;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("anvil 在 10 秒内没有响应: {}", anvil.url);
+    }
+
+    fn web3(&self) -> web3::Web3<FailoverTransport> {
+        web3::Web3::new(FailoverTransport::new(std::slice::from_ref(&self.url), 3).expect("无法创建 RPC 传输"))
+    }
+}
+
+impl Drop for Anvil {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// 对任意调用都返回 `return_data` 的运行时字节码：
+/// `PUSH2 len PUSH1 0x0e PUSH1 0 CODECOPY PUSH2 len PUSH1 0 RETURN`，返回数据紧跟在14字节的代码之后
+fn constant_return_code(return_data: &[u8]) -> Vec<u8> {
+    let len = u16::try_from(return_data.len()).expect("返回数据过长");
+    let [hi, lo] = len.to_be_bytes();
+    let mut code = vec![0x61, hi, lo, 0x60, 0x0e, 0x60, 0x00, 0x39, 0x61, hi, lo, 0x60, 0x00, 0xf3];
+    code.extend_from_slice(return_data);
+    code
+}
+
+/// 按 ABI 编码一组 uint256 返回值
+fn words(values: &[U256]) -> Vec<u8> {
+    ethabi::encode(&values.iter().map(|value| ethabi::Token::Uint(*value)).collect::<Vec<_>>())
+}
+
+/// 用 `anvil_setCode` 在 `address` 上安装模拟合约
+async fn install_mock(web3: &web3::Web3<FailoverTransport>, address: &str, return_data: &[u8]) {
+    let code = format!("0x{}", hex_encode(&constant_return_code(return_data)));
+    web3.transport()
+        .execute("anvil_setCode", vec![serde_json::json!(address), serde_json::json!(code)])
+        .await
+        .expect("anvil_setCode 失败");
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn wad(value: u64) -> U256 {
+    U256::from(value) * U256::exp10(18)
+}
+
+/// 指向 anvil 和模拟合约的测试配置：1个确认即视为成功，价格按8位精度读取
+fn test_config(anvil: &Anvil) -> AppConfig {
+    let mut config = AppConfig {
+        rpc_url: anvil.url.clone(),
+        ws_url: None,
+        private_key: Some(ANVIL_PRIVATE_KEY.to_string()),
+        liquidation_check_interval: 1,
+        confirmation_blocks: 1,
+        confirmation_timeout_secs: 30,
+        ..AppConfig::default()
+    };
+    config.contracts.oracle = ORACLE.to_string();
+    config.contracts.liquidation_manager = LIQUIDATION_MANAGER.to_string();
+    config.contracts.custodian = CUSTODIAN.to_string();
+    config.contracts.auction_manager = AUCTION_MANAGER.to_string();
+    config.contracts.interest_manager = INTEREST_MANAGER.to_string();
+    config.contracts.token = TOKEN.to_string();
+    config.oracle.decimals = Some(8);
+    config.liquidation_safety.on_chain_nav_check = true;
+    config
+}
+
+fn test_system_params() -> SystemParams {
+    SystemParams {
+        liquidation_threshold: U256::exp10(17) * 3,
+        price_multiplier: U256::exp10(17) * 12,
+        reset_time: U256::from(3600u64),
+        price_drop_threshold: U256::exp10(17) * 5,
+        min_auction_amount: wad(1),
+        ..SystemParams::default()
+    }
+}
+
+fn address(value: &str) -> Address {
+    value.parse().expect("无效的测试地址")
+}
+
+#[tokio::test]
+async fn liquidation_monitor_barks_undercollateralized_position() {
+    let anvil = Anvil::spawn().await;
+    let web3 = anvil.web3();
+    let config = test_config(&anvil);
+
+    // 价格从铸币时的 2000 跌到 1100：2倍杠杆持仓的净值 (2 × 1100 - 2000) / 2000 = 0.1，低于清算阈值 0.3
+    let answer = U256::from(1100u64) * U256::exp10(8);
+    let updated_at = U256::from(unix_now());
+    install_mock(&web3, ORACLE, &words(&[U256::one(), answer, updated_at, updated_at, U256::one()])).await;
+    // checkFreezeStatus 返回 false；bark 的返回值不被读取
+    install_mock(&web3, LIQUIDATION_MANAGER, &words(&[U256::zero()])).await;
+    // getSingleLeverageTokenNavV2：余额、粗净值、除息净值与本地计算一致
+    let net_nav = U256::exp10(17);
+    install_mock(&web3, CUSTODIAN, &words(&[
+        wad(100), net_nav, net_nav, wad(10), wad(10), U256::zero(), wad(1100), U256::zero(),
+    ])).await;
+
+    let database = Arc::new(Database::open_temp());
+    database.set_system_params(&SystemParams {
+        annual_interest_rate: U256::zero(),
+        ..test_system_params()
+    }).unwrap();
+    let user = address("0x00000000000000000000000000000000000000aa");
+    let token_id = U256::from(7u64);
+    database.store_user_position(&UserPosition {
+        user,
+        token_id,
+        amount: wad(100),
+        timestamp: unix_now(),
+        total_interest: U256::zero(),
+        leverage: LeverageType::Aggressive,
+        mint_price: wad(2000),
+        last_applied_log: None,
+        epoch: 0,
+    }).unwrap();
+
+    let tx_sender = Arc::new(TransactionSender::new(web3.clone(), &config).unwrap());
+    let nav_monitor = NavMonitor::new(web3.clone(), database.clone(), &config).unwrap();
+    let mut monitor = LiquidationMonitor::new(
+        web3.clone(),
+        nav_monitor,
+        database.clone(),
+        tx_sender,
+        config.clone(),
+        config.contracts.liquidation_manager.clone(),
+        Arc::new(KeeperStatus::new()),
+    ).unwrap();
+
+    let shutdown = CancellationToken::new();
+    let handle = tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move { monitor.run(shutdown).await }
+    });
+
+    let mut record = None;
+    for _ in 0..300 {
+        record = database.get_liquidation_record(user, token_id).unwrap();
+        if record.is_some() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    shutdown.cancel();
+    handle.await.unwrap().unwrap();
+
+    let record = record.expect("30 秒内没有完成清算");
+    let receipt = web3.eth().transaction_receipt(record.tx_hash).await.unwrap().expect("bark 交易未被打包");
+    assert_eq!(receipt.to, Some(address(LIQUIDATION_MANAGER)));
+    assert_eq!(receipt.status, Some(U64::one()));
+    assert_eq!(record.auction_id, None);
+    assert!(database.get_liquidation_in_flight(user, token_id).unwrap().is_some());
+}
+
+#[tokio::test]
+async fn auction_reset_monitor_resets_expired_auction() {
+    let anvil = Anvil::spawn().await;
+    let web3 = anvil.web3();
+    let config = test_config(&anvil);
+    install_mock(&web3, AUCTION_MANAGER, &[]).await;
+
+    let database = Arc::new(Database::open_temp());
+    database.set_system_params(&test_system_params()).unwrap();
+    // 线性曲线 tau = 7200：开始 7300 秒后价格已降到0，需要立即重置
+    let auction = AuctionInfo {
+        auction_id: U256::from(3u64),
+        starting_price: wad(1200),
+        underlying_amount: wad(10),
+        original_owner: address("0x00000000000000000000000000000000000000aa"),
+        token_id: U256::from(7u64),
+        triggerer: address("0x00000000000000000000000000000000000000bb"),
+        reward_amount: U256::zero(),
+        start_time: unix_now() - 7300,
+        epoch: 0,
+    };
+    database.store_auction(&auction).unwrap();
+
+    let tx_sender = Arc::new(TransactionSender::new(web3.clone(), &config).unwrap());
+    let keeper = tx_sender.keeper_address().await.unwrap();
+    let monitor = AuctionResetMonitor::new(
+        database.clone(),
+        tx_sender,
+        config.contracts.auction_manager.clone(),
+        PriceCurve::Linear { tau: 7200 },
+        CancellationToken::new(),
+        Arc::new(NoopNotifier),
+    ).unwrap();
+
+    monitor.schedule_auction_reset(&auction).await.unwrap();
+
+    assert_eq!(web3.eth().transaction_count(keeper, None).await.unwrap(), U256::one());
+    let block = web3.eth().block_with_txs(BlockId::Number(BlockNumber::Latest)).await.unwrap().expect("没有最新区块");
+    let reset_tx = block.transactions.first().expect("最新区块中没有 resetAuction 交易");
+    assert_eq!(reset_tx.to, Some(address(AUCTION_MANAGER)));
+    let receipt = web3.eth().transaction_receipt(reset_tx.hash).await.unwrap().expect("resetAuction 交易未被打包");
+    assert_eq!(receipt.status, Some(U64::one()));
+}
@@ -0,0 +1,392 @@
+//! HTTP API 模块
+//!
+//! 提供只读的 HTTP JSON 接口，用于在不停止机器人的情况下查询内部状态和诊断清算决策。
+//!
+//! ## 接口列表：
+//! - `GET /health` 健康检查（同步落后过多或 RPC 不可达时返回 503，供 k8s 探针使用）
+//! - `GET /positions` 返回所有用户持仓
+//! - `GET /positions/{user}` 返回指定用户的所有持仓
+//! - `GET /positions/{user}/{token_id}/history` 返回持仓的NAV历史（需要启用 nav_history，按时间升序）
+//! - `GET /positions/at-risk?below=...` 返回最近一次定时计算中健康度低于阈值的持仓NAV（按健康度升序）
+//! - `GET /auctions` 返回所有进行中的拍卖
+//! - `GET /auctions/{auction_id}/origin` 返回产生该拍卖的清算记录
+//! - `GET /liquidations/{user}/{token_id}` 返回 Keeper 对指定持仓最近一次的清算记录
+//! - `GET /params` 返回当前系统参数
+//! - `GET /stats` 返回持仓数、拍卖数、数据库估算大小和同步进度（容量规划）
+//! - `GET /nav` 返回最近一次定时计算的所有持仓NAV
+//! - `GET /nav/{user}/{token_id}` 返回指定持仓最近一次定时计算的NAV
+//! - `GET /tvl` 返回最近一次定时计算的协议总敞口（按杠杆类型和 token_id 分组）
+//! - `GET /explain/{user}/{token_id}?price=...` 返回持仓NAV计算的完整分解
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use hyper::service::{make_service_fn, service_fn};
+use serde::Serialize;
+use web3::types::{Address, U256};
+use crate::database::Database;
+use crate::nav::{NavMonitor, TvlCache};
+use crate::failover::FailoverTransport;
+use crate::status::{unix_now, KeeperStatus};
+
+/// `/health` 查询链上最新区块的超时时间（秒）
+const HEALTH_RPC_TIMEOUT_SECS: u64 = 5;
+
+/// API 处理请求时共享的状态
+struct ApiState {
+    database: Arc<Database>,
+    nav_monitor: NavMonitor,
+    tvl_cache: Arc<TvlCache>,
+    web3: web3::Web3<FailoverTransport>,
+    status: Arc<KeeperStatus>,
+    max_sync_lag_blocks: u64,
+}
+
+/// `GET /health` 的响应
+#[derive(Debug, Serialize)]
+struct HealthReport {
+    healthy: bool,
+    /// 不健康的原因
+    problems: Vec<String>,
+    rpc_reachable: bool,
+    chain_head: Option<u64>,
+    last_synced_block: Option<u64>,
+    sync_lag_blocks: Option<u64>,
+    monitor_mode: Option<&'static str>,
+    websocket_connected: bool,
+    seconds_since_last_block: Option<u64>,
+    oracle_last_read_age_secs: Option<u64>,
+}
+
+/// `GET /stats` 的响应
+#[derive(Debug, Serialize)]
+struct StatsReport {
+    position_count: u64,
+    auction_count: u64,
+    /// RocksDB 估算的有效数据大小（字节）
+    estimated_live_data_bytes: u64,
+    last_synced_block: Option<u64>,
+}
+
+/// HTTP API 服务器
+pub struct ApiServer {
+    bind_address: SocketAddr,
+    state: Arc<ApiState>,
+}
+
+impl ApiServer {
+    pub fn new(
+        config: &crate::config::ApiConfig,
+        database: Arc<Database>,
+        nav_monitor: NavMonitor,
+        tvl_cache: Arc<TvlCache>,
+        web3: web3::Web3<FailoverTransport>,
+        status: Arc<KeeperStatus>,
+    ) -> anyhow::Result<Self> {
+        let bind_address = config.bind_address.parse::<SocketAddr>()
+            .map_err(|e| anyhow::anyhow!("无效的API监听地址 '{}': {}", config.bind_address, e))?;
+
+        tracing::info!("HTTP API 初始化 - 监听地址: {}", bind_address);
+
+        Ok(Self {
+            bind_address,
+            state: Arc::new(ApiState {
+                database,
+                nav_monitor,
+                tvl_cache,
+                web3,
+                status,
+                max_sync_lag_blocks: config.max_sync_lag_blocks,
+            }),
+        })
+    }
+
+    /// 启动 HTTP 服务
+    pub async fn run(&self) -> anyhow::Result<()> {
+        let state = self.state.clone();
+
+        let make_service = make_service_fn(move |_conn| {
+            let state = state.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let state = state.clone();
+                    async move { Ok::<_, Infallible>(handle_request(state, request).await) }
+                }))
+            }
+        });
+
+        tracing::info!("HTTP API 启动，监听 {}", self.bind_address);
+        Server::try_bind(&self.bind_address)?
+            .serve(make_service)
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// 路由分发
+async fn handle_request(state: Arc<ApiState>, request: Request<Body>) -> Response<Body> {
+    if request.method() != Method::GET {
+        return error_response(StatusCode::METHOD_NOT_ALLOWED, "只支持GET请求");
+    }
+
+    let path = request.uri().path().to_string();
+    let query = parse_query(request.uri().query());
+    let segments: Vec<&str> = path.trim_matches('/').split('/').filter(|s| !s.is_empty()).collect();
+
+    tracing::debug!("HTTP API 请求: {}", request.uri());
+
+    let result = match segments.as_slice() {
+        ["health"] => health(&state).await,
+        ["positions"] => state.database.get_all_user_positions()
+            .map_err(ApiError::internal)
+            .and_then(|positions| json_response(StatusCode::OK, &positions)),
+        ["positions", "at-risk"] => at_risk_positions(&state, &query),
+        ["positions", user] => user_positions(&state, user),
+        ["positions", user, token_id, "history"] => nav_history(&state, user, token_id),
+        ["auctions"] => state.database.get_all_auctions()
+            .map_err(ApiError::internal)
+            .and_then(|auctions| json_response(StatusCode::OK, &auctions)),
+        ["auctions", auction_id, "origin"] => auction_origin(&state, auction_id),
+        ["liquidations", user, token_id] => liquidation_record(&state, user, token_id),
+        ["params"] => state.database.get_system_params()
+            .map_err(ApiError::internal)
+            .and_then(|params| json_response(StatusCode::OK, &params)),
+        ["stats"] => stats(&state),
+        ["nav"] => state.database.get_all_nav_records()
+            .map_err(ApiError::internal)
+            .and_then(|records| json_response(StatusCode::OK, &records)),
+        ["nav", user, token_id] => nav_record(&state, user, token_id),
+        ["tvl"] => match state.tvl_cache.get() {
+            Some(report) => json_response(StatusCode::OK, &report),
+            None => Err(ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "尚未完成首次NAV定时计算")),
+        },
+        ["explain", user, token_id] => explain_position(&state, user, token_id, &query),
+        _ => Err(ApiError::new(StatusCode::NOT_FOUND, format!("未知接口: {}", path))),
+    };
+
+    match result {
+        Ok(response) => response,
+        Err(e) => error_response(e.status, &e.message),
+    }
+}
+
+/// GET /health
+async fn health(state: &ApiState) -> Result<Response<Body>, ApiError> {
+    let snapshot = state.status.snapshot();
+    let now = unix_now();
+    let mut problems = Vec::new();
+
+    let chain_head = match tokio::time::timeout(
+        std::time::Duration::from_secs(HEALTH_RPC_TIMEOUT_SECS),
+        state.web3.eth().block_number(),
+    ).await {
+        Ok(Ok(block_number)) => Some(block_number.as_u64()),
+        Ok(Err(e)) => {
+            problems.push(format!("RPC不可达: {}", e));
+            None
+        }
+        Err(_) => {
+            problems.push(format!("RPC请求超时（{}秒）", HEALTH_RPC_TIMEOUT_SECS));
+            None
+        }
+    };
+
+    let last_synced_block = state.database.get_last_synced_block().map_err(ApiError::internal)?;
+    let sync_lag_blocks = match (chain_head, last_synced_block) {
+        (Some(head), Some(synced)) => Some(head.saturating_sub(synced)),
+        _ => None,
+    };
+    if let Some(lag) = sync_lag_blocks {
+        if lag > state.max_sync_lag_blocks {
+            problems.push(format!("同步落后 {} 个区块，超过上限 {}", lag, state.max_sync_lag_blocks));
+        }
+    }
+
+    let report = HealthReport {
+        healthy: problems.is_empty(),
+        problems,
+        rpc_reachable: chain_head.is_some(),
+        chain_head,
+        last_synced_block,
+        sync_lag_blocks,
+        monitor_mode: snapshot.monitor_mode,
+        websocket_connected: snapshot.websocket_connected,
+        seconds_since_last_block: snapshot.last_block_processed_at.map(|at| now.saturating_sub(at)),
+        oracle_last_read_age_secs: snapshot.last_oracle_read_at.map(|at| now.saturating_sub(at)),
+    };
+
+    let status = if report.healthy { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    json_response(status, &report)
+}
+
+/// GET /stats
+fn stats(state: &ApiState) -> Result<Response<Body>, ApiError> {
+    let report = StatsReport {
+        position_count: state.database.get_position_count(),
+        auction_count: state.database.get_auction_count(),
+        estimated_live_data_bytes: state.database.estimate_live_data_size().map_err(ApiError::internal)?,
+        last_synced_block: state.database.get_last_synced_block().map_err(ApiError::internal)?,
+    };
+    json_response(StatusCode::OK, &report)
+}
+
+/// GET /positions/{user}
+fn user_positions(state: &ApiState, user: &str) -> Result<Response<Body>, ApiError> {
+    let user = parse_address(user)?;
+    let positions = state.database.get_user_positions(user).map_err(ApiError::internal)?;
+    json_response(StatusCode::OK, &positions)
+}
+
+/// GET /positions/at-risk?below=...
+fn at_risk_positions(state: &ApiState, query: &HashMap<String, String>) -> Result<Response<Body>, ApiError> {
+    let below = query.get("below")
+        .ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "缺少查询参数 below（price_precision_decimals 位精度的健康度）"))?;
+    let below = parse_u256(below, "below")?;
+
+    let mut records: Vec<_> = state.database.get_all_nav_records()
+        .map_err(ApiError::internal)?
+        .into_iter()
+        .filter(|record| record.health_factor.is_some_and(|health_factor| health_factor < below))
+        .collect();
+    records.sort_by_key(|record| record.health_factor);
+    json_response(StatusCode::OK, &records)
+}
+
+/// GET /positions/{user}/{token_id}/history
+fn nav_history(state: &ApiState, user: &str, token_id: &str) -> Result<Response<Body>, ApiError> {
+    let user = parse_address(user)?;
+    let token_id = parse_u256(token_id, "token_id")?;
+    let samples = state.database.get_nav_history(user, token_id).map_err(ApiError::internal)?;
+    json_response(StatusCode::OK, &samples)
+}
+
+/// GET /nav/{user}/{token_id}
+fn nav_record(state: &ApiState, user: &str, token_id: &str) -> Result<Response<Body>, ApiError> {
+    let user = parse_address(user)?;
+    let token_id = parse_u256(token_id, "token_id")?;
+
+    match state.database.get_nav_record(user, token_id).map_err(ApiError::internal)? {
+        Some(record) => json_response(StatusCode::OK, &record),
+        None => Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            format!("NAV记录不存在 - 用户: {:?}, TokenID: {}", user, token_id),
+        )),
+    }
+}
+
+/// GET /auctions/{auction_id}/origin
+fn auction_origin(state: &ApiState, auction_id: &str) -> Result<Response<Body>, ApiError> {
+    let auction_id = parse_u256(auction_id, "auction_id")?;
+
+    match state.database.get_auction_origin(auction_id).map_err(ApiError::internal)? {
+        Some(record) => json_response(StatusCode::OK, &record),
+        None => Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            format!("没有产生拍卖 {} 的清算记录", auction_id),
+        )),
+    }
+}
+
+/// GET /liquidations/{user}/{token_id}
+fn liquidation_record(state: &ApiState, user: &str, token_id: &str) -> Result<Response<Body>, ApiError> {
+    let user = parse_address(user)?;
+    let token_id = parse_u256(token_id, "token_id")?;
+
+    match state.database.get_liquidation_record(user, token_id).map_err(ApiError::internal)? {
+        Some(record) => json_response(StatusCode::OK, &record),
+        None => Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            format!("清算记录不存在 - 用户: {:?}, TokenID: {}", user, token_id),
+        )),
+    }
+}
+
+/// GET /explain/{user}/{token_id}?price=...
+fn explain_position(
+    state: &ApiState,
+    user: &str,
+    token_id: &str,
+    query: &HashMap<String, String>,
+) -> Result<Response<Body>, ApiError> {
+    let user = parse_address(user)?;
+    let token_id = parse_u256(token_id, "token_id")?;
+    let price = query.get("price")
+        .ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "缺少查询参数 price（price_precision_decimals 位精度）"))?;
+    let price = parse_u256(price, "price")?;
+
+    match state.nav_monitor.explain_position(user, token_id, price) {
+        Ok(Some(explanation)) => json_response(StatusCode::OK, &explanation),
+        Ok(None) => Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            format!("持仓不存在 - 用户: {:?}, TokenID: {}", user, token_id),
+        )),
+        Err(e) => Err(ApiError::internal(e)),
+    }
+}
+
+/// API 错误（状态码 + 错误信息）
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self { status, message: message.into() }
+    }
+
+    fn internal(e: impl std::fmt::Display) -> Self {
+        tracing::error!("HTTP API 内部错误: {}", e);
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+    }
+}
+
+/// 解析查询字符串为键值对
+fn parse_query(query: Option<&str>) -> HashMap<String, String> {
+    query.unwrap_or_default()
+        .split('&')
+        .filter_map(|pair| {
+            let mut parts = pair.splitn(2, '=');
+            let key = parts.next()?;
+            if key.is_empty() {
+                return None;
+            }
+            Some((key.to_string(), parts.next().unwrap_or_default().to_string()))
+        })
+        .collect()
+}
+
+fn parse_address(value: &str) -> Result<Address, ApiError> {
+    value.parse::<Address>()
+        .map_err(|_| ApiError::new(StatusCode::BAD_REQUEST, format!("无效的地址: {}", value)))
+}
+
+/// 解析十进制或0x前缀的十六进制数值
+fn parse_u256(value: &str, name: &str) -> Result<U256, ApiError> {
+    let parsed = match value.strip_prefix("0x") {
+        Some(hex) => U256::from_str_radix(hex, 16).ok(),
+        None => U256::from_dec_str(value).ok(),
+    };
+    parsed.ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, format!("无效的{}: {}", name, value)))
+}
+
+fn json_response<T: Serialize>(status: StatusCode, body: &T) -> Result<Response<Body>, ApiError> {
+    let body = serde_json::to_vec(body).map_err(ApiError::internal)?;
+    Ok(Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(body))
+        .unwrap_or_default())
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response<Body> {
+    let body = serde_json::json!({ "error": message }).to_string();
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(body))
+        .unwrap_or_default()
+}
@@ -0,0 +1,252 @@
+//! 持仓一致性审计模块
+//!
+//! 本地持仓由 PositionIncreased、InterestCollected 等事件增量维护，事件乱序或遗漏时会与链上状态产生偏差。
+//! 审计逐个读取 InterestManager 的 `userPositions(user, tokenId)`，与本地记录比较：
+//! - 持仓数量（lAmountInWei）
+//! - 已结算的累计利息（accruedInterest，不含上次结算后新产生的利息，与事件中的 totalInterest 口径一致）
+//!
+//! 链上状态固定读取最后同步区块（本地记录反映的区块），避免把之后尚未同步的事件误报为偏差。
+//! 偏差超过容差时报告；启用修复时用链上状态覆盖本地记录，最后应用日志位置推进到该区块末尾，
+//! 之后同步不晚于该区块的事件时跳过；链上已关闭的持仓直接删除。
+
+use web3::types::{Address, BlockId, BlockNumber, Bytes, CallRequest, U256, U64};
+use web3::ethabi::{self, Token};
+use crate::database::{Database, LogPosition, UserPosition};
+use crate::error::{KeeperError, KeeperResult};
+use crate::failover::FailoverTransport;
+use crate::rpc::rpc_retry;
+
+/// InterestManager 中记录的持仓
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainPosition {
+    pub amount: U256,
+    pub timestamp: u64,
+    pub accrued_interest: U256,
+    pub active: bool,
+}
+
+impl ChainPosition {
+    /// 链上持仓是否已关闭（数量归零或标记为不活跃）
+    pub fn is_closed(&self) -> bool {
+        self.amount.is_zero() || !self.active
+    }
+}
+
+/// 本地记录与链上状态的偏差
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionDiscrepancy {
+    pub user: Address,
+    pub token_id: U256,
+    pub local_amount: U256,
+    pub chain_amount: U256,
+    pub local_interest: U256,
+    pub chain_interest: U256,
+    pub closed_on_chain: bool,
+}
+
+/// 审计结果汇总
+#[derive(Debug, Default)]
+pub struct AuditSummary {
+    pub audited: usize,
+    pub discrepancies: usize,
+    pub corrected: usize,
+    pub failed: usize,
+}
+
+/// 比较本地持仓和链上持仓，数量或利息的偏差超过容差（wei）时返回偏差
+pub fn find_discrepancy(local: &UserPosition, chain: &ChainPosition, tolerance: U256) -> Option<PositionDiscrepancy> {
+    let differs = |a: U256, b: U256| a.max(b) - a.min(b) > tolerance;
+    let closed_on_chain = chain.is_closed();
+
+    if !closed_on_chain
+        && !differs(local.amount, chain.amount)
+        && !differs(local.total_interest, chain.accrued_interest)
+    {
+        return None;
+    }
+
+    Some(PositionDiscrepancy {
+        user: local.user,
+        token_id: local.token_id,
+        local_amount: local.amount,
+        chain_amount: chain.amount,
+        local_interest: local.total_interest,
+        chain_interest: chain.accrued_interest,
+        closed_on_chain,
+    })
+}
+
+/// 用 `block` 的链上持仓修正本地记录，最后应用日志位置推进到该区块末尾
+fn corrected_position(local: UserPosition, chain: &ChainPosition, block: u64) -> UserPosition {
+    UserPosition {
+        amount: chain.amount,
+        total_interest: chain.accrued_interest,
+        timestamp: chain.timestamp,
+        last_applied_log: Some(LogPosition { block_number: block, log_index: u64::MAX }),
+        ..local
+    }
+}
+
+/// InterestManager 持仓读取器
+pub struct PositionAuditor {
+    web3: web3::Web3<FailoverTransport>,
+    interest_manager_address: Address,
+}
+
+impl PositionAuditor {
+    pub fn new(web3: web3::Web3<FailoverTransport>, interest_manager_address: &str) -> KeeperResult<Self> {
+        let interest_manager_address = interest_manager_address.parse::<Address>()
+            .map_err(|e| KeeperError::Config(format!("无效的 InterestManager 地址 '{}': {}", interest_manager_address, e)))?;
+        Ok(Self { web3, interest_manager_address })
+    }
+
+    /// 以 `eth_call` 读取 `userPositions(user, tokenId)`
+    pub async fn read_position(&self, user: Address, token_id: U256) -> KeeperResult<ChainPosition> {
+        self.read_position_at(user, token_id, None).await
+    }
+
+    /// 读取指定区块（None 为最新区块）的 `userPositions(user, tokenId)`
+    pub async fn read_position_at(&self, user: Address, token_id: U256, block: Option<BlockId>) -> KeeperResult<ChainPosition> {
+        let contract = get_contract()?;
+        let function = contract.function("userPositions")?;
+        let request = CallRequest {
+            to: Some(self.interest_manager_address),
+            data: Some(Bytes(function.encode_input(&[Token::Address(user), Token::Uint(token_id)])?)),
+            ..Default::default()
+        };
+        let result = rpc_retry("eth_call", || self.web3.eth().call(request.clone(), block)).await?;
+        let tokens = function.decode_output(&result.0)?;
+
+        let uint_field = |index: usize, name: &str| {
+            tokens.get(index).cloned()
+                .and_then(Token::into_uint)
+                .ok_or_else(|| KeeperError::Decode(format!("无法解析 {}", name)))
+        };
+        Ok(ChainPosition {
+            amount: uint_field(0, "lAmountInWei")?,
+            timestamp: uint_field(1, "timestamp")?.low_u64(),
+            accrued_interest: uint_field(2, "accruedInterest")?,
+            active: tokens.get(3).cloned()
+                .and_then(Token::into_bool)
+                .ok_or_else(|| KeeperError::Decode("无法解析 active".to_string()))?,
+        })
+    }
+
+    /// 审计所有本地持仓；`fix` 为 true 时用链上状态修正存在偏差的持仓
+    ///
+    /// 读取最后同步区块的链上状态；还没有同步进度时读取最新区块。
+    pub async fn audit(&self, database: &Database, tolerance: U256, fix: bool) -> KeeperResult<AuditSummary> {
+        let block = match database.get_last_synced_block()? {
+            Some(block) => block,
+            None => rpc_retry("eth_blockNumber", || self.web3.eth().block_number()).await?.as_u64(),
+        };
+        let block_id = Some(BlockId::Number(BlockNumber::Number(U64::from(block))));
+        tracing::info!("按区块 {} 的链上状态审计持仓", block);
+
+        let positions = database.get_all_user_positions()?;
+        let mut summary = AuditSummary { audited: positions.len(), ..Default::default() };
+
+        for local in positions {
+            let chain = match self.read_position_at(local.user, local.token_id, block_id).await {
+                Ok(chain) => chain,
+                Err(e) => {
+                    tracing::warn!("读取链上持仓失败 - 用户: {:?}, TokenID: {}, 错误: {}", local.user, local.token_id, e);
+                    summary.failed += 1;
+                    continue;
+                }
+            };
+            let Some(discrepancy) = find_discrepancy(&local, &chain, tolerance) else {
+                continue;
+            };
+
+            summary.discrepancies += 1;
+            tracing::warn!(
+                "持仓不一致 - 用户: {:?}, TokenID: {}, 本地数量: {}, 链上数量: {}, 本地利息: {}, 链上利息: {}, 链上已关闭: {}",
+                discrepancy.user, discrepancy.token_id, discrepancy.local_amount, discrepancy.chain_amount,
+                discrepancy.local_interest, discrepancy.chain_interest, discrepancy.closed_on_chain
+            );
+
+            if !fix {
+                continue;
+            }
+            if discrepancy.closed_on_chain {
+                database.delete_user_position(local.user, local.token_id)?;
+            } else {
+                database.store_user_position(&corrected_position(local, &chain, block))?;
+            }
+            summary.corrected += 1;
+        }
+
+        Ok(summary)
+    }
+}
+
+/// InterestManager 的只读 ABI（`userPositions` 为公开 mapping 的 getter）
+fn get_contract() -> KeeperResult<ethabi::Contract> {
+    let abi = r#"[
+        {
+            "name": "userPositions",
+            "type": "function",
+            "stateMutability": "view",
+            "inputs": [
+                {"type": "address", "name": "user"},
+                {"type": "uint256", "name": "tokenId"}
+            ],
+            "outputs": [
+                {"type": "uint256", "name": "lAmountInWei"},
+                {"type": "uint256", "name": "timestamp"},
+                {"type": "uint256", "name": "accruedInterest"},
+                {"type": "bool", "name": "active"}
+            ]
+        }
+    ]"#;
+
+    Ok(ethabi::Contract::load(abi.as_bytes())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::LeverageType;
+
+    #[test]
+    fn discrepancies_beyond_tolerance_are_reported() {
+        let local = UserPosition {
+            user: Address::repeat_byte(0x11),
+            token_id: U256::from(3),
+            amount: U256::from(1_000),
+            timestamp: 1_700_000_000,
+            total_interest: U256::from(40),
+            leverage: LeverageType::Moderate,
+            mint_price: U256::exp10(18),
+            last_applied_log: None,
+            epoch: 0,
+        };
+        let chain = ChainPosition {
+            amount: U256::from(1_000),
+            timestamp: 1_700_000_000,
+            accrued_interest: U256::from(42),
+            active: true,
+        };
+
+        assert_eq!(find_discrepancy(&local, &chain, U256::from(2)), None);
+
+        let discrepancy = find_discrepancy(&local, &chain, U256::from(1)).unwrap();
+        assert_eq!(discrepancy.chain_interest, U256::from(42));
+        assert!(!discrepancy.closed_on_chain);
+
+        let amount_drift = ChainPosition { amount: U256::from(900), ..chain.clone() };
+        assert!(find_discrepancy(&local, &amount_drift, U256::from(2)).is_some());
+
+        // 链上已关闭的持仓无论容差多大都要报告
+        let closed = ChainPosition { amount: U256::zero(), active: false, ..chain };
+        assert!(find_discrepancy(&local, &closed, U256::MAX).unwrap().closed_on_chain);
+
+        // 修正后的记录推进到审计区块末尾，同步该区块及之前的事件时不会再叠加到链上状态上
+        let corrected = corrected_position(local, &amount_drift, 120);
+        assert_eq!(corrected.amount, U256::from(900));
+        assert_eq!(corrected.total_interest, U256::from(42));
+        assert_eq!(corrected.last_applied_log, Some(LogPosition { block_number: 120, log_index: u64::MAX }));
+        assert_eq!(corrected.mint_price, U256::exp10(18));
+    }
+}
@@ -0,0 +1,447 @@
+//! 命令行模块
+//!
+//! 解析命令行参数并执行一次性的运维命令。不带子命令时正常启动 Keeper。
+//!
+//! ## 子命令：
+//! - `export-raw-logs --from <block> --to <block> [--out <path>]` 导出归档的原始日志（NDJSON）
+//! - `resume-liquidations` 解除安全保护触发的清算暂停
+//! - `backfill --from <block> --to <block> [--force]` 重新同步指定区块范围的事件，修复中断期间的历史数据；
+//!   `--force` 时先把本地状态退回 `--from` 之前，不被已应用日志位置跳过
+//! - `audit [--tolerance <wei>] [--fix]` 与 InterestManager 核对本地持仓的数量和累计利息，可选用链上状态修正
+//! - `export --out <path>` 将持仓、拍卖和系统参数导出为 JSON，用于调试和迁移主机
+//! - `import --in <path>` 将 `export` 导出的 JSON 导入到新数据库
+//! - `verify --from <block>` 在临时数据库中重放事件直到最后同步区块，与生产数据库比较并报告不一致
+//! - `migrate-contracts --archive <path> --from <block>` 确认合约重新部署：归档旧部署的持仓或拍卖后从数据库移除，
+//!   同步进度退回新合约的部署区块 `<block>` 之前
+//! - `resync-auction --id <auction_id>` 以链上当前状态覆盖单个拍卖的本地记录
+//! - `resync-position --user <address> --token-id <token_id>` 以链上当前状态覆盖单个持仓的本地记录
+//!
+//! `export-raw-logs`、`export` 和 `verify` 只读取数据库，以 secondary 实例打开，Keeper 运行期间也可以执行；
+//! 其余命令会写入数据库，需要先停止 Keeper（运行中的 Keeper 持有数据库锁），执行后再启动。
+
+use std::io::Write;
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+use web3::types::{Address, U256};
+use crate::database::{Database, DatabaseExport};
+
+const USAGE: &str = "用法:
+  rust_liquidation_keeper                                                   启动 Keeper
+  rust_liquidation_keeper export-raw-logs --from <block> --to <block> [--out <path>]
+                                                                            导出归档的原始日志（NDJSON，默认输出到标准输出）
+  rust_liquidation_keeper resume-liquidations                               解除安全保护触发的清算暂停
+  rust_liquidation_keeper backfill --from <block> --to <block> [--force]    重新同步指定区块范围的事件后退出，
+                                                                            --force 时按 --from 之前的链上状态重放，不跳过已应用位置之前的事件
+  rust_liquidation_keeper audit [--tolerance <wei>] [--fix]                 核对本地持仓与链上状态，--fix 时用链上状态修正
+  rust_liquidation_keeper export --out <path>                               导出持仓、拍卖和系统参数（JSON）
+  rust_liquidation_keeper import --in <path>                                将导出的 JSON 导入到新数据库
+  rust_liquidation_keeper verify --from <block>                             重放事件（从合约部署区块开始）并与数据库比较
+  rust_liquidation_keeper migrate-contracts --archive <path> --from <block>
+                                                                            合约重新部署后归档旧部署的状态并更新记录的地址，
+                                                                            从新合约的部署区块 <block> 开始重新同步
+  rust_liquidation_keeper resync-auction --id <auction_id>                  以链上当前状态覆盖单个拍卖的本地记录
+  rust_liquidation_keeper resync-position --user <address> --token-id <token_id>
+                                                                            以链上当前状态覆盖单个持仓的本地记录";
+
+/// 不带取值的开关选项
+const FLAGS: &[&str] = &["fix", "force"];
+
+/// 命令行子命令
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// 启动 Keeper（默认）
+    Run,
+    /// 导出归档的原始日志
+    ExportRawLogs {
+        from_block: u64,
+        to_block: u64,
+        out: Option<String>,
+    },
+    /// 解除清算暂停
+    ResumeLiquidations,
+    /// 重新同步指定区块范围
+    Backfill {
+        from_block: u64,
+        to_block: u64,
+        force: bool,
+    },
+    /// 核对本地持仓与链上状态
+    Audit {
+        tolerance: U256,
+        fix: bool,
+    },
+    /// 导出数据库状态
+    Export {
+        out: String,
+    },
+    /// 导入数据库状态
+    Import {
+        input: String,
+    },
+    /// 重放事件并与数据库比较
+    Verify {
+        from_block: u64,
+    },
+    /// 迁移到重新部署的合约
+    MigrateContracts {
+        archive: String,
+        from_block: u64,
+    },
+    /// 以链上状态覆盖单个拍卖
+    ResyncAuction {
+        auction_id: U256,
+    },
+    /// 以链上状态覆盖单个持仓
+    ResyncPosition {
+        user: Address,
+        token_id: U256,
+    },
+}
+
+/// 解析命令行参数（不包含程序名）
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> anyhow::Result<Command> {
+    let mut args = args.into_iter();
+
+    let subcommand = match args.next() {
+        Some(subcommand) => subcommand,
+        None => return Ok(Command::Run),
+    };
+
+    let options = parse_options(args)?;
+
+    match subcommand.as_str() {
+        "run" => Ok(Command::Run),
+        "export-raw-logs" => {
+            let from_block = required_block(&options, "from")?;
+            let to_block = required_block(&options, "to")?;
+            if from_block > to_block {
+                return Err(anyhow::anyhow!("--from ({}) 不能大于 --to ({})", from_block, to_block));
+            }
+            Ok(Command::ExportRawLogs {
+                from_block,
+                to_block,
+                out: options.get("out").cloned(),
+            })
+        }
+        "resume-liquidations" => Ok(Command::ResumeLiquidations),
+        "backfill" => {
+            let from_block = required_block(&options, "from")?;
+            let to_block = required_block(&options, "to")?;
+            if from_block > to_block {
+                return Err(anyhow::anyhow!("--from ({}) 不能大于 --to ({})", from_block, to_block));
+            }
+            Ok(Command::Backfill { from_block, to_block, force: options.contains_key("force") })
+        }
+        "audit" => {
+            let tolerance = match options.get("tolerance") {
+                Some(value) => U256::from_dec_str(value)
+                    .map_err(|_| anyhow::anyhow!("参数 --tolerance 不是有效的 wei 数量: {}", value))?,
+                None => U256::zero(),
+            };
+            Ok(Command::Audit { tolerance, fix: options.contains_key("fix") })
+        }
+        "export" => Ok(Command::Export { out: required_path(&options, "out")? }),
+        "import" => Ok(Command::Import { input: required_path(&options, "in")? }),
+        "verify" => Ok(Command::Verify { from_block: required_block(&options, "from")? }),
+        "migrate-contracts" => Ok(Command::MigrateContracts {
+            archive: required_path(&options, "archive")?,
+            from_block: required_block(&options, "from")?,
+        }),
+        "resync-auction" => Ok(Command::ResyncAuction { auction_id: required_uint(&options, "id")? }),
+        "resync-position" => {
+            let user = required_path(&options, "user")?;
+            let user = user.parse::<Address>()
+                .map_err(|_| anyhow::anyhow!("参数 --user 不是有效的地址: {}", user))?;
+            Ok(Command::ResyncPosition { user, token_id: required_uint(&options, "token-id")? })
+        }
+        "help" | "--help" | "-h" => Err(anyhow::anyhow!("{}", USAGE)),
+        other => Err(anyhow::anyhow!("未知子命令: {}\n{}", other, USAGE)),
+    }
+}
+
+/// 解析 `--key value` 形式的选项，`FLAGS` 中的开关选项不带取值
+fn parse_options<I: Iterator<Item = String>>(mut args: I) -> anyhow::Result<std::collections::HashMap<String, String>> {
+    let mut options = std::collections::HashMap::new();
+
+    while let Some(arg) = args.next() {
+        let key = arg.strip_prefix("--")
+            .ok_or_else(|| anyhow::anyhow!("无法识别的参数: {}\n{}", arg, USAGE))?;
+        if FLAGS.contains(&key) {
+            options.insert(key.to_string(), String::new());
+            continue;
+        }
+        let value = args.next()
+            .ok_or_else(|| anyhow::anyhow!("参数 --{} 缺少取值", key))?;
+        options.insert(key.to_string(), value);
+    }
+
+    Ok(options)
+}
+
+fn required_block(options: &std::collections::HashMap<String, String>, name: &str) -> anyhow::Result<u64> {
+    let value = options.get(name)
+        .ok_or_else(|| anyhow::anyhow!("缺少参数 --{}\n{}", name, USAGE))?;
+    value.parse::<u64>()
+        .map_err(|_| anyhow::anyhow!("参数 --{} 不是有效的区块号: {}", name, value))
+}
+
+fn required_uint(options: &std::collections::HashMap<String, String>, name: &str) -> anyhow::Result<U256> {
+    let value = options.get(name)
+        .ok_or_else(|| anyhow::anyhow!("缺少参数 --{}\n{}", name, USAGE))?;
+    U256::from_dec_str(value)
+        .map_err(|_| anyhow::anyhow!("参数 --{} 不是有效的十进制整数: {}", name, value))
+}
+
+fn required_path(options: &std::collections::HashMap<String, String>, name: &str) -> anyhow::Result<String> {
+    options.get(name)
+        .cloned()
+        .ok_or_else(|| anyhow::anyhow!("缺少参数 --{}\n{}", name, USAGE))
+}
+
+/// 将归档的原始日志导出为 NDJSON（每行一条日志）
+pub fn export_raw_logs(
+    database: &Database,
+    from_block: u64,
+    to_block: u64,
+    out: Option<&str>,
+) -> anyhow::Result<()> {
+    let logs = database.get_archived_raw_logs(from_block, to_block)?;
+
+    let mut writer: Box<dyn Write> = match out {
+        Some(path) => Box::new(std::io::BufWriter::new(std::fs::File::create(path)?)),
+        None => Box::new(std::io::stdout().lock()),
+    };
+
+    for log in &logs {
+        serde_json::to_writer(&mut writer, log)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+
+    tracing::info!("导出归档日志完成: 区块 {} - {}, 共 {} 条", from_block, to_block, logs.len());
+    Ok(())
+}
+
+/// 将持仓、拍卖和系统参数从一致的数据库快照导出到 JSON 文件
+pub fn export_state(database: &Database, out: &str) -> anyhow::Result<()> {
+    let state = database.export_state()?;
+
+    let mut writer = std::io::BufWriter::new(std::fs::File::create(out)?);
+    serde_json::to_writer_pretty(&mut writer, &state)?;
+    writer.flush()?;
+
+    tracing::info!(
+        "导出数据库状态完成: {} 个持仓, {} 个拍卖, 最后同步区块 {:?}, 输出到 {}",
+        state.positions.len(), state.auctions.len(), state.last_synced_block, out
+    );
+    Ok(())
+}
+
+/// 将 `export` 导出的 JSON 文件导入到新数据库
+pub fn import_state(database: &Database, input: &str) -> anyhow::Result<()> {
+    let reader = std::io::BufReader::new(std::fs::File::open(input)?);
+    let state: DatabaseExport = serde_json::from_reader(reader)?;
+
+    database.import_state(&state)?;
+    tracing::info!(
+        "导入数据库状态完成: {} 个持仓, {} 个拍卖, 最后同步区块 {:?}",
+        state.positions.len(), state.auctions.len(), state.last_synced_block
+    );
+    Ok(())
+}
+
+/// 合约重新部署后，把旧部署的持仓或拍卖归档到 `archive` 后从数据库移除，并记录新的合约地址
+///
+/// 同步进度退回 `from_block`（新合约的部署区块）之前，Keeper 下次启动时从该区块同步新合约的事件。
+pub fn migrate_contracts(config: &crate::config::AppConfig, database: &Database, archive: &str, from_block: u64) -> anyhow::Result<()> {
+    let Some(plan) = crate::deployment::plan_migration(database, &config.contracts)? else {
+        tracing::info!("配置的合约地址与数据库记录一致，无需迁移");
+        return Ok(());
+    };
+
+    for (name, old, new) in &plan.changes {
+        tracing::info!("合约 {} 已变更: {:?} -> {:?}", name, old, new);
+    }
+
+    let mut writer = std::io::BufWriter::new(std::fs::File::create(archive)?);
+    serde_json::to_writer_pretty(&mut writer, &plan.archive)?;
+    writer.flush()?;
+
+    crate::deployment::apply_migration(database, &plan, from_block)?;
+    tracing::info!(
+        "合约迁移完成 - 纪元 {}, 归档并移除 {} 个持仓和 {} 个拍卖到 {}, 从区块 {} 开始同步新合约的事件",
+        plan.next.epoch, plan.archive.positions.len(), plan.archive.auctions.len(), archive, from_block
+    );
+    Ok(())
+}
+
+/// 运维人员确认后解除清算暂停（需要先停止 Keeper，执行后重新启动）
+pub fn resume_liquidations(database: &Database) -> anyhow::Result<()> {
+    if !database.is_liquidation_paused()? {
+        tracing::info!("清算当前未暂停，无需恢复");
+        return Ok(());
+    }
+
+    database.set_liquidation_paused(false)?;
+    tracing::info!("清算暂停已解除，Keeper 启动后恢复清算");
+    Ok(())
+}
+
+/// 重新同步 `[from_block, to_block]` 范围内的事件后退出，不启动实时监控
+///
+/// 复用事件监控器的历史同步逻辑；已应用过的日志按持仓和系统参数记录的最后应用位置跳过，
+/// 因此可以对已同步过的范围安全地重复执行。`force` 时先按 `from_block` 之前的链上状态重新同步受影响的记录，
+/// 再重放范围内的所有事件（见 `EventMonitor::backfill`）。Ctrl+C 时在当前分段完成后停止。
+pub async fn backfill(
+    config: &crate::config::AppConfig,
+    database: Arc<Database>,
+    from_block: u64,
+    to_block: u64,
+    force: bool,
+) -> anyhow::Result<()> {
+    let web3 = web3::Web3::new(crate::failover::FailoverTransport::from_config(config)?);
+    let tx_sender = Arc::new(crate::tx::TransactionSender::new(web3.clone(), config)?);
+    let status = Arc::new(crate::status::KeeperStatus::new());
+
+    let shutdown = CancellationToken::new();
+    let ctrl_c_shutdown = shutdown.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            tracing::info!("收到关闭信号，当前分段完成后停止回填");
+            ctrl_c_shutdown.cancel();
+        }
+    });
+
+    let mut event_monitor = crate::events::EventMonitor::new(
+        web3,
+        database,
+        tx_sender,
+        config.clone(),
+        shutdown,
+        status,
+        // 回填只重放历史事件，不发送运维通知
+        Arc::new(crate::notify::NoopNotifier),
+    ).await?;
+
+    event_monitor.backfill(from_block, to_block, force).await?;
+    tracing::info!("回填完成: 区块 {} - {}", from_block, to_block);
+    Ok(())
+}
+
+/// 在临时数据库中重放 `from_block` 到生产数据库最后同步区块之间的事件，与生产数据库比较并报告不一致
+///
+/// 重放只经 `events::replay_block_range` 写入临时数据库，不创建交易发送器，不会计划或发送拍卖重置。
+/// `from_block` 应为合约部署区块，否则更早的事件缺失会表现为不一致。
+/// 生产数据库由调用方以 secondary 实例只读打开，Keeper 运行期间也可以执行；临时数据库在比较结束后删除。
+pub async fn verify(
+    config: &crate::config::AppConfig,
+    database: &Database,
+    from_block: u64,
+) -> anyhow::Result<()> {
+    let live = database.export_state()?;
+    let to_block = live.last_synced_block
+        .ok_or_else(|| anyhow::anyhow!("数据库还没有同步记录，无法确定重放范围"))?;
+    if from_block > to_block {
+        return Err(anyhow::anyhow!("--from ({}) 不能大于最后同步区块 ({})", from_block, to_block));
+    }
+
+    let replay_path = std::env::temp_dir().join(format!("keeper_verify_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&replay_path);
+    let replay_path_str = replay_path.to_str()
+        .ok_or_else(|| anyhow::anyhow!("临时数据库路径无效: {:?}", replay_path))?;
+    let replay_database = Arc::new(Database::open(replay_path_str, &config.database)?);
+
+    tracing::info!("开始重放区块 {} - {} 的事件到临时数据库 {}", from_block, to_block, replay_path_str);
+    let result = replay_and_compare(config, replay_database, &live, from_block, to_block).await;
+
+    if let Err(e) = std::fs::remove_dir_all(&replay_path) {
+        tracing::warn!("删除临时数据库 {} 失败: {}", replay_path_str, e);
+    }
+    result
+}
+
+async fn replay_and_compare(
+    config: &crate::config::AppConfig,
+    replay_database: Arc<Database>,
+    live: &DatabaseExport,
+    from_block: u64,
+    to_block: u64,
+) -> anyhow::Result<()> {
+    let web3 = web3::Web3::new(crate::failover::FailoverTransport::from_config(config)?);
+
+    let shutdown = CancellationToken::new();
+    let ctrl_c_shutdown = shutdown.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            tracing::info!("收到关闭信号，当前分段完成后停止重放");
+            ctrl_c_shutdown.cancel();
+        }
+    });
+
+    crate::events::replay_block_range(&web3, &replay_database, config, from_block, to_block, &shutdown).await?;
+
+    let replayed = replay_database.export_state()?;
+    if replayed.last_synced_block != Some(to_block) {
+        return Err(anyhow::anyhow!(
+            "重放未完成（停止于区块 {:?}，目标区块 {}），不进行比较", replayed.last_synced_block, to_block
+        ));
+    }
+
+    let mismatches = crate::verify::diff_states(live, &replayed);
+    for mismatch in &mismatches {
+        tracing::warn!("{}", mismatch);
+    }
+    tracing::info!(
+        "重放校验完成 - 区块 {} - {}, 重放得到 {} 个持仓和 {} 个拍卖, 不一致 {} 处",
+        from_block, to_block, replayed.positions.len(), replayed.auctions.len(), mismatches.len()
+    );
+    Ok(())
+}
+
+/// 核对所有本地持仓与 InterestManager 中的链上状态，`fix` 为 true 时用链上状态修正偏差
+pub async fn audit(
+    config: &crate::config::AppConfig,
+    database: &Database,
+    tolerance: U256,
+    fix: bool,
+) -> anyhow::Result<()> {
+    let web3 = web3::Web3::new(crate::failover::FailoverTransport::from_config(config)?);
+    let auditor = crate::audit::PositionAuditor::new(web3, &config.contracts.interest_manager)?;
+
+    let summary = auditor.audit(database, tolerance, fix).await?;
+    tracing::info!(
+        "持仓审计完成 - 共 {} 个持仓, 不一致 {} 个, 已修正 {} 个, 读取失败 {} 个 (容差 {} wei)",
+        summary.audited, summary.discrepancies, summary.corrected, summary.failed, tolerance
+    );
+    if summary.discrepancies > summary.corrected {
+        tracing::warn!("存在未修正的不一致持仓，确认后可使用 --fix 用链上状态覆盖本地记录");
+    }
+    Ok(())
+}
+
+/// 以链上当前状态覆盖单个拍卖的本地记录，拍卖已结束时删除本地记录
+pub async fn resync_auction(config: &crate::config::AppConfig, database: &Database, auction_id: U256) -> anyhow::Result<()> {
+    let web3 = web3::Web3::new(crate::failover::FailoverTransport::from_config(config)?);
+    let resyncer = crate::resync::Resyncer::new(web3, config)?;
+
+    if resyncer.resync_auction(database, auction_id).await? == crate::resync::ResyncOutcome::Absent {
+        tracing::info!("拍卖 {} 在链上不活跃，本地也没有记录，无需同步", auction_id);
+    }
+    Ok(())
+}
+
+/// 以链上当前状态覆盖单个持仓的本地记录，持仓已关闭时删除本地记录
+pub async fn resync_position(
+    config: &crate::config::AppConfig,
+    database: &Database,
+    user: Address,
+    token_id: U256,
+) -> anyhow::Result<()> {
+    let web3 = web3::Web3::new(crate::failover::FailoverTransport::from_config(config)?);
+    let resyncer = crate::resync::Resyncer::new(web3, config)?;
+
+    if resyncer.resync_position(database, user, token_id).await? == crate::resync::ResyncOutcome::Absent {
+        tracing::info!("持仓在链上已关闭，本地也没有记录，无需同步 - 用户: {:?}, TokenID: {}", user, token_id);
+    }
+    Ok(())
+}
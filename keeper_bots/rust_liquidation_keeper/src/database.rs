@@ -1,13 +1,31 @@
 //! 数据库模块
 //!
 //! 使用 RocksDB 存储系统参数、用户持仓、NAV数据和auction信息。
+//!
+//! 各类数据分别存放在独立的列族中（positions、auctions、params、block_timestamps、meta、nav、token_index、
+//! liquidations、auction_origins、raw_logs、liquidation_cooldowns），旧版本存放在默认列族中的数据会在首次打开时自动迁移。
+//!
+//! 内存表大小、WAL 上限等 RocksDB 选项由 `[database]` 配置段设置。
+//!
+//! 持仓和拍卖的数量在打开时统计一次，之后随写入和删除维护，查询数量不需要全表扫描。
+//!
+//! 最近查询或写入的区块时间戳另外保存在容量有限的内存 LRU 中，重复查询同一批区块时不访问 RocksDB。
+//!
+//! 持仓、拍卖和系统参数可以从一致的快照导出为 JSON（`DatabaseExport`），再导入到其他主机的新数据库。
+//!
+//! 只读的运维命令用 `open_secondary` 以 secondary 实例打开数据库，不影响正在运行的 Keeper。
 
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use hashlink::LruCache;
 use rocksdb::{DB, Options};
-use web3::types::{Address, U256};
+use crate::config::DatabaseConfig;
+use crate::error::{KeeperError, KeeperResult};
+use web3::types::{Address, H256, U256};
 use serde::{Deserialize, Serialize};
 
 /// 杠杆类型枚举 - 对应 Solidity 的 LeverageType
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum LeverageType {
     Conservative,  // 保守型 (1S8L)
     Moderate,      // 温和型 (1S4L)
@@ -16,12 +34,50 @@ pub enum LeverageType {
 
 impl LeverageType {
     /// 从uint8值转换为LeverageType枚举
-    pub fn from_u8(value: u8) -> anyhow::Result<Self> {
+    pub fn from_u8(value: u8) -> KeeperResult<Self> {
         match value {
             0 => Ok(LeverageType::Conservative),
             1 => Ok(LeverageType::Moderate),
             2 => Ok(LeverageType::Aggressive),
-            _ => Err(anyhow::anyhow!("Invalid leverage type value: {}", value)),
+            _ => Err(KeeperError::Decode(format!("Invalid leverage type value: {}", value))),
+        }
+    }
+
+    /// 转换为合约使用的uint8值（`from_u8` 的逆操作），用于编码调用参数
+    pub fn to_u8(&self) -> u8 {
+        match self {
+            LeverageType::Conservative => 0,
+            LeverageType::Moderate => 1,
+            LeverageType::Aggressive => 2,
+        }
+    }
+}
+
+impl std::fmt::Display for LeverageType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            LeverageType::Conservative => "Conservative",
+            LeverageType::Moderate => "Moderate",
+            LeverageType::Aggressive => "Aggressive",
+        };
+        f.write_str(name)
+    }
+}
+
+/// 从配置或命令行解析杠杆类型：接受变体名称（不区分大小写）或合约的uint8值
+impl std::str::FromStr for LeverageType {
+    type Err = KeeperError;
+
+    fn from_str(value: &str) -> KeeperResult<Self> {
+        let value = value.trim();
+        if let Ok(number) = value.parse::<u8>() {
+            return Self::from_u8(number);
+        }
+        match value.to_ascii_lowercase().as_str() {
+            "conservative" => Ok(LeverageType::Conservative),
+            "moderate" => Ok(LeverageType::Moderate),
+            "aggressive" => Ok(LeverageType::Aggressive),
+            _ => Err(KeeperError::Decode(format!("Invalid leverage type: {}", value))),
         }
     }
 }
@@ -37,6 +93,16 @@ pub struct AuctionInfo {
     pub triggerer: Address,
     pub reward_amount: U256,
     pub start_time: u64,      // 拍卖开始时间戳
+    /// 写入时的合约部署纪元（见 `ContractDeployment`）
+    #[serde(default)]
+    pub epoch: u64,
+}
+
+/// 日志在链上的位置，用于判断事件是否已应用（先比较区块号，再比较区块内日志索引）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct LogPosition {
+    pub block_number: u64,
+    pub log_index: u64,
 }
 
 /// 用户持仓信息结构体
@@ -49,22 +115,205 @@ pub struct UserPosition {
     pub total_interest: U256,   // 累计利息
     pub leverage: LeverageType, // 杠杆类型
     pub mint_price: U256,       // 铸币价格
+    /// 最后应用到该持仓的事件日志，重复处理不晚于它的日志时跳过
+    #[serde(default)]
+    pub last_applied_log: Option<LogPosition>,
+    /// 创建时的合约部署纪元（见 `ContractDeployment`）
+    #[serde(default)]
+    pub epoch: u64,
+}
+
+/// 最近一次定时计算的持仓NAV记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NavRecord {
+    pub user: Address,
+    pub token_id: U256,
+    pub price: U256,            // 计算使用的底层资产价格（price_precision_decimals 位精度）
+    pub gross_nav: U256,        // 粗净值（price_precision_decimals 位精度）
+    pub net_nav: U256,          // 除息净值（price_precision_decimals 位精度）
+    pub position_amount: U256,  // 持仓数量
+    pub total_value: U256,      // 总价值
+    pub net_value: U256,        // 净价值
+    pub accrued_interest: U256, // 累计利息
+    /// 健康度 = net_nav / liquidation_threshold（price_precision 精度，旧版本写入的记录为 None）
+    #[serde(default)]
+    pub health_factor: Option<U256>,
+    pub calculated_at: u64,     // 计算时间戳
+}
+
+/// 持仓 NAV 历史中的一个样本
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NavSample {
+    pub timestamp: u64,    // 计算时间戳
+    pub net_nav: U256,     // 除息净值（price_precision_decimals 位精度）
+    pub gross_nav: U256,   // 粗净值（price_precision_decimals 位精度）
 }
 
+/// Keeper 发出的清算（bark）记录，关联被清算的持仓和产生的拍卖
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LiquidationRecord {
+    pub user: Address,
+    pub token_id: U256,
+    /// 交易回执中 AuctionStarted 事件的拍卖ID（交易确认但没有产生拍卖时为 None）
+    pub auction_id: Option<U256>,
+    pub tx_hash: H256,
+    pub block_number: u64,
+    pub liquidated_at: u64,     // 记录时间戳
+}
+
+/// 区块时间锚点 - 用于在缓存和RPC都不可用时估算区块时间戳
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct BlockTimeAnchor {
+    pub block_number: u64,
+    pub timestamp: u64,
+}
+
+/// 数据库中状态所对应的合约部署
+///
+/// 持仓和拍卖记录创建时的纪元；合约重新部署（配置中的地址变更）后经 `migrate-contracts` 确认，纪元加一。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContractDeployment {
+    pub epoch: u64,
+    pub custodian: Address,
+    pub liquidation_manager: Address,
+    pub auction_manager: Address,
+    pub interest_manager: Address,
+}
+
+/// 同步边界：最后同步的区块号及同步时该高度的区块哈希
+///
+/// 重启时用哈希确认该高度仍在规范链上；不一致时在此前记录的同步边界中查找分叉点。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncCheckpoint {
+    pub block_number: u64,
+    /// 旧版本只记录了区块号，或同步时未能获取哈希时为 None
+    pub block_hash: Option<H256>,
+    /// 此前带哈希的同步边界 (区块号, 哈希)，按区块号递增，最多保留 `SYNC_CHECKPOINT_HISTORY` 个
+    #[serde(default)]
+    pub history: Vec<(u64, H256)>,
+}
 
+/// 同步边界历史的保留数量
+const SYNC_CHECKPOINT_HISTORY: usize = 64;
+
+/// 正在处理（最近一个）区块中已处理的事件，重启后用于该区块的去重
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProcessedEventsRecord {
+    pub block_number: u64,
+    /// 已处理事件的 (交易索引, 日志索引)
+    pub events: Vec<(u64, u64)>,
+}
+
+/// 数据库状态导出（持仓、拍卖、系统参数），用于调试和迁移到其他主机
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseExport {
+    /// 导出时最后同步的区块号，导入后从这里继续同步
+    pub last_synced_block: Option<u64>,
+    pub params: SystemParams,
+    pub positions: Vec<UserPosition>,
+    pub auctions: Vec<AuctionInfo>,
+}
+
+// 列族划分：每类数据单独一个列族，前缀扫描只遍历对应的数据
+/// 用户持仓（键：`{user:?}_{token_id}`）
+const POSITIONS_CF: &str = "positions";
+/// 拍卖信息（键：拍卖ID）
+const AUCTIONS_CF: &str = "auctions";
+/// 系统参数
+const PARAMS_CF: &str = "params";
+/// 区块时间戳缓存（键：大端序区块号，按区块号有序）
+const BLOCK_TIMESTAMPS_CF: &str = "block_timestamps";
+/// 同步进度、暂停状态等元数据
+const META_CF: &str = "meta";
+/// 持仓最新的 NAV 记录（键：`{user:?}_{token_id}`）
+const NAV_CF: &str = "nav";
+/// Keeper 发出的清算记录（键：`{user:?}_{token_id}`）
+const LIQUIDATIONS_CF: &str = "liquidations";
+/// 拍卖到清算记录的反向关联（键：拍卖ID）
+const AUCTION_ORIGINS_CF: &str = "auction_origins";
+/// 持仓按 token_id 的二级索引（键：`{token_id}_{user:?}`，值为空）
+const TOKEN_INDEX_CF: &str = "token_index";
+/// 原始日志归档列族
+const RAW_LOGS_CF: &str = "raw_logs";
+/// 已发出 bark 但拍卖尚未出现的持仓（键：`{user:?}_{token_id}`，值：发出时间戳）
+const LIQUIDATION_COOLDOWNS_CF: &str = "liquidation_cooldowns";
+/// 持仓的 NAV 历史样本（键：`{user:?}_{token_id}_{timestamp:020}`，同一持仓的样本按时间有序）
+const NAV_HISTORY_CF: &str = "nav_history";
+
+const COLUMN_FAMILIES: [&str; 12] = [
+    POSITIONS_CF,
+    AUCTIONS_CF,
+    PARAMS_CF,
+    BLOCK_TIMESTAMPS_CF,
+    META_CF,
+    NAV_CF,
+    TOKEN_INDEX_CF,
+    LIQUIDATIONS_CF,
+    AUCTION_ORIGINS_CF,
+    RAW_LOGS_CF,
+    LIQUIDATION_COOLDOWNS_CF,
+    NAV_HISTORY_CF,
+];
+
+/// 数据布局版本（存放在 meta 列族）
+///
+/// - 1: 数据按类型拆分到各列族
+/// - 2: 新增持仓的 token_id 二级索引
+/// - 3: 系统参数按字段拆分为独立的键
+const CF_LAYOUT_VERSION_KEY: &[u8] = b"cf_layout_version";
+const CF_LAYOUT_VERSION: u32 = 3;
+
+/// 同步边界在 meta 列族中的键
+const LAST_SYNCED_BLOCK_KEY: &[u8] = b"last_synced_block";
+/// 合约部署记录在 meta 列族中的键
+const CONTRACT_DEPLOYMENT_KEY: &[u8] = b"contract_deployment";
+/// 布局版本 3 之前整体存放系统参数的键（params 列族）
+const LEGACY_SYSTEM_PARAMS_KEY: &[u8] = b"system_params";
+
+/// RocksDB 迭代器返回的一条键值对
+type KeyValue = (Box<[u8]>, Box<[u8]>);
 
 /// 数据库连接
 pub struct Database {
     db: DB,
+    /// 当前存储的持仓数量
+    position_count: AtomicU64,
+    /// 当前存储的拍卖数量
+    auction_count: AtomicU64,
+    /// 最近使用的区块时间戳（区块号 -> 时间戳），容量为0时不启用
+    block_timestamp_cache: Option<Mutex<LruCache<u64, u64>>>,
+    /// 当前合约部署纪元，新建的持仓和拍卖记录使用
+    contract_epoch: AtomicU64,
+    /// 同步进度的写入是否等待 WAL 刷写到磁盘
+    sync_checkpoint_writes: bool,
+    /// secondary 实例的工作目录；字段按声明顺序析构，放在 `db` 之后保证数据库关闭后再删除
+    secondary_dir: Option<SecondaryDir>,
+}
+
+/// secondary 实例的工作目录（每次打开新建），析构时删除
+struct SecondaryDir(std::path::PathBuf);
+
+impl Drop for SecondaryDir {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.0) {
+            tracing::warn!("删除 secondary 实例目录 {} 失败: {}", self.0.display(), e);
+        }
+    }
 }
 
 /// 系统参数结构体
+///
+/// 每个字段作为独立的键存放在 params 列族中（键为字段名，值为字段的 JSON），
+/// 单个参数的更新是一次独立的原子写入，并发更新不同参数时互不覆盖。
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct SystemParams {
     // 清算相关参数
     pub liquidation_threshold: U256,     // 强制清算阈值
     pub adjustment_threshold: U256,      // 净值调整阈值
     pub penalty: U256,                   // 清算惩罚金
+    /// 链上清算开关（LiquidationConfigInfo.enabled），关闭时 Keeper 跳过整轮清算
+    #[serde(default = "default_liquidation_enabled")]
+    pub liquidation_enabled: bool,
 
     // Auction相关参数
     pub price_multiplier: U256,
@@ -73,9 +322,20 @@ pub struct SystemParams {
     pub percentage_reward: U256,
     pub fixed_reward: U256,
     pub min_auction_amount: U256,
+    /// 拍卖断路器（circuitBreaker > 0 时触发），触发期间 Keeper 不计划也不执行拍卖重置
+    #[serde(default)]
+    pub circuit_breaker: bool,
 
     // 利息相关参数
     pub annual_interest_rate: U256,
+
+    /// 最后应用的参数类事件日志，重复处理不晚于它的日志时跳过
+    #[serde(default)]
+    pub last_applied_log: Option<LogPosition>,
+}
+
+fn default_liquidation_enabled() -> bool {
+    true
 }
 
 impl Default for SystemParams {
@@ -85,6 +345,7 @@ impl Default for SystemParams {
             liquidation_threshold: U256::from(300000000000000000u64), // 0.3 (30%)
             adjustment_threshold: U256::from(500000000000000000u64), // 0.5 (50%)
             penalty: U256::from(3000000000000000u64), // 0.03 (3%)
+            liquidation_enabled: default_liquidation_enabled(),
 
             // Auction相关参数
             price_multiplier: U256::from(1000u64),      // 示例值
@@ -93,30 +354,315 @@ impl Default for SystemParams {
             percentage_reward: U256::from(100u64),       // 1%
             fixed_reward: U256::from(1000000000000000000u64), // 1e18
             min_auction_amount: U256::from(1000000000000000000u64), // 1e18
+            circuit_breaker: false,
 
             // 利息相关参数
             annual_interest_rate: U256::from(300u64),    // 3%
+
+            last_applied_log: None,
         }
     }
 }
 
 impl Database {
-    pub async fn new() -> anyhow::Result<Self> {
+    /// 在配置的路径打开数据库，路径不可写时给出明确的错误
+    pub async fn new(db_path: &str, config: &DatabaseConfig) -> KeeperResult<Self> {
+        ensure_writable(db_path)?;
+        let database = Self::open(db_path, config)?;
+
+        if config.compact_on_startup {
+            tracing::info!("启动时手动压缩数据库...");
+            database.compact();
+            tracing::info!("数据库压缩完成");
+        }
+
+        Ok(database)
+    }
+
+    /// 在指定路径打开（或创建）数据库
+    pub fn open(db_path: &str, config: &DatabaseConfig) -> KeeperResult<Self> {
         let mut opts = Options::default();
         opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
 
         // 设置数据库打开选项
-        opts.set_max_open_files(512);
+        opts.set_max_open_files(config.max_open_files);
+        opts.set_write_buffer_size((config.write_buffer_size_mb * 1024 * 1024) as usize);
+        opts.set_max_background_jobs(config.max_background_jobs);
+        opts.set_max_total_wal_size(config.max_total_wal_size_mb * 1024 * 1024);
+
+        // 打开已有的全部列族，并补齐所需的列族
+        let mut column_families = DB::list_cf(&opts, db_path).unwrap_or_default();
+        for name in COLUMN_FAMILIES {
+            if !column_families.iter().any(|existing| existing == name) {
+                column_families.push(name.to_string());
+            }
+        }
+        let db = DB::open_cf(&opts, db_path, &column_families)?;
 
-        let db_path = "keeper_data";
-        let db = DB::open(&opts, db_path)?;
+        let database = Self::from_db(db, config);
+        database.migrate()?;
+        database.load_counters()?;
 
         tracing::info!("数据库初始化成功: {}", db_path);
 
-        Ok(Self { db })
+        Ok(database)
     }
 
-    pub async fn close(self) -> anyhow::Result<()> {
+    /// 以 secondary 实例只读打开数据库（导出、重放校验等只读命令使用）
+    ///
+    /// secondary 实例不获取 RocksDB 的 LOCK，Keeper 运行期间也可以打开，打开时读取主实例已写入的数据；
+    /// 不执行迁移，所有写入都会失败。数据布局版本落后时需要先用新版本启动一次 Keeper 完成迁移。
+    /// secondary 实例在系统临时目录下的工作目录在数据库关闭时删除。
+    pub fn open_secondary(db_path: &str, config: &DatabaseConfig) -> KeeperResult<Self> {
+        let mut opts = Options::default();
+        // secondary 实例要求保持所有文件打开
+        opts.set_max_open_files(-1);
+
+        let column_families = DB::list_cf(&opts, db_path)
+            .map_err(|e| KeeperError::Database(format!("无法读取数据库 {} 的列族: {}", db_path, e)))?;
+        let secondary_dir = SecondaryDir(std::env::temp_dir().join(format!("keeper_secondary_{}", std::process::id())));
+        std::fs::create_dir_all(&secondary_dir.0)
+            .map_err(|e| KeeperError::Database(format!("无法创建 secondary 实例目录 {}: {}", secondary_dir.0.display(), e)))?;
+        let db = DB::open_cf_as_secondary(&opts, std::path::Path::new(db_path), secondary_dir.0.as_path(), &column_families)?;
+
+        let mut database = Self::from_db(db, config);
+        database.secondary_dir = Some(secondary_dir);
+        let version = database.layout_version()?;
+        if version != CF_LAYOUT_VERSION {
+            return Err(KeeperError::Database(format!(
+                "数据库布局版本为 {}，当前版本需要 {}，请先启动一次 Keeper 完成迁移", version, CF_LAYOUT_VERSION
+            )));
+        }
+        database.load_counters()?;
+
+        tracing::info!("以只读方式打开数据库: {}", db_path);
+        Ok(database)
+    }
+
+    fn from_db(db: DB, config: &DatabaseConfig) -> Self {
+        Self {
+            db,
+            position_count: AtomicU64::new(0),
+            auction_count: AtomicU64::new(0),
+            block_timestamp_cache: (config.block_timestamp_memory_cache_size > 0)
+                .then(|| Mutex::new(LruCache::new(config.block_timestamp_memory_cache_size))),
+            contract_epoch: AtomicU64::new(0),
+            sync_checkpoint_writes: config.sync_checkpoint_writes,
+            secondary_dir: None,
+        }
+    }
+
+    /// 统计持仓和拍卖数量，载入当前合约部署纪元
+    fn load_counters(&self) -> KeeperResult<()> {
+        self.position_count.store(self.count_keys(POSITIONS_CF)?, Ordering::Relaxed);
+        self.auction_count.store(self.count_keys(AUCTIONS_CF)?, Ordering::Relaxed);
+        if let Some(deployment) = self.get_contract_deployment()? {
+            self.contract_epoch.store(deployment.epoch, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+
+    /// 对所有列族执行全范围手动压缩
+    pub fn compact(&self) {
+        for name in COLUMN_FAMILIES {
+            if let Some(cf) = self.db.cf_handle(name) {
+                self.db.compact_range_cf(cf, None::<&[u8]>, None::<&[u8]>);
+            }
+        }
+    }
+
+    fn cf(&self, name: &str) -> KeeperResult<&rocksdb::ColumnFamily> {
+        self.db.cf_handle(name)
+            .ok_or_else(|| KeeperError::Database(format!("列族 {} 不存在", name)))
+    }
+
+    /// 统计列族中的键数量（仅在打开数据库时用于初始化计数器）
+    fn count_keys(&self, cf_name: &str) -> KeeperResult<u64> {
+        let mut count = 0u64;
+        for item in self.db.iterator_cf(self.cf(cf_name)?, rocksdb::IteratorMode::Start) {
+            item?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// 当前存储的持仓数量
+    pub fn get_position_count(&self) -> u64 {
+        self.position_count.load(Ordering::Relaxed)
+    }
+
+    /// 当前存储的拍卖数量
+    pub fn get_auction_count(&self) -> u64 {
+        self.auction_count.load(Ordering::Relaxed)
+    }
+
+    /// 估算数据库的磁盘占用（字节），为各列族 `rocksdb.estimate-live-data-size` 属性之和
+    pub fn estimate_live_data_size(&self) -> KeeperResult<u64> {
+        let mut total = 0u64;
+        for name in COLUMN_FAMILIES {
+            let size = self.db.property_int_value_cf(self.cf(name)?, "rocksdb.estimate-live-data-size")?;
+            total = total.saturating_add(size.unwrap_or(0));
+        }
+        Ok(total)
+    }
+
+    /// 按数据布局版本依次执行尚未完成的迁移
+    fn migrate(&self) -> KeeperResult<()> {
+        let version = self.layout_version()?;
+        if version >= CF_LAYOUT_VERSION {
+            return Ok(());
+        }
+
+        if version < 1 {
+            self.migrate_to_column_families()?;
+        }
+        if version < 2 {
+            self.rebuild_token_index()?;
+        }
+        if version < 3 {
+            self.split_system_params()?;
+        }
+
+        self.db.put_cf(self.cf(META_CF)?, CF_LAYOUT_VERSION_KEY, serde_json::to_vec(&CF_LAYOUT_VERSION)?)?;
+        Ok(())
+    }
+
+    /// 已完成迁移的数据布局版本（新建的数据库为 0）
+    fn layout_version(&self) -> KeeperResult<u32> {
+        match self.db.get_cf(self.cf(META_CF)?, CF_LAYOUT_VERSION_KEY)? {
+            Some(data) => Ok(serde_json::from_slice(&data)?),
+            None => Ok(0),
+        }
+    }
+
+    /// 一次性迁移：将旧版本存放在默认列族中的数据移动到各自的列族
+    ///
+    /// 持仓键在旧版本中使用地址的缩写形式，迁移时根据记录内容重新生成完整地址的键。
+    fn migrate_to_column_families(&self) -> KeeperResult<()> {
+        let mut batch = rocksdb::WriteBatch::default();
+        let mut migrated = 0usize;
+
+        for item in self.db.iterator(rocksdb::IteratorMode::Start) {
+            let (key, value) = item?;
+            let key_str = match std::str::from_utf8(&key) {
+                Ok(key_str) => key_str,
+                Err(_) => continue,
+            };
+
+            let (cf_name, new_key) = if key_str.starts_with("position_") {
+                let position: UserPosition = serde_json::from_slice(&value)?;
+                (POSITIONS_CF, Self::position_key(position.user, position.token_id).into_bytes())
+            } else if let Some(auction_id) = key_str.strip_prefix("auction_") {
+                (AUCTIONS_CF, auction_id.as_bytes().to_vec())
+            } else if let Some(block_str) = key_str.strip_prefix("block_timestamp_") {
+                match block_str.parse::<u64>() {
+                    Ok(block_number) => (BLOCK_TIMESTAMPS_CF, block_number.to_be_bytes().to_vec()),
+                    Err(_) => continue,
+                }
+            } else if key_str.starts_with("nav_") {
+                let record: NavRecord = serde_json::from_slice(&value)?;
+                (NAV_CF, Self::position_key(record.user, record.token_id).into_bytes())
+            } else if key_str == "system_params" {
+                (PARAMS_CF, key.to_vec())
+            } else if matches!(key_str, "last_synced_block" | "liquidation_paused" | "block_time_anchor") {
+                (META_CF, key.to_vec())
+            } else {
+                continue;
+            };
+
+            batch.put_cf(self.cf(cf_name)?, new_key, &value);
+            batch.delete(&key);
+            migrated += 1;
+        }
+
+        self.db.write(batch)?;
+
+        if migrated > 0 {
+            tracing::info!("数据库列族迁移完成: 迁移了 {} 条记录", migrated);
+        }
+        Ok(())
+    }
+
+    /// 根据现有持仓重建 token_id 二级索引
+    fn rebuild_token_index(&self) -> KeeperResult<()> {
+        let index_cf = self.cf(TOKEN_INDEX_CF)?;
+        let mut batch = rocksdb::WriteBatch::default();
+
+        for item in self.db.iterator_cf(self.cf(POSITIONS_CF)?, rocksdb::IteratorMode::Start) {
+            let (_, value) = item?;
+            let position: UserPosition = serde_json::from_slice(&value)?;
+            batch.put_cf(index_cf, Self::token_index_key(position.token_id, position.user), b"");
+        }
+
+        let indexed = batch.len();
+        self.db.write(batch)?;
+        if indexed > 0 {
+            tracing::info!("token_id 索引重建完成: {} 个持仓", indexed);
+        }
+        Ok(())
+    }
+
+    /// 将整体存放的系统参数拆分为每个字段一个键
+    fn split_system_params(&self) -> KeeperResult<()> {
+        let params_cf = self.cf(PARAMS_CF)?;
+        let Some(data) = self.db.get_cf(params_cf, LEGACY_SYSTEM_PARAMS_KEY)? else {
+            return Ok(());
+        };
+        let params: SystemParams = serde_json::from_slice(&data)?;
+
+        let mut batch = rocksdb::WriteBatch::default();
+        for (name, value) in system_param_entries(&params)? {
+            batch.put_cf(params_cf, name, value);
+        }
+        batch.delete_cf(params_cf, LEGACY_SYSTEM_PARAMS_KEY);
+        self.db.write(batch)?;
+        tracing::info!("系统参数已拆分为按字段存储");
+        Ok(())
+    }
+
+    /// 持仓和 NAV 记录的键
+    fn position_key(user: Address, token_id: U256) -> String {
+        format!("{:?}_{}", user, token_id)
+    }
+
+    /// token_id 索引的键（`_` 分隔保证 token 1 的前缀不会匹配 token 12）
+    fn token_index_key(token_id: U256, user: Address) -> String {
+        format!("{}_{:?}", token_id, user)
+    }
+
+    /// 在系统临时目录下打开一个独立的数据库（仅用于测试）
+    #[cfg(test)]
+    pub fn open_temp() -> Self {
+        Self::open(&Self::temp_path(), &DatabaseConfig::default()).expect("无法打开测试数据库")
+    }
+
+    /// 系统临时目录下一个尚不存在的数据库路径（仅用于测试）
+    #[cfg(test)]
+    pub fn temp_path() -> String {
+        static COUNTER: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "keeper_test_{}_{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst)
+        ));
+        let _ = std::fs::remove_dir_all(&path);
+        path.to_str().expect("临时目录路径无效").to_string()
+    }
+
+    /// 将内存表和 WAL 刷写到磁盘
+    ///
+    /// 正常关闭时在所有监控任务停止后调用，确保同步进度和最近写入的状态在进程退出后仍然存在。
+    pub fn flush(&self) -> KeeperResult<()> {
+        self.db.flush_wal(true)?;
+        for name in COLUMN_FAMILIES {
+            self.db.flush_cf(self.cf(name)?)?;
+        }
+        tracing::info!("数据库已刷写到磁盘");
+        Ok(())
+    }
+
+    pub async fn close(self) -> KeeperResult<()> {
         // RocksDB 会自动处理关闭，这里主要是为了API一致性
         drop(self.db);
         tracing::info!("数据库连接关闭");
@@ -124,140 +670,196 @@ impl Database {
     }
 
     /// 获取系统参数
-    pub fn get_system_params(&self) -> anyhow::Result<SystemParams> {
-        let key = b"system_params";
+    ///
+    /// 从同一个快照读取所有字段组装，并发更新时也不会读到一半新一半旧的参数；没有存储的字段使用默认值。
+    pub fn get_system_params(&self) -> KeeperResult<SystemParams> {
+        let snapshot = self.db.snapshot();
+        assemble_system_params(snapshot.iterator_cf(self.cf(PARAMS_CF)?, rocksdb::IteratorMode::Start))
+    }
 
-        match self.db.get(key)? {
-            Some(data) => {
-                let params: SystemParams = serde_json::from_slice(&data)?;
-                Ok(params)
-            }
-            None => {
-                // 返回默认值，并存储到数据库
-                let default_params = SystemParams::default();
-                self.set_system_params(&default_params)?;
-                Ok(default_params)
-            }
+    /// 设置系统参数（所有字段在同一个批次中写入）
+    pub fn set_system_params(&self, params: &SystemParams) -> KeeperResult<()> {
+        let params_cf = self.cf(PARAMS_CF)?;
+        let mut batch = rocksdb::WriteBatch::default();
+        for (name, value) in system_param_entries(params)? {
+            batch.put_cf(params_cf, name, value);
         }
+        self.db.write(batch)?;
+        tracing::debug!("系统参数已更新: {:?}", params);
+        Ok(())
+    }
+
+    /// 只写入单个系统参数，`name` 为 `SystemParams` 的字段名
+    fn set_system_param(&self, name: &str, value: &impl Serialize) -> KeeperResult<()> {
+        let data = serde_json::to_vec(value)?;
+        self.db.put_cf(self.cf(PARAMS_CF)?, name, &data)?;
+        tracing::debug!("系统参数 {} 已更新: {}", name, String::from_utf8_lossy(&data));
+        Ok(())
+    }
+
+    /// 记录最后应用的参数类事件日志
+    pub fn mark_params_log_applied(&self, position: LogPosition) -> KeeperResult<()> {
+        self.set_system_param("last_applied_log", &Some(position))
     }
 
-    /// 设置系统参数
-    pub fn set_system_params(&self, params: &SystemParams) -> anyhow::Result<()> {
-        let key = b"system_params";
-        let data = serde_json::to_vec(params)?;
-        self.db.put(key, data)?;
-        tracing::info!("系统参数已更新: {:?}", params);
+    /// 清除参数类事件的已应用日志记录（合约迁移后从新合约的部署区块重新应用参数事件）
+    pub fn clear_params_applied_log(&self) -> KeeperResult<()> {
+        self.set_system_param("last_applied_log", &None::<LogPosition>)
+    }
+
+    /// 链重组回滚：清除晚于分叉点的参数类事件已应用记录，使规范链上的参数事件可以重新应用
+    ///
+    /// 参数事件写入的是绝对值，重放规范链事件即可覆盖孤块写入的值；持仓由事件监控器按分叉点的链上状态重新同步。
+    pub fn rewind_params_applied_log(&self, fork_point: u64) -> KeeperResult<()> {
+        if self.get_system_params()?.last_applied_log.is_some_and(|log| log.block_number > fork_point) {
+            self.clear_params_applied_log()?;
+        }
         Ok(())
     }
 
     /// 更新单个系统参数
-    pub fn update_adjustment_threshold(&self, threshold: U256) -> anyhow::Result<()> {
-        let mut params = self.get_system_params()?;
-        params.adjustment_threshold = threshold;
-        self.set_system_params(&params)
+    pub fn update_adjustment_threshold(&self, threshold: U256) -> KeeperResult<()> {
+        self.set_system_param("adjustment_threshold", &threshold)
     }
 
-    pub fn update_liquidation_threshold(&self, threshold: U256) -> anyhow::Result<()> {
-        let mut params = self.get_system_params()?;
-        params.liquidation_threshold = threshold;
-        self.set_system_params(&params)
+    pub fn update_liquidation_threshold(&self, threshold: U256) -> KeeperResult<()> {
+        self.set_system_param("liquidation_threshold", &threshold)
     }
 
-    pub fn update_penalty(&self, penalty: U256) -> anyhow::Result<()> {
-        let mut params = self.get_system_params()?;
-        params.penalty = penalty;
-        self.set_system_params(&params)
+    pub fn update_penalty(&self, penalty: U256) -> KeeperResult<()> {
+        self.set_system_param("penalty", &penalty)
+    }
+
+    pub fn update_liquidation_enabled(&self, enabled: bool) -> KeeperResult<()> {
+        self.set_system_param("liquidation_enabled", &enabled)
     }
 
     // Auction 参数更新方法
-    pub fn update_price_multiplier(&self, multiplier: U256) -> anyhow::Result<()> {
-        let mut params = self.get_system_params()?;
-        params.price_multiplier = multiplier;
-        self.set_system_params(&params)
+    pub fn update_price_multiplier(&self, multiplier: U256) -> KeeperResult<()> {
+        self.set_system_param("price_multiplier", &multiplier)
+    }
+
+    pub fn update_reset_time(&self, reset_time: U256) -> KeeperResult<()> {
+        self.set_system_param("reset_time", &reset_time)
     }
 
-    pub fn update_reset_time(&self, reset_time: U256) -> anyhow::Result<()> {
-        let mut params = self.get_system_params()?;
-        params.reset_time = reset_time;
-        self.set_system_params(&params)
+    pub fn update_min_auction_amount(&self, amount: U256) -> KeeperResult<()> {
+        self.set_system_param("min_auction_amount", &amount)
     }
 
-    pub fn update_min_auction_amount(&self, amount: U256) -> anyhow::Result<()> {
-        let mut params = self.get_system_params()?;
-        params.min_auction_amount = amount;
-        self.set_system_params(&params)
+    pub fn update_price_drop_threshold(&self, threshold: U256) -> KeeperResult<()> {
+        self.set_system_param("price_drop_threshold", &threshold)
     }
 
-    pub fn update_price_drop_threshold(&self, threshold: U256) -> anyhow::Result<()> {
-        let mut params = self.get_system_params()?;
-        params.price_drop_threshold = threshold;
-        self.set_system_params(&params)
+    pub fn update_percentage_reward(&self, reward: U256) -> KeeperResult<()> {
+        self.set_system_param("percentage_reward", &reward)
     }
 
-    pub fn update_percentage_reward(&self, reward: U256) -> anyhow::Result<()> {
-        let mut params = self.get_system_params()?;
-        params.percentage_reward = reward;
-        self.set_system_params(&params)
+    pub fn update_circuit_breaker(&self, tripped: bool) -> KeeperResult<()> {
+        self.set_system_param("circuit_breaker", &tripped)
     }
 
-    pub fn update_fixed_reward(&self, reward: U256) -> anyhow::Result<()> {
-        let mut params = self.get_system_params()?;
-        params.fixed_reward = reward;
-        self.set_system_params(&params)
+    pub fn update_fixed_reward(&self, reward: U256) -> KeeperResult<()> {
+        self.set_system_param("fixed_reward", &reward)
     }
 
-    pub fn update_annual_interest_rate(&self, rate: U256) -> anyhow::Result<()> {
-        let mut params = self.get_system_params()?;
-        params.annual_interest_rate = rate;
-        self.set_system_params(&params)
+    pub fn update_annual_interest_rate(&self, rate: U256) -> KeeperResult<()> {
+        self.set_system_param("annual_interest_rate", &rate)
     }
 
     /// 获取最后同步的区块号
-    pub fn get_last_synced_block(&self) -> anyhow::Result<Option<u64>> {
-        let key = b"last_synced_block";
+    pub fn get_last_synced_block(&self) -> KeeperResult<Option<u64>> {
+        Ok(self.get_sync_checkpoint()?.map(|checkpoint| checkpoint.block_number))
+    }
 
-        match self.db.get(key)? {
-            Some(data) => {
-                let block_number: u64 = serde_json::from_slice(&data)?;
-                Ok(Some(block_number))
+    /// 获取同步边界（区块号和哈希）
+    pub fn get_sync_checkpoint(&self) -> KeeperResult<Option<SyncCheckpoint>> {
+        match self.db.get_cf(self.cf(META_CF)?, LAST_SYNCED_BLOCK_KEY)? {
+            Some(data) => Ok(Some(decode_sync_checkpoint(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// 设置最后同步的区块号及其哈希
+    ///
+    /// 上一个带哈希的同步边界早于新区块时移入历史；同步进度回退时（链重组回滚、回填）丢弃新区块之后的历史。
+    pub fn set_last_synced_block(&self, block_number: u64, block_hash: Option<H256>) -> KeeperResult<()> {
+        let mut history = Vec::new();
+        if let Some(previous) = self.get_sync_checkpoint()? {
+            history = previous.history;
+            if let Some(hash) = previous.block_hash {
+                history.push((previous.block_number, hash));
             }
+            history.retain(|(number, _)| *number < block_number);
+            if history.len() > SYNC_CHECKPOINT_HISTORY {
+                history.drain(..history.len() - SYNC_CHECKPOINT_HISTORY);
+            }
+        }
+
+        self.set_sync_checkpoint(&SyncCheckpoint { block_number, block_hash, history })?;
+        tracing::debug!("最后同步区块号已更新: {}, 哈希: {:?}", block_number, block_hash);
+        Ok(())
+    }
+
+    /// 原样写入同步边界（回填结束后恢复原来的进度时使用）
+    pub fn set_sync_checkpoint(&self, checkpoint: &SyncCheckpoint) -> KeeperResult<()> {
+        let data = serde_json::to_vec(checkpoint)?;
+        let mut write_options = rocksdb::WriteOptions::new();
+        write_options.set_sync(self.sync_checkpoint_writes);
+        self.db.put_cf_opt(self.cf(META_CF)?, LAST_SYNCED_BLOCK_KEY, data, &write_options)?;
+        crate::metrics::metrics().last_synced_block.set(checkpoint.block_number as i64);
+        Ok(())
+    }
+
+    /// 获取记录的合约部署
+    pub fn get_contract_deployment(&self) -> KeeperResult<Option<ContractDeployment>> {
+        match self.db.get_cf(self.cf(META_CF)?, CONTRACT_DEPLOYMENT_KEY)? {
+            Some(data) => Ok(Some(serde_json::from_slice(&data)?)),
             None => Ok(None),
         }
     }
 
-    /// 设置最后同步的区块号
-    pub fn set_last_synced_block(&self, block_number: u64) -> anyhow::Result<()> {
-        let key = b"last_synced_block";
-        let data = serde_json::to_vec(&block_number)?;
-        self.db.put(key, data)?;
-        tracing::debug!("最后同步区块号已更新: {}", block_number);
+    /// 记录合约部署，之后新建的持仓和拍卖使用其纪元
+    pub fn set_contract_deployment(&self, deployment: &ContractDeployment) -> KeeperResult<()> {
+        let data = serde_json::to_vec(deployment)?;
+        self.db.put_cf(self.cf(META_CF)?, CONTRACT_DEPLOYMENT_KEY, data)?;
+        self.contract_epoch.store(deployment.epoch, Ordering::Relaxed);
+        tracing::info!("合约部署记录已更新: {:?}", deployment);
         Ok(())
     }
 
-    /// 获取区块时间戳（从缓存中获取）
-    pub fn get_block_timestamp(&self, block_number: u64) -> anyhow::Result<Option<u64>> {
-        let key = format!("block_timestamp_{}", block_number);
+    /// 当前合约部署纪元
+    pub fn contract_epoch(&self) -> u64 {
+        self.contract_epoch.load(Ordering::Relaxed)
+    }
+
+    /// 获取区块时间戳（先查内存 LRU，再查 RocksDB 中的缓存）
+    pub fn get_block_timestamp(&self, block_number: u64) -> KeeperResult<Option<u64>> {
+        if let Some(timestamp) = self.with_block_timestamp_cache(|cache| cache.get(&block_number).copied()).flatten() {
+            return Ok(Some(timestamp));
+        }
 
-        match self.db.get(key.as_bytes())? {
+        match self.db.get_cf(self.cf(BLOCK_TIMESTAMPS_CF)?, block_number.to_be_bytes())? {
             Some(data) => {
                 let timestamp: u64 = serde_json::from_slice(&data)?;
+                self.with_block_timestamp_cache(|cache| cache.insert(block_number, timestamp));
                 Ok(Some(timestamp))
             }
             None => Ok(None),
         }
     }
 
-    /// 缓存区块时间戳
-    pub fn cache_block_timestamp(&self, block_number: u64, timestamp: u64) -> anyhow::Result<()> {
-        let key = format!("block_timestamp_{}", block_number);
+    /// 缓存区块时间戳（同时写入内存 LRU）
+    pub fn cache_block_timestamp(&self, block_number: u64, timestamp: u64) -> KeeperResult<()> {
         let data = serde_json::to_vec(&timestamp)?;
-        self.db.put(key.as_bytes(), data)?;
+        self.db.put_cf(self.cf(BLOCK_TIMESTAMPS_CF)?, block_number.to_be_bytes(), data)?;
+        self.with_block_timestamp_cache(|cache| cache.insert(block_number, timestamp));
         tracing::trace!("区块时间戳已缓存: 区块={}, 时间戳={}", block_number, timestamp);
         Ok(())
     }
 
     /// 批量缓存区块时间戳
-    pub fn cache_block_timestamps(&self, timestamps: &[(u64, u64)]) -> anyhow::Result<()> {
+    pub fn cache_block_timestamps(&self, timestamps: &[(u64, u64)]) -> KeeperResult<()> {
         for (block_number, timestamp) in timestamps {
             self.cache_block_timestamp(*block_number, *timestamp)?;
         }
@@ -265,55 +867,205 @@ impl Database {
         Ok(())
     }
 
-    /// 清理过期的区块时间戳缓存（保留最近的5,000个区块的缓存）
-    pub fn cleanup_old_block_timestamps(&self, current_block: u64) -> anyhow::Result<()> {
+    /// 在内存 LRU 上执行操作；未启用时返回 None
+    fn with_block_timestamp_cache<T>(&self, f: impl FnOnce(&mut LruCache<u64, u64>) -> T) -> Option<T> {
+        let cache = self.block_timestamp_cache.as_ref()?;
+        // 缓存内容只是 RocksDB 的副本，持有锁的线程 panic 后继续使用不会产生不一致
+        let mut cache = cache.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        Some(f(&mut cache))
+    }
+
+    /// 清理过期的区块时间戳缓存（保留最近 `retention_blocks` 个区块的缓存）
+    pub fn cleanup_old_block_timestamps(&self, current_block: u64, retention_blocks: u64) -> KeeperResult<()> {
+        let cf = self.cf(BLOCK_TIMESTAMPS_CF)?;
         let mut to_delete = Vec::new();
-        let keep_threshold = current_block.saturating_sub(5000);
+        let keep_threshold = current_block.saturating_sub(retention_blocks);
+
+        // 内存 LRU 中的过期区块一并移除，与 RocksDB 的保留范围保持一致
+        self.with_block_timestamp_cache(|cache| {
+            let expired: Vec<u64> = cache.iter()
+                .map(|(block_number, _)| *block_number)
+                .filter(|block_number| *block_number < keep_threshold)
+                .collect();
+            for block_number in expired {
+                cache.remove(&block_number);
+            }
+        });
 
-        // 收集需要删除的键
-        let iter = self.db.iterator(rocksdb::IteratorMode::Start);
+        // 键按区块号大端序排列，遇到第一个需要保留的区块即可停止
+        let iter = self.db.iterator_cf(cf, rocksdb::IteratorMode::Start);
         for item in iter {
             let (key, _) = item?;
-            let key_str = String::from_utf8(key.to_vec())?;
-
-            if key_str.starts_with("block_timestamp_") {
-                if let Some(block_str) = key_str.strip_prefix("block_timestamp_") {
-                    if let Ok(block_num) = block_str.parse::<u64>() {
-                        if block_num < keep_threshold {
-                            to_delete.push(key.to_vec());
-                        }
-                    }
-                }
+            let block_num = u64::from_be_bytes(key.as_ref().try_into().map_err(invalid_key)?);
+            if block_num >= keep_threshold {
+                break;
             }
+            to_delete.push(key);
         }
 
         // 批量删除
         if !to_delete.is_empty() {
             for key in &to_delete {
-                self.db.delete(key)?;
+                self.db.delete_cf(cf, key)?;
             }
             tracing::debug!("清理了 {} 个过期的区块时间戳缓存", to_delete.len());
+
+            // 压缩已删除的范围，及时回收删除标记占用的空间
+            self.db.compact_range_cf(cf, None::<&[u8]>, Some(keep_threshold.to_be_bytes()));
+        }
+
+        Ok(())
+    }
+
+    /// 获取持久化的区块时间锚点
+    pub fn get_block_time_anchor(&self) -> KeeperResult<Option<BlockTimeAnchor>> {
+        let key = b"block_time_anchor";
+
+        match self.db.get_cf(self.cf(META_CF)?, key)? {
+            Some(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// 持久化区块时间锚点
+    pub fn set_block_time_anchor(&self, anchor: &BlockTimeAnchor) -> KeeperResult<()> {
+        let key = b"block_time_anchor";
+        let data = serde_json::to_vec(anchor)?;
+        self.db.put_cf(self.cf(META_CF)?, key, data)?;
+        tracing::debug!("区块时间锚点已更新: 区块={}, 时间戳={}", anchor.block_number, anchor.timestamp);
+        Ok(())
+    }
+
+    /// 获取持久化的边界区块已处理事件
+    pub fn get_processed_events(&self) -> KeeperResult<Option<ProcessedEventsRecord>> {
+        let key = b"processed_events";
+
+        match self.db.get_cf(self.cf(META_CF)?, key)? {
+            Some(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            None => Ok(None),
         }
+    }
 
+    /// 持久化边界区块已处理事件（覆盖之前的记录）
+    pub fn set_processed_events(&self, record: &ProcessedEventsRecord) -> KeeperResult<()> {
+        let key = b"processed_events";
+        let data = serde_json::to_vec(record)?;
+        self.db.put_cf(self.cf(META_CF)?, key, data)?;
         Ok(())
     }
 
+    /// 删除持久化的边界区块已处理事件
+    pub fn clear_processed_events(&self) -> KeeperResult<()> {
+        self.db.delete_cf(self.cf(META_CF)?, b"processed_events")?;
+        Ok(())
+    }
+
+    /// 清算是否因安全保护而暂停
+    pub fn is_liquidation_paused(&self) -> KeeperResult<bool> {
+        let key = b"liquidation_paused";
+
+        match self.db.get_cf(self.cf(META_CF)?, key)? {
+            Some(data) => Ok(serde_json::from_slice(&data)?),
+            None => Ok(false),
+        }
+    }
+
+    /// 设置清算暂停状态
+    pub fn set_liquidation_paused(&self, paused: bool) -> KeeperResult<()> {
+        let key = b"liquidation_paused";
+        let data = serde_json::to_vec(&paused)?;
+        self.db.put_cf(self.cf(META_CF)?, key, data)?;
+        tracing::info!("清算暂停状态已更新: {}", paused);
+        Ok(())
+    }
+
+    // 原始日志归档相关数据库方法
+
+    /// 归档日志的键：区块号_交易索引_日志索引（补零以保证按链上顺序排列）
+    fn raw_log_key(block_number: u64, transaction_index: u64, log_index: u64) -> String {
+        format!("{:020}_{:010}_{:010}", block_number, transaction_index, log_index)
+    }
+
+    /// 归档一条原始日志
+    pub fn archive_raw_log(&self, log: &web3::types::Log) -> KeeperResult<()> {
+        let key = Self::raw_log_key(
+            log.block_number.unwrap_or_default().as_u64(),
+            log.transaction_index.unwrap_or_default().as_u64(),
+            log.log_index.unwrap_or_default().as_u64(),
+        );
+        let data = serde_json::to_vec(log)?;
+        self.db.put_cf(self.cf(RAW_LOGS_CF)?, key.as_bytes(), data)?;
+        tracing::trace!("原始日志已归档: {}", key);
+        Ok(())
+    }
+
+    /// 按区块范围读取归档的原始日志（包含两端），按链上顺序返回
+    pub fn get_archived_raw_logs(&self, from_block: u64, to_block: u64) -> KeeperResult<Vec<web3::types::Log>> {
+        let mut logs = Vec::new();
+        let start_key = Self::raw_log_key(from_block, 0, 0);
+
+        let iter = self.db.iterator_cf(
+            self.cf(RAW_LOGS_CF)?,
+            rocksdb::IteratorMode::From(start_key.as_bytes(), rocksdb::Direction::Forward),
+        );
+        for item in iter {
+            let (key, value) = item?;
+            let block_number = String::from_utf8(key[0..20].to_vec()).map_err(invalid_key)?.parse::<u64>().map_err(invalid_key)?;
+            if block_number > to_block {
+                break;
+            }
+            let log: web3::types::Log = serde_json::from_slice(&value)?;
+            logs.push(log);
+        }
+
+        Ok(logs)
+    }
+
+    /// 删除早于指定区块的归档日志
+    pub fn prune_archived_raw_logs(&self, before_block: u64) -> KeeperResult<usize> {
+        let cf = self.cf(RAW_LOGS_CF)?;
+        let mut to_delete = Vec::new();
+
+        for item in self.db.iterator_cf(cf, rocksdb::IteratorMode::Start) {
+            let (key, _) = item?;
+            let block_number = String::from_utf8(key[0..20].to_vec()).map_err(invalid_key)?.parse::<u64>().map_err(invalid_key)?;
+            if block_number >= before_block {
+                break;
+            }
+            to_delete.push(key);
+        }
+
+        for key in &to_delete {
+            self.db.delete_cf(cf, key)?;
+        }
+        if !to_delete.is_empty() {
+            tracing::debug!("清理了 {} 条过期的归档日志", to_delete.len());
+        }
+
+        Ok(to_delete.len())
+    }
+
     /// 拍卖相关数据库方法
 
     /// 存储拍卖信息
-    pub fn store_auction(&self, auction: &AuctionInfo) -> anyhow::Result<()> {
-        let key = format!("auction_{}", auction.auction_id);
+    pub fn store_auction(&self, auction: &AuctionInfo) -> KeeperResult<()> {
+        let key = auction.auction_id.to_string();
         let data = serde_json::to_vec(auction)?;
-        self.db.put(key.as_bytes(), data)?;
-        tracing::info!("拍卖已存储: ID={}", auction.auction_id);
+        let cf = self.cf(AUCTIONS_CF)?;
+        let is_new = self.db.get_cf(cf, key.as_bytes())?.is_none();
+        self.db.put_cf(cf, key.as_bytes(), data)?;
+        if is_new {
+            self.auction_count.fetch_add(1, Ordering::Relaxed);
+        }
+        tracing::debug!("拍卖已存储: ID={}", auction.auction_id);
         Ok(())
     }
 
     /// 获取拍卖信息
-    pub fn get_auction(&self, auction_id: U256) -> anyhow::Result<Option<AuctionInfo>> {
-        let key = format!("auction_{}", auction_id);
+    pub fn get_auction(&self, auction_id: U256) -> KeeperResult<Option<AuctionInfo>> {
+        let key = auction_id.to_string();
 
-        match self.db.get(key.as_bytes())? {
+        match self.db.get_cf(self.cf(AUCTIONS_CF)?, key.as_bytes())? {
             Some(data) => {
                 let auction: AuctionInfo = serde_json::from_slice(&data)?;
                 Ok(Some(auction))
@@ -325,36 +1077,36 @@ impl Database {
 
 
     /// 删除拍卖信息
-    pub fn delete_auction(&self, auction_id: U256) -> anyhow::Result<()> {
-        let key = format!("auction_{}", auction_id);
-        self.db.delete(key.as_bytes())?;
-        tracing::info!("拍卖已删除: ID={}", auction_id);
+    pub fn delete_auction(&self, auction_id: U256) -> KeeperResult<()> {
+        let key = auction_id.to_string();
+        let cf = self.cf(AUCTIONS_CF)?;
+        let existed = self.db.get_cf(cf, key.as_bytes())?.is_some();
+        self.db.delete_cf(cf, key.as_bytes())?;
+        if existed {
+            self.auction_count.fetch_sub(1, Ordering::Relaxed);
+        }
+        tracing::debug!("拍卖已删除: ID={}", auction_id);
         Ok(())
     }
 
     /// 获取所有拍卖（通过存在性判断活跃状态）
-    pub fn get_all_auctions(&self) -> anyhow::Result<Vec<AuctionInfo>> {
+    pub fn get_all_auctions(&self) -> KeeperResult<Vec<AuctionInfo>> {
         let mut auctions = Vec::new();
 
-        // 遍历所有auction_开头的记录
-        let iter = self.db.iterator(rocksdb::IteratorMode::Start);
+        let iter = self.db.iterator_cf(self.cf(AUCTIONS_CF)?, rocksdb::IteratorMode::Start);
         for item in iter {
-            let (key, value) = item?;
-            let key_str = String::from_utf8(key.to_vec())?;
-
-            if key_str.starts_with("auction_") {
-                let auction: AuctionInfo = serde_json::from_slice(&value)?;
-                auctions.push(auction);
-            }
+            let (_, value) = item?;
+            let auction: AuctionInfo = serde_json::from_slice(&value)?;
+            auctions.push(auction);
         }
 
         Ok(auctions)
     }
 
     /// 检查拍卖记录是否存在（存在即为活跃）
-    pub fn auction_exists(&self, auction_id: U256) -> anyhow::Result<bool> {
-        let key = format!("auction_{}", auction_id);
-        match self.db.get(key.as_bytes())? {
+    pub fn auction_exists(&self, auction_id: U256) -> KeeperResult<bool> {
+        let key = auction_id.to_string();
+        match self.db.get_cf(self.cf(AUCTIONS_CF)?, key.as_bytes())? {
             Some(_) => Ok(true),
             None => Ok(false),
         }
@@ -363,19 +1115,27 @@ impl Database {
     /// 用户持仓相关数据库方法
 
     /// 存储用户持仓信息
-    pub fn store_user_position(&self, position: &UserPosition) -> anyhow::Result<()> {
-        let key = format!("position_{}_{}", position.user, position.token_id);
+    pub fn store_user_position(&self, position: &UserPosition) -> KeeperResult<()> {
+        let key = Self::position_key(position.user, position.token_id);
         let data = serde_json::to_vec(position)?;
-        self.db.put(key.as_bytes(), data)?;
-        tracing::info!("用户持仓已记录 - 用户: {:?}, TokenID: {}, 数量: {}", position.user, position.token_id, position.amount);
+        let positions_cf = self.cf(POSITIONS_CF)?;
+        let is_new = self.db.get_cf(positions_cf, key.as_bytes())?.is_none();
+        let mut batch = rocksdb::WriteBatch::default();
+        batch.put_cf(positions_cf, key.as_bytes(), data);
+        batch.put_cf(self.cf(TOKEN_INDEX_CF)?, Self::token_index_key(position.token_id, position.user), b"");
+        self.db.write(batch)?;
+        if is_new {
+            self.position_count.fetch_add(1, Ordering::Relaxed);
+        }
+        tracing::debug!("用户持仓已记录 - 用户: {:?}, TokenID: {}, 数量: {}", position.user, position.token_id, position.amount);
         Ok(())
     }
 
     /// 获取用户特定token的持仓
-    pub fn get_user_position(&self, user: Address, token_id: U256) -> anyhow::Result<Option<UserPosition>> {
-        let key = format!("position_{}_{}", user, token_id);
+    pub fn get_user_position(&self, user: Address, token_id: U256) -> KeeperResult<Option<UserPosition>> {
+        let key = Self::position_key(user, token_id);
 
-        match self.db.get(key.as_bytes())? {
+        match self.db.get_cf(self.cf(POSITIONS_CF)?, key.as_bytes())? {
             Some(data) => {
                 let position: UserPosition = serde_json::from_slice(&data)?;
                 Ok(Some(position))
@@ -385,18 +1145,41 @@ impl Database {
     }
 
     /// 获取用户所有持仓信息
-    pub fn get_user_positions(&self, user: Address) -> anyhow::Result<Vec<UserPosition>> {
+    pub fn get_user_positions(&self, user: Address) -> KeeperResult<Vec<UserPosition>> {
         let mut positions = Vec::new();
-        let prefix = format!("position_{}_", user);
+        let prefix = format!("{:?}_", user);
 
-        let iter = self.db.iterator(rocksdb::IteratorMode::Start);
+        let iter = self.db.prefix_iterator_cf(self.cf(POSITIONS_CF)?, prefix.as_bytes());
         for item in iter {
             let (key, value) = item?;
-            let key_str = String::from_utf8(key.to_vec())?;
+            if !key.starts_with(prefix.as_bytes()) {
+                break;
+            }
+            let position: UserPosition = serde_json::from_slice(&value)?;
+            positions.push(position);
+        }
 
-            if key_str.starts_with(&prefix) {
-                let position: UserPosition = serde_json::from_slice(&value)?;
-                positions.push(position);
+        Ok(positions)
+    }
+
+    /// 通过 token_id 索引获取持有该 token 的所有持仓
+    pub fn get_positions_by_token(&self, token_id: U256) -> KeeperResult<Vec<UserPosition>> {
+        let mut positions = Vec::new();
+        let prefix = format!("{}_", token_id);
+
+        let iter = self.db.prefix_iterator_cf(self.cf(TOKEN_INDEX_CF)?, prefix.as_bytes());
+        for item in iter {
+            let (key, _) = item?;
+            if !key.starts_with(prefix.as_bytes()) {
+                break;
+            }
+            let user = std::str::from_utf8(&key[prefix.len()..]).map_err(invalid_key)?
+                .parse::<Address>()
+                .map_err(|e| KeeperError::Decode(format!("token_id 索引中的地址无效: {}", e)))?;
+
+            match self.get_user_position(user, token_id)? {
+                Some(position) => positions.push(position),
+                None => tracing::warn!("token_id 索引指向不存在的持仓 - 用户: {:?}, TokenID: {}", user, token_id),
             }
         }
 
@@ -404,29 +1187,677 @@ impl Database {
     }
 
     /// 删除用户持仓信息
-    pub fn delete_user_position(&self, user: Address, token_id: U256) -> anyhow::Result<()> {
-        let key = format!("position_{}_{}", user, token_id);
-        self.db.delete(key.as_bytes())?;
-        tracing::info!("用户持仓已删除 - 用户: {:?}, TokenID: {}", user, token_id);
+    pub fn delete_user_position(&self, user: Address, token_id: U256) -> KeeperResult<()> {
+        let key = Self::position_key(user, token_id);
+        let positions_cf = self.cf(POSITIONS_CF)?;
+        let existed = self.db.get_cf(positions_cf, key.as_bytes())?.is_some();
+        let mut batch = rocksdb::WriteBatch::default();
+        batch.delete_cf(positions_cf, key.as_bytes());
+        batch.delete_cf(self.cf(TOKEN_INDEX_CF)?, Self::token_index_key(token_id, user));
+        self.db.write(batch)?;
+        if existed {
+            self.position_count.fetch_sub(1, Ordering::Relaxed);
+        }
+        self.delete_nav_record(user, token_id)?;
+        self.delete_nav_history(user, token_id)?;
+        tracing::debug!("用户持仓已删除 - 用户: {:?}, TokenID: {}", user, token_id);
         Ok(())
     }
 
     /// 获取所有用户的持仓信息
-    pub fn get_all_user_positions(&self) -> anyhow::Result<Vec<UserPosition>> {
+    pub fn get_all_user_positions(&self) -> KeeperResult<Vec<UserPosition>> {
         let mut positions = Vec::new();
 
-        // 遍历所有以"position_"开头的记录
-        let iter = self.db.iterator(rocksdb::IteratorMode::Start);
+        let iter = self.db.iterator_cf(self.cf(POSITIONS_CF)?, rocksdb::IteratorMode::Start);
+        for item in iter {
+            let (_, value) = item?;
+            let position: UserPosition = serde_json::from_slice(&value)?;
+            positions.push(position);
+        }
+
+        Ok(positions)
+    }
+
+    /// 导出持仓、拍卖和系统参数
+    ///
+    /// 所有数据从同一个 RocksDB 快照读取，导出期间 Keeper 继续写入也不会导出前后不一致的状态。
+    pub fn export_state(&self) -> KeeperResult<DatabaseExport> {
+        let snapshot = self.db.snapshot();
+
+        let params = assemble_system_params(snapshot.iterator_cf(self.cf(PARAMS_CF)?, rocksdb::IteratorMode::Start))?;
+        let last_synced_block = match snapshot.get_cf(self.cf(META_CF)?, LAST_SYNCED_BLOCK_KEY)? {
+            Some(data) => Some(decode_sync_checkpoint(&data)?.block_number),
+            None => None,
+        };
+
+        let mut positions = Vec::new();
+        for item in snapshot.iterator_cf(self.cf(POSITIONS_CF)?, rocksdb::IteratorMode::Start) {
+            let (_, value) = item?;
+            positions.push(serde_json::from_slice(&value)?);
+        }
+
+        let mut auctions = Vec::new();
+        for item in snapshot.iterator_cf(self.cf(AUCTIONS_CF)?, rocksdb::IteratorMode::Start) {
+            let (_, value) = item?;
+            auctions.push(serde_json::from_slice(&value)?);
+        }
+
+        Ok(DatabaseExport { last_synced_block, params, positions, auctions })
+    }
+
+    /// 将导出的状态写入数据库，只允许导入到没有持仓和拍卖的新数据库
+    pub fn import_state(&self, state: &DatabaseExport) -> KeeperResult<()> {
+        if self.get_position_count() > 0 || self.get_auction_count() > 0 {
+            return Err(KeeperError::Database(format!(
+                "只能导入到新数据库，当前已有 {} 个持仓和 {} 个拍卖",
+                self.get_position_count(), self.get_auction_count()
+            )));
+        }
+
+        self.set_system_params(&state.params)?;
+        for position in &state.positions {
+            self.store_user_position(position)?;
+        }
+        for auction in &state.auctions {
+            self.store_auction(auction)?;
+        }
+        if let Some(block_number) = state.last_synced_block {
+            self.set_last_synced_block(block_number, None)?;
+        }
+        Ok(())
+    }
+
+    // 清算记录相关数据库方法
+
+    /// 存储清算记录，产生了拍卖时同时写入拍卖到持仓的反向关联
+    pub fn store_liquidation_record(&self, record: &LiquidationRecord) -> KeeperResult<()> {
+        let data = serde_json::to_vec(record)?;
+        let mut batch = rocksdb::WriteBatch::default();
+        batch.put_cf(self.cf(LIQUIDATIONS_CF)?, Self::position_key(record.user, record.token_id), &data);
+        if let Some(auction_id) = record.auction_id {
+            batch.put_cf(self.cf(AUCTION_ORIGINS_CF)?, auction_id.to_string(), &data);
+        }
+        self.db.write(batch)?;
+        tracing::info!(
+            "清算记录已存储 - 用户: {:?}, TokenID: {}, 拍卖ID: {:?}",
+            record.user, record.token_id, record.auction_id
+        );
+        Ok(())
+    }
+
+    /// 获取持仓最近一次的清算记录
+    pub fn get_liquidation_record(&self, user: Address, token_id: U256) -> KeeperResult<Option<LiquidationRecord>> {
+        let key = Self::position_key(user, token_id);
+
+        match self.db.get_cf(self.cf(LIQUIDATIONS_CF)?, key.as_bytes())? {
+            Some(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// 记录持仓的 bark 交易已发出（清算冷却期开始）
+    pub fn set_liquidation_in_flight(&self, user: Address, token_id: U256, barked_at: u64) -> KeeperResult<()> {
+        let key = Self::position_key(user, token_id);
+        self.db.put_cf(self.cf(LIQUIDATION_COOLDOWNS_CF)?, key.as_bytes(), serde_json::to_vec(&barked_at)?)?;
+        Ok(())
+    }
+
+    /// 获取持仓最近一次发出 bark 的时间戳（没有进行中的清算时为 None）
+    pub fn get_liquidation_in_flight(&self, user: Address, token_id: U256) -> KeeperResult<Option<u64>> {
+        let key = Self::position_key(user, token_id);
+
+        match self.db.get_cf(self.cf(LIQUIDATION_COOLDOWNS_CF)?, key.as_bytes())? {
+            Some(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// 清除持仓的进行中清算记录（拍卖已出现、交易被 revert 或冷却期已过）
+    pub fn clear_liquidation_in_flight(&self, user: Address, token_id: U256) -> KeeperResult<()> {
+        let key = Self::position_key(user, token_id);
+        self.db.delete_cf(self.cf(LIQUIDATION_COOLDOWNS_CF)?, key.as_bytes())?;
+        Ok(())
+    }
+
+    /// 根据拍卖ID查找产生该拍卖的清算记录
+    pub fn get_auction_origin(&self, auction_id: U256) -> KeeperResult<Option<LiquidationRecord>> {
+        let key = auction_id.to_string();
+
+        match self.db.get_cf(self.cf(AUCTION_ORIGINS_CF)?, key.as_bytes())? {
+            Some(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    // NAV 记录相关数据库方法
+
+    /// 批量写入最新的 NAV 记录（覆盖同一持仓的旧记录）
+    pub fn store_nav_records(&self, records: &[NavRecord]) -> KeeperResult<()> {
+        let cf = self.cf(NAV_CF)?;
+        let mut batch = rocksdb::WriteBatch::default();
+        for record in records {
+            let key = Self::position_key(record.user, record.token_id);
+            batch.put_cf(cf, key.as_bytes(), serde_json::to_vec(record)?);
+        }
+        self.db.write(batch)?;
+        tracing::debug!("已写入 {} 条NAV记录", records.len());
+        Ok(())
+    }
+
+    /// 获取持仓最新的 NAV 记录
+    pub fn get_nav_record(&self, user: Address, token_id: U256) -> KeeperResult<Option<NavRecord>> {
+        let key = Self::position_key(user, token_id);
+
+        match self.db.get_cf(self.cf(NAV_CF)?, key.as_bytes())? {
+            Some(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// 获取所有持仓最新的 NAV 记录
+    pub fn get_all_nav_records(&self) -> KeeperResult<Vec<NavRecord>> {
+        let mut records = Vec::new();
+
+        let iter = self.db.iterator_cf(self.cf(NAV_CF)?, rocksdb::IteratorMode::Start);
+        for item in iter {
+            let (_, value) = item?;
+            records.push(serde_json::from_slice(&value)?);
+        }
+
+        Ok(records)
+    }
+
+    /// 为每条 NAV 记录追加一个历史样本，并删除对应持仓早于 `prune_before` 的样本
+    pub fn append_nav_history(&self, records: &[NavRecord], prune_before: u64) -> KeeperResult<()> {
+        let cf = self.cf(NAV_HISTORY_CF)?;
+        let mut batch = rocksdb::WriteBatch::default();
+        for record in records {
+            // 键中的时间戳定长补零，按时间有序：过期样本是一个连续的键范围，按范围删除，不需要读取
+            batch.delete_range_cf(
+                cf,
+                Self::nav_history_key(record.user, record.token_id, 0),
+                Self::nav_history_key(record.user, record.token_id, prune_before),
+            );
+
+            let sample = NavSample {
+                timestamp: record.calculated_at,
+                net_nav: record.net_nav,
+                gross_nav: record.gross_nav,
+            };
+            batch.put_cf(cf, Self::nav_history_key(record.user, record.token_id, sample.timestamp), serde_json::to_vec(&sample)?);
+        }
+        self.db.write(batch)?;
+        tracing::debug!("已追加 {} 个NAV历史样本，删除了早于 {} 的样本", records.len(), prune_before);
+        Ok(())
+    }
+
+    /// 获取持仓的 NAV 历史（按时间升序）
+    pub fn get_nav_history(&self, user: Address, token_id: U256) -> KeeperResult<Vec<NavSample>> {
+        self.nav_history_entries(user, token_id)?
+            .iter()
+            .map(|(_, value)| Ok(serde_json::from_slice(value)?))
+            .collect()
+    }
+
+    /// 删除持仓的全部 NAV 历史（持仓被移除时调用）
+    pub fn delete_nav_history(&self, user: Address, token_id: U256) -> KeeperResult<()> {
+        let cf = self.cf(NAV_HISTORY_CF)?;
+        let mut batch = rocksdb::WriteBatch::default();
+        for (key, _) in self.nav_history_entries(user, token_id)? {
+            batch.delete_cf(cf, key);
+        }
+        self.db.write(batch)?;
+        Ok(())
+    }
+
+    /// 持仓全部 NAV 历史样本（按时间升序）
+    fn nav_history_entries(&self, user: Address, token_id: U256) -> KeeperResult<Vec<KeyValue>> {
+        let prefix = Self::nav_history_prefix(user, token_id);
+        let mut entries = Vec::new();
+
+        let iter = self.db.iterator_cf(
+            self.cf(NAV_HISTORY_CF)?,
+            rocksdb::IteratorMode::From(prefix.as_bytes(), rocksdb::Direction::Forward),
+        );
         for item in iter {
             let (key, value) = item?;
-            let key_str = String::from_utf8(key.to_vec())?;
+            if !key.starts_with(prefix.as_bytes()) {
+                break;
+            }
+            entries.push((key, value));
+        }
 
-            if key_str.starts_with("position_") {
-                let position: UserPosition = serde_json::from_slice(&value)?;
-                positions.push(position);
+        Ok(entries)
+    }
+
+    /// NAV 历史样本键的持仓前缀（以 `_` 结尾，token 1 的前缀不会匹配 token 12）
+    fn nav_history_prefix(user: Address, token_id: U256) -> String {
+        format!("{}_", Self::position_key(user, token_id))
+    }
+
+    /// NAV 历史样本的键（时间戳补零到固定宽度，按字节序即按时间排序）
+    fn nav_history_key(user: Address, token_id: U256, timestamp: u64) -> String {
+        format!("{}{:020}", Self::nav_history_prefix(user, token_id), timestamp)
+    }
+
+    /// 删除持仓的 NAV 记录（持仓被移除时调用）
+    pub fn delete_nav_record(&self, user: Address, token_id: U256) -> KeeperResult<()> {
+        let key = Self::position_key(user, token_id);
+        self.db.delete_cf(self.cf(NAV_CF)?, key.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// 系统参数按字段展开为 JSON 对象
+fn system_param_fields(params: &SystemParams) -> KeeperResult<serde_json::Map<String, serde_json::Value>> {
+    match serde_json::to_value(params)? {
+        serde_json::Value::Object(fields) => Ok(fields),
+        other => Err(KeeperError::Decode(format!("系统参数不是 JSON 对象: {}", other))),
+    }
+}
+
+/// 系统参数每个字段的存储键值对（键为字段名，值为字段的 JSON）
+fn system_param_entries(params: &SystemParams) -> KeeperResult<Vec<(String, Vec<u8>)>> {
+    system_param_fields(params)?
+        .into_iter()
+        .map(|(name, value)| Ok((name, serde_json::to_vec(&value)?)))
+        .collect()
+}
+
+/// 由 params 列族中按字段存储的键值对组装系统参数，缺少的字段使用默认值，未知的键忽略
+fn assemble_system_params<K: AsRef<[u8]>, V: AsRef<[u8]>>(
+    entries: impl IntoIterator<Item = Result<(K, V), rocksdb::Error>>,
+) -> KeeperResult<SystemParams> {
+    let mut fields = system_param_fields(&SystemParams::default())?;
+    for entry in entries {
+        let (key, value) = entry?;
+        let name = std::str::from_utf8(key.as_ref()).map_err(invalid_key)?;
+        if let Some(field) = fields.get_mut(name) {
+            *field = serde_json::from_slice(value.as_ref())?;
+        }
+    }
+    Ok(serde_json::from_value(serde_json::Value::Object(fields))?)
+}
+
+/// 无法解析的数据库键（数据损坏或格式变更）
+fn invalid_key(e: impl std::fmt::Display) -> KeeperError {
+    KeeperError::Decode(format!("数据库键格式无效: {}", e))
+}
+
+/// 解析同步边界，兼容旧版本只存储区块号的格式
+fn decode_sync_checkpoint(data: &[u8]) -> KeeperResult<SyncCheckpoint> {
+    if let Ok(block_number) = serde_json::from_slice::<u64>(data) {
+        return Ok(SyncCheckpoint { block_number, block_hash: None, history: Vec::new() });
+    }
+    Ok(serde_json::from_slice(data)?)
+}
+
+/// 检查数据库目录可以创建且可写
+///
+/// 在目录下创建并删除一个探测文件；RocksDB 自身的打开错误往往只报告某个内部文件，难以定位到配置问题。
+fn ensure_writable(db_path: &str) -> KeeperResult<()> {
+    let path = std::path::Path::new(db_path);
+    std::fs::create_dir_all(path)
+        .map_err(|e| KeeperError::Database(format!("无法创建数据库目录 '{}'（请检查 db_path 配置）: {}", db_path, e)))?;
+
+    let probe = path.join(".keeper_write_probe");
+    std::fs::write(&probe, b"")
+        .and_then(|_| std::fs::remove_file(&probe))
+        .map_err(|e| KeeperError::Database(format!("数据库目录 '{}' 不可写（请检查 db_path 配置和目录权限）: {}", db_path, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leverage_type_round_trips_through_u8_and_string() {
+        for leverage in [LeverageType::Conservative, LeverageType::Moderate, LeverageType::Aggressive] {
+            assert_eq!(LeverageType::from_u8(leverage.to_u8()).unwrap(), leverage);
+            assert_eq!(leverage.to_string().parse::<LeverageType>().unwrap(), leverage);
+            assert_eq!(leverage.to_u8().to_string().parse::<LeverageType>().unwrap(), leverage);
+        }
+        assert_eq!(" aggressive ".parse::<LeverageType>().unwrap(), LeverageType::Aggressive);
+        assert!(LeverageType::from_u8(3).is_err());
+        assert!("3".parse::<LeverageType>().is_err());
+        assert!("extreme".parse::<LeverageType>().is_err());
+    }
+
+    #[test]
+    fn concurrent_param_updates_do_not_lose_writes() {
+        let database = Database::open_temp();
+        std::thread::scope(|scope| {
+            for round in 1..=200u64 {
+                let database = &database;
+                scope.spawn(move || database.update_penalty(U256::from(round)).unwrap());
+                scope.spawn(move || database.update_annual_interest_rate(U256::from(round)).unwrap());
+                scope.spawn(move || database.mark_params_log_applied(LogPosition { block_number: round, log_index: 0 }).unwrap());
             }
+        });
+
+        // 每个字段都保留了某一次写入的值，没有被读到旧参数的并发更新改回默认值
+        let params = database.get_system_params().unwrap();
+        let defaults = SystemParams::default();
+        assert_ne!(params.penalty, defaults.penalty);
+        assert_ne!(params.annual_interest_rate, defaults.annual_interest_rate);
+        assert!(params.last_applied_log.is_some());
+    }
+
+    #[test]
+    fn system_params_are_split_into_per_field_keys() {
+        // 旧布局：参数整体存放在一个键中
+        let database = Database::open_temp();
+        let legacy = SystemParams { penalty: U256::from(42u64), circuit_breaker: true, ..SystemParams::default() };
+        let params_cf = database.cf(PARAMS_CF).unwrap();
+        database.db.put_cf(params_cf, LEGACY_SYSTEM_PARAMS_KEY, serde_json::to_vec(&legacy).unwrap()).unwrap();
+        database.db.put_cf(database.cf(META_CF).unwrap(), CF_LAYOUT_VERSION_KEY, b"2").unwrap();
+        database.migrate().unwrap();
+
+        assert!(database.db.get_cf(params_cf, LEGACY_SYSTEM_PARAMS_KEY).unwrap().is_none());
+        let params = database.get_system_params().unwrap();
+        assert_eq!((params.penalty, params.circuit_breaker), (U256::from(42u64), true));
+
+        // 每个更新方法只写入自己的字段
+        database.update_adjustment_threshold(U256::from(1u64)).unwrap();
+        database.update_liquidation_threshold(U256::from(2u64)).unwrap();
+        database.update_liquidation_enabled(false).unwrap();
+        database.update_price_multiplier(U256::from(3u64)).unwrap();
+        database.update_reset_time(U256::from(4u64)).unwrap();
+        database.update_min_auction_amount(U256::from(5u64)).unwrap();
+        database.update_price_drop_threshold(U256::from(6u64)).unwrap();
+        database.update_percentage_reward(U256::from(7u64)).unwrap();
+        database.update_fixed_reward(U256::from(8u64)).unwrap();
+        database.update_annual_interest_rate(U256::from(9u64)).unwrap();
+        let params = database.get_system_params().unwrap();
+        assert_eq!(
+            [params.adjustment_threshold, params.liquidation_threshold, params.price_multiplier, params.reset_time,
+             params.min_auction_amount, params.price_drop_threshold, params.percentage_reward, params.fixed_reward,
+             params.annual_interest_rate],
+            [1u64, 2, 3, 4, 5, 6, 7, 8, 9].map(U256::from)
+        );
+        assert_eq!((params.penalty, params.liquidation_enabled, params.circuit_breaker), (U256::from(42u64), false, true));
+    }
+
+    #[test]
+    fn flush_persists_sync_progress() {
+        let path = Database::temp_path();
+        let database = Database::open(&path, &DatabaseConfig::default()).unwrap();
+        assert!(database.sync_checkpoint_writes);
+        database.set_last_synced_block(120, Some(H256::repeat_byte(0x01))).unwrap();
+        database.flush().unwrap();
+        drop(database);
+
+        // 关闭后重新打开同一路径，刷写的同步进度仍然存在
+        let reopened = Database::open(&path, &DatabaseConfig::default()).unwrap();
+        assert_eq!(reopened.get_last_synced_block().unwrap(), Some(120));
+        assert_eq!(reopened.get_sync_checkpoint().unwrap().unwrap().block_hash, Some(H256::repeat_byte(0x01)));
+    }
+
+    #[test]
+    fn secondary_instance_reads_without_writing() {
+        let path = Database::temp_path();
+        let primary = Database::open(&path, &DatabaseConfig::default()).unwrap();
+        primary.set_last_synced_block(42, None).unwrap();
+        primary.set_liquidation_paused(true).unwrap();
+
+        // 主实例仍然打开时读取已写入的数据
+        let secondary = Database::open_secondary(&path, &DatabaseConfig::default()).unwrap();
+        assert_eq!(secondary.get_last_synced_block().unwrap(), Some(42));
+        assert!(secondary.is_liquidation_paused().unwrap());
+        assert!(secondary.set_liquidation_paused(false).is_err());
+        assert!(primary.is_liquidation_paused().unwrap());
+
+        // 关闭后删除 secondary 实例的工作目录
+        let secondary_path = secondary.secondary_dir.as_ref().unwrap().0.clone();
+        assert!(secondary_path.exists());
+        drop(secondary);
+        assert!(!secondary_path.exists());
+    }
+
+    #[test]
+    fn nav_history_is_appended_and_pruned_per_position() {
+        let database = Database::open_temp();
+        let user = Address::repeat_byte(0x11);
+        let record = |token_id: u64, calculated_at: u64| NavRecord {
+            user,
+            token_id: U256::from(token_id),
+            price: U256::zero(),
+            gross_nav: U256::from(calculated_at * 2),
+            net_nav: U256::from(calculated_at),
+            position_amount: U256::zero(),
+            total_value: U256::zero(),
+            net_value: U256::zero(),
+            accrued_interest: U256::zero(),
+            health_factor: None,
+            calculated_at,
+        };
+
+        for calculated_at in [100, 200, 300] {
+            database.append_nav_history(&[record(1, calculated_at), record(12, calculated_at)], 0).unwrap();
         }
+        // 保留时间窗口从 250 开始：token 1 的 100、200 被删除，token 12 的样本不受影响
+        database.append_nav_history(&[record(1, 400)], 250).unwrap();
 
-        Ok(positions)
+        let history = database.get_nav_history(user, U256::from(1)).unwrap();
+        assert_eq!(history.iter().map(|sample| sample.timestamp).collect::<Vec<_>>(), vec![300, 400]);
+        assert_eq!(history[1], NavSample { timestamp: 400, net_nav: U256::from(400), gross_nav: U256::from(800) });
+        assert_eq!(database.get_nav_history(user, U256::from(12)).unwrap().len(), 3);
+
+        database.delete_nav_history(user, U256::from(12)).unwrap();
+        assert!(database.get_nav_history(user, U256::from(12)).unwrap().is_empty());
+        assert_eq!(database.get_nav_history(user, U256::from(1)).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn database_path_must_be_a_writable_directory() {
+        let dir = std::env::temp_dir().join(format!("keeper_path_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let nested = dir.join("nested");
+        assert!(ensure_writable(nested.to_str().unwrap()).is_ok());
+        assert!(nested.is_dir());
+
+        // 路径被普通文件占用时无法作为数据库目录
+        let file = dir.join("occupied");
+        std::fs::write(&file, b"").unwrap();
+        assert!(ensure_writable(file.to_str().unwrap()).is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn recent_block_timestamps_are_served_from_memory() {
+        let database = Database::open_temp();
+        database.cache_block_timestamp(100, 1_700_000_000).unwrap();
+
+        // 删除 RocksDB 中的记录后仍能从内存 LRU 读到
+        database.db.delete_cf(database.cf(BLOCK_TIMESTAMPS_CF).unwrap(), 100u64.to_be_bytes()).unwrap();
+        assert_eq!(database.get_block_timestamp(100).unwrap(), Some(1_700_000_000));
+
+        // 超出容量时淘汰最久未使用的区块
+        let capacity = DatabaseConfig::default().block_timestamp_memory_cache_size as u64;
+        for block_number in 0..capacity {
+            database.cache_block_timestamp(1_000 + block_number, block_number).unwrap();
+        }
+        assert_eq!(database.get_block_timestamp(100).unwrap(), None);
+        database.db.delete_cf(database.cf(BLOCK_TIMESTAMPS_CF).unwrap(), 1_000u64.to_be_bytes()).unwrap();
+        assert_eq!(database.get_block_timestamp(1_000).unwrap(), Some(0));
+    }
+
+    #[test]
+    fn liquidation_in_flight_is_persisted_until_cleared() {
+        let database = Database::open_temp();
+        let user = Address::repeat_byte(0x22);
+        let token_id = U256::from(3u64);
+
+        assert_eq!(database.get_liquidation_in_flight(user, token_id).unwrap(), None);
+
+        database.set_liquidation_in_flight(user, token_id, 1_700_000_000).unwrap();
+        assert_eq!(database.get_liquidation_in_flight(user, token_id).unwrap(), Some(1_700_000_000));
+        assert_eq!(database.get_liquidation_in_flight(user, U256::from(4u64)).unwrap(), None);
+
+        database.clear_liquidation_in_flight(user, token_id).unwrap();
+        assert_eq!(database.get_liquidation_in_flight(user, token_id).unwrap(), None);
+    }
+
+    #[test]
+    fn legacy_default_cf_keys_are_migrated() {
+        let database = Database::open_temp();
+        let position = UserPosition {
+            user: Address::repeat_byte(0x11),
+            token_id: U256::from(7u64),
+            amount: U256::from(100u64),
+            timestamp: 1,
+            total_interest: U256::zero(),
+            leverage: LeverageType::Moderate,
+            mint_price: U256::from(2u64),
+            last_applied_log: None,
+            epoch: 0,
+        };
+
+        // 模拟旧版本写入默认列族的数据
+        let meta = database.cf(META_CF).unwrap();
+        database.db.delete_cf(meta, CF_LAYOUT_VERSION_KEY).unwrap();
+        let legacy_key = format!("position_{}_{}", position.user, position.token_id);
+        database.db.put(legacy_key.as_bytes(), serde_json::to_vec(&position).unwrap()).unwrap();
+        database.db.put(b"block_timestamp_42", serde_json::to_vec(&1000u64).unwrap()).unwrap();
+        database.db.put(b"last_synced_block", serde_json::to_vec(&42u64).unwrap()).unwrap();
+
+        database.migrate().unwrap();
+
+        let migrated = database.get_user_position(position.user, position.token_id).unwrap().unwrap();
+        assert_eq!(migrated.amount, position.amount);
+        assert_eq!(database.get_user_positions(position.user).unwrap().len(), 1);
+        assert_eq!(database.get_block_timestamp(42).unwrap(), Some(1000));
+        assert_eq!(database.get_last_synced_block().unwrap(), Some(42));
+        assert!(database.db.get(legacy_key.as_bytes()).unwrap().is_none());
+        assert_eq!(database.get_positions_by_token(position.token_id).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn token_index_tracks_position_writes() {
+        let database = Database::open_temp();
+        let position = |user: u8, token_id: u64| UserPosition {
+            user: Address::repeat_byte(user),
+            token_id: U256::from(token_id),
+            amount: U256::from(100u64),
+            timestamp: 1,
+            total_interest: U256::zero(),
+            leverage: LeverageType::Conservative,
+            mint_price: U256::from(2u64),
+            last_applied_log: None,
+            epoch: 0,
+        };
+
+        database.store_user_position(&position(0x01, 1)).unwrap();
+        database.store_user_position(&position(0x02, 1)).unwrap();
+        database.store_user_position(&position(0x01, 12)).unwrap();
+
+        assert_eq!(database.get_positions_by_token(U256::from(1u64)).unwrap().len(), 2);
+        assert_eq!(database.get_positions_by_token(U256::from(12u64)).unwrap().len(), 1);
+
+        database.delete_user_position(Address::repeat_byte(0x02), U256::from(1u64)).unwrap();
+        let remaining = database.get_positions_by_token(U256::from(1u64)).unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].user, Address::repeat_byte(0x01));
+    }
+
+    #[test]
+    fn position_count_ignores_overwrites_and_missing_deletes() {
+        let database = Database::open_temp();
+        let mut position = UserPosition {
+            user: Address::repeat_byte(0x01),
+            token_id: U256::from(1u64),
+            amount: U256::from(100u64),
+            timestamp: 1,
+            total_interest: U256::zero(),
+            leverage: LeverageType::Conservative,
+            mint_price: U256::from(2u64),
+            last_applied_log: None,
+            epoch: 0,
+        };
+
+        database.store_user_position(&position).unwrap();
+        position.amount = U256::from(50u64);
+        database.store_user_position(&position).unwrap();
+        assert_eq!(database.get_position_count(), 1);
+
+        database.delete_user_position(position.user, U256::from(2u64)).unwrap();
+        assert_eq!(database.get_position_count(), 1);
+        database.delete_user_position(position.user, position.token_id).unwrap();
+        assert_eq!(database.get_position_count(), 0);
+    }
+
+    #[test]
+    fn exported_state_imports_into_a_fresh_database() {
+        let source = Database::open_temp();
+        let position = UserPosition {
+            user: Address::repeat_byte(0x11),
+            token_id: U256::from(7u64),
+            amount: U256::from(100u64),
+            timestamp: 1,
+            total_interest: U256::from(3u64),
+            leverage: LeverageType::Aggressive,
+            mint_price: U256::from(2u64),
+            last_applied_log: Some(LogPosition { block_number: 40, log_index: 2 }),
+            epoch: 0,
+        };
+        source.store_user_position(&position).unwrap();
+        source.store_auction(&AuctionInfo {
+            auction_id: U256::from(5u64),
+            starting_price: U256::from(1_000u64),
+            underlying_amount: U256::from(100u64),
+            original_owner: position.user,
+            token_id: position.token_id,
+            triggerer: Address::repeat_byte(0x22),
+            reward_amount: U256::from(10u64),
+            start_time: 1_700_000_000,
+            epoch: 0,
+        }).unwrap();
+        source.update_penalty(U256::from(42u64)).unwrap();
+        source.set_last_synced_block(42, None).unwrap();
+
+        let json = serde_json::to_vec(&source.export_state().unwrap()).unwrap();
+        let state: DatabaseExport = serde_json::from_slice(&json).unwrap();
+
+        let target = Database::open_temp();
+        target.import_state(&state).unwrap();
+        let imported = target.get_user_position(position.user, position.token_id).unwrap().unwrap();
+        assert_eq!(imported.total_interest, position.total_interest);
+        assert_eq!(imported.last_applied_log, position.last_applied_log);
+        assert_eq!(target.get_positions_by_token(position.token_id).unwrap().len(), 1);
+        assert!(target.auction_exists(U256::from(5u64)).unwrap());
+        assert_eq!(target.get_system_params().unwrap().penalty, U256::from(42u64));
+        assert_eq!(target.get_last_synced_block().unwrap(), Some(42));
+
+        // 已有数据的数据库拒绝导入
+        assert!(target.import_state(&state).is_err());
+    }
+
+    #[test]
+    fn sync_checkpoint_keeps_hash_history_and_reads_legacy_numbers() {
+        let database = Database::open_temp();
+        database.db.put_cf(database.cf(META_CF).unwrap(), LAST_SYNCED_BLOCK_KEY, serde_json::to_vec(&7u64).unwrap()).unwrap();
+        assert_eq!(database.get_last_synced_block().unwrap(), Some(7));
+        assert_eq!(database.get_sync_checkpoint().unwrap().unwrap().block_hash, None);
+
+        let hash = |n: u8| Some(H256::repeat_byte(n));
+        database.set_last_synced_block(10, hash(10)).unwrap();
+        database.set_last_synced_block(20, None).unwrap();
+        database.set_last_synced_block(30, hash(30)).unwrap();
+        let checkpoint = database.get_sync_checkpoint().unwrap().unwrap();
+        assert_eq!(checkpoint.block_hash, hash(30));
+        assert_eq!(checkpoint.history, vec![(10, H256::repeat_byte(10))]);
+
+        // 回退到更早的区块时丢弃之后的历史
+        database.set_last_synced_block(5, hash(5)).unwrap();
+        assert!(database.get_sync_checkpoint().unwrap().unwrap().history.is_empty());
+
+        for block_number in 100..200 {
+            database.set_last_synced_block(block_number, Some(H256::from_low_u64_be(block_number))).unwrap();
+        }
+        let checkpoint = database.get_sync_checkpoint().unwrap().unwrap();
+        assert_eq!(checkpoint.history.len(), SYNC_CHECKPOINT_HISTORY);
+        assert_eq!(checkpoint.history.last().unwrap().0, 198);
     }
 }
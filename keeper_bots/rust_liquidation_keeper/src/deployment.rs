@@ -0,0 +1,189 @@
+//! 合约部署管理模块
+//!
+//! 数据库记录了其中状态所对应的合约地址（`ContractDeployment`）。受管合约重新部署后配置中的地址随之变化，
+//! 但旧部署的持仓和拍卖对新合约不再有效（例如旧的拍卖ID在新的 AuctionManager 上不存在），混用会导致错误的重置或清算：
+//! - 启动时比较配置与记录的地址，不一致时拒绝启动，提示运行 `migrate-contracts`
+//! - `migrate-contracts` 把受影响的记录归档到 JSON 文件后从数据库移除，并把部署纪元加一；
+//!   同步进度退回新合约的部署区块之前，参数类事件的已应用记录清除，新合约的事件从部署区块开始同步
+//!
+//! 受影响的记录按变更的合约决定：AuctionManager 变更时归档所有拍卖，InterestManager 或 CustodianFixed
+//! 变更时归档所有持仓；LiquidationManager 没有按其地址存储的状态，只更新记录。
+//! 数据库第一次检查时还没有记录，视为与当前配置一致（纪元 0）。
+
+use web3::types::Address;
+use crate::config::ContractAddresses;
+use crate::database::{ContractDeployment, Database, DatabaseExport};
+use crate::error::{KeeperError, KeeperResult};
+
+/// 一次合约迁移：变更的合约、迁移后的部署记录，以及需要归档并移除的记录
+#[derive(Debug)]
+pub struct MigrationPlan {
+    /// (合约名, 记录的地址, 配置的地址)
+    pub changes: Vec<(&'static str, Address, Address)>,
+    pub next: ContractDeployment,
+    pub archive: DatabaseExport,
+}
+
+/// 按配置的合约地址生成部署记录
+pub fn deployment_from_config(contracts: &ContractAddresses, epoch: u64) -> KeeperResult<ContractDeployment> {
+    let parse = |name: &str, address: &str| address.parse::<Address>()
+        .map_err(|e| KeeperError::Config(format!("无效的 {} 地址 '{}': {}", name, address, e)));
+    Ok(ContractDeployment {
+        epoch,
+        custodian: parse("custodian", &contracts.custodian)?,
+        liquidation_manager: parse("liquidation_manager", &contracts.liquidation_manager)?,
+        auction_manager: parse("auction_manager", &contracts.auction_manager)?,
+        interest_manager: parse("interest_manager", &contracts.interest_manager)?,
+    })
+}
+
+/// 地址发生变化的合约：(合约名, 记录的地址, 配置的地址)
+pub fn changed_contracts(recorded: &ContractDeployment, configured: &ContractDeployment) -> Vec<(&'static str, Address, Address)> {
+    [
+        ("custodian", recorded.custodian, configured.custodian),
+        ("liquidation_manager", recorded.liquidation_manager, configured.liquidation_manager),
+        ("auction_manager", recorded.auction_manager, configured.auction_manager),
+        ("interest_manager", recorded.interest_manager, configured.interest_manager),
+    ]
+    .into_iter()
+    .filter(|(_, old, new)| old != new)
+    .collect()
+}
+
+/// 读取记录的部署；还没有记录时按当前配置记录为纪元 0
+fn recorded_deployment(database: &Database, contracts: &ContractAddresses) -> KeeperResult<ContractDeployment> {
+    if let Some(deployment) = database.get_contract_deployment()? {
+        return Ok(deployment);
+    }
+    let deployment = deployment_from_config(contracts, 0)?;
+    database.set_contract_deployment(&deployment)?;
+    Ok(deployment)
+}
+
+/// 启动前检查配置的合约地址与数据库记录一致，不一致时返回配置错误
+pub fn ensure_deployment_matches(database: &Database, contracts: &ContractAddresses) -> KeeperResult<()> {
+    let recorded = recorded_deployment(database, contracts)?;
+    let changes = changed_contracts(&recorded, &deployment_from_config(contracts, recorded.epoch)?);
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    let details: Vec<String> = changes.iter()
+        .map(|(name, old, new)| format!("{}: {:?} -> {:?}", name, old, new))
+        .collect();
+    Err(KeeperError::Config(format!(
+        "合约地址与数据库记录的部署（纪元 {}）不一致: {}；确认合约已重新部署后运行 migrate-contracts --archive <path> --from <新合约部署区块> 归档旧部署的状态",
+        recorded.epoch, details.join(", ")
+    )))
+}
+
+/// 生成迁移计划；配置与记录一致时返回 None
+pub fn plan_migration(database: &Database, contracts: &ContractAddresses) -> KeeperResult<Option<MigrationPlan>> {
+    let recorded = recorded_deployment(database, contracts)?;
+    let next = deployment_from_config(contracts, recorded.epoch + 1)?;
+    let changes = changed_contracts(&recorded, &next);
+    if changes.is_empty() {
+        return Ok(None);
+    }
+
+    let retire_positions = changes.iter().any(|(name, _, _)| matches!(*name, "custodian" | "interest_manager"));
+    let retire_auctions = changes.iter().any(|(name, _, _)| *name == "auction_manager");
+
+    let mut archive = database.export_state()?;
+    if !retire_positions {
+        archive.positions.clear();
+    }
+    if !retire_auctions {
+        archive.auctions.clear();
+    }
+
+    Ok(Some(MigrationPlan { changes, next, archive }))
+}
+
+/// 执行迁移：移除归档的记录并写入新的部署记录（调用方需先保存 `plan.archive`）
+///
+/// 同步进度已超过新合约的部署区块 `from_block` 时退回到其前一个区块，否则已同步区块中新合约的事件会被跳过；
+/// 旧合约的参数事件记录的已应用位置同样清除。
+pub fn apply_migration(database: &Database, plan: &MigrationPlan, from_block: u64) -> KeeperResult<()> {
+    for position in &plan.archive.positions {
+        database.delete_user_position(position.user, position.token_id)?;
+    }
+    for auction in &plan.archive.auctions {
+        database.delete_auction(auction.auction_id)?;
+    }
+    if database.get_last_synced_block()?.is_some_and(|last_synced_block| last_synced_block >= from_block) {
+        database.set_last_synced_block(from_block.saturating_sub(1), None)?;
+    }
+    database.clear_params_applied_log()?;
+    database.set_contract_deployment(&plan.next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use web3::types::U256;
+    use crate::database::{AuctionInfo, LeverageType, LogPosition, UserPosition};
+
+    fn contracts(auction_manager: u8) -> ContractAddresses {
+        let address = |byte: u8| format!("{:?}", Address::repeat_byte(byte));
+        ContractAddresses {
+            custodian: address(0x01),
+            liquidation_manager: address(0x02),
+            auction_manager: address(auction_manager),
+            interest_manager: address(0x04),
+            token: address(0x05),
+            oracle: address(0x06),
+        }
+    }
+
+    #[test]
+    fn redeployed_auction_manager_requires_migration() {
+        let database = Database::open_temp();
+        ensure_deployment_matches(&database, &contracts(0x03)).unwrap();
+        assert_eq!(database.contract_epoch(), 0);
+
+        database.store_user_position(&UserPosition {
+            user: Address::repeat_byte(0x11),
+            token_id: U256::from(7),
+            amount: U256::from(100),
+            timestamp: 1,
+            total_interest: U256::zero(),
+            leverage: LeverageType::Moderate,
+            mint_price: U256::exp10(18),
+            last_applied_log: None,
+            epoch: 0,
+        }).unwrap();
+        database.store_auction(&AuctionInfo {
+            auction_id: U256::from(5),
+            starting_price: U256::from(1_000),
+            underlying_amount: U256::from(100),
+            original_owner: Address::repeat_byte(0x11),
+            token_id: U256::from(7),
+            triggerer: Address::repeat_byte(0x22),
+            reward_amount: U256::from(10),
+            start_time: 1,
+            epoch: 0,
+        }).unwrap();
+
+        let redeployed = contracts(0x33);
+        let message = ensure_deployment_matches(&database, &redeployed).unwrap_err().to_string();
+        assert!(message.contains("auction_manager"));
+
+        // 只有拍卖属于旧的 AuctionManager，持仓保留
+        let plan = plan_migration(&database, &redeployed).unwrap().unwrap();
+        assert_eq!(plan.archive.auctions.len(), 1);
+        assert!(plan.archive.positions.is_empty());
+
+        database.set_last_synced_block(500, None).unwrap();
+        database.mark_params_log_applied(LogPosition { block_number: 480, log_index: 0 }).unwrap();
+        apply_migration(&database, &plan, 400).unwrap();
+        assert!(!database.auction_exists(U256::from(5)).unwrap());
+        // 从新合约的部署区块重新同步，旧合约的参数事件位置不再跳过新合约的事件
+        assert_eq!(database.get_last_synced_block().unwrap(), Some(399));
+        assert_eq!(database.get_system_params().unwrap().last_applied_log, None);
+        assert_eq!(database.get_position_count(), 1);
+        assert_eq!(database.contract_epoch(), 1);
+        ensure_deployment_matches(&database, &redeployed).unwrap();
+        assert!(plan_migration(&database, &redeployed).unwrap().is_none());
+    }
+}
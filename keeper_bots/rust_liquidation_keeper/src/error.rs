@@ -0,0 +1,68 @@
+//! 错误类型模块
+//!
+//! 核心模块（数据库、链上读取、配置）返回结构化的 `KeeperError`，调用方可以按错误类别分别处理：
+//! RPC 临时错误可以重试，解码错误和配置错误应立即失败。
+//! 各监控任务的主循环仍使用 `anyhow::Result` 汇总错误，`KeeperError` 可以直接通过 `?` 转换。
+
+/// Keeper 错误类型
+#[derive(Debug, thiserror::Error)]
+pub enum KeeperError {
+    /// 节点 RPC 调用失败（超时、限流、连接断开等，可能是临时错误）
+    #[error("RPC调用失败: {0}")]
+    Rpc(#[from] web3::Error),
+    /// ABI 或存储数据解码失败（确定性错误，重试不会成功）
+    #[error("解码失败: {0}")]
+    Decode(String),
+    /// RocksDB 读写失败或数据库目录不可用
+    #[error("数据库错误: {0}")]
+    Database(String),
+    /// 配置无效
+    #[error("配置错误: {0}")]
+    Config(String),
+    /// 合约返回了不符合预期的结果（如报价无效、返回数量不匹配）
+    #[error("合约调用结果异常: {0}")]
+    Contract(String),
+}
+
+pub type KeeperResult<T> = Result<T, KeeperError>;
+
+impl KeeperError {
+    /// 是否为值得重试的临时错误（只有 RPC 临时错误会重试）
+    pub fn is_transient(&self) -> bool {
+        match self {
+            KeeperError::Rpc(e) => crate::rpc::is_transient_error(e),
+            _ => false,
+        }
+    }
+}
+
+impl From<rocksdb::Error> for KeeperError {
+    fn from(e: rocksdb::Error) -> Self {
+        KeeperError::Database(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for KeeperError {
+    fn from(e: serde_json::Error) -> Self {
+        KeeperError::Decode(e.to_string())
+    }
+}
+
+impl From<web3::ethabi::Error> for KeeperError {
+    fn from(e: web3::ethabi::Error) -> Self {
+        KeeperError::Decode(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_transient_rpc_errors_are_retryable() {
+        assert!(KeeperError::Rpc(web3::Error::Unreachable).is_transient());
+        assert!(!KeeperError::Rpc(web3::Error::Decoder("bad abi".to_string())).is_transient());
+        assert!(!KeeperError::Decode("bad abi".to_string()).is_transient());
+        assert!(!KeeperError::Database("io".to_string()).is_transient());
+    }
+}
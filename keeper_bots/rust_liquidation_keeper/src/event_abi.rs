@@ -0,0 +1,259 @@
+//! 监控事件的 ABI 定义模块
+//!
+//! 每个事件按合约源码中的声明只定义一次，事件签名（topic0）和日志解码都由这里的定义生成：
+//! indexed 参数从 topics 解码，其余参数从 data 按 ABI 编码解码。
+//! 签名、topic 数量或数据布局与定义不一致时返回解码错误，而不是按固定偏移读出错误的数值。
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use web3::ethabi::{Event, EventParam, ParamType, RawLog, Token};
+use web3::types::{Address, H256, U256};
+use crate::error::{KeeperError, KeeperResult};
+
+/// 所有监控事件的定义（事件名 -> 定义）
+fn events() -> &'static HashMap<String, Event> {
+    static EVENTS: OnceLock<HashMap<String, Event>> = OnceLock::new();
+    EVENTS.get_or_init(|| {
+        let address = || ParamType::Address;
+        let uint = || ParamType::Uint(256);
+        // Solidity 枚举在 ABI 中按 uint8 编码
+        let leverage = || ParamType::Uint(8);
+
+        [
+            // InterestManager
+            define_event("InterestRateChanged", &[("oldRate", uint(), false), ("newRate", uint(), false)]),
+            define_event("PositionIncreased", &[
+                ("user", address(), true),
+                ("tokenId", uint(), true),
+                ("amount", uint(), false),
+                ("totalAmount", uint(), false),
+                ("totalInterest", uint(), false),
+            ]),
+            define_event("InterestCollected", &[
+                ("user", address(), true),
+                ("tokenId", uint(), true),
+                ("deductLAmountInWei", uint(), false),
+                ("interestAmount", uint(), false),
+            ]),
+            // CustodianFixed
+            define_event("Mint", &[
+                ("user", address(), true),
+                ("tokenId", uint(), false),
+                ("underlyingAmountInWei", uint(), false),
+                ("leverageLevel", leverage(), false),
+                ("mintPriceInWei", uint(), false),
+                ("sAmountInWei", uint(), false),
+                ("lAmountInWei", uint(), false),
+            ]),
+            // LiquidationManager 和 AuctionManager 的 ParameterChanged 声明相同
+            define_event("ParameterChanged", &[("parameter", ParamType::FixedBytes(32), true), ("value", uint(), false)]),
+            // LiquidationManager
+            define_event("LiquidationConfigInfo", &[
+                ("adjustmentThreshold", uint(), false),
+                ("liquidationThreshold", uint(), false),
+                ("penalty", uint(), false),
+                ("enabled", ParamType::Bool, false),
+            ]),
+            define_event("NetValueAdjusted", &[
+                ("user", address(), true),
+                ("fromTokenId", uint(), true),
+                ("toTokenId", uint(), true),
+                ("leverage", leverage(), false),
+                ("newMintPrice", uint(), false),
+                ("adjustAmountInWei", uint(), false),
+                ("underlyingAmountInWei", uint(), false),
+            ]),
+            // AuctionManager
+            define_event("AuctionStarted", &[
+                ("auctionId", uint(), true),
+                ("startingPrice", uint(), false),
+                ("underlyinglAmount", uint(), false),
+                ("originalOwner", address(), false),
+                ("tokenId", uint(), true),
+                ("triggerer", address(), true),
+                ("rewardAmount", uint(), false),
+            ]),
+            define_event("AuctionReset", &[
+                ("auctionId", uint(), true),
+                ("newStartingPrice", uint(), false),
+                ("underlyingAmount", uint(), false),
+                ("originalOwner", address(), false),
+                ("tokenId", uint(), true),
+                ("triggerer", address(), true),
+                ("rewardAmount", uint(), false),
+            ]),
+            define_event("AuctionRemoved", &[("auctionId", uint(), true)]),
+            // 预言机价格推送：Chainlink 聚合器的 AnswerUpdated，以及 LTCPriceOracle 的 PriceUpdated
+            define_event("AnswerUpdated", &[
+                ("current", ParamType::Int(256), true),
+                ("roundId", uint(), true),
+                ("updatedAt", uint(), false),
+            ]),
+            define_event("PriceUpdated", &[
+                ("roundId", ParamType::Uint(80), true),
+                ("price", ParamType::Int(256), false),
+                ("timestamp", uint(), false),
+                ("updater", address(), true),
+            ]),
+        ]
+        .into_iter()
+        .map(|event| (event.name.clone(), event))
+        .collect()
+    })
+}
+
+fn define_event(name: &str, inputs: &[(&str, ParamType, bool)]) -> Event {
+    Event {
+        name: name.to_string(),
+        inputs: inputs.iter()
+            .map(|(name, kind, indexed)| EventParam { name: name.to_string(), kind: kind.clone(), indexed: *indexed })
+            .collect(),
+        anonymous: false,
+    }
+}
+
+/// 按事件名查找定义
+pub fn event(name: &str) -> KeeperResult<&'static Event> {
+    events().get(name).ok_or_else(|| KeeperError::Decode(format!("未定义的事件: {}", name)))
+}
+
+/// 事件签名（topic0）
+pub fn signature(name: &str) -> KeeperResult<H256> {
+    Ok(event(name)?.signature())
+}
+
+/// 解码后的事件参数（参数名 -> 值）
+#[derive(Debug)]
+pub struct DecodedEvent {
+    name: &'static str,
+    params: HashMap<String, Token>,
+}
+
+impl DecodedEvent {
+    fn param(&self, param: &str) -> KeeperResult<&Token> {
+        self.params.get(param)
+            .ok_or_else(|| KeeperError::Decode(format!("{} 事件缺少参数 {}", self.name, param)))
+    }
+
+    fn mismatch(&self, param: &str, expected: &str) -> KeeperError {
+        KeeperError::Decode(format!("{} 事件参数 {} 不是 {}", self.name, param, expected))
+    }
+
+    pub fn uint(&self, param: &str) -> KeeperResult<U256> {
+        match self.param(param)? {
+            Token::Uint(value) => Ok(*value),
+            _ => Err(self.mismatch(param, "uint")),
+        }
+    }
+
+    /// uint8 参数（如杠杆枚举），超出范围时返回解码错误
+    pub fn uint8(&self, param: &str) -> KeeperResult<u8> {
+        let value = self.uint(param)?;
+        if value > U256::from(u8::MAX) {
+            return Err(KeeperError::Decode(format!("{} 事件参数 {} 超出 uint8 范围: {}", self.name, param, value)));
+        }
+        Ok(value.low_u32() as u8)
+    }
+
+    /// int256 参数的补码表示，由调用方判断符号
+    pub fn int(&self, param: &str) -> KeeperResult<U256> {
+        match self.param(param)? {
+            Token::Int(value) => Ok(*value),
+            _ => Err(self.mismatch(param, "int")),
+        }
+    }
+
+    pub fn address(&self, param: &str) -> KeeperResult<Address> {
+        match self.param(param)? {
+            Token::Address(value) => Ok(*value),
+            _ => Err(self.mismatch(param, "address")),
+        }
+    }
+
+    pub fn bool(&self, param: &str) -> KeeperResult<bool> {
+        match self.param(param)? {
+            Token::Bool(value) => Ok(*value),
+            _ => Err(self.mismatch(param, "bool")),
+        }
+    }
+
+    pub fn fixed_bytes(&self, param: &str) -> KeeperResult<&[u8]> {
+        match self.param(param)? {
+            Token::FixedBytes(value) => Ok(value),
+            _ => Err(self.mismatch(param, "bytes32")),
+        }
+    }
+}
+
+/// 按事件定义解码日志：校验 topic0 与签名一致，indexed 参数从 topics 解码，其余参数从 data 解码
+pub fn decode_log(name: &str, log: &web3::types::Log) -> KeeperResult<DecodedEvent> {
+    let event = event(name)?;
+    let parsed = event.parse_log(RawLog { topics: log.topics.clone(), data: log.data.0.clone() })
+        .map_err(|e| KeeperError::Decode(format!(
+            "{} 事件解码失败 (区块 {:?}, 日志 {:?}): {}", name, log.block_number, log.log_index, e
+        )))?;
+
+    Ok(DecodedEvent {
+        name: event.name.as_str(),
+        params: parsed.params.into_iter().map(|param| (param.name, param.value)).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use web3::types::Bytes;
+
+    fn log(topics: Vec<H256>, data: Vec<u8>) -> web3::types::Log {
+        web3::types::Log {
+            address: Address::zero(),
+            topics,
+            data: Bytes(data),
+            block_hash: None,
+            block_number: None,
+            transaction_hash: None,
+            transaction_index: None,
+            log_index: None,
+            transaction_log_index: None,
+            log_type: None,
+            removed: None,
+        }
+    }
+
+    #[test]
+    fn signatures_match_contract_declarations() {
+        let expected = [
+            ("InterestRateChanged", "InterestRateChanged(uint256,uint256)"),
+            ("PositionIncreased", "PositionIncreased(address,uint256,uint256,uint256,uint256)"),
+            ("Mint", "Mint(address,uint256,uint256,uint8,uint256,uint256,uint256)"),
+            ("ParameterChanged", "ParameterChanged(bytes32,uint256)"),
+            ("NetValueAdjusted", "NetValueAdjusted(address,uint256,uint256,uint8,uint256,uint256,uint256)"),
+            ("AuctionStarted", "AuctionStarted(uint256,uint256,uint256,address,uint256,address,uint256)"),
+            ("AuctionRemoved", "AuctionRemoved(uint256)"),
+            ("AnswerUpdated", "AnswerUpdated(int256,uint256,uint256)"),
+            ("PriceUpdated", "PriceUpdated(uint80,int256,uint256,address)"),
+        ];
+        for (name, declaration) in expected {
+            assert_eq!(signature(name).unwrap(), H256::from(web3::signing::keccak256(declaration.as_bytes())), "{}", name);
+        }
+    }
+
+    #[test]
+    fn mismatched_signature_or_layout_is_a_decode_error() {
+        let collected = signature("InterestCollected").unwrap();
+        let user = H256::from(Address::repeat_byte(0x11));
+        let token_id = H256::from_low_u64_be(3);
+        let data = web3::ethabi::encode(&[Token::Uint(U256::from(30u64)), Token::Uint(U256::from(4u64))]);
+
+        let decoded = decode_log("InterestCollected", &log(vec![collected, user, token_id], data.clone())).unwrap();
+        assert_eq!(decoded.address("user").unwrap(), Address::repeat_byte(0x11));
+        assert_eq!(decoded.uint("tokenId").unwrap(), U256::from(3u64));
+        assert_eq!(decoded.uint("deductLAmountInWei").unwrap(), U256::from(30u64));
+        assert!(decoded.address("interestAmount").is_err());
+
+        // 签名不一致、缺少 indexed 参数、数据过短都必须报错
+        assert!(decode_log("PositionIncreased", &log(vec![collected, user, token_id], data.clone())).is_err());
+        assert!(decode_log("InterestCollected", &log(vec![collected, user], data.clone())).is_err());
+        assert!(decode_log("InterestCollected", &log(vec![collected, user, token_id], data[..40].to_vec())).is_err());
+    }
+}
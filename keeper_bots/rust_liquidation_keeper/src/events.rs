@@ -3,18 +3,42 @@
 //! 负责监听三个主要合约的事件：InterestManager、LiquidationManager、AuctionManager。
 //!
 //! ## 特性概述：
-//! - 支持实时监听（WebSocket）和轮询两种模式
+//! - 支持实时监听（WebSocket）和轮询两种模式，实时模式可订阅区块头或直接订阅合约日志
 //! - 事件去重机制防止重复处理
+//! - 实时模式下检测链重组，按分叉点的链上状态重新同步受影响的持仓和拍卖后重放规范链区块；
+//!   重启时校验最后同步区块的哈希，停机期间的重组同样回滚重放
 //! - 预计算事件签名提升性能
 //! - 内存缓存管理防止内存泄漏
 //! - 细粒度的事件处理和参数更新逻辑
 
 use std::sync::Arc;
-use std::collections::{HashMap, HashSet};
-use web3::types::{Address, BlockNumber, FilterBuilder, H256, U64};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use web3::api::SubscriptionStream;
+use web3::types::{Address, BlockHeader, BlockId, BlockNumber, Filter, FilterBuilder, H256, U64};
 use futures_util::StreamExt;
-use crate::database::{Database, AuctionInfo, UserPosition, LeverageType};
+use tokio_util::sync::CancellationToken;
+use crate::config::RealtimeSubscription;
+use crate::event_abi;
+use crate::database::{Database, AuctionInfo, BlockTimeAnchor, LogPosition, ProcessedEventsRecord, SyncCheckpoint, UserPosition, LeverageType};
 use crate::reset::AuctionResetMonitor;
+use crate::tx::TransactionSender;
+use crate::metrics::metrics;
+use crate::notify::Notifier;
+use crate::rpc::rpc_retry;
+use crate::failover::FailoverTransport;
+use crate::status::KeeperStatus;
+
+/// 常规事件处理（持仓、拍卖、参数存储）成功的日志：默认 debug 级别，
+/// 开启 `event_monitoring.verbose_event_logging` 时提升到 info。清算和拍卖重置相关的日志始终为 info。
+macro_rules! routine_log {
+    ($monitor:expr, $($arg:tt)+) => {
+        if $monitor.config.event_monitoring.verbose_event_logging {
+            tracing::info!($($arg)+)
+        } else {
+            tracing::debug!($($arg)+)
+        }
+    };
+}
 
 /// 事件唯一标识符 - 用于去重
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
@@ -24,6 +48,365 @@ struct EventId {
     log_index: usize,
 }
 
+impl EventId {
+    fn from_log(log: &web3::types::Log) -> Self {
+        Self {
+            block_number: log.block_number.unwrap_or_default().as_u64(),
+            transaction_index: log.transaction_index.unwrap_or_default().as_usize(),
+            log_index: log.log_index.unwrap_or_default().as_usize(),
+        }
+    }
+}
+
+/// 按区块分组的事件去重窗口
+///
+/// 只有重新查询某个区块时才会再次收到其日志，因此只需记住最近 `window_blocks` 个区块内已处理的事件，
+/// 更早的区块整块淘汰，不需要按时间戳估算或打分。
+#[derive(Debug)]
+struct ProcessedEventWindow {
+    blocks: BTreeMap<u64, HashSet<EventId>>,
+    window_blocks: u64,
+    len: usize,
+}
+
+impl ProcessedEventWindow {
+    fn new(window_blocks: u64) -> Self {
+        Self {
+            blocks: BTreeMap::new(),
+            window_blocks,
+            len: 0,
+        }
+    }
+
+    fn contains(&self, event_id: &EventId) -> bool {
+        self.blocks.get(&event_id.block_number)
+            .is_some_and(|events| events.contains(event_id))
+    }
+
+    fn insert(&mut self, event_id: EventId) {
+        if self.blocks.entry(event_id.block_number).or_default().insert(event_id) {
+            self.len += 1;
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    /// 窗口中最新的区块号
+    fn latest_block(&self) -> Option<u64> {
+        self.blocks.last_key_value().map(|(block_number, _)| *block_number)
+    }
+
+    /// 指定区块中已处理事件的持久化记录
+    fn block_record(&self, block_number: u64) -> ProcessedEventsRecord {
+        let mut events: Vec<(u64, u64)> = self.blocks.get(&block_number)
+            .map(|events| events.iter()
+                .map(|event_id| (event_id.transaction_index as u64, event_id.log_index as u64))
+                .collect())
+            .unwrap_or_default();
+        events.sort_unstable();
+        ProcessedEventsRecord { block_number, events }
+    }
+
+    /// 载入持久化的区块记录
+    fn restore(&mut self, record: &ProcessedEventsRecord) {
+        for (transaction_index, log_index) in &record.events {
+            self.insert(EventId {
+                block_number: record.block_number,
+                transaction_index: *transaction_index as usize,
+                log_index: *log_index as usize,
+            });
+        }
+    }
+
+    /// 淘汰早于 `latest_block - window_blocks` 的区块，返回淘汰的事件数
+    fn prune(&mut self, latest_block: u64) -> usize {
+        let oldest_retained = latest_block.saturating_sub(self.window_blocks);
+        let mut evicted = 0;
+        while let Some(entry) = self.blocks.first_entry() {
+            if *entry.key() >= oldest_retained {
+                break;
+            }
+            evicted += entry.remove().len();
+        }
+        self.len -= evicted;
+        evicted
+    }
+
+    /// 移除指定区块及之后的事件（链重组或重复推送时），返回移除的事件数
+    fn evict_from(&mut self, block_number: u64) -> usize {
+        let evicted: usize = self.blocks.split_off(&block_number).values().map(HashSet::len).sum();
+        self.len -= evicted;
+        evicted
+    }
+}
+
+/// 用于链重组检测的最近区块哈希保留数量
+const REORG_TRACKING_DEPTH: usize = 128;
+
+/// 轮询模式下尝试升级回 WebSocket 的间隔（秒）
+const WS_UPGRADE_INTERVAL_SECS: u64 = 60;
+
+/// WebSocket 单次重连重试上限的退避时间（秒）
+const WS_RECONNECT_MAX_DELAY_SECS: u64 = 300;
+
+/// 计算第 attempt 次重连前的等待时间：基础时间按 2 的幂增长，再叠加最多 50% 的随机抖动
+pub fn reconnect_delay(base_delay_secs: u64, attempt: u32) -> std::time::Duration {
+    let exponent = attempt.saturating_sub(1).min(16);
+    let delay_ms = base_delay_secs
+        .saturating_mul(1u64 << exponent)
+        .min(WS_RECONNECT_MAX_DELAY_SECS)
+        .saturating_mul(1000);
+
+    // 没有引入随机数依赖，用当前时间的纳秒部分作为抖动来源
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_nanos() as u64;
+    let jitter_ms = if delay_ms > 0 { nanos % (delay_ms / 2 + 1) } else { 0 };
+
+    std::time::Duration::from_millis(delay_ms + jitter_ms)
+}
+
+/// 判断 eth_getLogs 错误是否为提供方的结果数量/区块范围限制
+fn is_log_limit_error(error: &web3::Error) -> bool {
+    let message = error.to_string().to_lowercase();
+    ["more than", "too many", "limit exceeded", "block range", "response size"]
+        .iter()
+        .any(|pattern| message.contains(pattern))
+}
+
+/// 根据 topic0 查找事件名称（用于指标标签）
+///
+/// LiquidationManager 和 AuctionManager 的 ParameterChanged 签名相同，统一记为 `ParameterChanged`。
+fn event_metric_label(event_signatures: &HashMap<String, H256>, log: &web3::types::Log) -> String {
+    let topic0 = match log.topics.first() {
+        Some(topic0) => topic0,
+        None => return "unknown".to_string(),
+    };
+
+    event_signatures.iter()
+        .find(|(_, signature)| *signature == topic0)
+        .map(|(name, _)| if name.ends_with("ParameterChanged") { "ParameterChanged".to_string() } else { name.clone() })
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// eth_getLogs 和日志订阅的 topic0 过滤条件：未禁用事件的签名（去重后排序）
+///
+/// 两个 ParameterChanged 签名相同，只要其中一个未禁用就保留该签名，另一个在分发时跳过。
+fn handled_topics(event_signatures: &HashMap<String, H256>, disabled_events: &[String]) -> Vec<H256> {
+    let mut topics: Vec<H256> = event_signatures.iter()
+        .filter(|(name, _)| !disabled_events.contains(name))
+        .map(|(_, signature)| *signature)
+        .collect();
+    topics.sort();
+    topics.dedup();
+    topics
+}
+
+/// 日志的 topic0 是否为已处理的事件
+///
+/// 查询已按 topic0 过滤，正常情况下总是 true；节点忽略 topics 过滤条件时用于跳过其他事件。
+fn is_handled_event(event_signatures: &HashMap<String, H256>, log: &web3::types::Log) -> bool {
+    log.topics.first().is_some_and(|topic0| event_signatures.values().any(|signature| signature == topic0))
+}
+
+/// 日志是否为配置禁用的事件（按 topic0 和发出日志的合约确定事件）
+fn is_disabled_event(config: &crate::config::AppConfig, event_signatures: &HashMap<String, H256>, log: &web3::types::Log) -> bool {
+    let Some(topic0) = log.topics.first() else {
+        return false;
+    };
+    MONITORED_EVENTS.iter().any(|(key, _, contract)| {
+        event_signatures.get(*key) == Some(topic0)
+            && contract.address(&config.contracts).parse::<Address>().is_ok_and(|address| address == log.address)
+            && config.event_monitoring.disabled_events.iter().any(|disabled| disabled == key)
+    })
+}
+
+/// 需要查询日志的合约地址：至少有一个事件未禁用的合约
+fn enabled_contract_addresses(config: &crate::config::AppConfig) -> anyhow::Result<Vec<Address>> {
+    let mut addresses = Vec::new();
+    for contract in [EventContract::InterestManager, EventContract::LiquidationManager, EventContract::AuctionManager, EventContract::Custodian] {
+        let enabled = MONITORED_EVENTS.iter()
+            .any(|(key, _, event_contract)| *event_contract == contract && !config.event_monitoring.disabled_events.iter().any(|disabled| disabled == key));
+        if enabled {
+            addresses.push(contract.address(&config.contracts).parse()?);
+        }
+    }
+    Ok(addresses)
+}
+
+/// 把 `[from_block, to_block]` 范围内的事件重放到 `database`（`verify` 子命令使用）
+///
+/// 按 `log_query_chunk_blocks` 分段查询，日志按链上顺序经 `dispatch_log_static` 处理，每段结束后推进最后同步区块号。
+/// 只写入 `database`：不创建交易发送器和拍卖重置监控器，重放的拍卖事件不会计划或发送任何重置交易。
+pub async fn replay_block_range(
+    web3: &web3::Web3<FailoverTransport>,
+    database: &Arc<Database>,
+    config: &crate::config::AppConfig,
+    from_block: u64,
+    to_block: u64,
+    shutdown: &CancellationToken,
+) -> anyhow::Result<()> {
+    let event_signatures = event_signature_map();
+    let addresses = enabled_contract_addresses(config)?;
+    let topics = handled_topics(&event_signatures, &config.event_monitoring.disabled_events);
+    let chunk_blocks = config.event_monitoring.log_query_chunk_blocks.max(1);
+
+    let mut current_block = from_block;
+    while current_block <= to_block {
+        if shutdown.is_cancelled() {
+            tracing::info!("收到关闭信号，重放在区块 {} 之前停止", current_block);
+            return Ok(());
+        }
+
+        let requested_end = current_block.saturating_add(chunk_blocks - 1).min(to_block);
+        let (mut logs, chunk_end) = EventMonitor::get_logs_chunk(web3, &addresses, &topics, current_block, requested_end).await?;
+        logs.sort_by_key(log_position);
+        for log in &logs {
+            EventMonitor::dispatch_log_static(database, config, &event_signatures, log).await;
+        }
+        database.set_last_synced_block(chunk_end, None)?;
+        tracing::debug!("已重放至区块 {}，本段 {} 个事件", chunk_end, logs.len());
+
+        current_block = chunk_end + 1;
+    }
+    Ok(())
+}
+
+/// 持仓类事件（PositionIncreased/InterestCollected/NetValueAdjusted/Mint）所属的 token_id，其他事件返回 None
+///
+/// 按 `event_abi` 中的事件定义解码（NetValueAdjusted 取调整后的 toTokenId），解码失败时返回 None，由事件处理报告错误。
+fn position_event_token_id(event_signatures: &HashMap<String, H256>, log: &web3::types::Log) -> Option<web3::types::U256> {
+    const POSITION_EVENTS: [(&str, &str); 4] = [
+        ("PositionIncreased", "tokenId"),
+        ("InterestCollected", "tokenId"),
+        ("NetValueAdjusted", "toTokenId"),
+        ("Mint", "tokenId"),
+    ];
+
+    let topic0 = log.topics.first()?;
+    let (event_name, field) = POSITION_EVENTS.into_iter().find(|(name, _)| event_signatures.get(*name) == Some(topic0))?;
+    event_abi::decode_log(event_name, log).ok()?.uint(field).ok()
+}
+
+/// 是否为未关注 token 的持仓事件（配置了 `watched_token_ids` 时不存储这些持仓）
+fn is_unwatched_position_event(config: &crate::config::AppConfig, event_signatures: &HashMap<String, H256>, log: &web3::types::Log) -> bool {
+    position_event_token_id(event_signatures, log)
+        .is_some_and(|token_id| !crate::config::is_token_watched(config.watched_token_ids.as_deref(), token_id))
+}
+
+/// 发出监控事件的合约
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EventContract {
+    InterestManager,
+    LiquidationManager,
+    AuctionManager,
+    Custodian,
+}
+
+impl EventContract {
+    /// 配置中的合约地址
+    fn address(self, contracts: &crate::config::ContractAddresses) -> &str {
+        match self {
+            EventContract::InterestManager => &contracts.interest_manager,
+            EventContract::LiquidationManager => &contracts.liquidation_manager,
+            EventContract::AuctionManager => &contracts.auction_manager,
+            EventContract::Custodian => &contracts.custodian,
+        }
+    }
+}
+
+/// 所有监控的事件：(事件名, `event_abi` 中的事件定义, 发出事件的合约)
+///
+/// LiquidationManager 和 AuctionManager 的 ParameterChanged 签名相同，分别以合约名前缀区分。
+const MONITORED_EVENTS: [(&str, &str, EventContract); 11] = [
+    ("InterestRateChanged", "InterestRateChanged", EventContract::InterestManager),
+    ("PositionIncreased", "PositionIncreased", EventContract::InterestManager),
+    ("InterestCollected", "InterestCollected", EventContract::InterestManager),
+    ("Mint", "Mint", EventContract::Custodian),
+    ("LiquidationParameterChanged", "ParameterChanged", EventContract::LiquidationManager),
+    ("LiquidationConfigInfo", "LiquidationConfigInfo", EventContract::LiquidationManager),
+    ("NetValueAdjusted", "NetValueAdjusted", EventContract::LiquidationManager),
+    ("AuctionParameterChanged", "ParameterChanged", EventContract::AuctionManager),
+    ("AuctionStarted", "AuctionStarted", EventContract::AuctionManager),
+    ("AuctionReset", "AuctionReset", EventContract::AuctionManager),
+    ("AuctionRemoved", "AuctionRemoved", EventContract::AuctionManager),
+];
+
+/// 所有监控事件的名称（`event_monitoring.disabled_events` 的可选值）
+pub fn monitored_event_names() -> Vec<&'static str> {
+    MONITORED_EVENTS.iter().map(|(key, _, _)| *key).collect()
+}
+
+/// 所有监控事件的签名（事件名 -> topic0），由 `event_abi` 中的事件定义生成
+fn event_signature_map() -> HashMap<String, H256> {
+    MONITORED_EVENTS
+    .into_iter()
+    .map(|(key, event_name, _)| {
+        let signature = event_abi::signature(event_name).expect("监控的事件都在 event_abi 中定义");
+        (key.to_string(), signature)
+    })
+    .collect()
+}
+
+/// 从缓冲中取出区块号不超过 `confirmed_block` 的条目（按区块号升序）
+fn take_confirmed<T>(pending: &mut BTreeMap<u64, T>, confirmed_block: u64) -> BTreeMap<u64, T> {
+    let remaining = pending.split_off(&confirmed_block.saturating_add(1));
+    std::mem::replace(pending, remaining)
+}
+
+/// 以区块时间锚点为基准，按配置的平均出块时间推算区块时间戳；
+/// 尚无锚点时以最后同步区块和当前时间作为近似锚点
+fn estimate_block_timestamp(
+    database: &Database,
+    config: &crate::config::AppConfig,
+    anchor: Option<BlockTimeAnchor>,
+    block_number: u64,
+) -> u64 {
+    let anchor = match anchor {
+        Some(anchor) => anchor,
+        None => match database.get_last_synced_block() {
+            Ok(Some(last_synced_block)) => BlockTimeAnchor {
+                block_number: last_synced_block,
+                timestamp: current_timestamp(),
+            },
+            _ => return current_timestamp(),
+        },
+    };
+
+    let block_time_secs = config.event_monitoring.average_block_time_secs;
+    if block_number >= anchor.block_number {
+        let blocks_diff = block_number - anchor.block_number;
+        anchor.timestamp + (blocks_diff as f64 * block_time_secs) as u64
+    } else {
+        let blocks_diff = anchor.block_number - block_number;
+        anchor.timestamp.saturating_sub((blocks_diff as f64 * block_time_secs) as u64)
+    }
+}
+
+/// 冷启动同步的起始区块，返回 None 表示只从最新区块开始、不同步历史
+///
+/// 配置了 `cold_start_from_block` 时优先使用（晚于最新区块时返回错误），否则按 `cold_start_backtrace_blocks` 回溯，
+/// 回溯数量超过链高度时从创世区块开始。
+fn cold_start_block(config: &crate::config::EventMonitoringConfig, latest_block: u64) -> anyhow::Result<Option<u64>> {
+    if let Some(from_block) = config.cold_start_from_block {
+        if from_block > latest_block {
+            return Err(anyhow::anyhow!(
+                "cold_start_from_block {} 晚于链上最新区块 {}，请检查配置的区块号和 RPC 节点所在的链",
+                from_block, latest_block
+            ));
+        }
+        return Ok(Some(from_block));
+    }
+
+    match config.cold_start_backtrace_blocks {
+        0 => Ok(None),
+        backtrace_blocks => Ok(Some(latest_block.saturating_sub(backtrace_blocks))),
+    }
+}
+
 /// 获取当前时间戳的工具函数
 fn current_timestamp() -> u64 {
     std::time::SystemTime::now()
@@ -32,6 +415,36 @@ fn current_timestamp() -> u64 {
         .as_secs()
 }
 
+/// 日志在链上的位置
+fn log_position(log: &web3::types::Log) -> LogPosition {
+    LogPosition {
+        block_number: log.block_number.unwrap_or_default().as_u64(),
+        log_index: log.log_index.unwrap_or_default().as_u64(),
+    }
+}
+
+/// 状态记录的最后应用日志不早于该日志时，说明该日志已经应用过（重同步、重启后重复处理）
+fn already_applied(last_applied: Option<LogPosition>, log: &web3::types::Log) -> bool {
+    last_applied.is_some_and(|applied| applied >= log_position(log))
+}
+
+/// 持仓数值减法：结果会小于零时（事件乱序，如 InterestCollected 早于对应的 PositionIncreased）
+/// 记录警告并截断为零，避免单条日志导致事件监控任务 panic
+fn sub_or_clamp(value: web3::types::U256, amount: web3::types::U256, field: &str, user: Address, token_id: web3::types::U256) -> web3::types::U256 {
+    value.checked_sub(amount).unwrap_or_else(|| {
+        tracing::warn!(
+            "{} 扣减下溢，截断为0 - 用户: {:?}, TokenID: {}, 当前值: {}, 扣减量: {}",
+            field, user, token_id, value, amount
+        );
+        web3::types::U256::zero()
+    })
+}
+
+/// 会覆盖全局系统参数的事件，重复处理时按系统参数记录的最后应用日志跳过
+fn is_params_event(event_name: &str) -> bool {
+    matches!(event_name, "InterestRateChanged" | "ParameterChanged" | "LiquidationConfigInfo")
+}
+
 /// 监听模式
 #[derive(Debug, Clone)]
 pub enum MonitorMode {
@@ -41,70 +454,62 @@ pub enum MonitorMode {
     Realtime,
 }
 
-/// 事件清理紧急程度
-#[derive(Debug, Clone, Copy)]
-enum CleanupUrgency {
-    Light,      // 轻度：维护性清理
-    Moderate,   // 中度：预防性清理
-    Critical,   // 紧急：必须清理
-}
-
-/// 事件元数据结构
-#[derive(Debug, Clone)]
-struct EventMetadata {
-    event_id: EventId,
-    timestamp: u64,
-    is_hot: bool,      // 是否在热点时间窗口内
-    priority: i8,      // 清理优先级 (负数优先保留，正数优先清理)
-}
-
 /// 事件监控器
 pub struct EventMonitor {
-    web3_http: Option<web3::Web3<web3::transports::Http>>,
+    web3_http: Option<web3::Web3<FailoverTransport>>,
     web3_ws: Option<web3::Web3<web3::transports::WebSocket>>,
     database: Arc<Database>,
     config: crate::config::AppConfig,
     /// 预计算的事件签名缓存
     event_signatures: HashMap<String, H256>,
-    /// 已处理的事件ID缓存 - 用于去重
-    processed_events: HashSet<EventId>,
+    /// 最近区块内已处理的事件ID - 用于去重
+    processed_events: ProcessedEventWindow,
     /// 监听模式
     mode: MonitorMode,
     /// 拍卖重置监控器
     auction_reset_monitor: AuctionResetMonitor,
+    /// 实时模式下最后处理的区块头编号
+    last_processed_head: Option<u64>,
+    /// 实时模式下检测到的区块缺口次数
+    detected_block_gaps: u64,
+    /// 最近处理的区块哈希（区块号 -> 哈希），用于链重组检测
+    recent_block_hashes: BTreeMap<u64, H256>,
+    /// 实时模式下检测到的链重组次数
+    detected_reorgs: u64,
+    /// 上次清理区块时间戳缓存时的区块号
+    last_timestamp_cleanup_block: Option<u64>,
+    /// 提供方拒绝了日志订阅，之后的实时监听改用区块头订阅
+    log_subscription_unsupported: bool,
+    /// 尚未达到确认深度的区块头（区块号 -> 区块头），配置了 finality_lag_blocks 时使用
+    pending_heads: BTreeMap<u64, BlockHeader>,
+    /// 日志订阅推送的、所在区块尚未达到确认深度的日志（区块号 -> 日志）
+    pending_logs: BTreeMap<u64, Vec<web3::types::Log>>,
+    /// 建立日志订阅时的最新区块，此前出块的区块不会推送日志
+    log_subscription_start_block: Option<u64>,
+    /// 区块时间锚点（最近一次校准时的区块号和时间戳）
+    block_time_anchor: Option<BlockTimeAnchor>,
+    /// 上次校准区块时间锚点的时刻
+    last_anchor_refresh: Option<std::time::Instant>,
+    /// 关闭信号，在处理完一个区块/一个同步分段后检查
+    shutdown: CancellationToken,
+    /// 共享运行状态（供 `/health` 接口使用）
+    status: Arc<KeeperStatus>,
 }
 
 
 
 impl EventMonitor {
     pub async fn new(
-        web3_http: web3::Web3<web3::transports::Http>,
+        web3_http: web3::Web3<FailoverTransport>,
         database: Arc<Database>,
+        tx_sender: Arc<TransactionSender>,
         config: crate::config::AppConfig,
+        shutdown: CancellationToken,
+        status: Arc<KeeperStatus>,
+        notifier: Arc<dyn Notifier>,
     ) -> anyhow::Result<Self> {
         // 预计算所有事件签名以提高性能
-        let mut event_signatures = HashMap::new();
-
-        // InterestManager 事件签名
-        event_signatures.insert("InterestRateChanged".to_string(), H256::from_slice(&web3::signing::keccak256("InterestRateChanged(uint256,uint256)".as_bytes())));
-        event_signatures.insert("PositionIncreased".to_string(), H256::from_slice(&web3::signing::keccak256("PositionIncreased(address,uint256,uint256,uint256,uint256)".as_bytes())));
-        // PositionOpened 事件不再监控，根据用户的指示
-        // event_signatures.insert("PositionOpened".to_string(), H256::from_slice(&web3::signing::keccak256("PositionOpened(address,uint256,uint256,uint256)".as_bytes())));
-        event_signatures.insert("InterestCollected".to_string(), H256::from_slice(&web3::signing::keccak256("InterestCollected(address,uint256,uint256,uint256)".as_bytes())));
-
-        // CustodianFixed 事件签名
-        event_signatures.insert("Mint".to_string(), H256::from_slice(&web3::signing::keccak256("Mint(address,uint256,uint256,uint8,uint256,uint256,uint256)".as_bytes())));
-
-        // LiquidationManager 事件签名
-        event_signatures.insert("LiquidationParameterChanged".to_string(), H256::from_slice(&web3::signing::keccak256("ParameterChanged(bytes32,uint256)".as_bytes())));
-        event_signatures.insert("LiquidationConfigInfo".to_string(), H256::from_slice(&web3::signing::keccak256("LiquidationConfigInfo(uint256,uint256,uint256,bool)".as_bytes())));
-        event_signatures.insert("NetValueAdjusted".to_string(), H256::from_slice(&web3::signing::keccak256("NetValueAdjusted(address,uint256,uint256,uint8,uint256,uint256,uint256)".as_bytes())));
-
-        // AuctionManager 事件签名
-        event_signatures.insert("AuctionParameterChanged".to_string(), H256::from_slice(&web3::signing::keccak256("ParameterChanged(bytes32,uint256)".as_bytes())));
-        event_signatures.insert("AuctionStarted".to_string(), H256::from_slice(&web3::signing::keccak256("AuctionStarted(uint256,uint256,uint256,address,uint256,address,uint256)".as_bytes())));
-        event_signatures.insert("AuctionReset".to_string(), H256::from_slice(&web3::signing::keccak256("AuctionReset(uint256,uint256,uint256,address,uint256,address,uint256)".as_bytes())));
-        event_signatures.insert("AuctionRemoved".to_string(), H256::from_slice(&web3::signing::keccak256("AuctionRemoved(uint256)".as_bytes())));
+        let event_signatures = event_signature_map();
 
         // 验证合约地址可以正确解析
         let _ = config.contracts.interest_manager.parse::<Address>()?;
@@ -119,6 +524,10 @@ impl EventMonitor {
                     tracing::info!("WebSocket连接成功，使用实时监听模式");
                     (Some(ws_web3), MonitorMode::Realtime)
                 }
+                Err(web3::Error::Transport(web3::error::TransportError::Code(status @ (401 | 403)))) => {
+                    tracing::warn!("WebSocket握手被拒绝（HTTP {}），请检查 ws_url 中的认证信息，回退到轮询模式", status);
+                    (None, MonitorMode::Polling)
+                }
                 Err(e) => {
                     tracing::warn!("WebSocket连接失败，回退到轮询模式: {}", e);
                     (None, MonitorMode::Polling)
@@ -129,117 +538,697 @@ impl EventMonitor {
         };
 
         // 初始化拍卖重置监控器
-        let web3_for_reset = web3_http.clone();
         let auction_reset_monitor = AuctionResetMonitor::new(
-            web3_for_reset,
             database.clone(),
+            tx_sender,
             config.contracts.auction_manager.clone(),
+            config.auction.price_curve,
+            shutdown.clone(),
+            notifier,
         )?;
 
+        // 加载上次运行持久化的区块时间锚点，启动后会重新校准
+        let block_time_anchor = database.get_block_time_anchor()?;
+
         tracing::info!(
             "事件监控器初始化完成 - 模式: {:?}, 预计算了 {} 个事件签名",
             mode, event_signatures.len()
         );
 
+        // 载入上次运行最后处理区块的去重记录，避免重启后重新查询该区块时重复处理日志
+        let mut processed_events = ProcessedEventWindow::new(config.event_monitoring.dedup_window_blocks);
+        if let Some(record) = database.get_processed_events()? {
+            tracing::info!("载入区块 {} 的 {} 条已处理事件记录", record.block_number, record.events.len());
+            processed_events.restore(&record);
+        }
+
         Ok(Self {
             web3_http: Some(web3_http),
             web3_ws,
             database,
             config,
             event_signatures,
-            processed_events: HashSet::new(),
+            processed_events,
             mode,
             auction_reset_monitor,
+            last_processed_head: None,
+            detected_block_gaps: 0,
+            recent_block_hashes: BTreeMap::new(),
+            detected_reorgs: 0,
+            last_timestamp_cleanup_block: None,
+            log_subscription_unsupported: false,
+            pending_heads: BTreeMap::new(),
+            pending_logs: BTreeMap::new(),
+            log_subscription_start_block: None,
+            block_time_anchor,
+            last_anchor_refresh: None,
+            shutdown,
+            status,
         })
     }
 
+    /// 拍卖重置监控器（与事件处理共享待处理的重置任务），用于在独立任务中检查遗漏的重置
+    pub fn auction_reset_monitor(&self) -> AuctionResetMonitor {
+        self.auction_reset_monitor.clone()
+    }
+
     pub async fn run(&mut self) -> anyhow::Result<()> {
         tracing::info!("开始监听区块链事件...");
 
+        // 用最新区块校准区块时间锚点
+        self.refresh_block_time_anchor_if_due().await;
+
         // 执行初始历史同步
         if let Err(e) = self.perform_initial_sync().await {
             tracing::error!("初始历史同步失败: {}", e);
             // 继续运行，但记录错误
         }
 
-        match self.mode {
-            MonitorMode::Realtime => {
-                self.run_realtime_mode().await
-            }
-            MonitorMode::Polling => {
-                self.run_polling_mode().await
+        // 实时模式断开且重连失败时切换到轮询模式，轮询模式恢复WebSocket后切换回实时模式
+        while !self.shutdown.is_cancelled() {
+            match self.mode {
+                MonitorMode::Realtime => {
+                    self.status.set_monitor_mode("realtime");
+                    self.run_realtime_mode().await?;
+                }
+                MonitorMode::Polling => {
+                    self.status.set_monitor_mode("polling");
+                    self.run_polling_mode().await?;
+                }
             }
         }
+
+        tracing::info!("事件监控器已停止，最后同步区块: {:?}", self.database.get_last_synced_block()?);
+        Ok(())
     }
 
     /// 实时监听模式（推荐）
+    ///
+    /// 按配置订阅合约日志或区块头；提供方不支持日志订阅时改用区块头订阅。
+    /// WebSocket 断开后按指数退避重连，重连次数耗尽时切换到轮询模式并返回。
     async fn run_realtime_mode(&mut self) -> anyhow::Result<()> {
         tracing::info!("🚀 启动实时监听模式，使用WebSocket订阅新区块事件");
 
-        let web3_ws = self.web3_ws.as_ref().ok_or_else(|| anyhow::anyhow!("WebSocket未初始化"))?;
+        loop {
+            let web3_ws = self.web3_ws.clone().ok_or_else(|| anyhow::anyhow!("WebSocket未初始化"))?;
+
+            let subscribe_logs = self.config.event_monitoring.realtime_subscription == RealtimeSubscription::Logs
+                && !self.log_subscription_unsupported;
+            if subscribe_logs {
+                match web3_ws.eth_subscribe().subscribe_logs(self.log_subscription_filter()?).await {
+                    Ok(logs) => self.run_log_subscription(&web3_ws, logs).await?,
+                    // 提供方返回了 JSON-RPC 错误，说明连接正常但不支持日志订阅
+                    Err(web3::Error::Rpc(e)) => {
+                        tracing::warn!("提供方不支持日志订阅，改用区块头订阅: {}", e);
+                        self.log_subscription_unsupported = true;
+                        continue;
+                    }
+                    Err(e) => tracing::error!("订阅合约日志失败: {}", e),
+                }
+            } else {
+                self.run_head_subscription(&web3_ws).await?;
+            }
 
-        // 创建新的区块头订阅
-        let mut subscription = web3_ws.eth_subscribe().subscribe_new_heads().await?;
+            if self.shutdown.is_cancelled() {
+                return Ok(());
+            }
+
+            if !self.reconnect_websocket().await {
+                if self.shutdown.is_cancelled() {
+                    return Ok(());
+                }
+                tracing::warn!("WebSocket重连失败，回退到轮询模式...");
+                self.web3_ws = None;
+                self.mode = MonitorMode::Polling;
+                return Ok(());
+            }
+
+            // 重连成功，回补断线期间错过的区块
+            if let Err(e) = self.perform_initial_sync().await {
+                tracing::error!("重连后回补区块失败: {}", e);
+            }
+        }
+    }
+
+    /// 订阅区块头，每个新区块查询一次该区块的日志，直到订阅断开或收到关闭信号
+    async fn run_head_subscription(&mut self, web3_ws: &web3::Web3<web3::transports::WebSocket>) -> anyhow::Result<()> {
+        let mut subscription = match web3_ws.eth_subscribe().subscribe_new_heads().await {
+            Ok(subscription) => subscription,
+            Err(e) => {
+                tracing::error!("订阅新区块头失败: {}", e);
+                return Ok(());
+            }
+        };
 
         tracing::info!("✅ 已订阅新区块头，实时监听开始...");
+        self.status.set_websocket_connected(true);
+
+        // 以已同步的终点作为区块头连续性检查的起点，断线前缓冲的区块头由重连后的回补覆盖
+        self.last_processed_head = self.database.get_last_synced_block()?;
+        self.pending_heads.clear();
+
+        loop {
+            // 只在两个区块之间响应关闭信号，保证已开始处理的区块完整处理
+            let block_header = tokio::select! {
+                _ = self.shutdown.cancelled() => return Ok(()),
+                block_header = subscription.next() => match block_header {
+                    Some(block_header) => block_header,
+                    None => break,
+                },
+            };
 
-        while let Some(block_header) = subscription.next().await {
             match block_header {
                 Ok(header) => {
                     tracing::debug!("收到新区块: {}", header.number.unwrap_or_default());
 
-                    // 处理区块中的事件
-                    if let Err(e) = self.process_block_events(header.number.unwrap_or_default().as_u64()).await {
+                    // 处理已达到确认深度的区块中的事件
+                    if let Err(e) = self.handle_confirmed_heads(header).await {
                         tracing::error!("处理区块事件失败: {}", e);
                         // 继续监听，不中断
                     }
                 }
                 Err(e) => {
                     tracing::error!("WebSocket订阅错误: {}", e);
-                    // WebSocket断开，回退到轮询模式
-                    tracing::warn!("WebSocket断开，正在回退到轮询模式...");
-                    self.mode = MonitorMode::Polling;
-                    return self.run_polling_mode().await;
+                    break;
                 }
             }
         }
 
+        tracing::warn!("WebSocket订阅已断开");
+        self.status.set_websocket_connected(false);
         Ok(())
     }
 
+    /// 订阅合约日志，由 WebSocket 直接推送匹配的日志，省去每个区块的 eth_getLogs
+    ///
+    /// 同时订阅区块头，只用于推进同步进度：提供方不保证区块 N 的日志先于区块头 N 推送，
+    /// 因此收到区块头 N 时只把最后同步区块推进到 N-1。链重组由提供方推送的 `removed` 日志触发回滚。
+    async fn run_log_subscription(
+        &mut self,
+        web3_ws: &web3::Web3<web3::transports::WebSocket>,
+        mut logs: SubscriptionStream<web3::transports::WebSocket, web3::types::Log>,
+    ) -> anyhow::Result<()> {
+        let mut heads = match web3_ws.eth_subscribe().subscribe_new_heads().await {
+            Ok(heads) => heads,
+            Err(e) => {
+                tracing::error!("订阅新区块头失败: {}", e);
+                return Ok(());
+            }
+        };
+
+        tracing::info!("✅ 已订阅合约日志，实时监听开始...");
+        self.status.set_websocket_connected(true);
+
+        // 日志订阅只推送订阅建立之后的日志：先订阅再回补到已确认的区块，
+        // 两者重叠的日志由去重记录保证只处理一次，订阅建立前出块但尚未确认的区块在确认后用 eth_getLogs 同步
+        let web3 = self.web3_http.clone().ok_or_else(|| anyhow::anyhow!("HTTP客户端未初始化"))?;
+        let latest_block = rpc_retry("eth_blockNumber", || web3.eth().block_number()).await?.as_u64();
+        self.log_subscription_start_block = Some(latest_block);
+        self.pending_logs.clear();
+        if let Err(e) = self.perform_initial_sync().await {
+            tracing::error!("订阅后回补区块失败: {}", e);
+        }
+        self.last_processed_head = self.database.get_last_synced_block()?;
+
+        loop {
+            // 只在两条推送之间响应关闭信号，保证已开始处理的日志完整处理
+            tokio::select! {
+                _ = self.shutdown.cancelled() => return Ok(()),
+                log = logs.next() => match log {
+                    Some(Ok(log)) => self.handle_subscribed_log(&log).await,
+                    Some(Err(e)) => {
+                        tracing::error!("WebSocket日志订阅错误: {}", e);
+                        break;
+                    }
+                    None => break,
+                },
+                header = heads.next() => match header {
+                    Some(Ok(header)) => {
+                        if let Err(e) = self.advance_log_subscription_head(&header).await {
+                            tracing::error!("推进同步进度失败: {}", e);
+                        }
+                    }
+                    Some(Err(e)) => {
+                        tracing::error!("WebSocket订阅错误: {}", e);
+                        break;
+                    }
+                    None => break,
+                },
+            }
+        }
+
+        tracing::warn!("WebSocket订阅已断开");
+        self.status.set_websocket_connected(false);
+        Ok(())
+    }
+
+    /// 日志订阅的过滤条件：四个监控合约地址，topic0 为所有已知事件签名
+    fn log_subscription_filter(&self) -> anyhow::Result<Filter> {
+        Ok(FilterBuilder::default()
+            .address(self.monitored_addresses()?)
+            .topics(Some(self.handled_topics()), None, None, None)
+            .build())
+    }
+
+    /// 监控合约（InterestManager、LiquidationManager、AuctionManager、CustodianFixed）中有事件未禁用的合约地址
+    fn monitored_addresses(&self) -> anyhow::Result<Vec<Address>> {
+        enabled_contract_addresses(&self.config)
+    }
+
+    /// 日志查询的 topic0 过滤条件
+    fn handled_topics(&self) -> Vec<H256> {
+        handled_topics(&self.event_signatures, &self.config.event_monitoring.disabled_events)
+    }
+
+    /// 处理日志订阅推送的一条日志
+    ///
+    /// 配置了确认深度时，日志先缓冲到所在区块达到确认深度再处理。
+    /// `removed` 日志表示所在区块已成为孤块：仍在缓冲中时直接丢弃对应日志；
+    /// 已处理时回滚到其父区块，之后推送的规范链日志会覆盖孤块事件写入的状态。
+    async fn handle_subscribed_log(&mut self, log: &web3::types::Log) {
+        let block_number = log.block_number.unwrap_or_default().as_u64();
+
+        if let Some(pending) = self.pending_logs.get_mut(&block_number) {
+            if log.removed == Some(true) {
+                pending.retain(|pending_log| {
+                    pending_log.transaction_hash != log.transaction_hash || pending_log.log_index != log.log_index
+                });
+                tracing::debug!("区块 {} 尚未确认的日志被移除，已从缓冲中丢弃", block_number);
+                return;
+            }
+        }
+
+        if self.config.event_monitoring.finality_lag_blocks > 0 && log.removed != Some(true) {
+            self.pending_logs.entry(block_number).or_default().push(log.clone());
+            return;
+        }
+
+        if log.removed == Some(true) {
+            // 同一次重组会推送多条 removed 日志，只在第一条时回滚
+            if self.last_processed_head.is_some_and(|head| head >= block_number) {
+                self.detected_reorgs += 1;
+                metrics().reorgs.inc();
+                tracing::warn!(
+                    "检测到链重组: 区块 {} 的日志被移除 (累计重组次数: {})",
+                    block_number, self.detected_reorgs
+                );
+                let head = self.last_processed_head.unwrap_or(block_number);
+                // 失败时不更新最后处理的区块头，同一次重组的下一条 removed 日志会重试回滚
+                if let Err(e) = self.rollback_to_fork_point(block_number.saturating_sub(1), head).await {
+                    tracing::error!("链重组回滚失败: {}", e);
+                }
+            }
+            return;
+        }
+
+        if self.dispatch_log(log).await {
+            tracing::debug!("处理了区块 {} 的日志 {:?}", block_number, log.log_index);
+        }
+    }
+
+    /// 日志订阅模式下收到新区块头：处理达到确认深度的缓冲日志，推进同步进度并执行按区块触发的维护任务
+    ///
+    /// 同步进度推进到 `N - max(finality_lag_blocks, 1)`；订阅建立前出块的区块没有推送的日志，
+    /// 确认后用 eth_getLogs 同步。
+    async fn advance_log_subscription_head(&mut self, header: &BlockHeader) -> anyhow::Result<()> {
+        let block_number = header.number.unwrap_or_default().as_u64();
+        tracing::debug!("收到新区块: {}", block_number);
+
+        let lag = self.config.event_monitoring.finality_lag_blocks.max(1);
+        if let Some(synced_block) = block_number.checked_sub(lag) {
+            let last_synced_block = self.database.get_last_synced_block()?;
+            if let (Some(start_block), Some(last_block)) = (self.log_subscription_start_block, last_synced_block) {
+                let end_block = synced_block.min(start_block);
+                if end_block > last_block {
+                    let web3 = self.web3_http.clone().ok_or_else(|| anyhow::anyhow!("HTTP客户端未初始化"))?;
+                    self.sync_block_range(&web3, last_block + 1, end_block, false).await?;
+                }
+            }
+
+            // 推送顺序不保证与链上顺序一致
+            let mut confirmed_logs: Vec<web3::types::Log> = take_confirmed(&mut self.pending_logs, synced_block).into_values().flatten().collect();
+            confirmed_logs.sort_by_key(log_position);
+            for log in confirmed_logs {
+                self.dispatch_log(&log).await;
+            }
+
+            if last_synced_block.is_none_or(|last| synced_block > last) {
+                let web3 = self.web3_http.clone().ok_or_else(|| anyhow::anyhow!("HTTP客户端未初始化"))?;
+                let block_hash = Self::boundary_block_hash(&web3, synced_block).await;
+                self.database.set_last_synced_block(synced_block, block_hash)?;
+                Self::prune_raw_log_archive(&self.database, &self.config, synced_block);
+            }
+        }
+
+        self.last_processed_head = Some(block_number);
+        self.processed_events.prune(block_number);
+        metrics().processed_events_cache_size.set(self.processed_events.len() as i64);
+        self.status.record_block_processed();
+        self.refresh_block_time_anchor_if_due().await;
+        self.cleanup_block_timestamps_if_due(block_number);
+        Ok(())
+    }
+
+    /// 按指数退避（带随机抖动）尝试重新建立 WebSocket 连接
+    async fn reconnect_websocket(&mut self) -> bool {
+        let ws_url = match self.config.ws_url.clone() {
+            Some(ws_url) => ws_url,
+            None => return false,
+        };
+
+        let max_attempts = self.config.event_monitoring.ws_reconnect_max_attempts;
+        let base_delay_secs = self.config.event_monitoring.ws_reconnect_base_delay_secs;
+
+        for attempt in 1..=max_attempts {
+            let delay = reconnect_delay(base_delay_secs, attempt);
+            tracing::info!("{:.1}秒后进行第 {}/{} 次WebSocket重连...", delay.as_secs_f64(), attempt, max_attempts);
+            tokio::select! {
+                _ = self.shutdown.cancelled() => return false,
+                _ = tokio::time::sleep(delay) => {}
+            }
+
+            match web3::transports::WebSocket::new(&ws_url).await {
+                Ok(ws_transport) => {
+                    tracing::info!("WebSocket重连成功 (第 {} 次尝试)", attempt);
+                    self.web3_ws = Some(web3::Web3::new(ws_transport));
+                    return true;
+                }
+                Err(e) => {
+                    tracing::warn!("第 {}/{} 次WebSocket重连失败: {}", attempt, max_attempts, e);
+                }
+            }
+        }
+
+        false
+    }
+
+    /// 缓冲收到的区块头，按区块号顺序处理已达到确认深度（`N - finality_lag_blocks`）的区块头
+    ///
+    /// 同一高度或更低的区块头到达时，缓冲中更高的区块头已被链重组替换，直接丢弃，不会被处理。
+    async fn handle_confirmed_heads(&mut self, header: BlockHeader) -> anyhow::Result<()> {
+        let lag = self.config.event_monitoring.finality_lag_blocks;
+        if lag == 0 {
+            return self.handle_new_head(&header).await;
+        }
+
+        let block_number = header.number.unwrap_or_default().as_u64();
+        let replaced = self.pending_heads.split_off(&block_number);
+        if replaced.keys().any(|number| *number > block_number) {
+            tracing::debug!("区块头 {} 替换了尚未确认的区块 {:?}", block_number, replaced.keys().collect::<Vec<_>>());
+        }
+        self.pending_heads.insert(block_number, header);
+
+        let confirmed_block = block_number.saturating_sub(lag);
+        for header in take_confirmed(&mut self.pending_heads, confirmed_block).into_values() {
+            self.handle_new_head(&header).await?;
+        }
+        Ok(())
+    }
+
+    /// 处理实时模式收到的新区块头，并检查区块头编号的连续性
+    ///
+    /// WebSocket 提供方并不保证区块头推送连续且有序：
+    /// - 父哈希与记录的上一区块哈希不一致时，视为链重组，回滚到分叉点后重放
+    /// - 编号跳跃超过1时，先回补中间缺失的区块，再处理当前区块
+    /// - 编号小于等于上一次处理的区块时（重复推送或链重组），重新处理该区块
+    ///
+    /// 处理失败时不更新最后处理的区块头，该区块会在下一个区块头到达时作为缺口回补。
+    async fn handle_new_head(&mut self, header: &BlockHeader) -> anyhow::Result<()> {
+        let block_number = header.number.unwrap_or_default().as_u64();
+
+        if let Some(fork_point) = self.detect_reorg(block_number, header.parent_hash).await? {
+            self.rollback_to_fork_point(fork_point, block_number).await?;
+        }
+
+        match self.last_processed_head {
+            Some(last_head) if block_number > last_head + 1 => {
+                self.detected_block_gaps += 1;
+                metrics().block_gaps.inc();
+                tracing::warn!(
+                    "检测到区块缺口: 上一个区块 {}, 当前区块 {}, 缺失 {} 个区块，开始回补 (累计缺口次数: {})",
+                    last_head, block_number, block_number - last_head - 1, self.detected_block_gaps
+                );
+
+                let web3 = self.web3_http.clone().ok_or_else(|| anyhow::anyhow!("HTTP客户端未初始化"))?;
+                self.sync_block_range(&web3, last_head + 1, block_number - 1, false).await?;
+            }
+            Some(last_head) if block_number <= last_head => {
+                match (self.recent_block_hashes.get(&block_number), header.hash) {
+                    (Some(recorded_hash), Some(hash)) if *recorded_hash == hash => {
+                        tracing::debug!("收到重复推送的区块头 {}，跳过", block_number);
+                        return Ok(());
+                    }
+                    (Some(_), _) => {
+                        tracing::warn!(
+                            "收到非递增的区块头: 上一个区块 {}, 当前区块 {}（哈希已变化，可能是链重组），重新处理该区块",
+                            last_head, block_number
+                        );
+                        // 同一高度的区块已被替换，清除该高度及之后的去重记录，保证新区块的日志被处理
+                        self.evict_processed_events_from(block_number);
+                    }
+                    // 没有哈希记录说明该区块由历史同步处理（订阅建立前的交接区块），去重记录保证已处理的日志不会重复处理
+                    (None, _) => {
+                        tracing::debug!("区块头 {} 已由历史同步处理，只处理去重记录之外的日志", block_number);
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        self.process_block_events(block_number, header.hash).await?;
+        self.last_processed_head = Some(block_number);
+        self.status.record_block_processed();
+        self.refresh_block_time_anchor_if_due().await;
+        self.cleanup_block_timestamps_if_due(block_number);
+
+        if let Some(hash) = header.hash {
+            self.record_block_hash(block_number, hash);
+        }
+        Ok(())
+    }
+
+    /// 检测链重组，返回分叉点（最后一个仍在规范链上的区块号）
+    ///
+    /// 新区块的父哈希与记录的上一区块哈希不一致时，从高到低逐个比对记录的哈希与
+    /// 链上当前规范链的哈希，找到第一个一致的区块作为分叉点。
+    async fn detect_reorg(&mut self, block_number: u64, parent_hash: H256) -> anyhow::Result<Option<u64>> {
+        let parent_number = match block_number.checked_sub(1) {
+            Some(parent_number) => parent_number,
+            None => return Ok(None),
+        };
+
+        match self.recent_block_hashes.get(&parent_number) {
+            Some(recorded_hash) if *recorded_hash != parent_hash => {}
+            _ => return Ok(None),
+        }
+
+        self.detected_reorgs += 1;
+        metrics().reorgs.inc();
+        tracing::warn!(
+            "检测到链重组: 区块 {} 的父哈希 {:?} 与记录的区块 {} 哈希不一致 (累计重组次数: {})",
+            block_number, parent_hash, parent_number, self.detected_reorgs
+        );
+
+        let web3 = self.web3_http.clone().ok_or_else(|| anyhow::anyhow!("HTTP客户端未初始化"))?;
+        let recorded: Vec<(u64, H256)> = self.recent_block_hashes.range(..block_number)
+            .rev()
+            .map(|(number, hash)| (*number, *hash))
+            .collect();
+
+        for (number, recorded_hash) in &recorded {
+            if Self::block_hash(&web3, *number).await? == Some(*recorded_hash) {
+                return Ok(Some(*number));
+            }
+        }
+
+        // 重组深度超过记录范围，从最早记录的区块之前开始重放
+        let fork_point = recorded.last()
+            .map(|(number, _)| number.saturating_sub(1))
+            .unwrap_or(parent_number);
+        tracing::error!(
+            "链重组深度超过记录的 {} 个区块，从区块 {} 之后开始重放",
+            REORG_TRACKING_DEPTH, fork_point
+        );
+        Ok(Some(fork_point))
+    }
+
+    /// 回滚到分叉点，随后的缺口回补会重放分叉点之后的规范链日志
+    async fn rollback_to_fork_point(&mut self, fork_point: u64, new_head: u64) -> anyhow::Result<()> {
+        tracing::warn!("链重组回滚: 分叉点 {}, 重放区块 {} - {}", fork_point, fork_point + 1, new_head);
+        self.rewind_to_block(fork_point).await
+    }
+
+    /// 把本地状态退回到 `fork_point` 区块末尾：按该区块的链上状态重新同步之后的事件改动过的记录，
+    /// 丢弃之后的区块哈希和去重记录，并将同步进度退回该区块
+    ///
+    /// 重新同步失败时不做任何回滚，由调用方稍后重试。
+    async fn rewind_to_block(&mut self, fork_point: u64) -> anyhow::Result<()> {
+        self.resync_state_at(fork_point).await?;
+        self.recent_block_hashes.retain(|number, _| *number <= fork_point);
+        self.evict_processed_events_from(fork_point + 1);
+        if let Err(e) = self.database.rewind_params_applied_log(fork_point) {
+            tracing::error!("回滚参数事件的已应用日志记录失败: {}", e);
+        }
+
+        if let Err(e) = self.database.set_last_synced_block(fork_point, self.recent_block_hashes.get(&fork_point).copied()) {
+            tracing::error!("回滚最后同步区块失败: {}", e);
+        }
+        self.last_processed_head = Some(fork_point);
+        Ok(())
+    }
+
+    /// 按 `fork_point` 区块的链上状态重新同步可能被之后的事件改动的记录
+    ///
+    /// 只清除已应用日志记录会让重放的事件叠加在已应用事件的结果上。重新同步后：
+    /// - 最后应用日志晚于该区块的持仓覆盖为该区块的链上状态（之后新建的持仓被删除），应用日志位置推进到该区块末尾
+    /// - 所有本地拍卖覆盖为该区块的链上状态，之后开始的拍卖被删除
+    ///
+    /// 被孤块中的 AuctionRemoved 删除、规范链上仍活跃的拍卖无法从本地发现，需要用 `resync-auction` 命令恢复。
+    async fn resync_state_at(&self, fork_point: u64) -> anyhow::Result<()> {
+        let web3 = self.web3_http.clone().ok_or_else(|| anyhow::anyhow!("HTTP客户端未初始化"))?;
+        let resyncer = crate::resync::Resyncer::new(web3, &self.config)?;
+        let block = U64::from(fork_point);
+
+        let orphaned_positions: Vec<UserPosition> = self.database.get_all_user_positions()?
+            .into_iter()
+            .filter(|position| position.last_applied_log.is_some_and(|log| log.block_number > fork_point))
+            .collect();
+        for position in &orphaned_positions {
+            resyncer.resync_position_at(&self.database, position.user, position.token_id, block).await?;
+        }
+
+        let auctions = self.database.get_all_auctions()?;
+        for auction in &auctions {
+            resyncer.resync_auction_at(&self.database, auction.auction_id, block).await?;
+        }
+
+        tracing::info!(
+            "按区块 {} 的链上状态重新同步了 {} 个持仓和 {} 个拍卖",
+            fork_point, orphaned_positions.len(), auctions.len()
+        );
+        Ok(())
+    }
+
+    /// 从去重缓存中移除指定区块及之后的事件
+    fn evict_processed_events_from(&mut self, block_number: u64) {
+        let evicted = self.processed_events.evict_from(block_number);
+        metrics().processed_events_cache_size.set(self.processed_events.len() as i64);
+
+        // 持久化的记录属于被移除的区块时一并删除，避免重启后误跳过规范链上的日志
+        match self.database.get_processed_events() {
+            Ok(Some(record)) if record.block_number >= block_number => {
+                if let Err(e) = self.database.clear_processed_events() {
+                    tracing::error!("删除持久化的去重记录失败: {}", e);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => tracing::error!("读取持久化的去重记录失败: {}", e),
+        }
+        if evicted > 0 {
+            tracing::debug!("从去重缓存中移除了区块 {} 及之后的 {} 个事件", block_number, evicted);
+        }
+    }
+
+    /// 记录区块哈希，只保留最近 REORG_TRACKING_DEPTH 个区块
+    fn record_block_hash(&mut self, block_number: u64, hash: H256) {
+        self.recent_block_hashes.insert(block_number, hash);
+        while self.recent_block_hashes.len() > REORG_TRACKING_DEPTH {
+            self.recent_block_hashes.pop_first();
+        }
+    }
+
     /// 轮询监听模式（fallback）
+    ///
+    /// 配置了 WebSocket 地址时，定期尝试升级回实时模式，升级成功后返回。
     async fn run_polling_mode(&mut self) -> anyhow::Result<()> {
         tracing::info!("⏰ 启动轮询监听模式，间隔: {}秒", self.config.event_monitoring.polling_interval_secs);
 
         let mut interval = tokio::time::interval(std::time::Duration::from_secs(self.config.event_monitoring.polling_interval_secs));
+        let mut last_upgrade_attempt = tokio::time::Instant::now();
 
         loop {
-            interval.tick().await;
+            tokio::select! {
+                _ = self.shutdown.cancelled() => return Ok(()),
+                _ = interval.tick() => {}
+            }
 
-            // 定期清理长时间没有活跃的已处理事件缓存，避免内存泄漏
-            self.cleanup_processed_events_cache();
+            self.refresh_block_time_anchor_if_due().await;
 
             // 监听所有合约的事件
-            if let Err(e) = self.monitor_all_events().await {
-                tracing::error!("事件监听错误: {}", e);
-                // 继续运行，不中断
+            match self.monitor_all_events().await {
+                Ok(()) => self.status.record_block_processed(),
+                Err(e) => {
+                    tracing::error!("事件监听错误: {}", e);
+                    // 继续运行，不中断
+                }
+            }
+            if let Ok(Some(current_block)) = self.database.get_last_synced_block() {
+                self.cleanup_block_timestamps_if_due(current_block);
+            }
+
+            // 定期尝试恢复WebSocket连接
+            if let Some(ws_url) = self.config.ws_url.clone() {
+                if last_upgrade_attempt.elapsed() >= std::time::Duration::from_secs(WS_UPGRADE_INTERVAL_SECS) {
+                    last_upgrade_attempt = tokio::time::Instant::now();
+
+                    match web3::transports::WebSocket::new(&ws_url).await {
+                        Ok(ws_transport) => {
+                            tracing::info!("WebSocket连接已恢复，切换回实时监听模式");
+                            self.web3_ws = Some(web3::Web3::new(ws_transport));
+                            self.mode = MonitorMode::Realtime;
+
+                            // 回补轮询期间尚未同步的区块
+                            if let Err(e) = self.perform_initial_sync().await {
+                                tracing::error!("切换实时模式前回补区块失败: {}", e);
+                            }
+                            return Ok(());
+                        }
+                        Err(e) => {
+                            tracing::debug!("WebSocket仍不可用，继续轮询: {}", e);
+                        }
+                    }
+                }
             }
         }
     }
 
+    /// 每推进 `block_timestamp_cleanup_interval_blocks` 个区块清理一次过期的区块时间戳缓存
+    fn cleanup_block_timestamps_if_due(&mut self, current_block: u64) {
+        let database_config = &self.config.database;
+        let interval_blocks = database_config.block_timestamp_cleanup_interval_blocks;
+        if interval_blocks == 0 {
+            return;
+        }
+        if self.last_timestamp_cleanup_block
+            .is_some_and(|last_block| current_block < last_block.saturating_add(interval_blocks))
+        {
+            return;
+        }
+
+        match self.database.cleanup_old_block_timestamps(current_block, database_config.block_timestamp_retention_blocks) {
+            Ok(()) => self.last_timestamp_cleanup_block = Some(current_block),
+            Err(e) => tracing::warn!("清理区块时间戳缓存失败: {}", e),
+        }
+    }
+
     /// 执行初始历史同步
-    async fn perform_initial_sync(&self) -> anyhow::Result<()> {
+    ///
+    /// 从 `最后同步区块 + 1` 同步到当前最新区块；实时模式的第一个区块头据此继续，
+    /// 交接处重复的区块由同步时写入的去重记录保证只处理一次。
+    async fn perform_initial_sync(&mut self) -> anyhow::Result<()> {
         tracing::info!("开始执行初始历史同步...");
 
-        let web3 = self.web3_http.as_ref().ok_or_else(|| anyhow::anyhow!("HTTP客户端未初始化"))?;
+        let web3 = self.web3_http.clone().ok_or_else(|| anyhow::anyhow!("HTTP客户端未初始化"))?;
 
-        // 获取当前最新区块号
-        let latest_block = web3.eth().block_number().await?;
-        let latest_block_num = latest_block.as_u64();
-        tracing::info!("当前链上最新区块号: {}", latest_block_num);
+        // 获取已达到确认深度的最新区块号
+        let latest_block_num = self.confirmed_block_number(&web3).await?;
+        tracing::info!("当前已确认的最新区块号: {}", latest_block_num);
 
-        // 获取最后同步的区块号
-        let last_synced_block = self.database.get_last_synced_block()?;
+        // 获取最后同步的区块号，并确认停机期间该区块没有被链重组替换
+        let last_synced_block = match self.database.get_sync_checkpoint()? {
+            Some(checkpoint) => Some(self.verify_sync_checkpoint(&web3, checkpoint).await?),
+            None => None,
+        };
 
         match last_synced_block {
             Some(last_block) => {
@@ -255,44 +1244,144 @@ impl EventMonitor {
                 let start_block = last_block + 1;
                 let end_block = latest_block_num;
 
-                self.sync_block_range(web3, start_block, end_block).await?;
+                self.sync_block_range(&web3, start_block, end_block, false).await?;
             }
             None => {
                 // 如果没有最后同步记录，执行冷启动
-                self.perform_cold_start_sync(web3, latest_block_num).await?;
+                self.perform_cold_start_sync(&web3, latest_block_num).await?;
             }
         }
 
         Ok(())
     }
 
-    /// 执行冷启动同步
-    async fn perform_cold_start_sync(&self, web3: &web3::Web3<web3::transports::Http>, latest_block: u64) -> anyhow::Result<()> {
-        if self.config.event_monitoring.cold_start_backtrace_blocks == 0 {
-            // 不回溯历史，只从最新区块开始
-            tracing::info!("冷启动配置: 只从最新区块 {} 开始同步，不回溯历史", latest_block);
-            self.database.set_last_synced_block(latest_block)?;
-            return Ok(());
+    /// 校验同步边界的区块哈希仍在规范链上，返回继续同步的起点（最后同步的区块号）
+    ///
+    /// 哈希不一致说明停机期间发生了链重组：从高到低比对记录的同步边界历史，找到仍在规范链上的区块作为分叉点，
+    /// 回滚到分叉点后从其下一个区块重新同步；历史中没有一致的区块时，从最早记录的区块之前开始。
+    async fn verify_sync_checkpoint(&mut self, web3: &web3::Web3<FailoverTransport>, checkpoint: SyncCheckpoint) -> anyhow::Result<u64> {
+        let Some(recorded_hash) = checkpoint.block_hash else {
+            tracing::debug!("最后同步区块 {} 没有记录哈希，跳过校验", checkpoint.block_number);
+            return Ok(checkpoint.block_number);
+        };
+        let canonical_hash = Self::block_hash(web3, checkpoint.block_number).await?;
+        if canonical_hash == Some(recorded_hash) {
+            return Ok(checkpoint.block_number);
         }
 
-        // 回溯指定数量的区块
-        let backtrace_blocks = self.config.event_monitoring.cold_start_backtrace_blocks;
-        let start_block = if latest_block > backtrace_blocks {
-            latest_block - backtrace_blocks
-        } else {
-            0 // 从创世区块开始
+        self.detected_reorgs += 1;
+        metrics().reorgs.inc();
+        tracing::warn!(
+            "最后同步区块 {} 的哈希 {:?} 与规范链 {:?} 不一致，停机期间发生了链重组 (累计重组次数: {})",
+            checkpoint.block_number, recorded_hash, canonical_hash, self.detected_reorgs
+        );
+
+        for (number, hash) in checkpoint.history.iter().rev() {
+            if Self::block_hash(web3, *number).await? == Some(*hash) {
+                self.record_block_hash(*number, *hash);
+                self.rollback_to_fork_point(*number, checkpoint.block_number).await?;
+                return Ok(*number);
+            }
+        }
+
+        let fork_point = checkpoint.history.first()
+            .map(|(number, _)| number.saturating_sub(1))
+            .unwrap_or(checkpoint.block_number.saturating_sub(1));
+        tracing::error!("记录的同步边界中没有仍在规范链上的区块，从区块 {} 之后开始重放", fork_point);
+        self.rollback_to_fork_point(fork_point, checkpoint.block_number).await?;
+        Ok(fork_point)
+    }
+
+    /// 规范链上指定区块的哈希（区块不存在时为 None）
+    async fn block_hash(web3: &web3::Web3<FailoverTransport>, block_number: u64) -> anyhow::Result<Option<H256>> {
+        let block_id = BlockId::Number(BlockNumber::Number(U64::from(block_number)));
+        let block = rpc_retry("eth_getBlockByNumber", || web3.eth().block(block_id)).await?;
+        Ok(block.and_then(|block| block.hash))
+    }
+
+    /// 记录同步边界用的区块哈希；获取失败时只记录区块号，不影响同步
+    async fn boundary_block_hash(web3: &web3::Web3<FailoverTransport>, block_number: u64) -> Option<H256> {
+        match Self::block_hash(web3, block_number).await {
+            Ok(hash) => hash,
+            Err(e) => {
+                tracing::warn!("获取区块 {} 的哈希失败，同步边界只记录区块号: {}", block_number, e);
+                None
+            }
+        }
+    }
+
+    /// 已达到确认深度的最新区块号（`最新区块 - finality_lag_blocks`）
+    async fn confirmed_block_number(&self, web3: &web3::Web3<FailoverTransport>) -> anyhow::Result<u64> {
+        let latest_block = rpc_retry("eth_blockNumber", || web3.eth().block_number()).await?;
+        Ok(latest_block.as_u64().saturating_sub(self.config.event_monitoring.finality_lag_blocks))
+    }
+
+    /// 执行冷启动同步
+    async fn perform_cold_start_sync(&mut self, web3: &web3::Web3<FailoverTransport>, latest_block: u64) -> anyhow::Result<()> {
+        let start_block = match cold_start_block(&self.config.event_monitoring, latest_block)? {
+            Some(start_block) => start_block,
+            None => {
+                // 不回溯历史，只从最新区块开始
+                tracing::info!("冷启动配置: 只从最新区块 {} 开始同步，不回溯历史", latest_block);
+                let block_hash = Self::boundary_block_hash(web3, latest_block).await;
+                self.database.set_last_synced_block(latest_block, block_hash)?;
+                return Ok(());
+            }
         };
 
-        tracing::info!("冷启动配置: 从区块 {} 回溯 {} 个区块到区块 {}",
-                     start_block, backtrace_blocks, latest_block);
+        match self.config.event_monitoring.cold_start_from_block {
+            Some(_) => tracing::info!("冷启动配置: 从指定区块 {} 同步到区块 {}", start_block, latest_block),
+            None => tracing::info!("冷启动配置: 从区块 {} 回溯 {} 个区块到区块 {}",
+                                 start_block, self.config.event_monitoring.cold_start_backtrace_blocks, latest_block),
+        }
 
-        self.sync_block_range(web3, start_block, latest_block).await?;
+        self.sync_block_range(web3, start_block, latest_block, false).await?;
 
         Ok(())
     }
 
+    /// 按需重新同步指定区块范围（`backfill` 子命令使用）
+    ///
+    /// 同步会把最后同步区块号推进到各分段终点；范围早于当前同步进度时，结束后恢复原来的进度，
+    /// 避免下次启动时重新同步之后的所有区块。
+    ///
+    /// 最后应用日志位置已晚于遗漏事件的记录会跳过该事件；`force` 为 true 时先把本地状态退回 `from_block` 之前
+    /// （按该区块的链上状态重新同步之后改动过的持仓和拍卖），再重放范围内的事件，同步进度停在 `to_block`，
+    /// Keeper 启动时从这里重新同步到最新区块。
+    pub async fn backfill(&mut self, from_block: u64, to_block: u64, force: bool) -> anyhow::Result<()> {
+        let web3 = self.web3_http.clone().ok_or_else(|| anyhow::anyhow!("HTTP客户端未初始化"))?;
+        let checkpoint = self.database.get_sync_checkpoint()?;
+
+        tracing::info!(
+            "回填区块 {} - {}，当前同步进度: {:?}，强制模式: {}",
+            from_block, to_block, checkpoint.as_ref().map(|checkpoint| checkpoint.block_number), force
+        );
+        if force {
+            self.rewind_to_block(from_block.saturating_sub(1)).await?;
+        }
+        let result = self.sync_block_range(&web3, from_block, to_block, false).await;
+
+        if let Some(checkpoint) = checkpoint.filter(|checkpoint| checkpoint.block_number > to_block) {
+            if force {
+                tracing::info!("强制回填后同步进度为区块 {}，Keeper 启动时从这里重新同步到最新区块", to_block);
+            } else {
+                self.database.set_sync_checkpoint(&checkpoint)?;
+            }
+        }
+
+        result
+    }
+
     /// 同步指定区块范围
-    async fn sync_block_range(&self, web3: &web3::Web3<web3::transports::Http>, start_block: u64, end_block: u64) -> anyhow::Result<()> {
+    ///
+    /// 按 `log_query_chunk_blocks` 分段查询日志，每段一次 eth_getLogs 请求，
+    /// 按区块顺序处理后推进最后同步区块号。提供方限制结果数量时自动缩小分段。
+    /// 配置了 `backfill_throttle_ms` 时，每轮并发查询之后等待该时间再开始下一轮。
+    /// 处理的日志同时写入去重记录，与实时模式重叠的区块不会被重复处理。
+    ///
+    /// `live` 为 true 时（轮询模式）日志经 `dispatch_log` 处理，新拍卖会计划重置；
+    /// 其他情况只更新数据库，没有重置任务的拍卖由定期的遗漏重置检查补上。
+    async fn sync_block_range(&mut self, web3: &web3::Web3<FailoverTransport>, start_block: u64, end_block: u64, live: bool) -> anyhow::Result<()> {
         if start_block > end_block {
             tracing::info!("同步范围无效: start_block={} > end_block={}", start_block, end_block);
             return Ok(());
@@ -300,126 +1389,219 @@ impl EventMonitor {
 
         tracing::info!("开始从区块 {} 同步到区块 {}", start_block, end_block);
 
-        // 批量同步区块，每次处理一批
-        let batch_size = 100; // 每批处理的区块数量
+        let max_logs_per_request = self.config.event_monitoring.max_logs_per_request;
+        let concurrency = self.config.event_monitoring.sync_concurrency.max(1);
+        let mut chunk_blocks = self.config.event_monitoring.log_query_chunk_blocks.max(1);
+        let throttle = std::time::Duration::from_millis(self.config.event_monitoring.backfill_throttle_ms);
         let mut current_block = start_block;
         let mut total_events_processed = 0;
 
         while current_block <= end_block {
-            let batch_end = std::cmp::min(current_block + batch_size - 1, end_block);
-            tracing::debug!("处理区块批次: {} - {}", current_block, batch_end);
-
-            // 并行处理一批区块
-            let mut handles = vec![];
-
-            for block_num in current_block..=batch_end {
-                let web3_clone = web3.clone();
-                let database_clone = self.database.clone();
-                let config_clone = self.config.clone();
-                let event_signatures_clone = self.event_signatures.clone();
-
-                let handle = tokio::spawn(async move {
-                    Self::sync_single_block(
-                        web3_clone,
-                        database_clone,
-                        config_clone,
-                        event_signatures_clone,
-                        block_num,
-                    ).await
-                });
-                handles.push(handle);
+            // 已完成的分段都已推进最后同步区块号，收到关闭信号时在分段之间停止
+            if self.shutdown.is_cancelled() {
+                tracing::info!("收到关闭信号，同步在区块 {} 之前停止", current_block);
+                return Ok(());
             }
 
-            // 等待这一批的所有任务完成
-            for handle in handles {
-                match handle.await {
-                    Ok(Ok(events_count)) => {
-                        total_events_processed += events_count;
+            // 回补限速：从第二轮开始，每轮之前等待，让其他任务的 RPC 调用优先
+            if current_block > start_block && !throttle.is_zero() {
+                tokio::select! {
+                    _ = self.shutdown.cancelled() => continue,
+                    _ = tokio::time::sleep(throttle) => {}
+                }
+            }
+
+            // 按当前分段大小划分接下来最多 sync_concurrency 个分段，并发查询日志，再按区块顺序处理
+            let mut ranges = Vec::with_capacity(concurrency);
+            let mut range_start = current_block;
+            while ranges.len() < concurrency && range_start <= end_block {
+                let range_end = std::cmp::min(range_start.saturating_add(chunk_blocks - 1), end_block);
+                ranges.push((range_start, range_end));
+                range_start = range_end + 1;
+            }
+            let addresses = self.monitored_addresses()?;
+            let topics = self.handled_topics();
+            let chunks: Vec<anyhow::Result<(Vec<web3::types::Log>, u64)>> = futures_util::stream::iter(ranges.iter().copied())
+                .map(|(from_block, to_block)| Self::get_logs_chunk(web3, &addresses, &topics, from_block, to_block))
+                .buffered(concurrency)
+                .collect()
+                .await;
+
+            for ((_, requested_end), chunk) in ranges.into_iter().zip(chunks) {
+                let (logs, chunk_end) = chunk?;
+                tracing::debug!("处理区块分段: {} - {}, 日志数量: {}", current_block, chunk_end, logs.len());
+
+                // 分段被缩小过时，后续分段沿用缩小后的大小
+                chunk_blocks = chunk_blocks.min(chunk_end - current_block + 1);
+                if logs.len() > max_logs_per_request && chunk_blocks > 1 {
+                    chunk_blocks /= 2;
+                    tracing::debug!("分段日志数量 {} 超过 {}，后续分段缩小为 {} 个区块", logs.len(), max_logs_per_request, chunk_blocks);
+                }
+
+                // 按区块拆分，逐个区块按日志顺序处理（持仓和参数按最后应用的日志位置跳过重复日志，必须按链上顺序应用）
+                let mut logs = logs;
+                logs.sort_by_key(log_position);
+                let mut logs_by_block: BTreeMap<u64, Vec<web3::types::Log>> = BTreeMap::new();
+                for log in logs {
+                    let block_number = log.block_number.unwrap_or_default().as_u64();
+                    logs_by_block.entry(block_number).or_default().push(log);
+                }
+
+                for (block_number, block_logs) in &logs_by_block {
+                    if live {
+                        for log in block_logs {
+                            self.dispatch_log(log).await;
+                        }
+                        total_events_processed += block_logs.len();
+                        continue;
                     }
-                    Ok(Err(e)) => {
-                        tracing::error!("同步区块失败: {}", e);
+                    for log in block_logs {
+                        // 重启后的第一个区块或与实时模式重叠的区块可能已部分处理
+                        let event_id = EventId::from_log(log);
+                        if self.processed_events.contains(&event_id) {
+                            tracing::debug!("跳过已处理的事件: 区块 {}, 日志 {:?}", block_number, log.log_index);
+                            continue;
+                        }
+                        Self::dispatch_log_static(&self.database, &self.config, &self.event_signatures, log).await;
+                        self.processed_events.insert(event_id);
                     }
-                    Err(e) => {
-                        tracing::error!("任务执行失败: {}", e);
+                    // 只持久化最新区块的记录（回填较早的区块时不覆盖实时模式写入的记录）
+                    if self.processed_events.latest_block() == Some(*block_number) {
+                        if let Err(e) = self.database.set_processed_events(&self.processed_events.block_record(*block_number)) {
+                            tracing::error!("持久化区块 {} 的去重记录失败: {}", block_number, e);
+                        }
                     }
+                    tracing::debug!("区块 {} 处理了 {} 个事件", block_number, block_logs.len());
+                    total_events_processed += block_logs.len();
                 }
-            }
+                self.processed_events.prune(chunk_end);
+                metrics().processed_events_cache_size.set(self.processed_events.len() as i64);
+
+                // 更新最后同步区块号为当前分段的结束区块
+                let block_hash = Self::boundary_block_hash(web3, chunk_end).await;
+                self.database.set_last_synced_block(chunk_end, block_hash)?;
+                Self::prune_raw_log_archive(&self.database, &self.config, chunk_end);
+                tracing::debug!("已同步至区块 {}，累计处理事件数量: {}", chunk_end, total_events_processed);
 
-            // 更新最后同步区块号为当前批次的结束区块
-            self.database.set_last_synced_block(batch_end)?;
-            tracing::debug!("已同步至区块 {}，累计处理事件数量: {}", batch_end, total_events_processed);
+                current_block = chunk_end + 1;
 
-            current_block = batch_end + 1;
+                // 分段因结果数量超限被缩小时，本轮后续分段与其不再连续，丢弃剩余结果并从缩小后的结束区块继续
+                if chunk_end < requested_end {
+                    break;
+                }
+            }
         }
 
         tracing::info!("历史同步完成! 从区块 {} 同步到 {}, 总共处理了 {} 个事件",
                      start_block, end_block, total_events_processed);
 
-        Ok(())
-    }
-
-    /// 同步单个区块的事件（静态方法，用于并行处理）
-    async fn sync_single_block(
-        web3: web3::Web3<web3::transports::Http>,
-        database: Arc<Database>,
-        config: crate::config::AppConfig,
-        event_signatures: HashMap<String, H256>,
-        block_number: u64,
-    ) -> anyhow::Result<usize> {
-        // 获取区块号范围进行过滤（单个区块）
-        let filter = FilterBuilder::default()
-            .from_block(BlockNumber::Number(U64::from(block_number)))
-            .to_block(BlockNumber::Number(U64::from(block_number)))
-            .address(vec![
-                config.contracts.interest_manager.parse()?,
-                config.contracts.liquidation_manager.parse()?,
-                config.contracts.auction_manager.parse()?,
-                config.contracts.custodian.parse()?, // 添加CustodianFixed地址
-            ])
-            .build();
-
-        match web3.eth().logs(filter).await {
-            Ok(logs) => {
-                let mut processed_count = 0;
-
-                for log in logs {
-                    // 根据合约地址确定事件类型并处理
-                    // log.address 在有address过滤器的情况下总是Some
-                    if Self::contract_matches_static(&log.address, &config.contracts.interest_manager) {
-                        if let Err(e) = Self::process_interest_event_from_log_static(&database, &event_signatures, &log).await {
-                            tracing::error!("处理InterestManager事件失败: {}", e);
-                        }
-                    } else if Self::contract_matches_static(&log.address, &config.contracts.liquidation_manager) {
-                        if let Err(e) = Self::process_liquidation_event_from_log_static(&database, &event_signatures, &log).await {
-                            tracing::error!("处理LiquidationManager事件失败: {}", e);
-                        }
-                    } else if Self::contract_matches_static(&log.address, &config.contracts.auction_manager) {
-                        if let Err(e) = Self::process_auction_event_from_log_static(&database, &event_signatures, &log).await {
-                            tracing::error!("处理AuctionManager事件失败: {}", e);
-                        }
-                    } else if Self::contract_matches_static(&log.address, &config.contracts.custodian) {
-                        // 处理CustodianFixed事件
-                        if let Err(e) = Self::process_custodian_event_from_log_static(&database, &event_signatures, &log).await {
-                            tracing::error!("处理CustodianFixed事件失败: {}", e);
-                        }
-                    }
-
-                    processed_count += 1;
-                }
+        Ok(())
+    }
 
-                if processed_count > 0 {
-                    tracing::debug!("区块 {} 处理了 {} 个事件", block_number, processed_count);
-                }
+    /// 一次 eth_getLogs 查询 `[from_block, to_block]` 范围内所有监控合约的已处理事件日志
+    ///
+    /// 按合约地址和 topic0 过滤，节点只返回 Keeper 处理的事件。
+    /// 提供方返回结果数量超限错误时，将范围减半后重试。返回日志及实际查询到的结束区块。
+    async fn get_logs_chunk(
+        web3: &web3::Web3<FailoverTransport>,
+        addresses: &[Address],
+        topics: &[H256],
+        from_block: u64,
+        to_block: u64,
+    ) -> anyhow::Result<(Vec<web3::types::Log>, u64)> {
+        let mut to_block = to_block;
+        loop {
+            let filter = FilterBuilder::default()
+                .from_block(BlockNumber::Number(U64::from(from_block)))
+                .to_block(BlockNumber::Number(U64::from(to_block)))
+                .address(addresses.to_vec())
+                .topics(Some(topics.to_vec()), None, None, None)
+                .build();
 
-                Ok(processed_count)
+            match rpc_retry("eth_getLogs", || web3.eth().logs(filter.clone())).await {
+                Ok(logs) => return Ok((logs, to_block)),
+                Err(e) if is_log_limit_error(&e) && to_block > from_block => {
+                    let halved_end = from_block + (to_block - from_block + 1).div_ceil(2) - 1;
+                    tracing::warn!(
+                        "查询区块 {} - {} 的日志超出提供方限制，缩小为 {} - {} 后重试: {}",
+                        from_block, to_block, from_block, halved_end, e
+                    );
+                    to_block = halved_end;
+                }
+                Err(e) => {
+                    return Err(anyhow::anyhow!("获取区块 {} - {} 日志失败: {}", from_block, to_block, e));
+                }
             }
-            Err(e) => {
-                tracing::warn!("获取区块 {} 日志失败: {}", block_number, e);
-                Ok(0)
+        }
+    }
+
+    /// 根据合约地址将单条日志分发到对应的静态事件处理函数
+    ///
+    /// 单条日志处理失败只记录错误，不影响同一区块中其他日志的处理。
+    #[tracing::instrument(name = "log", skip_all, fields(
+        block_number = log.block_number.map(|n| n.as_u64()),
+        log_index = log.log_index.map(|i| i.as_u64()),
+    ))]
+    async fn dispatch_log_static(
+        database: &Arc<Database>,
+        config: &crate::config::AppConfig,
+        event_signatures: &HashMap<String, H256>,
+        log: &web3::types::Log,
+    ) {
+        Self::archive_log_if_enabled(database, config, log);
+        metrics().events_processed
+            .with_label_values(&[&event_metric_label(event_signatures, log)])
+            .inc();
+
+        // 根据合约地址确定事件类型并处理
+        // log.address 在有address过滤器的情况下总是Some
+        if !is_handled_event(event_signatures, log) {
+            tracing::trace!("跳过未处理的事件: 合约 {:?}, topic0 {:?}, 区块 {:?}", log.address, log.topics.first(), log.block_number);
+        } else if is_disabled_event(config, event_signatures, log) {
+            tracing::trace!("跳过已禁用的事件: 合约 {:?}, topic0 {:?}, 区块 {:?}", log.address, log.topics.first(), log.block_number);
+        } else if is_unwatched_position_event(config, event_signatures, log) {
+            tracing::trace!("跳过未关注 token 的持仓事件: 区块 {:?}, 日志 {:?}", log.block_number, log.log_index);
+        } else if Self::contract_matches_static(&log.address, &config.contracts.interest_manager) {
+            if let Err(e) = Self::process_interest_event_from_log_static(database, event_signatures, log).await {
+                tracing::error!("处理InterestManager事件失败: {}", e);
+            }
+        } else if Self::contract_matches_static(&log.address, &config.contracts.liquidation_manager) {
+            if let Err(e) = Self::process_liquidation_event_from_log_static(database, event_signatures, log).await {
+                tracing::error!("处理LiquidationManager事件失败: {}", e);
+            }
+        } else if Self::contract_matches_static(&log.address, &config.contracts.auction_manager) {
+            if let Err(e) = Self::process_auction_event_from_log_static(database, config, event_signatures, log).await {
+                tracing::error!("处理AuctionManager事件失败: {}", e);
+            }
+        } else if Self::contract_matches_static(&log.address, &config.contracts.custodian) {
+            // 处理CustodianFixed事件
+            if let Err(e) = Self::process_custodian_event_from_log_static(database, event_signatures, log).await {
+                tracing::error!("处理CustodianFixed事件失败: {}", e);
             }
         }
     }
 
+    /// 在处理前归档原始日志（仅在启用 archive_raw_logs 时）
+    fn archive_log_if_enabled(database: &Arc<Database>, config: &crate::config::AppConfig, log: &web3::types::Log) {
+        if !config.event_monitoring.archive_raw_logs {
+            return;
+        }
+        if let Err(e) = database.archive_raw_log(log) {
+            tracing::warn!("归档原始日志失败: 区块={:?}, 日志索引={:?}, 错误={}", log.block_number, log.log_index, e);
+        }
+    }
+
+    /// 按保留窗口清理归档日志（raw_log_retention_blocks 为0时永久保留）
+    fn prune_raw_log_archive(database: &Arc<Database>, config: &crate::config::AppConfig, current_block: u64) {
+        let retention = config.event_monitoring.raw_log_retention_blocks;
+        if !config.event_monitoring.archive_raw_logs || retention == 0 {
+            return;
+        }
+        if let Err(e) = database.prune_archived_raw_logs(current_block.saturating_sub(retention)) {
+            tracing::warn!("清理归档日志失败: {}", e);
+        }
+    }
+
     /// 静态方法版本的事件处理函数（用于历史同步）
 
     async fn process_interest_event_from_log_static(
@@ -465,6 +1647,7 @@ impl EventMonitor {
 
     async fn process_auction_event_from_log_static(
         database: &Arc<Database>,
+        config: &crate::config::AppConfig,
         event_signatures: &HashMap<String, H256>,
         log: &web3::types::Log,
     ) -> anyhow::Result<()> {
@@ -485,7 +1668,7 @@ impl EventMonitor {
             "Unknown"
         };
 
-        Self::process_auction_event_static(database, event_name, log).await
+        Self::process_auction_event_static(database, config, event_name, log).await
     }
 
     async fn process_custodian_event_from_log_static(
@@ -512,74 +1695,81 @@ impl EventMonitor {
         event_name: &str,
         log: &web3::types::Log,
     ) -> anyhow::Result<()> {
+        if Self::params_event_applied(database, event_name, log)? {
+            return Ok(());
+        }
+
         match event_name {
             "InterestRateChanged" => {
-                if log.topics.len() >= 3 {
-                    let new_rate = web3::types::U256::from_big_endian(&log.topics[2].as_bytes());
-                    database.update_annual_interest_rate(new_rate)?;
-                    tracing::trace!("同步历史事件：InterestManager: 利率更新为 {}", new_rate);
-                }
+                let event = event_abi::decode_log(event_name, log)?;
+                let new_rate = event.uint("newRate")?;
+                database.update_annual_interest_rate(new_rate)?;
+                tracing::trace!("同步历史事件：InterestManager: 利率更新为 {}", new_rate);
             }
             "PositionIncreased" => {
-                if log.topics.len() >= 3 {
-                    let user = Address::from_slice(&log.topics[1].as_bytes()[12..32]);
-                    let token_id = web3::types::U256::from_big_endian(&log.topics[2].as_bytes());
-
-                    if log.data.0.len() >= 96 {
-                        let total_amount = web3::types::U256::from_big_endian(&log.data.0[32..64]);
-                        let total_interest = web3::types::U256::from_big_endian(&log.data.0[64..96]);
-
-                        let position = match database.get_user_position(user, token_id) {
-                            Ok(Some(mut existing)) => {
-                                existing.amount = total_amount;
-                                existing.total_interest = total_interest;
-                                existing.timestamp = current_timestamp();
-                                existing
-                            },
-                            _ => {
-                                UserPosition {
-                                    user,
-                                    token_id,
-                                    amount: total_amount,
-                                    timestamp: current_timestamp(),
-                                    total_interest,
-                                    leverage: LeverageType::Conservative,
-                                    mint_price: web3::types::U256::zero(),
-                                }
-                            }
-                        };
-
-                        database.store_user_position(&position)?;
-                        tracing::trace!("同步历史事件：InterestManager: 持仓更新 - 用户: {:?}, TokenID: {}, 总数量: {}", user, token_id, total_amount);
+                let event = event_abi::decode_log(event_name, log)?;
+                let user = event.address("user")?;
+                let token_id = event.uint("tokenId")?;
+                let total_amount = event.uint("totalAmount")?;
+                let total_interest = event.uint("totalInterest")?;
+
+                let position = match database.get_user_position(user, token_id) {
+                    Ok(Some(existing)) if already_applied(existing.last_applied_log, log) => {
+                        tracing::debug!("PositionIncreased: 跳过已应用的事件 - 用户: {:?}, TokenID: {}", user, token_id);
+                        return Ok(());
                     }
-                }
+                    Ok(Some(mut existing)) => {
+                        existing.amount = total_amount;
+                        existing.total_interest = total_interest;
+                        existing.timestamp = current_timestamp();
+                        existing.last_applied_log = Some(log_position(log));
+                        existing
+                    },
+                    _ => {
+                        UserPosition {
+                            user,
+                            token_id,
+                            amount: total_amount,
+                            timestamp: current_timestamp(),
+                            total_interest,
+                            leverage: LeverageType::Conservative,
+                            mint_price: web3::types::U256::zero(),
+                            last_applied_log: Some(log_position(log)),
+                            epoch: database.contract_epoch(),
+                        }
+                    }
+                };
+
+                database.store_user_position(&position)?;
+                tracing::trace!("同步历史事件：InterestManager: 持仓更新 - 用户: {:?}, TokenID: {}, 总数量: {}", user, token_id, total_amount);
             }
             "InterestCollected" => {
-                if log.topics.len() >= 3 {
-                    let user = Address::from_slice(&log.topics[1].as_bytes()[12..32]);
-                    let token_id = web3::types::U256::from_big_endian(&log.topics[2].as_bytes());
-
-                    if log.data.0.len() >= 64 {
-                        let deduct_amount = web3::types::U256::from_big_endian(&log.data.0[0..32]);
-                        let interest_amount = web3::types::U256::from_big_endian(&log.data.0[32..64]);
-
-                        if let Ok(Some(mut position)) = database.get_user_position(user, token_id) {
-                            position.amount = position.amount - deduct_amount;
-                            position.total_interest = position.total_interest - interest_amount;
-                            position.timestamp = current_timestamp();
-
-                            if position.amount == web3::types::U256::zero() {
-                                database.delete_user_position(user, token_id)?;
-                            } else {
-                                database.store_user_position(&position)?;
-                            }
-                        }
+                let event = event_abi::decode_log(event_name, log)?;
+                let user = event.address("user")?;
+                let token_id = event.uint("tokenId")?;
+                let deduct_amount = event.uint("deductLAmountInWei")?;
+                let interest_amount = event.uint("interestAmount")?;
+
+                if let Ok(Some(mut position)) = database.get_user_position(user, token_id) {
+                    if already_applied(position.last_applied_log, log) {
+                        tracing::debug!("InterestCollected: 跳过已应用的事件 - 用户: {:?}, TokenID: {}", user, token_id);
+                        return Ok(());
+                    }
+                    position.amount = sub_or_clamp(position.amount, deduct_amount, "持仓数量", user, token_id);
+                    position.total_interest = sub_or_clamp(position.total_interest, interest_amount, "累计利息", user, token_id);
+                    position.timestamp = current_timestamp();
+                    position.last_applied_log = Some(log_position(log));
+
+                    if position.amount == web3::types::U256::zero() {
+                        database.delete_user_position(user, token_id)?;
+                    } else {
+                        database.store_user_position(&position)?;
                     }
                 }
             }
             _ => {}
         }
-        Ok(())
+        Self::mark_params_event_applied(database, event_name, log)
     }
 
     async fn process_liquidation_event_static(
@@ -587,140 +1777,129 @@ impl EventMonitor {
         event_name: &str,
         log: &web3::types::Log,
     ) -> anyhow::Result<()> {
+        if Self::params_event_applied(database, event_name, log)? {
+            return Ok(());
+        }
+
         match event_name {
             "ParameterChanged" => {
-                if log.topics.len() >= 2 {
-                    let parameter_bytes = log.topics[1].as_bytes();
-                    let value = if log.data.0.len() >= 32 {
-                        web3::types::U256::from_big_endian(&log.data.0[0..32])
-                    } else {
-                        return Ok(());
-                    };
-                    Self::update_liquidation_parameter_static(database, parameter_bytes, value).await?;
-                }
+                let event = event_abi::decode_log(event_name, log)?;
+                Self::update_liquidation_parameter_static(database, event.fixed_bytes("parameter")?, event.uint("value")?).await?;
             }
             "LiquidationConfigInfo" => {
-                if log.data.0.len() >= 128 {
-                    let adjustment_threshold = web3::types::U256::from_big_endian(&log.data.0[0..32]);
-                    let liquidation_threshold = web3::types::U256::from_big_endian(&log.data.0[32..64]);
-                    let penalty = web3::types::U256::from_big_endian(&log.data.0[64..96]);
-
-                    database.update_adjustment_threshold(adjustment_threshold)?;
-                    database.update_liquidation_threshold(liquidation_threshold)?;
-                    database.update_penalty(penalty)?;
-                }
+                let event = event_abi::decode_log(event_name, log)?;
+                database.update_adjustment_threshold(event.uint("adjustmentThreshold")?)?;
+                database.update_liquidation_threshold(event.uint("liquidationThreshold")?)?;
+                database.update_penalty(event.uint("penalty")?)?;
+                database.update_liquidation_enabled(event.bool("enabled")?)?;
             }
             "NetValueAdjusted" => {
-                if log.topics.len() >= 4 {
-                    let user = Address::from_slice(&log.topics[1].as_bytes()[12..32]);
-                    let to_token_id = web3::types::U256::from_big_endian(&log.topics[3].as_bytes());
-
-                    if log.data.0.len() >= 97 {
-                        let leverage_value = log.data.0[0];
-                        let new_mint_price = web3::types::U256::from_big_endian(&log.data.0[1..33]);
-                        let adjust_amount_in_wei = web3::types::U256::from_big_endian(&log.data.0[33..65]);
-
-                        let leverage = LeverageType::from_u8(leverage_value)?;
+                let event = event_abi::decode_log(event_name, log)?;
+                let user = event.address("user")?;
+                let to_token_id = event.uint("toTokenId")?;
+                let leverage_value = event.uint8("leverage")?;
+                let new_mint_price = event.uint("newMintPrice")?;
+                let adjust_amount_in_wei = event.uint("adjustAmountInWei")?;
+
+                let leverage = match LeverageType::from_u8(leverage_value) {
+                    Ok(leverage) => leverage,
+                    Err(e) => {
+                        tracing::warn!("NetValueAdjusted: 跳过杠杆类型无效的事件 - 原始字节: {}, 用户: {:?}, 到TokenID: {}, 错误: {}",
+                                     leverage_value, user, to_token_id, e);
+                        return Ok(());
+                    }
+                };
 
-                        let existing_position = database.get_user_position(user, to_token_id)?;
+                let existing_position = database.get_user_position(user, to_token_id)?;
 
-                        match existing_position {
-                            Some(mut position) => {
-                                position.leverage = leverage.clone();
-                                position.mint_price = new_mint_price;
-                                database.store_user_position(&position)?;
-                            }
-                            None => {
-                                let new_position = UserPosition {
-                                    user,
-                                    token_id: to_token_id,
-                                    amount: adjust_amount_in_wei,
-                                    timestamp: current_timestamp(),
-                                    total_interest: web3::types::U256::zero(),
-                                    leverage: leverage.clone(),
-                                    mint_price: new_mint_price,
-                                };
-                                database.store_user_position(&new_position)?;
-                            }
-                        }
+                match existing_position {
+                    Some(position) if already_applied(position.last_applied_log, log) => {
+                        tracing::debug!("NetValueAdjusted: 跳过已应用的事件 - 用户: {:?}, 到TokenID: {}", user, to_token_id);
+                    }
+                    Some(mut position) => {
+                        position.leverage = leverage.clone();
+                        position.mint_price = new_mint_price;
+                        position.last_applied_log = Some(log_position(log));
+                        database.store_user_position(&position)?;
+                    }
+                    None => {
+                        let new_position = UserPosition {
+                            user,
+                            token_id: to_token_id,
+                            amount: adjust_amount_in_wei,
+                            timestamp: current_timestamp(),
+                            total_interest: web3::types::U256::zero(),
+                            leverage: leverage.clone(),
+                            mint_price: new_mint_price,
+                            last_applied_log: Some(log_position(log)),
+                            epoch: database.contract_epoch(),
+                        };
+                        database.store_user_position(&new_position)?;
                     }
                 }
             }
             _ => {}
         }
-        Ok(())
+        Self::mark_params_event_applied(database, event_name, log)
     }
 
     async fn process_auction_event_static(
         database: &Arc<Database>,
+        config: &crate::config::AppConfig,
         event_name: &str,
         log: &web3::types::Log,
     ) -> anyhow::Result<()> {
+        if Self::params_event_applied(database, event_name, log)? {
+            return Ok(());
+        }
+
         match event_name {
             "ParameterChanged" => {
-                if log.topics.len() >= 2 {
-                    let parameter_bytes = log.topics[1].as_bytes();
-                    let value = if log.data.0.len() >= 32 {
-                        web3::types::U256::from_big_endian(&log.data.0[0..32])
-                    } else {
-                        return Ok(());
-                    };
-                    Self::update_auction_parameter_static(database, parameter_bytes, value).await?;
-                }
+                let event = event_abi::decode_log(event_name, log)?;
+                Self::update_auction_parameter_static(database, event.fixed_bytes("parameter")?, event.uint("value")?).await?;
             }
             "AuctionStarted" => {
-                if log.topics.len() >= 4 {
-                    let auction_id = web3::types::U256::from_big_endian(&log.topics[1].as_bytes());
-                    let token_id = web3::types::U256::from_big_endian(&log.topics[2].as_bytes());
-
-                    if log.data.0.len() >= 128 {
-                        let starting_price = web3::types::U256::from_big_endian(&log.data.0[0..32]);
-                        let underlying_amount = web3::types::U256::from_big_endian(&log.data.0[32..64]);
-                        let original_owner = Address::from_slice(&log.data.0[76..96]);
-                        let reward_amount = web3::types::U256::from_big_endian(&log.data.0[96..128]);
-                        let triggerer = Address::from_slice(&log.topics[3].as_bytes()[12..32]);
-
-                        let auction_info = AuctionInfo {
-                            auction_id,
-                            starting_price,
-                            underlying_amount,
-                            original_owner,
-                            token_id,
-                            triggerer: triggerer.clone(),
-                            reward_amount,
-                            start_time: current_timestamp(),
-                        };
-
-                        database.store_auction(&auction_info)?;
-                        tracing::trace!("同步历史事件：AuctionManager: 新拍卖开始 - ID: {}", auction_id);
-                    }
-                }
+                let event = event_abi::decode_log(event_name, log)?;
+                let auction_id = event.uint("auctionId")?;
+                let token_id = event.uint("tokenId")?;
+                let original_owner = event.address("originalOwner")?;
+
+                let auction_info = AuctionInfo {
+                    auction_id,
+                    starting_price: event.uint("startingPrice")?,
+                    underlying_amount: event.uint("underlyinglAmount")?,
+                    original_owner,
+                    token_id,
+                    triggerer: event.address("triggerer")?,
+                    reward_amount: event.uint("rewardAmount")?,
+                    start_time: Self::log_block_timestamp_static(database, config, log),
+                    epoch: database.contract_epoch(),
+                };
+
+                database.store_auction(&auction_info)?;
+                database.clear_liquidation_in_flight(original_owner, token_id)?;
+                tracing::trace!("同步历史事件：AuctionManager: 新拍卖开始 - ID: {}", auction_id);
             }
             "AuctionReset" => {
-                if log.topics.len() >= 4 {
-                    let auction_id = web3::types::U256::from_big_endian(&log.topics[1].as_bytes());
-
-                    if log.data.0.len() >= 32 {
-                        let new_starting_price = web3::types::U256::from_big_endian(&log.data.0[0..32]);
-
-                        if let Ok(Some(mut auction_info)) = database.get_auction(auction_id) {
-                            auction_info.starting_price = new_starting_price;
-                            auction_info.start_time = current_timestamp();
-                            database.store_auction(&auction_info)?;
-                        }
-                    }
+                let event = event_abi::decode_log(event_name, log)?;
+                let auction_id = event.uint("auctionId")?;
+
+                if let Ok(Some(mut auction_info)) = database.get_auction(auction_id) {
+                    auction_info.starting_price = event.uint("newStartingPrice")?;
+                    auction_info.underlying_amount = event.uint("underlyingAmount")?;
+                    auction_info.reward_amount = event.uint("rewardAmount")?;
+                    auction_info.start_time = Self::log_block_timestamp_static(database, config, log);
+                    database.store_auction(&auction_info)?;
                 }
             }
             "AuctionRemoved" => {
-                if log.topics.len() >= 2 {
-                    let auction_id = web3::types::U256::from_big_endian(&log.topics[1].as_bytes());
-                    database.delete_auction(auction_id)?;
-                    tracing::trace!("同步历史事件：拍卖 {} 已结束/取消", auction_id);
-                }
+                let auction_id = event_abi::decode_log(event_name, log)?.uint("auctionId")?;
+                database.delete_auction(auction_id)?;
+                tracing::trace!("同步历史事件：拍卖 {} 已结束/取消", auction_id);
             }
             _ => {}
         }
-        Ok(())
+        Self::mark_params_event_applied(database, event_name, log)
     }
 
     async fn process_custodian_event_static(
@@ -730,38 +1909,47 @@ impl EventMonitor {
     ) -> anyhow::Result<()> {
         match event_name {
             "Mint" => {
-                if log.topics.len() >= 2 {
-                    let user = Address::from_slice(&log.topics[1].as_bytes()[12..32]);
-
-                    if log.data.0.len() >= 161 {
-                        let token_id = web3::types::U256::from_big_endian(&log.data.0[0..32]);
-                        let leverage_value = log.data.0[64];
-                        let mint_price = web3::types::U256::from_big_endian(&log.data.0[65..97]);
-                        let l_amount = web3::types::U256::from_big_endian(&log.data.0[129..161]);
-
-                        let leverage = LeverageType::from_u8(leverage_value)?;
+                let event = event_abi::decode_log(event_name, log)?;
+                let user = event.address("user")?;
+                let token_id = event.uint("tokenId")?;
+                let leverage_value = event.uint8("leverageLevel")?;
+                let mint_price = event.uint("mintPriceInWei")?;
+                let l_amount = event.uint("lAmountInWei")?;
+
+                let leverage = match LeverageType::from_u8(leverage_value) {
+                    Ok(leverage) => leverage,
+                    Err(e) => {
+                        tracing::warn!("Mint: 跳过杠杆类型无效的事件 - 原始字节: {}, 用户: {:?}, TokenID: {}, 错误: {}",
+                                     leverage_value, user, token_id, e);
+                        return Ok(());
+                    }
+                };
 
-                        let existing_position = database.get_user_position(user, token_id)?;
+                let existing_position = database.get_user_position(user, token_id)?;
 
-                        match existing_position {
-                            Some(mut position) => {
-                                position.mint_price = mint_price;
-                                position.leverage = leverage.clone();
-                                database.store_user_position(&position)?;
-                            }
-                            None => {
-                                let new_position = UserPosition {
-                                    user,
-                                    token_id,
-                                    amount: l_amount,
-                                    timestamp: current_timestamp(),
-                                    total_interest: web3::types::U256::zero(),
-                                    leverage: leverage.clone(),
-                                    mint_price,
-                                };
-                                database.store_user_position(&new_position)?;
-                            }
-                        }
+                match existing_position {
+                    Some(position) if already_applied(position.last_applied_log, log) => {
+                        tracing::debug!("Mint: 跳过已应用的事件 - 用户: {:?}, TokenID: {}", user, token_id);
+                    }
+                    Some(mut position) => {
+                        position.mint_price = mint_price;
+                        position.leverage = leverage.clone();
+                        position.last_applied_log = Some(log_position(log));
+                        database.store_user_position(&position)?;
+                    }
+                    None => {
+                        let new_position = UserPosition {
+                            user,
+                            token_id,
+                            amount: l_amount,
+                            timestamp: current_timestamp(),
+                            total_interest: web3::types::U256::zero(),
+                            leverage: leverage.clone(),
+                            mint_price,
+                            last_applied_log: Some(log_position(log)),
+                            epoch: database.contract_epoch(),
+                        };
+                        database.store_user_position(&new_position)?;
                     }
                 }
             }
@@ -821,12 +2009,33 @@ impl EventMonitor {
             "priceDropThreshold" => database.update_price_drop_threshold(value)?,
             "percentageReward" => database.update_percentage_reward(value)?,
             "fixedReward" => database.update_fixed_reward(value)?,
+            "circuitBreaker" => database.update_circuit_breaker(!value.is_zero())?,
             _ => {}
         }
 
         Ok(())
     }
 
+    /// 参数类事件是否已经应用过（系统参数记录的最后应用日志不早于该日志）
+    fn params_event_applied(database: &Database, event_name: &str, log: &web3::types::Log) -> anyhow::Result<bool> {
+        if !is_params_event(event_name) {
+            return Ok(false);
+        }
+        let applied = already_applied(database.get_system_params()?.last_applied_log, log);
+        if applied {
+            tracing::debug!("{}: 跳过已应用的参数事件 - 区块: {:?}, 日志索引: {:?}", event_name, log.block_number, log.log_index);
+        }
+        Ok(applied)
+    }
+
+    /// 参数类事件应用后记录其日志位置
+    fn mark_params_event_applied(database: &Database, event_name: &str, log: &web3::types::Log) -> anyhow::Result<()> {
+        if is_params_event(event_name) {
+            database.mark_params_log_applied(log_position(log))?;
+        }
+        Ok(())
+    }
+
     fn contract_matches_static(contract_address: &web3::types::Address, config_address: &str) -> bool {
         if let Ok(parsed_address) = config_address.parse::<web3::types::Address>() {
             contract_address == &parsed_address
@@ -836,80 +2045,102 @@ impl EventMonitor {
     }
 
     /// 处理指定区块的事件（实时模式使用）
-    async fn process_block_events(&mut self, block_number: u64) -> anyhow::Result<()> {
+    #[tracing::instrument(name = "block", skip(self))]
+    async fn process_block_events(&mut self, block_number: u64, block_hash: Option<H256>) -> anyhow::Result<()> {
         let web3 = self.web3_http.as_ref().ok_or_else(|| anyhow::anyhow!("HTTP客户端未初始化"))?;
 
         // 获取区块号范围进行过滤（当前区块）
         let filter = FilterBuilder::default()
             .from_block(BlockNumber::Number(U64::from(block_number)))
             .to_block(BlockNumber::Number(U64::from(block_number)))
-            .address(vec![
-                self.config.contracts.interest_manager.parse()?,
-                self.config.contracts.liquidation_manager.parse()?,
-                self.config.contracts.auction_manager.parse()?,
-                self.config.contracts.custodian.parse()?, // 添加CustodianFixed地址
-            ])
+            .address(self.monitored_addresses()?)
+            .topics(Some(self.handled_topics()), None, None, None)
             .build();
 
-        match web3.eth().logs(filter).await {
-            Ok(logs) => {
+        match rpc_retry("eth_getLogs", || web3.eth().logs(filter.clone())).await {
+            Ok(mut logs) => {
+                logs.sort_by_key(log_position);
                 let mut processed_count = 0;
                 for log in logs {
-                    // 去重检查
-                    let event_id = EventId {
-                        block_number: log.block_number.unwrap_or_default().as_u64(),
-                        transaction_index: log.transaction_index.unwrap_or_default().as_usize(),
-                        log_index: log.log_index.unwrap_or_default().as_usize(),
-                    };
-
-                    if self.processed_events.contains(&event_id) {
-                        tracing::debug!("跳过已处理的事件: {:?}", event_id);
-                        continue;
-                    }
-
-                    // 根据合约地址确定事件类型并处理
-                    // log.address 在有address过滤器的情况下总是Some
-                    if self.contract_matches(&log.address, &self.config.contracts.interest_manager) {
-                        if let Err(e) = self.process_interest_event_from_log(&log).await {
-                            tracing::error!("处理InterestManager事件失败: {}", e);
-                        }
-                    } else if self.contract_matches(&log.address, &self.config.contracts.liquidation_manager) {
-                        if let Err(e) = self.process_liquidation_event_from_log(&log).await {
-                            tracing::error!("处理LiquidationManager事件失败: {}", e);
-                        }
-                    } else if self.contract_matches(&log.address, &self.config.contracts.auction_manager) {
-                        if let Err(e) = self.process_auction_event_from_log(&log).await {
-                            tracing::error!("处理AuctionManager事件失败: {}", e);
-                        }
-                    } else if self.contract_matches(&log.address, &self.config.contracts.custodian) {
-                        // 处理CustodianFixed事件
-                        if let Err(e) = self.process_custodian_event_from_log(&log).await {
-                            tracing::error!("处理CustodianFixed事件失败: {}", e);
-                        }
+                    if self.dispatch_log(&log).await {
+                        processed_count += 1;
                     }
-
-                    // 标记为已处理
-                    self.processed_events.insert(event_id);
-                    processed_count += 1;
                 }
+                self.processed_events.prune(block_number);
+                metrics().processed_events_cache_size.set(self.processed_events.len() as i64);
 
                 if processed_count > 0 {
-                    tracing::info!("处理了区块 {} 的事件数量: {}", block_number, processed_count);
+                    routine_log!(self, "处理了区块 {} 的事件数量: {}", block_number, processed_count);
                 }
 
                 // 实时监听模式下，处理完区块后更新最后同步区块号
                 if processed_count > 0 || block_number > 0 {
-                    self.database.set_last_synced_block(block_number)?;
+                    self.database.set_last_synced_block(block_number, block_hash)?;
+                    Self::prune_raw_log_archive(&self.database, &self.config, block_number);
                 }
             }
             Err(e) => {
-                tracing::warn!("获取区块 {} 日志失败: {}", block_number, e);
+                // 不推进区块头进度，下一个区块头到达时按缺口回补该区块
+                return Err(anyhow::anyhow!("获取区块 {} 日志失败: {}", block_number, e));
             }
         }
 
         Ok(())
     }
 
+    /// 处理一条实时日志：去重后按合约地址分发，并持久化所在区块的去重记录
+    ///
+    /// 日志已处理过时跳过并返回 false。
+    async fn dispatch_log(&mut self, log: &web3::types::Log) -> bool {
+        // 去重检查
+        let event_id = EventId::from_log(log);
+
+        if self.processed_events.contains(&event_id) {
+            tracing::debug!("跳过已处理的事件: {:?}", event_id);
+            return false;
+        }
+
+        Self::archive_log_if_enabled(&self.database, &self.config, log);
+        metrics().events_processed
+            .with_label_values(&[&event_metric_label(&self.event_signatures, log)])
+            .inc();
+
+        // 根据合约地址确定事件类型并处理
+        // log.address 在有address过滤器的情况下总是Some
+        if !is_handled_event(&self.event_signatures, log) {
+            tracing::trace!("跳过未处理的事件: 合约 {:?}, topic0 {:?}, 区块 {:?}", log.address, log.topics.first(), log.block_number);
+        } else if is_disabled_event(&self.config, &self.event_signatures, log) {
+            tracing::trace!("跳过已禁用的事件: 合约 {:?}, topic0 {:?}, 区块 {:?}", log.address, log.topics.first(), log.block_number);
+        } else if is_unwatched_position_event(&self.config, &self.event_signatures, log) {
+            tracing::trace!("跳过未关注 token 的持仓事件: 区块 {:?}, 日志 {:?}", log.block_number, log.log_index);
+        } else if self.contract_matches(&log.address, &self.config.contracts.interest_manager) {
+            if let Err(e) = self.process_interest_event_from_log(log).await {
+                tracing::error!("处理InterestManager事件失败: {}", e);
+            }
+        } else if self.contract_matches(&log.address, &self.config.contracts.liquidation_manager) {
+            if let Err(e) = self.process_liquidation_event_from_log(log).await {
+                tracing::error!("处理LiquidationManager事件失败: {}", e);
+            }
+        } else if self.contract_matches(&log.address, &self.config.contracts.auction_manager) {
+            if let Err(e) = self.process_auction_event_from_log(log).await {
+                tracing::error!("处理AuctionManager事件失败: {}", e);
+            }
+        } else if self.contract_matches(&log.address, &self.config.contracts.custodian) {
+            // 处理CustodianFixed事件
+            if let Err(e) = self.process_custodian_event_from_log(log).await {
+                tracing::error!("处理CustodianFixed事件失败: {}", e);
+            }
+        }
+
+        // 标记为已处理，并立即持久化本区块的去重记录，区块处理中途重启时也不会重复处理
+        let block_number = event_id.block_number;
+        self.processed_events.insert(event_id);
+        if let Err(e) = self.database.set_processed_events(&self.processed_events.block_record(block_number)) {
+            tracing::error!("持久化区块 {} 的去重记录失败: {}", block_number, e);
+        }
+        true
+    }
+
     /// 根据事件签名确定事件名称并处理
     async fn process_interest_event_from_log(&self, log: &web3::types::Log) -> anyhow::Result<()> {
         if log.topics.is_empty() {
@@ -980,248 +2211,6 @@ impl EventMonitor {
         self.process_custodian_event(event_name, log).await
     }
 
-    /// 生产级事件缓存清理策略
-    ///
-    /// 采用多层次的智能清理策略，结合：
-    /// - 内存阈值控制：防止内存溢出
-    /// - 时间窗口策略：优先清理过期事件
-    /// - 自适应批次清理：分阶段渐进式清理
-    /// - 重要事件保护：确保最近事件不被过度清理
-    /// - 性能监控：详细的清理统计和耗时追踪
-    fn cleanup_processed_events_cache(&mut self) {
-        let cleanup_start = std::time::Instant::now();
-        let initial_size = self.processed_events.len();
-
-        // === 第一阶段：快速健康检查 ===
-        // 获取配置参数（生产环境中应该从配置加载）
-        const MAX_CACHE_SIZE: usize = 5000;      // 硬性内存限制，超过必须清理
-        const SOFT_CACHE_SIZE: usize = 3500;     // 软性阈值，开始轻量级清理
-        const TARGET_CACHE_SIZE: usize = 2000;   // 理想缓存大小
-        const MIN_RETAIN_SIZE: usize = 500;      // 最少保留的事件数，防过度清理
-        const CLEANUP_TIME_WINDOW: u64 = 300;    // 5分钟时间窗口
-
-        // 如果缓存大小正常，跳过清理
-        if initial_size <= SOFT_CACHE_SIZE {
-            return;
-        }
-
-        // 决定清理紧急程度
-        let cleanup_urgency = if initial_size >= MAX_CACHE_SIZE {
-            CleanupUrgency::Critical     // 必须清理，系统压力大
-        } else if initial_size >= SOFT_CACHE_SIZE {
-            CleanupUrgency::Moderate     // 适度清理，预防性
-        } else {
-            CleanupUrgency::Light         // 轻度清理，维护性
-        };
-
-        tracing::debug!(
-            "启动事件缓存清理 - 大小: {}, 紧急程度: {:?}",
-            initial_size, cleanup_urgency
-        );
-
-        // === 第二阶段：收集事件元数据 ===
-        let current_timestamp = current_timestamp();
-        let cleanup_deadline = current_timestamp.saturating_sub(CLEANUP_TIME_WINDOW);
-
-        // 预分配合理的容量，避免频繁重分配
-        let estimated_capacity = initial_size;
-        let mut event_metadata = Vec::with_capacity(estimated_capacity);
-        let mut block_timestamp_cache = HashMap::with_capacity(initial_size / 4); // 区块倾向于连续
-
-        // 为每个事件收集元数据
-        for event_id in &self.processed_events {
-            let block_num = event_id.block_number;
-            let estimated_ts = self.get_or_cache_block_timestamp(block_num, &mut block_timestamp_cache);
-            let is_hot = estimated_ts >= cleanup_deadline;
-
-            event_metadata.push(EventMetadata {
-                event_id: event_id.clone(),
-                timestamp: estimated_ts,
-                is_hot,
-                priority: self.calculate_event_priority(event_id),
-            });
-        }
-
-        // === 第三阶段：智能事件评分排序 ===
-        // 按清理优先级排序：先清理低优先级的过期事件
-        event_metadata.sort_by(|a, b| {
-            match (a.is_hot, b.is_hot) {
-                (true, false) => std::cmp::Ordering::Greater,  // 热点事件优先保留
-                (false, true) => std::cmp::Ordering::Less,     // 过期事件后处理
-                _ => match a.priority.cmp(&b.priority) {
-                    std::cmp::Ordering::Equal => a.timestamp.cmp(&b.timestamp), // 同优先级按时间排序
-                    ordering => ordering,
-                }
-            }
-        });
-
-        // === 第四阶段：分层清理策略 ===
-        let mut events_to_remove = Vec::new();
-        let mut retained_events = HashSet::new();
-
-        // 根据清理紧急程度采用不同策略
-        match cleanup_urgency {
-            CleanupUrgency::Critical => {
-                // 紧急清理：快速达到安全阈值
-                self.aggressive_cleanup(&event_metadata, &mut events_to_remove, &mut retained_events);
-            }
-            CleanupUrgency::Moderate => {
-                // 适度清理：平衡性能和内存
-                self.balanced_cleanup(&event_metadata, &mut events_to_remove, &mut retained_events);
-            }
-            CleanupUrgency::Light => {
-                // 轻度清理：最小化影响，保留近期事件
-                self.conservative_cleanup(&event_metadata, &mut events_to_remove, &mut retained_events);
-            }
-        }
-
-        // === 第五阶段：后处理验证 ===
-        // 确保清理后的状态满足基本要求
-        self.post_cleanup_validation(&mut events_to_remove, &mut retained_events, MIN_RETAIN_SIZE);
-
-        // === 第六阶段：执行清理 ===
-        let remove_count = events_to_remove.len();
-        for event_id in &events_to_remove {
-            self.processed_events.remove(event_id);
-        }
-
-        // === 第七阶段：统计和监控 ===
-        let final_size = self.processed_events.len();
-        let cleanup_duration = cleanup_start.elapsed();
-        let cleanup_efficiency = if initial_size > 0 {
-            (remove_count as f64 / initial_size as f64) * 100.0
-        } else { 0.0 };
-
-        // 输出详细的清理报告
-        tracing::info!(
-            "事件缓存清理完成 - 大小: {}=>{}(减少{}), 效率: {:.1}%, 用时: {:.2}ms",
-            initial_size, final_size, remove_count, cleanup_efficiency, cleanup_duration.as_millis()
-        );
-
-        // 额外监控指标
-        if final_size >= MAX_CACHE_SIZE {
-            tracing::error!(
-                "缓存大小仍超出限制 - 当前: {}, 可能存在清理策略问题",
-                final_size
-            );
-        } else if final_size <= MIN_RETAIN_SIZE && initial_size > MIN_RETAIN_SIZE {
-            tracing::warn!(
-                "事件缓存过小 - 当前: {}, 可能影响事件去重效果",
-                final_size
-            );
-        }
-
-        // 验证清理后的缓存完整性
-        debug_assert!(final_size <= MAX_CACHE_SIZE, "清理后缓存大小应在安全范围内");
-        debug_assert!(final_size >= MIN_RETAIN_SIZE || final_size == 0,
-                     "保留的事件数应该足够或者缓存为空");
-    }
-
-    ///  计算事件的清理优先级
-    /// 负数=优先保留，正数=优先清理，0=中性
-    fn calculate_event_priority(&self, event_id: &EventId) -> i8 {
-        // 简单的优先级策略：区块号越大越新，越应该保留
-        // 生产环境中可以根据事件类型、合约重要性等因素调整
-
-        // 基础优先级：较新的事件获得保留优先级
-        if event_id.block_number > 20_000_000 {
-            // 较新的主网区块，优先保留
-            -1
-        } else {
-            // 较旧的区块，可以考虑清理
-            1
-        }
-    }
-
-    /// 获取或缓存区块时间戳
-    fn get_or_cache_block_timestamp(&self, block_number: u64, cache: &mut HashMap<u64, u64>) -> u64 {
-        if let Some(&cached) = cache.get(&block_number) {
-            cached
-        } else {
-            let estimated = self.estimate_block_timestamp(block_number);
-            cache.insert(block_number, estimated);
-            estimated
-        }
-    }
-
-    /// 紧急清理策略：快速达到安全阈值
-    fn aggressive_cleanup(&self, metadata: &[EventMetadata], to_remove: &mut Vec<EventId>, retained: &mut HashSet<EventId>) {
-        let mut remove_count = 0;
-
-        // 第一轮：清理所有过期事件
-        for meta in metadata {
-            if meta.timestamp < current_timestamp().saturating_sub(300) { // 5分钟前
-                to_remove.push(meta.event_id.clone());
-                remove_count += 1;
-            } else {
-                retained.insert(meta.event_id.clone());
-                if retained.len() >= 1000 { // 至少保留1000个最近事件
-                    break;
-                }
-            }
-        }
-
-        // 如果还没达到安全阈值，继续清理
-        if self.processed_events.len() - remove_count > 2500 {
-            // 继续清理直到达到安全大小
-            for meta in metadata.iter().rev() { // 从最老的开始清理
-                if !retained.contains(&meta.event_id) {
-                    to_remove.push(meta.event_id.clone());
-                    remove_count += 1;
-                    if self.processed_events.len() - remove_count <= 2000 {
-                        break;
-                    }
-                }
-            }
-        }
-    }
-
-    /// 平衡清理策略：考虑时间窗口和事件优先级
-    fn balanced_cleanup(&self, metadata: &[EventMetadata], to_remove: &mut Vec<EventId>, retained: &mut HashSet<EventId>) {
-        let mut target_removals = self.processed_events.len().saturating_sub(2000).saturating_sub(500);
-
-        // 优先清理低优先级的过期事件
-        for meta in metadata.iter().rev() { // 从最旧的开始遍历
-            if target_removals == 0 {
-                break;
-            }
-
-            if meta.priority > 0 && !meta.is_hot { // 低优先级且过期
-                to_remove.push(meta.event_id.clone());
-                target_removals -= 1;
-            } else {
-                retained.insert(meta.event_id.clone());
-            }
-        }
-    }
-
-    /// 保守清理策略：仅清理明显过期且低价值的事件
-    fn conservative_cleanup(&self, metadata: &[EventMetadata], to_remove: &mut Vec<EventId>, retained: &mut HashSet<EventId>) {
-        // 只清理明显过期且低优先级的事件
-        for meta in metadata.iter().rev() {
-            if meta.priority > 1 && meta.timestamp < current_timestamp().saturating_sub(600) { // 10分钟前
-                to_remove.push(meta.event_id.clone());
-            } else {
-                retained.insert(meta.event_id.clone());
-            }
-        }
-    }
-
-    /// 清理后验证和修正
-    fn post_cleanup_validation(&self, to_remove: &mut Vec<EventId>, retained: &mut HashSet<EventId>, min_retain: usize) {
-        // 确保至少保留最小数量的事件
-        if retained.len() < min_retain && !self.processed_events.is_empty() {
-            // 如果保留的事件太少，从删除列表中恢复一些
-            let need_recover = min_retain.saturating_sub(retained.len());
-
-            // 从删除列表末尾恢复最近的几条（模拟栈的行为）
-            for i in (0..need_recover.min(to_remove.len())).rev() {
-                retained.insert(to_remove.swap_remove(i));
-                // 成功恢复了一条事件
-            }
-        }
-    }
-
     /// 根据区块号获取区块时间戳（生产级实现）
     /// 优先从数据库缓存获取，如果没有则从RPC获取并缓存
     async fn get_block_timestamp(&self, block_number: u64) -> u64 {
@@ -1233,7 +2222,8 @@ impl EventMonitor {
         // 缓存未命中，从RPC获取
         if let Some(web3) = &self.web3_http {
             // 尝试获取区块信息
-            match web3.eth().block(web3::types::BlockId::Number(web3::types::BlockNumber::Number(U64::from(block_number)))).await {
+            let block_id = web3::types::BlockId::Number(web3::types::BlockNumber::Number(U64::from(block_number)));
+            match rpc_retry("eth_getBlockByNumber", || web3.eth().block(block_id)).await {
                 Ok(Some(block)) => {
                     let timestamp_u64 = block.timestamp.as_u64();
                     // 缓存到数据库
@@ -1243,534 +2233,408 @@ impl EventMonitor {
                     tracing::debug!("从RPC获取并缓存区块时间戳: 区块={}, 时间戳={}", block_number, timestamp_u64);
                     return timestamp_u64;
                 }
-                Ok(None) => {
-                    tracing::warn!("区块 {} 不存在（可能超出当前链高度）", block_number);
-                }
-                Err(e) => {
-                    tracing::warn!("从RPC获取区块 {} 时间戳失败，将使用估算值: {}", block_number, e);
-                }
-            }
-        }
-
-        // RPC获取失败，使用估算算法作为fallback
-        let estimated = self.estimate_block_timestamp_fallback(block_number);
-        tracing::debug!("使用估算值作为区块时间戳fallback: 区块={}, 时间戳={}", block_number, estimated);
-        estimated
-    }
-
-    /// 根据区块号估算区块时间戳（fallback算法）
-    /// 当RPC不可用时使用，用于确保服务连续性
-    fn estimate_block_timestamp_fallback(&self, block_number: u64) -> u64 {
-        // 简化的估算实现：基于已知的以太坊出块规律（约12秒一个区块）
-        // 这些基准值应该是定期更新的，不应该是hardcoded
-
-        // 基准点：使用一个相对较新的区块作为基准
-        // 注意：这些值在生产环境中应该根据当前链状态定期更新
-        const BASE_BLOCK: u64 = 18_000_000;
-        const BASE_TIMESTAMP: u64 = 1_670_534_400; // 2022-12-15 00:00:00 UTC（已校准的基准值）
-        const BLOCKS_PER_SECOND: f64 = 1.0 / 12.0; // 以太坊平均出块时间
-
-        if block_number >= BASE_BLOCK {
-            let blocks_diff = block_number - BASE_BLOCK;
-            BASE_TIMESTAMP + (blocks_diff as f64 / BLOCKS_PER_SECOND) as u64
-        } else {
-            let blocks_diff = BASE_BLOCK - block_number;
-            BASE_TIMESTAMP.saturating_sub((blocks_diff as f64 / BLOCKS_PER_SECOND) as u64)
-        }
-    }
-
-    /// 同步版本的区块时间戳获取（用于事件缓存清理）
-    /// 这个方法主要用于不需要async的方法中，如缓存清理时的优先级计算
-    fn estimate_block_timestamp(&self, block_number: u64) -> u64 {
-        // 首先尝试从缓存获取
-        if let Ok(Some(cached_timestamp)) = self.database.get_block_timestamp(block_number) {
-            return cached_timestamp;
-        }
-
-        // 缓存不可用，使用估算fallback
-        self.estimate_block_timestamp_fallback(block_number)
-    }
-
-    async fn monitor_all_events(&self) -> anyhow::Result<()> {
-        // 监听 InterestManager 事件
-        self.monitor_interest_manager_events().await?;
-
-        // 监听 LiquidationManager 事件
-        self.monitor_liquidation_manager_events().await?;
-
-        // 监听 AuctionManager 事件
-        self.monitor_auction_manager_events().await?;
-
-        Ok(())
-    }
-
-    async fn monitor_interest_manager_events(&self) -> anyhow::Result<()> {
-        let contract_address = self.config.contracts.interest_manager.parse()?;
-
-        // InterestManager 事件签名
-        let events = vec![
-            ("InterestRateChanged", "InterestRateChanged(uint256,uint256)"),
-            ("PositionIncreased", "PositionIncreased(address,uint256,uint256,uint256,uint256,uint8)"),
-            ("PositionOpened", "PositionOpened(address,uint256,uint256,uint256,uint8)"),
-            ("InterestCollected", "InterestCollected(address,uint256,uint256,uint256)"),
-        ];
-
-        for (event_name, signature) in events {
-            let topic = web3::signing::keccak256(signature.as_bytes());
-            let filter = FilterBuilder::default()
-                .address(vec![contract_address])
-                .topics(Some(vec![H256::from_slice(&topic)]), None, None, None)
-                .build();
-
-            match self.web3_http.as_ref().ok_or_else(|| anyhow::anyhow!("HTTP客户端未初始化"))?.eth().logs(filter).await {
-                Ok(logs) => {
-                    for log in logs {
-                        self.process_interest_event(event_name, &log).await?;
-                    }
-                }
-                Err(e) => {
-                    tracing::warn!("获取 {} 事件失败: {}", event_name, e);
-                }
-            }
-        }
-
-        Ok(())
-    }
-
-    async fn monitor_liquidation_manager_events(&self) -> anyhow::Result<()> {
-        let contract_address = self.config.contracts.liquidation_manager.parse()?;
-
-        let events = vec![
-            ("ParameterChanged", "ParameterChanged(bytes32,uint256)"),
-            ("LiquidationConfigInfo", "LiquidationConfigInfo(uint256,uint256,uint256,bool)"),
-            ("NetValueAdjusted", "NetValueAdjusted(address,uint256,uint256,uint8,uint256,uint256,uint256)"),
-        ];
-
-        for (event_name, signature) in events {
-            let topic = web3::signing::keccak256(signature.as_bytes());
-            let filter = FilterBuilder::default()
-                .address(vec![contract_address])
-                .topics(Some(vec![H256::from_slice(&topic)]), None, None, None)
-                .build();
-
-            match self.web3_http.as_ref().ok_or_else(|| anyhow::anyhow!("HTTP客户端未初始化"))?.eth().logs(filter).await {
-                Ok(logs) => {
-                    for log in logs {
-                        self.process_liquidation_event(event_name, &log).await?;
-                    }
-                }
+                Ok(None) => {
+                    tracing::warn!("区块 {} 不存在（可能超出当前链高度）", block_number);
+                }
                 Err(e) => {
-                    tracing::warn!("获取 {} 事件失败: {}", event_name, e);
+                    tracing::warn!("从RPC获取区块 {} 时间戳失败，将使用估算值: {}", block_number, e);
                 }
             }
         }
 
-        Ok(())
+        // RPC获取失败，使用估算算法作为fallback
+        let estimated = self.estimate_block_timestamp_fallback(block_number);
+        tracing::debug!("使用估算值作为区块时间戳fallback: 区块={}, 时间戳={}", block_number, estimated);
+        estimated
     }
 
-    async fn monitor_auction_manager_events(&self) -> anyhow::Result<()> {
-        let contract_address = self.config.contracts.auction_manager.parse()?;
+    /// 日志所在区块的时间戳，拍卖的开始时间以此为准（追赶历史区块时不能用当前时间）
+    async fn log_block_timestamp(&self, log: &web3::types::Log) -> u64 {
+        match log.block_number {
+            Some(block_number) => self.get_block_timestamp(block_number.as_u64()).await,
+            None => current_timestamp(),
+        }
+    }
 
-        let events = vec![
-            ("ParameterChanged", "ParameterChanged(bytes32,uint256)"),
-            ("AuctionStarted", "AuctionStarted(uint256,uint256,uint256,address,uint256,address,uint256)"),
-            ("AuctionReset", "AuctionReset(uint256,uint256,uint256,address,uint256,address,uint256)"),
-            ("AuctionRemoved", "AuctionRemoved(uint256)"),
-        ];
+    /// 静态处理路径的日志区块时间戳：只查数据库缓存，未命中时按持久化的区块时间锚点估算
+    fn log_block_timestamp_static(database: &Database, config: &crate::config::AppConfig, log: &web3::types::Log) -> u64 {
+        let Some(block_number) = log.block_number.map(|block_number| block_number.as_u64()) else {
+            return current_timestamp();
+        };
+        if let Ok(Some(timestamp)) = database.get_block_timestamp(block_number) {
+            return timestamp;
+        }
+        let anchor = database.get_block_time_anchor().ok().flatten();
+        estimate_block_timestamp(database, config, anchor, block_number)
+    }
 
-        for (event_name, signature) in events {
-            let topic = web3::signing::keccak256(signature.as_bytes());
-            let filter = FilterBuilder::default()
-                .address(vec![contract_address])
-                .topics(Some(vec![H256::from_slice(&topic)]), None, None, None)
-                .build();
+    /// 到达刷新间隔时，用链上最新区块重新校准区块时间锚点并持久化
+    async fn refresh_block_time_anchor_if_due(&mut self) {
+        let refresh_interval = std::time::Duration::from_secs(self.config.event_monitoring.block_time_anchor_refresh_secs);
+        if let Some(last_refresh) = self.last_anchor_refresh {
+            if last_refresh.elapsed() < refresh_interval {
+                return;
+            }
+        }
+        self.last_anchor_refresh = Some(std::time::Instant::now());
 
-            match self.web3_http.as_ref().ok_or_else(|| anyhow::anyhow!("HTTP客户端未初始化"))?.eth().logs(filter).await {
-                Ok(logs) => {
-                    for log in logs {
-                        self.process_auction_event(event_name, &log).await?;
-                    }
-                }
-                Err(e) => {
-                    tracing::warn!("获取 {} 事件失败: {}", event_name, e);
+        let web3 = match &self.web3_http {
+            Some(web3) => web3,
+            None => return,
+        };
+
+        match rpc_retry("eth_getBlockByNumber", || web3.eth().block(web3::types::BlockId::Number(BlockNumber::Latest))).await {
+            Ok(Some(block)) => {
+                let anchor = BlockTimeAnchor {
+                    block_number: block.number.unwrap_or_default().as_u64(),
+                    timestamp: block.timestamp.as_u64(),
+                };
+                if let Err(e) = self.database.set_block_time_anchor(&anchor) {
+                    tracing::warn!("持久化区块时间锚点失败: {}", e);
                 }
+                tracing::debug!("区块时间锚点已校准: 区块={}, 时间戳={}", anchor.block_number, anchor.timestamp);
+                self.block_time_anchor = Some(anchor);
+            }
+            Ok(None) => {
+                tracing::warn!("获取最新区块为空，沿用现有区块时间锚点");
+            }
+            Err(e) => {
+                tracing::warn!("校准区块时间锚点失败，沿用现有锚点: {}", e);
             }
         }
+    }
 
-        Ok(())
+    /// 根据区块号估算区块时间戳（fallback算法）
+    /// 当RPC不可用时使用，用于确保服务连续性
+    ///
+    /// 以运行时校准的区块时间锚点为基准，按配置的平均出块时间推算。
+    /// 尚无锚点时以最后同步区块和当前时间作为近似锚点。
+    fn estimate_block_timestamp_fallback(&self, block_number: u64) -> u64 {
+        estimate_block_timestamp(&self.database, &self.config, self.block_time_anchor, block_number)
+    }
+
+    /// 轮询一次：按分段同步 `最后同步区块 + 1` 到已确认的最新区块之间的事件
+    ///
+    /// 与历史同步共用 `sync_block_range`（分段查询、结果数量超限时缩小分段、逐段推进同步进度），
+    /// 日志经 `dispatch_log` 去重和过滤后处理，新拍卖同时计划重置。
+    async fn monitor_all_events(&mut self) -> anyhow::Result<()> {
+        let web3 = self.web3_http.clone().ok_or_else(|| anyhow::anyhow!("HTTP客户端未初始化"))?;
+        let to_block = self.confirmed_block_number(&web3).await?;
+        let from_block = match self.database.get_last_synced_block()? {
+            Some(last_block) if last_block >= to_block => return Ok(()),
+            Some(last_block) => last_block + 1,
+            None => to_block,
+        };
+
+        self.sync_block_range(&web3, from_block, to_block, true).await
     }
 
     async fn process_interest_event(&self, event_name: &str, log: &web3::types::Log) -> anyhow::Result<()> {
+        if Self::params_event_applied(&self.database, event_name, log)? {
+            return Ok(());
+        }
+
         match event_name {
             "InterestRateChanged" => {
                 // InterestRateChanged(uint256 oldRate, uint256 newRate)
-                if log.topics.len() >= 3 {
-                    let new_rate = web3::types::U256::from_big_endian(&log.topics[2].as_bytes());
-                    self.database.update_annual_interest_rate(new_rate)?;
-                    tracing::info!("InterestManager: 利率更新为 {}", new_rate);
-                }
+                let event = event_abi::decode_log(event_name, log)?;
+                let new_rate = event.uint("newRate")?;
+                self.database.update_annual_interest_rate(new_rate)?;
+                routine_log!(self, "InterestManager: 利率更新为 {}", new_rate);
             }
             "PositionIncreased" => {
                 // PositionIncreased(address indexed user, uint256 indexed tokenId, uint256 amount, uint256 totalAmount, uint256 totalInterest)
+                let event = event_abi::decode_log(event_name, log)?;
+                let user = event.address("user")?;
+                let token_id = event.uint("tokenId")?;
+                let total_amount = event.uint("totalAmount")?; // 最新的总持仓量
+                let total_interest = event.uint("totalInterest")?; // 当前的累计利息
+
+                // 获取或创建持仓记录 - PositionIncreased可能会早于Mint事件被监测到
+                let position = match self.database.get_user_position(user, token_id) {
+                    Ok(Some(existing)) if already_applied(existing.last_applied_log, log) => {
+                        tracing::debug!("PositionIncreased: 跳过已应用的事件 - 用户: {:?}, TokenID: {}", user, token_id);
+                        return Ok(());
+                    }
+                    Ok(Some(mut existing)) => {
+                        // 更新现有持仓：最新的总数量、累计利息和更新时间戳
+                        existing.amount = total_amount;
+                        existing.total_interest = total_interest;
+                        existing.timestamp = current_timestamp();
+                        existing.last_applied_log = Some(log_position(log));
+                        existing
+                    },
+                    _ => {
+                        // 如果没有现存记录，创建新记录，杠杆比例和mintPrice都设为0
+                        // PositionIncreased可能会早于Mint或NetValueAdjusted事件被监测到
+                        routine_log!(self, "PositionIncreased: 创建新的持仓记录，杠杆和铸币价格设为0 - 用户: {:?}, TokenID: {}", user, token_id);
+                        UserPosition {
+                            user,
+                            token_id,
+                            amount: total_amount,
+                            timestamp: current_timestamp(),
+                            total_interest,
+                            leverage: LeverageType::Conservative, // 杠杆设置为默认Conservative
+                            mint_price: web3::types::U256::zero(), // 铸币价格设为0
+                            last_applied_log: Some(log_position(log)),
+                            epoch: self.database.contract_epoch(),
+                        }
+                    }
+                };
 
-                if log.topics.len() >= 3 {
-                    // 解析 indexed 参数
-                    let user = Address::from_slice(&log.topics[1].as_bytes()[12..32]); // indexed address
-                    let token_id = web3::types::U256::from_big_endian(&log.topics[2].as_bytes()); // indexed uint256
-
-                    // 从 log.data 中解析非 indexed 参数: 3个uint256 = 96字节
-                    if log.data.0.len() >= 96 { // 3*32 = 96字节
-                        let _amount = web3::types::U256::from_big_endian(&log.data.0[0..32]); // 增加的量，不需要
-                        let total_amount = web3::types::U256::from_big_endian(&log.data.0[32..64]); // 最新的总持仓量
-                        let total_interest = web3::types::U256::from_big_endian(&log.data.0[64..96]); // 当前的累计利息
-
-                        // 获取或创建持仓记录 - PositionIncreased可能会早于Mint事件被监测到
-                        let position = match self.database.get_user_position(user, token_id) {
-                            Ok(Some(mut existing)) => {
-                                // 更新现有持仓：最新的总数量、累计利息和更新时间戳
-                                existing.amount = total_amount;
-                                existing.total_interest = total_interest;
-                                existing.timestamp = current_timestamp();
-                                existing
-                            },
-                            _ => {
-                                // 如果没有现存记录，创建新记录，杠杆比例和mintPrice都设为0
-                                // PositionIncreased可能会早于Mint或NetValueAdjusted事件被监测到
-                                tracing::info!("PositionIncreased: 创建新的持仓记录，杠杆和铸币价格设为0 - 用户: {:?}, TokenID: {}", user, token_id);
-                                UserPosition {
-                                    user,
-                                    token_id,
-                                    amount: total_amount,
-                                    timestamp: current_timestamp(),
-                                    total_interest,
-                                    leverage: LeverageType::Conservative, // 杠杆设置为默认Conservative
-                                    mint_price: web3::types::U256::zero(), // 铸币价格设为0
-                                }
-                            }
-                        };
-
-                        // 保存到数据库
-                        self.database.store_user_position(&position)?;
+                // 保存到数据库
+                self.database.store_user_position(&position)?;
 
-                        tracing::info!("InterestManager: 持仓更新 - 用户: {:?}, TokenID: {}, 总数量: {}, 累计利息: {}",
-                                     user, token_id, total_amount, total_interest);
-                    } else {
-                        tracing::warn!("PositionIncreased event data too short, got {} bytes (expected 96)", log.data.0.len());
-                    }
-                } else {
-                    tracing::warn!("PositionIncreased event has insufficient topics: {}", log.topics.len());
-                }
+                routine_log!(self, "InterestManager: 持仓更新 - 用户: {:?}, TokenID: {}, 总数量: {}, 累计利息: {}",
+                             user, token_id, total_amount, total_interest);
             }
 
             "InterestCollected" => {
                 // InterestCollected(address indexed user, uint256 indexed tokenId, uint256 deductLAmountInWei, uint256 interestAmount)
+                let event = event_abi::decode_log(event_name, log)?;
+                let user = event.address("user")?;
+                let token_id = event.uint("tokenId")?;
+                let deduct_amount = event.uint("deductLAmountInWei")?;
+                let interest_amount = event.uint("interestAmount")?;
+
+                // 检查用户持仓是否存在
+                if let Ok(Some(mut position)) = self.database.get_user_position(user, token_id) {
+                    // 扣减不是幂等操作，同一事件只能应用一次
+                    if already_applied(position.last_applied_log, log) {
+                        tracing::debug!("InterestCollected: 跳过已应用的事件 - 用户: {:?}, TokenID: {}", user, token_id);
+                        return Ok(());
+                    }
+                    position.last_applied_log = Some(log_position(log));
 
-                if log.topics.len() >= 3 {
-                    // 解析 indexed 参数
-                    let user = Address::from_slice(&log.topics[1].as_bytes()[12..32]); // indexed address
-                    let token_id = web3::types::U256::from_big_endian(&log.topics[2].as_bytes()); // indexed uint256
-
-                    // 从 log.data 中解析非 indexed 参数
-                    if log.data.0.len() >= 64 { // 2个参数 * 32字节
-                        let deduct_amount = web3::types::U256::from_big_endian(&log.data.0[0..32]); // deductLAmountInWei
-                        let interest_amount = web3::types::U256::from_big_endian(&log.data.0[32..64]); // interestAmount
-
-                        // 检查用户持仓是否存在
-                        if let Ok(Some(mut position)) = self.database.get_user_position(user, token_id) {
-                            // 更新持仓：balance = balance - deductLAmountInWei
-                            position.amount = position.amount - deduct_amount;
-
-                            // 更新累计利息：totalInterest = totalInterest - interestAmount
-                            position.total_interest = position.total_interest - interest_amount;
-
-                            // 更新时间戳
-                            position.timestamp = current_timestamp();
-
-                            if position.amount == web3::types::U256::zero() {
-                                // balance == 0，删除这个代币持仓
-                                self.database.delete_user_position(user, token_id)?;
-                                tracing::info!("InterestManager: 利息收集后持仓清零，已删除 - 用户: {:?}, TokenID: {}, 扣除量: {}, 利息金额: {}",
-                                             user, token_id, deduct_amount, interest_amount);
-                            } else {
-                                // 保存更新后的持仓信息
-                                self.database.store_user_position(&position)?;
-                                tracing::info!("InterestManager: 利息收集更新 - 用户: {:?}, TokenID: {}, 扣除量: {}, 利息金额: {}, 剩余持仓: {}, 剩余累计利息: {}",
-                                             user, token_id, deduct_amount, interest_amount, position.amount, position.total_interest);
-                            }
-                        } else {
-                            tracing::warn!("InterestCollected: 用户持仓不存在 - 用户: {:?}, TokenID: {}", user, token_id);
-                        }
+                    // 更新持仓：balance = balance - deductLAmountInWei
+                    position.amount = sub_or_clamp(position.amount, deduct_amount, "持仓数量", user, token_id);
+
+                    // 更新累计利息：totalInterest = totalInterest - interestAmount
+                    position.total_interest = sub_or_clamp(position.total_interest, interest_amount, "累计利息", user, token_id);
+
+                    // 更新时间戳
+                    position.timestamp = current_timestamp();
+
+                    if position.amount == web3::types::U256::zero() {
+                        // balance == 0，删除这个代币持仓
+                        self.database.delete_user_position(user, token_id)?;
+                        routine_log!(self, "InterestManager: 利息收集后持仓清零，已删除 - 用户: {:?}, TokenID: {}, 扣除量: {}, 利息金额: {}",
+                                     user, token_id, deduct_amount, interest_amount);
                     } else {
-                        tracing::warn!("InterestCollected event data too short, got {} bytes (expected 64)", log.data.0.len());
+                        // 保存更新后的持仓信息
+                        self.database.store_user_position(&position)?;
+                        routine_log!(self, "InterestManager: 利息收集更新 - 用户: {:?}, TokenID: {}, 扣除量: {}, 利息金额: {}, 剩余持仓: {}, 剩余累计利息: {}",
+                                     user, token_id, deduct_amount, interest_amount, position.amount, position.total_interest);
                     }
                 } else {
-                    tracing::warn!("InterestCollected event has insufficient topics: {}", log.topics.len());
+                    tracing::warn!("InterestCollected: 用户持仓不存在 - 用户: {:?}, TokenID: {}", user, token_id);
                 }
             }
 
             _ => {}
         }
-        Ok(())
+        Self::mark_params_event_applied(&self.database, event_name, log)
     }
 
     async fn process_liquidation_event(&self, event_name: &str, log: &web3::types::Log) -> anyhow::Result<()> {
+        if Self::params_event_applied(&self.database, event_name, log)? {
+            return Ok(());
+        }
+
         match event_name {
             "ParameterChanged" => {
                 // ParameterChanged(bytes32 indexed parameter, uint256 value)
-                // 需要解析 indexed parameter (topic[1]) 和 value (data)
-
-                if log.topics.len() >= 2 {
-                    // 解析 bytes32 parameter 从 topic[1] (字符串左对齐)
-                    // 对于字符串参数，取整个32字节并找到第一个null字节之前的部分
-                    let parameter_bytes = log.topics[1].as_bytes(); // 整个32字节
-
-                    // 从 log.data 中解析 uint256 value
-                    // event 数据会是 ABI 编码的，所以第一个32字节是 value
-                    let value = if log.data.0.len() >= 32 {
-                        web3::types::U256::from_big_endian(&log.data.0[0..32])
-                    } else {
-                        tracing::warn!("ParameterChanged event data too short");
-                        return Ok(());
-                    };
-
-                    // 根据参数名更新数据库 - 传递32字节数组
-                    self.update_liquidation_parameter(parameter_bytes, value).await?;
-                } else {
-                    tracing::warn!("ParameterChanged event has insufficient topics");
-                }
+                // parameter 为左对齐的字符串，取整个32字节并找到第一个null字节之前的部分
+                let event = event_abi::decode_log(event_name, log)?;
+                self.update_liquidation_parameter(event.fixed_bytes("parameter")?, event.uint("value")?).await?;
             }
             "LiquidationConfigInfo" => {
                 // LiquidationConfigInfo(uint256 adjustmentThreshold, uint256 liquidationThreshold, uint256 penalty, bool enabled)
                 // 这是一个全配置事件，用于同步所有清算参数
-                // 在ABI编码中：uint256=32字节，bool=32字节，总共4*32=128字节
-
-                if log.data.0.len() >= 128 { // 3*uint256 + 1*bool = 4*32 = 128字节
-                    let adjustment_threshold = web3::types::U256::from_big_endian(&log.data.0[0..32]);
-                    let liquidation_threshold = web3::types::U256::from_big_endian(&log.data.0[32..64]);
-                    let penalty = web3::types::U256::from_big_endian(&log.data.0[64..96]);
-                    let enabled = web3::types::U256::from_big_endian(&log.data.0[96..128]);
-
-                    // 更新数据库中的清算参数（enabled是个开关状态，不需要存储在参数库中）
-                    self.database.update_adjustment_threshold(adjustment_threshold)?;
-                    self.database.update_liquidation_threshold(liquidation_threshold)?;
-                    self.database.update_penalty(penalty)?;
-
-                    let enabled_flag = enabled.low_u32() != 0; // U256转换为bool：非0即true
-                    tracing::info!("LiquidationManager: 清算配置同步 - adjustment_threshold: {}, liquidation_threshold: {}, penalty: {}, enabled: {}",
-                                 adjustment_threshold, liquidation_threshold, penalty, enabled_flag);
-                } else {
-                    tracing::warn!("LiquidationConfigInfo event data too short, got {} bytes (expected 128)", log.data.0.len());
-                }
+                let event = event_abi::decode_log(event_name, log)?;
+                let adjustment_threshold = event.uint("adjustmentThreshold")?;
+                let liquidation_threshold = event.uint("liquidationThreshold")?;
+                let penalty = event.uint("penalty")?;
+                let enabled_flag = event.bool("enabled")?;
+
+                // 更新数据库中的清算参数，enabled 开关决定 Keeper 是否继续清算
+                self.database.update_adjustment_threshold(adjustment_threshold)?;
+                self.database.update_liquidation_threshold(liquidation_threshold)?;
+                self.database.update_penalty(penalty)?;
+                self.database.update_liquidation_enabled(enabled_flag)?;
+                routine_log!(self, "LiquidationManager: 清算配置同步 - adjustment_threshold: {}, liquidation_threshold: {}, penalty: {}, enabled: {}",
+                             adjustment_threshold, liquidation_threshold, penalty, enabled_flag);
             }
             "NetValueAdjusted" => {
                 // NetValueAdjusted(address indexed user, uint256 indexed fromTokenId, uint256 indexed toTokenId,
                 //                  LeverageType leverage, uint256 newMintPrice, uint256 adjustAmountInWei, uint256 underlyingAmountInWei)
+                let event = event_abi::decode_log(event_name, log)?;
+                let user = event.address("user")?;
+                let to_token_id = event.uint("toTokenId")?;
+                let leverage_value = event.uint8("leverage")?;
+                let new_mint_price = event.uint("newMintPrice")?;
+                let adjust_amount_in_wei = event.uint("adjustAmountInWei")?;
+
+                let leverage = match LeverageType::from_u8(leverage_value) {
+                    Ok(leverage) => leverage,
+                    Err(e) => {
+                        // 单个事件的杠杆类型越界（合约升级新增类型）不应中断整个区块的处理
+                        tracing::warn!("NetValueAdjusted: 跳过杠杆类型无效的事件 - 原始字节: {}, 用户: {:?}, 到TokenID: {}, 错误: {}",
+                                     leverage_value, user, to_token_id, e);
+                        return Ok(());
+                    }
+                };
 
-                if log.topics.len() >= 4 {
-                    // 解析 indexed 参数
-                    let user = Address::from_slice(&log.topics[1].as_bytes()[12..32]); // indexed address
-                    let _from_token_id = web3::types::U256::from_big_endian(&log.topics[2].as_bytes()); // indexed fromTokenId
-                    let to_token_id = web3::types::U256::from_big_endian(&log.topics[3].as_bytes()); // indexed toTokenId
-
-                    // 从 log.data 中解析非 indexed 参数: 4个参数（uint8 + 3个uint256） = 1 + 96 = 97字节
-                    if log.data.0.len() >= 97 { // 1*uint8 + 3*uint256 = 97字节
-                        let leverage_value = log.data.0[0]; // uint8 LeverageType
-                        let new_mint_price = web3::types::U256::from_big_endian(&log.data.0[1..33]); // 从1开始的32字节
-                        let adjust_amount_in_wei = web3::types::U256::from_big_endian(&log.data.0[33..65]); // adjustAmountInWei
-                        let _underlying_amount_in_wei = web3::types::U256::from_big_endian(&log.data.0[65..97]); // 未使用
-
-                        let leverage = LeverageType::from_u8(leverage_value)?;
-
-                        // 检查database中有没有该user对于toTokenId的记录
-                        let existing_position = self.database.get_user_position(user, to_token_id)?;
-
-                        match existing_position {
-                            Some(mut position) => {
-                                // 如果有该记录，只需要更新杠杆比例和铸币价格
-                                position.leverage = leverage.clone();
-                                position.mint_price = new_mint_price;
-                                self.database.store_user_position(&position)?;
-                                tracing::info!("LiquidationManager: NetValueAdjusted - 更新现有持仓杠杆和铸币价格 - 用户: {:?}, 到TokenID: {}, 杠杆: {:?}, 新铸币价格: {}",
-                                             user, to_token_id, leverage, new_mint_price);
-                            }
-                            None => {
-                                // 如果没有记录，创建新记录：杠杆比例为leverage，铸币价格为newMintPrice，持仓数量为adjustAmountInWei
-                                let new_position = UserPosition {
-                                    user,
-                                    token_id: to_token_id,
-                                    amount: adjust_amount_in_wei, // 使用adjustAmountInWei作为持仓数量
-                                    timestamp: current_timestamp(),
-                                    total_interest: web3::types::U256::zero(),
-                                    leverage: leverage.clone(),
-                                    mint_price: new_mint_price,
-                                };
-                                self.database.store_user_position(&new_position)?;
-                                tracing::info!("LiquidationManager: NetValueAdjusted - 创建新持仓记录 - 用户: {:?}, 到TokenID: {}, 杠杆: {:?}, 铸币价格: {}, 持仓数量: {}",
-                                             user, to_token_id, leverage, new_mint_price, adjust_amount_in_wei);
-                            }
-                        }
-                    } else {
-                        tracing::warn!("NetValueAdjusted event data too short, got {} bytes (expected 97)", log.data.0.len());
+                // 检查database中有没有该user对于toTokenId的记录
+                let existing_position = self.database.get_user_position(user, to_token_id)?;
+
+                match existing_position {
+                    Some(position) if already_applied(position.last_applied_log, log) => {
+                        tracing::debug!("NetValueAdjusted: 跳过已应用的事件 - 用户: {:?}, 到TokenID: {}", user, to_token_id);
+                    }
+                    Some(mut position) => {
+                        // 如果有该记录，只需要更新杠杆比例和铸币价格
+                        position.leverage = leverage.clone();
+                        position.mint_price = new_mint_price;
+                        position.last_applied_log = Some(log_position(log));
+                        self.database.store_user_position(&position)?;
+                        routine_log!(self, "LiquidationManager: NetValueAdjusted - 更新现有持仓杠杆和铸币价格 - 用户: {:?}, 到TokenID: {}, 杠杆: {:?}, 新铸币价格: {}",
+                                     user, to_token_id, leverage, new_mint_price);
+                    }
+                    None => {
+                        // 如果没有记录，创建新记录：杠杆比例为leverage，铸币价格为newMintPrice，持仓数量为adjustAmountInWei
+                        let new_position = UserPosition {
+                            user,
+                            token_id: to_token_id,
+                            amount: adjust_amount_in_wei, // 使用adjustAmountInWei作为持仓数量
+                            timestamp: current_timestamp(),
+                            total_interest: web3::types::U256::zero(),
+                            leverage: leverage.clone(),
+                            mint_price: new_mint_price,
+                            last_applied_log: Some(log_position(log)),
+                            epoch: self.database.contract_epoch(),
+                        };
+                        self.database.store_user_position(&new_position)?;
+                        routine_log!(self, "LiquidationManager: NetValueAdjusted - 创建新持仓记录 - 用户: {:?}, 到TokenID: {}, 杠杆: {:?}, 铸币价格: {}, 持仓数量: {}",
+                                     user, to_token_id, leverage, new_mint_price, adjust_amount_in_wei);
                     }
-                } else {
-                    tracing::warn!("NetValueAdjusted event has insufficient topics: {}", log.topics.len());
                 }
             }
             _ => {
                 tracing::debug!("Unknown liquidation event: {}", event_name);
             }
         }
-        Ok(())
+        Self::mark_params_event_applied(&self.database, event_name, log)
     }
 
     async fn process_auction_event(&self, event_name: &str, log: &web3::types::Log) -> anyhow::Result<()> {
+        if Self::params_event_applied(&self.database, event_name, log)? {
+            return Ok(());
+        }
+
         match event_name {
             "ParameterChanged" => {
                 // ParameterChanged(bytes32 indexed parameter, uint256 value)
-                // 需要解析 indexed parameter (topic[1]) 和 value (data)
-
-                if log.topics.len() >= 2 {
-                    // 解析 bytes32 parameter 从 topic[1] (字符串左对齐)
-                    // 对于字符串参数，取整个32字节并找到第一个null字节之前的部分
-                    let parameter_bytes = log.topics[1].as_bytes(); // 整个32字节
-
-                    // 从 log.data 中解析 uint256 value
-                    // event 数据会是 ABI 编码的，所以第一个32字节是 value
-                    let value = if log.data.0.len() >= 32 {
-                        web3::types::U256::from_big_endian(&log.data.0[0..32])
-                    } else {
-                        tracing::warn!("ParameterChanged event data too short");
-                        return Ok(());
-                    };
-
-                    // 根据参数名更新数据库
-                    self.update_auction_parameter(parameter_bytes, value).await?;
-                } else {
-                    tracing::warn!("ParameterChanged event has insufficient topics");
-                }
+                let event = event_abi::decode_log(event_name, log)?;
+                self.update_auction_parameter(event.fixed_bytes("parameter")?, event.uint("value")?).await?;
             }
             "AuctionStarted" => {
                 // AuctionStarted(uint256 indexed auctionId, uint256 startingPrice, uint256 underlyinglAmount,
                 //                 address originalOwner, uint256 indexed tokenId, address indexed triggerer, uint256 rewardAmount)
-
-                if log.topics.len() >= 4 {
-                    // 解析 indexed 参数
-                    let auction_id = web3::types::U256::from_big_endian(&log.topics[1].as_bytes());
-                    let token_id = web3::types::U256::from_big_endian(&log.topics[2].as_bytes());
-
-                    // 从 log.data 中解析非 indexed 参数
-                    if log.data.0.len() >= 128 { // 4个参数 * 32字节
-                        let starting_price = web3::types::U256::from_big_endian(&log.data.0[0..32]);
-                        let underlying_amount = web3::types::U256::from_big_endian(&log.data.0[32..64]);
-                        let original_owner = Address::from_slice(&log.data.0[76..96]); // address 在第12-32字节位置
-                        let reward_amount = web3::types::U256::from_big_endian(&log.data.0[96..128]);
-
-                        // topics[3] 包含 triggerer 地址 (indexed)
-                        let triggerer = Address::from_slice(&log.topics[3].as_bytes()[12..32]);
-
-                        // 创建拍卖信息并存储到数据库
-                        let auction_info = AuctionInfo {
-                            auction_id,
-                            starting_price,
-                            underlying_amount,
-                            original_owner,
-                            token_id,
-                            triggerer: triggerer.clone(),
-                            reward_amount,
-                            start_time: current_timestamp(),
-                        };
-
-                        // 存储到数据库
-                        self.database.store_auction(&auction_info)?;
-
-                        tracing::info!(
-                            "AuctionManager: 新拍卖开始 - ID: {}, 起始价格: {}, 标的总量: {}, 原始持有者: {:?}, 触发者: {:?}",
-                            auction_id, starting_price, underlying_amount, original_owner, triggerer
-                        );
-
-                        // 为新拍卖设置自动重置定时器
-                        match self.auction_reset_monitor.schedule_auction_reset(auction_id, starting_price).await {
-                            Ok(()) => {
-                                tracing::debug!("AuctionManager: 拍卖 {} 重置定时器设置成功", auction_id);
-                            }
-                            Err(e) => {
-                                tracing::error!("AuctionManager: 拍卖 {} 重置定时器设置失败: {}", auction_id, e);
-                            }
-                        }
-                    } else {
-                        tracing::warn!("AuctionStarted event data too short, got {} bytes (expected 128)", log.data.0.len());
+                let event = event_abi::decode_log(event_name, log)?;
+                let auction_id = event.uint("auctionId")?;
+                let token_id = event.uint("tokenId")?;
+                let starting_price = event.uint("startingPrice")?;
+                let underlying_amount = event.uint("underlyinglAmount")?;
+                let original_owner = event.address("originalOwner")?;
+                let triggerer = event.address("triggerer")?;
+                let reward_amount = event.uint("rewardAmount")?;
+                let start_time = self.log_block_timestamp(log).await;
+
+                // 创建拍卖信息并存储到数据库
+                let auction_info = AuctionInfo {
+                    auction_id,
+                    starting_price,
+                    underlying_amount,
+                    original_owner,
+                    token_id,
+                    triggerer,
+                    reward_amount,
+                    start_time,
+                    epoch: self.database.contract_epoch(),
+                };
+
+                // 存储到数据库，拍卖已出现，结束该持仓的清算冷却期
+                self.database.store_auction(&auction_info)?;
+                self.database.clear_liquidation_in_flight(original_owner, token_id)?;
+
+                tracing::info!(
+                    "AuctionManager: 新拍卖开始 - ID: {}, 起始价格: {}, 标的总量: {}, 原始持有者: {:?}, 触发者: {:?}",
+                    auction_id, starting_price, underlying_amount, original_owner, triggerer
+                );
+
+                // 为新拍卖设置自动重置定时器
+                match self.auction_reset_monitor.schedule_auction_reset(&auction_info).await {
+                    Ok(()) => {
+                        tracing::debug!("AuctionManager: 拍卖 {} 重置定时器设置成功", auction_id);
+                    }
+                    Err(e) => {
+                        tracing::error!("AuctionManager: 拍卖 {} 重置定时器设置失败: {}", auction_id, e);
                     }
-                } else {
-                    tracing::warn!("AuctionStarted event has insufficient topics: {}", log.topics.len());
                 }
             }
             "AuctionReset" => {
                 // AuctionReset(uint256 indexed auctionId, uint256 newStartingPrice, uint256 underlyingAmount,
                 //               address originalOwner, uint256 indexed tokenId, address indexed triggerer, uint256 rewardAmount)
-
-                if log.topics.len() >= 4 {
-                    // 解析 indexed 参数
-                    let auction_id = web3::types::U256::from_big_endian(&log.topics[1].as_bytes());
-
-                    // 从 log.data 中解析 newStartingPrice
-                    if log.data.0.len() >= 32 {
-                        let new_starting_price = web3::types::U256::from_big_endian(&log.data.0[0..32]);
-
-                    // 更新拍卖数据库记录：新的起始价格和起始时间
-                    if let Ok(Some(mut auction_info)) = self.database.get_auction(auction_id) {
-                        auction_info.starting_price = new_starting_price;
-                        auction_info.start_time = current_timestamp();
-
-                        // 重新保存更新后的拍卖信息
-                        self.database.store_auction(&auction_info)?;
-
-                        tracing::info!("AuctionManager: 拍卖 {} 重置 - 新起始价格: {}, 新起始时间: {}",
-                                     auction_id, new_starting_price, auction_info.start_time);
-
-                        // 重置后的拍卖需要重新设置重置定时器，因为它还是活跃的拍卖
-                        match self.auction_reset_monitor.schedule_auction_reset(auction_id, new_starting_price).await {
-                            Ok(()) => {
-                                tracing::debug!("AuctionManager: 重置后的拍卖 {} 重置定时器设置成功", auction_id);
-                            }
-                            Err(e) => {
-                                tracing::error!("AuctionManager: 重置后的拍卖 {} 重置定时器设置失败: {}", auction_id, e);
-                            }
+                let event = event_abi::decode_log(event_name, log)?;
+                let auction_id = event.uint("auctionId")?;
+                let new_starting_price = event.uint("newStartingPrice")?;
+                let underlying_amount = event.uint("underlyingAmount")?;
+                let reward_amount = event.uint("rewardAmount")?;
+
+                // 更新拍卖数据库记录：新的起始价格、标的数量、奖励和起始时间（事件所在区块的时间戳）
+                if let Ok(Some(mut auction_info)) = self.database.get_auction(auction_id) {
+                    auction_info.starting_price = new_starting_price;
+                    auction_info.underlying_amount = underlying_amount;
+                    auction_info.reward_amount = reward_amount;
+                    auction_info.start_time = self.log_block_timestamp(log).await;
+
+                    // 重新保存更新后的拍卖信息
+                    self.database.store_auction(&auction_info)?;
+
+                    tracing::info!("AuctionManager: 拍卖 {} 重置 - 新起始价格: {}, 标的总量: {}, 奖励: {}, 新起始时间: {}",
+                                 auction_id, new_starting_price, underlying_amount, reward_amount, auction_info.start_time);
+
+                    // 重置后的拍卖需要重新设置重置定时器（按新的标的数量重新判断是否低于 minAuctionAmount）
+                    match self.auction_reset_monitor.schedule_auction_reset(&auction_info).await {
+                        Ok(()) => {
+                            tracing::debug!("AuctionManager: 重置后的拍卖 {} 重置定时器设置成功", auction_id);
+                        }
+                        Err(e) => {
+                            tracing::error!("AuctionManager: 重置后的拍卖 {} 重置定时器设置失败: {}", auction_id, e);
                         }
-                    } else {
-                        tracing::warn!("AuctionReset: 尝试重置不存在的拍卖 {}", auction_id);
-                    }
-                    } else {
-                        tracing::warn!("AuctionReset event data too short, got {} bytes (expected at least 32)", log.data.0.len());
                     }
                 } else {
-                    tracing::warn!("AuctionReset event has insufficient topics: {}", log.topics.len());
+                    tracing::warn!("AuctionReset: 尝试重置不存在的拍卖 {}", auction_id);
                 }
             }
             "AuctionRemoved" => {
                 // AuctionRemoved(uint256 indexed auctionId)
-                // 单参数事件，auctionId 在 topic[1] 中
                 // AuctionRemoved 会在两种情况下发出：
                 // 1. 拍卖正常结束 (underlyingAmount == 0)
                 // 2. 管理员主动取消拍卖
+                let auction_id = event_abi::decode_log(event_name, log)?.uint("auctionId")?;
 
-                if log.topics.len() >= 2 {
-                    // topics[0]: 事件签名哈希
-                    // topics[1]: indexed auctionId 参数
-                    let auction_id = web3::types::U256::from_big_endian(&log.topics[1].as_bytes());
+                // 首先取消对应的重置定时器
+                self.auction_reset_monitor.cancel_auction_reset(&auction_id);
 
-                    // 首先取消对应的重置定时器
-                    self.auction_reset_monitor.cancel_auction_reset(&auction_id);
-
-                    // 然后删除数据库中的拍卖记录
-                    self.database.delete_auction(auction_id)?;
-                    tracing::info!("拍卖 {} 已结束/取消，已从数据库删除", auction_id);
-                } else {
-                    tracing::warn!("AuctionRemoved event has insufficient topics: {}", log.topics.len());
-                }
+                // 然后删除数据库中的拍卖记录
+                self.database.delete_auction(auction_id)?;
+                routine_log!(self, "拍卖 {} 已结束/取消，已从数据库删除", auction_id);
             }
             _ => {}
         }
-        Ok(())
+        Self::mark_params_event_applied(&self.database, event_name, log)
     }
 
     /// 根据 Solidity setParameter 函数更新相应的数据库参数
@@ -1798,15 +2662,15 @@ impl EventMonitor {
         match parameter_name {
             "adjustmentThreshold" => {
                 self.database.update_adjustment_threshold(value)?;
-                tracing::info!("LiquidationManager: adjustmentThreshold 更新为 {}", value);
+                routine_log!(self, "LiquidationManager: adjustmentThreshold 更新为 {}", value);
             }
             "liquidationThreshold" => {
                 self.database.update_liquidation_threshold(value)?;
-                tracing::info!("LiquidationManager: liquidationThreshold 更新为 {}", value);
+                routine_log!(self, "LiquidationManager: liquidationThreshold 更新为 {}", value);
             }
             "penalty" => {
                 self.database.update_penalty(value)?;
-                tracing::info!("LiquidationManager: penalty 更新为 {}", value);
+                routine_log!(self, "LiquidationManager: penalty 更新为 {}", value);
             }
             _unrecognized => {
                 // 根据 Solidity 代码，这应该会 revert，但是我们记录警告
@@ -1852,33 +2716,46 @@ impl EventMonitor {
         match parameter_name {
             "priceMultiplier" => {
                 self.database.update_price_multiplier(value)?;
-                tracing::info!("AuctionManager: priceMultiplier 更新为 {}", value);
+                routine_log!(self, "AuctionManager: priceMultiplier 更新为 {}", value);
             }
             "resetTime" => {
                 self.database.update_reset_time(value)?;
-                tracing::info!("AuctionManager: resetTime 更新为 {}", value);
+                routine_log!(self, "AuctionManager: resetTime 更新为 {}", value);
             }
             "minAuctionAmount" => {
                 self.database.update_min_auction_amount(value)?;
-                tracing::info!("AuctionManager: minAuctionAmount 更新为 {}", value);
+                routine_log!(self, "AuctionManager: minAuctionAmount 更新为 {}", value);
             }
             "priceDropThreshold" => {
                 self.database.update_price_drop_threshold(value)?;
-                tracing::info!("AuctionManager: priceDropThreshold 更新为 {}", value);
+                routine_log!(self, "AuctionManager: priceDropThreshold 更新为 {}", value);
             }
             "percentageReward" => {
                 self.database.update_percentage_reward(value)?;
-                tracing::info!("AuctionManager: percentageReward 更新为 {}", value);
+                routine_log!(self, "AuctionManager: percentageReward 更新为 {}", value);
             }
             "fixedReward" => {
                 self.database.update_fixed_reward(value)?;
-                tracing::info!("AuctionManager: fixedReward 更新为 {}", value);
+                routine_log!(self, "AuctionManager: fixedReward 更新为 {}", value);
             }
             "circuitBreaker" => {
-                // circuitBreaker 是一个特殊的参数，用于控制拍卖断路器
-                // 这个参数可能需要单独处理，目前我们只记录日志
-                tracing::info!("AuctionManager: circuitBreaker 更新为 {} (break when > 0)", value);
-                // TODO: 根据需要存储或处理 circuitBreaker 状态
+                // 拍卖断路器：value > 0 时触发，触发期间不计划也不执行拍卖重置
+                let tripped = !value.is_zero();
+                let was_tripped = self.database.get_system_params()?.circuit_breaker;
+                self.database.update_circuit_breaker(tripped)?;
+
+                if tripped {
+                    tracing::warn!("AuctionManager: 拍卖断路器已触发 (circuitBreaker = {})，暂停拍卖重置", value);
+                } else {
+                    tracing::info!("AuctionManager: 拍卖断路器已解除");
+                    if was_tripped {
+                        // 断路器触发期间到期的重置任务已被跳过，为仍在进行的拍卖重新计划
+                        match self.auction_reset_monitor.resume_pending_resets().await {
+                            Ok(count) => tracing::info!("AuctionManager: 断路器解除后重新计划了 {} 个拍卖的重置", count),
+                            Err(e) => tracing::error!("AuctionManager: 断路器解除后重新计划拍卖重置失败: {}", e),
+                        }
+                    }
+                }
             }
             _unrecognized => {
                 // 根据 Solidity 代码，这应该会 revert，但是我们记录警告
@@ -1894,55 +2771,56 @@ impl EventMonitor {
         match event_name {
             "Mint" => {
                 // Mint(address indexed user, uint256 tokenId, uint256 underlyingAmountInWei, LeverageType leverageLevel, uint256 mintPriceInWei, uint256 sAmountInWei, uint256 lAmountInWei)
+                let event = event_abi::decode_log(event_name, log)?;
+                let user = event.address("user")?;
+                let token_id = event.uint("tokenId")?;
+                let leverage_value = event.uint8("leverageLevel")?;
+                let mint_price = event.uint("mintPriceInWei")?;
+                let l_amount = event.uint("lAmountInWei")?;
+
+                let leverage = match LeverageType::from_u8(leverage_value) {
+                    Ok(leverage) => leverage,
+                    Err(e) => {
+                        // 单个事件的杠杆类型越界（合约升级新增类型）不应中断整个区块的处理
+                        tracing::warn!("CustodianFixed: 跳过杠杆类型无效的Mint事件 - 原始字节: {}, 用户: {:?}, TokenID: {}, 错误: {}",
+                                     leverage_value, user, token_id, e);
+                        return Ok(());
+                    }
+                };
 
-                if log.topics.len() >= 2 {
-                    // 解析 indexed 参数
-                    let user = Address::from_slice(&log.topics[1].as_bytes()[12..32]); // indexed address
-
-                    // 从 log.data 中解析非 indexed 参数: 6个参数（uint256*5 + uint8*1） = 160 + 1 = 161字节
-                    if log.data.0.len() >= 161 { // 5*32 + 1 = 161字节
-                        let token_id = web3::types::U256::from_big_endian(&log.data.0[0..32]);
-                        let _underlying_amount = web3::types::U256::from_big_endian(&log.data.0[32..64]); // 未使用
-                        let leverage_value = log.data.0[64]; // uint8 LeverageType
-                        let mint_price = web3::types::U256::from_big_endian(&log.data.0[65..97]); // 从65开始的32字节
-                        let _s_amount = web3::types::U256::from_big_endian(&log.data.0[97..129]); // 未使用
-                        let l_amount = web3::types::U256::from_big_endian(&log.data.0[129..161]); // 使用
-
-                        let leverage = LeverageType::from_u8(leverage_value)?;
-
-                        // 检查数据库中是否已有此用户此tokenID的持仓记录
-                        let existing_position = self.database.get_user_position(user, token_id)?;
-
-                        match existing_position {
-                            Some(mut position) => {
-                                // 如果数据库中已有记录，只更新mintPrice和杠杆比例
-                                position.mint_price = mint_price;
-                                position.leverage = leverage.clone();
-                                self.database.store_user_position(&position)?;
-                                tracing::info!("CustodianFixed: 更新现有持仓杠杆和铸币价格 - 用户: {:?}, TokenID: {}, 杠杆: {:?}, 铸币价格: {}",
-                                             user, token_id, leverage, mint_price);
-                            }
-                            None => {
-                                // 如果数据库中没有记录，使用l_amount作为初始持仓量
-                                let new_position = UserPosition {
-                                    user,
-                                    token_id,
-                                    amount: l_amount, // 使用l_amount作为初始持仓量
-                                    timestamp: current_timestamp(),
-                                    total_interest: web3::types::U256::zero(),
-                                    leverage: leverage.clone(),
-                                    mint_price,
-                                };
-                                self.database.store_user_position(&new_position)?;
-                                tracing::info!("CustodianFixed: 创建新持仓记录 - 用户: {:?}, TokenID: {}, 杠杆: {:?}, 铸币价格: {}, 初始持仓量: {}",
-                                             user, token_id, leverage, mint_price, l_amount);
-                            }
-                        }
-                    } else {
-                        tracing::warn!("Mint event data too short, got {} bytes (expected 161)", log.data.0.len());
+                // 检查数据库中是否已有此用户此tokenID的持仓记录
+                let existing_position = self.database.get_user_position(user, token_id)?;
+
+                match existing_position {
+                    Some(position) if already_applied(position.last_applied_log, log) => {
+                        tracing::debug!("Mint: 跳过已应用的事件 - 用户: {:?}, TokenID: {}", user, token_id);
+                    }
+                    Some(mut position) => {
+                        // 如果数据库中已有记录，只更新mintPrice和杠杆比例
+                        position.mint_price = mint_price;
+                        position.leverage = leverage.clone();
+                        position.last_applied_log = Some(log_position(log));
+                        self.database.store_user_position(&position)?;
+                        routine_log!(self, "CustodianFixed: 更新现有持仓杠杆和铸币价格 - 用户: {:?}, TokenID: {}, 杠杆: {:?}, 铸币价格: {}",
+                                     user, token_id, leverage, mint_price);
+                    }
+                    None => {
+                        // 如果数据库中没有记录，使用l_amount作为初始持仓量
+                        let new_position = UserPosition {
+                            user,
+                            token_id,
+                            amount: l_amount, // 使用l_amount作为初始持仓量
+                            timestamp: current_timestamp(),
+                            total_interest: web3::types::U256::zero(),
+                            leverage: leverage.clone(),
+                            mint_price,
+                            last_applied_log: Some(log_position(log)),
+                            epoch: self.database.contract_epoch(),
+                        };
+                        self.database.store_user_position(&new_position)?;
+                        routine_log!(self, "CustodianFixed: 创建新持仓记录 - 用户: {:?}, TokenID: {}, 杠杆: {:?}, 铸币价格: {}, 初始持仓量: {}",
+                                     user, token_id, leverage, mint_price, l_amount);
                     }
-                } else {
-                    tracing::warn!("Mint event has insufficient topics: {}", log.topics.len());
                 }
             }
             _ => {
@@ -1952,3 +2830,678 @@ impl EventMonitor {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use web3::ethabi::Token;
+    use web3::types::{Bytes, U256};
+
+    const CUSTODIAN: &str = "0x00000000000000000000000000000000000000c1";
+
+    fn test_config() -> crate::config::AppConfig {
+        let mut config = crate::config::AppConfig::default();
+        config.contracts.interest_manager = "0x00000000000000000000000000000000000000a1".to_string();
+        config.contracts.liquidation_manager = "0x00000000000000000000000000000000000000b1".to_string();
+        config.contracts.auction_manager = "0x00000000000000000000000000000000000000d1".to_string();
+        config.contracts.custodian = CUSTODIAN.to_string();
+        config
+    }
+
+
+    fn uint(value: u64) -> Token {
+        Token::Uint(U256::from(value))
+    }
+
+    /// 构造区块 100 中的日志，非 indexed 参数用 ethabi 按合约 ABI 编码
+    fn encoded_log(address: &str, topics: Vec<H256>, params: &[Token], log_index: u64) -> web3::types::Log {
+        web3::types::Log {
+            address: address.parse().unwrap(),
+            topics,
+            data: Bytes(web3::ethabi::encode(params)),
+            block_hash: None,
+            block_number: Some(U64::from(100)),
+            transaction_hash: None,
+            transaction_index: Some(web3::types::Index::from(0)),
+            log_index: Some(U256::from(log_index)),
+            transaction_log_index: None,
+            log_type: None,
+            removed: None,
+        }
+    }
+
+    /// Mint(address indexed user, uint256 tokenId, uint256 underlyingAmountInWei, LeverageType leverageLevel,
+    ///      uint256 mintPriceInWei, uint256 sAmountInWei, uint256 lAmountInWei)
+    fn mint_log(user: Address, token_id: u64, leverage: u8, mint_price: u64, l_amount: u64, log_index: u64) -> web3::types::Log {
+        encoded_log(
+            CUSTODIAN,
+            vec![event_signature_map()["Mint"], H256::from(user)],
+            &[uint(token_id), uint(0), uint(leverage as u64), uint(mint_price), uint(0), uint(l_amount)],
+            log_index,
+        )
+    }
+
+    #[tokio::test]
+    async fn invalid_leverage_byte_skips_only_that_event() {
+        let database = Arc::new(Database::open_temp());
+        let config = test_config();
+        let signatures = event_signature_map();
+        let user = Address::from_low_u64_be(0x42);
+
+        // 同一区块中：一个杠杆字节越界的事件，后面跟着两个正常事件
+        let logs = vec![
+            mint_log(user, 1, 7, 2_000, 10, 0),
+            mint_log(user, 2, 0, 3_000, 20, 1),
+            mint_log(user, 3, 2, 4_000, 30, 2),
+        ];
+
+        for log in &logs {
+            EventMonitor::dispatch_log_static(&database, &config, &signatures, log).await;
+        }
+
+        assert!(database.get_user_position(user, U256::from(1)).unwrap().is_none());
+
+        let second = database.get_user_position(user, U256::from(2)).unwrap().expect("第二个事件应被处理");
+        assert!(matches!(second.leverage, LeverageType::Conservative));
+        assert_eq!(second.mint_price, U256::from(3_000));
+        assert_eq!(second.amount, U256::from(20));
+
+        let third = database.get_user_position(user, U256::from(3)).unwrap().expect("第三个事件应被处理");
+        assert!(matches!(third.leverage, LeverageType::Aggressive));
+        assert_eq!(third.amount, U256::from(30));
+    }
+
+    #[tokio::test]
+    async fn unwatched_token_positions_are_not_stored() {
+        let database = Arc::new(Database::open_temp());
+        let mut config = test_config();
+        config.watched_token_ids = Some(vec![2]);
+        let signatures = event_signature_map();
+        let user = Address::from_low_u64_be(0x42);
+
+        for log in [mint_log(user, 1, 0, 2_000, 10, 0), mint_log(user, 2, 0, 3_000, 20, 1)] {
+            EventMonitor::dispatch_log_static(&database, &config, &signatures, &log).await;
+        }
+
+        assert!(database.get_user_position(user, U256::from(1)).unwrap().is_none());
+        assert!(database.get_user_position(user, U256::from(2)).unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn replayed_interest_collected_is_applied_once() {
+        let database = Arc::new(Database::open_temp());
+        let user = Address::from_low_u64_be(0x42);
+        database.store_user_position(&UserPosition {
+            user,
+            token_id: U256::from(1),
+            amount: U256::from(100),
+            timestamp: 0,
+            total_interest: U256::from(10),
+            leverage: LeverageType::Conservative,
+            mint_price: U256::from(2_000),
+            last_applied_log: None,
+            epoch: 0,
+        }).unwrap();
+
+        let log = encoded_log(
+            &test_config().contracts.interest_manager,
+            vec![event_signature_map()["InterestCollected"], H256::from(user), H256::from_low_u64_be(1)],
+            &[uint(30), uint(4)], // deductLAmountInWei, interestAmount
+            5,
+        );
+
+        // 重同步时同一日志被处理两次
+        for _ in 0..2 {
+            EventMonitor::process_interest_event_static(&database, "InterestCollected", &log).await.unwrap();
+        }
+
+        let position = database.get_user_position(user, U256::from(1)).unwrap().unwrap();
+        assert_eq!(position.amount, U256::from(70));
+        assert_eq!(position.total_interest, U256::from(6));
+        assert_eq!(position.last_applied_log, Some(LogPosition { block_number: 100, log_index: 5 }));
+    }
+
+    #[test]
+    fn position_subtraction_clamps_to_zero() {
+        let user = Address::from_low_u64_be(0x42);
+        assert_eq!(sub_or_clamp(U256::from(10), U256::from(3), "持仓数量", user, U256::one()), U256::from(7));
+        assert_eq!(sub_or_clamp(U256::from(3), U256::from(10), "持仓数量", user, U256::one()), U256::zero());
+    }
+
+    #[test]
+    fn log_filters_cover_handled_event_topics_only() {
+        let signatures = event_signature_map();
+        let topics = handled_topics(&signatures, &[]);
+        // 两个 ParameterChanged 事件签名相同，只出现一次
+        assert_eq!(topics.len(), signatures.len() - 1);
+        assert!(topics.contains(&signatures["AuctionStarted"]));
+
+        let mut log = encoded_log(&test_config().contracts.auction_manager, vec![signatures["AuctionRemoved"]], &[], 0);
+        assert!(is_handled_event(&signatures, &log));
+        log.topics = vec![H256::from_low_u64_be(0xdead)];
+        assert!(!is_handled_event(&signatures, &log));
+        log.topics.clear();
+        assert!(!is_handled_event(&signatures, &log));
+    }
+
+    #[test]
+    fn disabled_events_are_dropped_from_log_filters() {
+        let signatures = event_signature_map();
+        let mut config = test_config();
+        config.event_monitoring.disabled_events = ["InterestRateChanged", "PositionIncreased", "InterestCollected", "LiquidationParameterChanged"]
+            .map(String::from)
+            .to_vec();
+        let disabled = &config.event_monitoring.disabled_events;
+
+        let topics = handled_topics(&signatures, disabled);
+        assert!(!topics.contains(&signatures["PositionIncreased"]));
+        // AuctionManager 的 ParameterChanged 仍启用，共享的签名保留
+        assert!(topics.contains(&signatures["AuctionParameterChanged"]));
+
+        // InterestManager 的事件全部禁用，不再查询该合约
+        let addresses = enabled_contract_addresses(&config).unwrap();
+        assert_eq!(addresses.len(), 3);
+        assert!(!addresses.contains(&config.contracts.interest_manager.parse().unwrap()));
+
+        let parameter_changed = vec![signatures["LiquidationParameterChanged"]];
+        let liquidation_log = encoded_log(&config.contracts.liquidation_manager, parameter_changed.clone(), &[], 0);
+        assert!(is_disabled_event(&config, &signatures, &liquidation_log));
+        let auction_log = encoded_log(&config.contracts.auction_manager, parameter_changed, &[], 0);
+        assert!(!is_disabled_event(&config, &signatures, &auction_log));
+    }
+
+    /// 模拟的 JSON-RPC 节点收到的 eth_getLogs 过滤条件
+    type RecordedFilters = Arc<std::sync::Mutex<Vec<serde_json::Value>>>;
+
+    /// 在本地端口启动模拟的 JSON-RPC 节点：eth_blockNumber 返回 `head`，eth_getLogs 按区块范围、地址和 topic0
+    /// 从 `logs` 中筛选（保持给定顺序），其他方法返回 null
+    async fn mock_rpc(head: u64, logs: Vec<web3::types::Log>) -> (web3::Web3<FailoverTransport>, RecordedFilters) {
+        mock_rpc_with_calls(head, logs, HashMap::new()).await
+    }
+
+    /// 同 `mock_rpc`，eth_call 按函数选择器返回 `calls` 中的 ABI 编码结果
+    async fn mock_rpc_with_calls(
+        head: u64,
+        logs: Vec<web3::types::Log>,
+        calls: HashMap<[u8; 4], Vec<u8>>,
+    ) -> (web3::Web3<FailoverTransport>, RecordedFilters) {
+        use hyper::service::{make_service_fn, service_fn};
+
+        let (logs, calls) = (Arc::new(logs), Arc::new(calls));
+        let filters: RecordedFilters = Arc::default();
+        let recorded = filters.clone();
+        let make_service = make_service_fn(move |_conn| {
+            let (logs, calls, recorded) = (logs.clone(), calls.clone(), recorded.clone());
+            async move {
+                Ok::<_, std::convert::Infallible>(service_fn(move |request: hyper::Request<hyper::Body>| {
+                    let (logs, calls, recorded) = (logs.clone(), calls.clone(), recorded.clone());
+                    async move {
+                        let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+                        let call: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                        let result = match call["method"].as_str() {
+                            Some("eth_blockNumber") => serde_json::json!(format!("{:#x}", head)),
+                            Some("eth_getLogs") => {
+                                let filter = call["params"][0].clone();
+                                recorded.lock().unwrap().push(filter.clone());
+                                serde_json::to_value(logs.iter().filter(|log| filter_matches(&filter, log)).collect::<Vec<_>>()).unwrap()
+                            }
+                            Some("eth_call") => {
+                                let data: Bytes = serde_json::from_value(call["params"][0]["data"].clone()).unwrap();
+                                let selector: [u8; 4] = data.0[..4].try_into().unwrap();
+                                serde_json::to_value(Bytes(calls[&selector].clone())).unwrap()
+                            }
+                            _ => serde_json::Value::Null,
+                        };
+                        let response = serde_json::json!({ "jsonrpc": "2.0", "id": call["id"], "result": result });
+                        Ok::<_, std::convert::Infallible>(hyper::Response::new(hyper::Body::from(response.to_string())))
+                    }
+                }))
+            }
+        });
+        let server = hyper::Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let url = format!("http://{}", server.local_addr());
+        tokio::spawn(server);
+
+        (web3::Web3::new(FailoverTransport::new(&[url], 3).unwrap()), filters)
+    }
+
+    /// eth_getLogs 过滤条件是否匹配日志（区块范围、地址列表、topic0 候选列表）
+    fn filter_matches(filter: &serde_json::Value, log: &web3::types::Log) -> bool {
+        let block = |key: &str| u64::from_str_radix(filter[key].as_str().unwrap().trim_start_matches("0x"), 16).unwrap();
+        let block_number = log.block_number.unwrap().as_u64();
+        let hex = |value: &serde_json::Value| value.as_str().unwrap().to_lowercase();
+        block_number >= block("fromBlock")
+            && block_number <= block("toBlock")
+            && filter["address"].as_array().unwrap().iter().any(|address| hex(address) == format!("{:?}", log.address))
+            && filter["topics"][0].as_array().unwrap().iter().any(|topic| hex(topic) == format!("{:?}", log.topics[0]))
+    }
+
+    /// 连接到 `web3` 的事件监控器（未配置 ws_url，处于轮询模式）
+    async fn polling_monitor(web3: web3::Web3<FailoverTransport>, database: Arc<Database>, config: crate::config::AppConfig) -> EventMonitor {
+        let tx_sender = Arc::new(TransactionSender::new(web3.clone(), &config).unwrap());
+        EventMonitor::new(
+            web3,
+            database,
+            tx_sender,
+            config,
+            CancellationToken::new(),
+            Arc::new(KeeperStatus::new()),
+            Arc::new(crate::notify::NoopNotifier),
+        ).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn polling_skips_disabled_events_and_syncs_custodian() {
+        let database = Arc::new(Database::open_temp());
+        database.set_last_synced_block(99, None).unwrap();
+        let mut config = test_config();
+        config.event_monitoring.disabled_events = vec!["InterestRateChanged".to_string(), "LiquidationParameterChanged".to_string()];
+        let signatures = event_signature_map();
+        let user = Address::from_low_u64_be(0x42);
+        let params_before = database.get_system_params().unwrap();
+
+        let mut parameter = [0u8; 32];
+        parameter[..7].copy_from_slice(b"penalty");
+        let logs = vec![
+            encoded_log(&config.contracts.interest_manager, vec![signatures["InterestRateChanged"]], &[uint(300), uint(900)], 0),
+            encoded_log(&config.contracts.liquidation_manager, vec![signatures["LiquidationParameterChanged"], H256::from(parameter)], &[uint(7)], 1),
+            mint_log(user, 1, 0, 2_000, 10, 2),
+        ];
+        let (web3, filters) = mock_rpc(100, logs).await;
+
+        let mut monitor = polling_monitor(web3, database.clone(), config).await;
+        monitor.monitor_all_events().await.unwrap();
+
+        // 轮询查询了 CustodianFixed，Mint 事件被处理
+        assert!(database.get_user_position(user, U256::from(1)).unwrap().is_some());
+        assert_eq!(database.get_last_synced_block().unwrap(), Some(100));
+
+        // 禁用的事件既不查询也不处理；共享 topic0 的 LiquidationManager ParameterChanged 被查询到，但在分发时跳过
+        let filters = filters.lock().unwrap();
+        assert_eq!(filters.len(), 1);
+        let topics = filters[0]["topics"][0].to_string();
+        assert!(!topics.contains(&format!("{:?}", signatures["InterestRateChanged"])));
+        assert!(topics.contains(&format!("{:?}", signatures["AuctionParameterChanged"])));
+        let params = database.get_system_params().unwrap();
+        assert_eq!(params.annual_interest_rate, params_before.annual_interest_rate);
+        assert_eq!(params.penalty, params_before.penalty);
+    }
+
+    #[tokio::test]
+    async fn out_of_order_logs_are_applied_in_chain_order() {
+        let database = Arc::new(Database::open_temp());
+        database.set_last_synced_block(99, None).unwrap();
+        let config = test_config();
+        let signatures = event_signature_map();
+        let user = Address::from_low_u64_be(0x42);
+        let position_topics = |event: &str| vec![signatures[event], H256::from(user), H256::from_low_u64_be(5)];
+
+        // 节点按与链上相反的顺序返回同一区块的两条日志
+        let logs = vec![
+            encoded_log(&config.contracts.interest_manager, position_topics("InterestCollected"), &[uint(30), uint(4)], 2),
+            encoded_log(&config.contracts.interest_manager, position_topics("PositionIncreased"), &[uint(20), uint(120), uint(6)], 1),
+        ];
+        let (web3, _) = mock_rpc(100, logs).await;
+
+        let mut monitor = polling_monitor(web3, database.clone(), config).await;
+        monitor.monitor_all_events().await.unwrap();
+
+        let position = database.get_user_position(user, U256::from(5u64)).unwrap().expect("持仓应被创建");
+        assert_eq!(position.amount, U256::from(90u64));
+        assert_eq!(position.last_applied_log, Some(LogPosition { block_number: 100, log_index: 2 }));
+    }
+
+    #[tokio::test]
+    async fn reorg_rollback_resyncs_orphaned_state_at_fork_point() {
+        use web3::ethabi::{short_signature, ParamType};
+
+        let database = Arc::new(Database::open_temp());
+        database.set_last_synced_block(102, None).unwrap();
+        let position = |token_id: u64, amount: u64, applied_block: u64| UserPosition {
+            user: Address::from_low_u64_be(0x42),
+            token_id: U256::from(token_id),
+            amount: U256::from(amount),
+            timestamp: 1_700_000_000,
+            total_interest: U256::zero(),
+            leverage: LeverageType::Moderate,
+            mint_price: U256::from(2_000u64),
+            last_applied_log: Some(LogPosition { block_number: applied_block, log_index: 0 }),
+            epoch: 0,
+        };
+        // 孤块 101 中的 PositionIncreased 把数量从 100 增加到 150；持仓 6 在分叉点之前已应用，不受影响
+        database.store_user_position(&position(5, 150, 101)).unwrap();
+        database.store_user_position(&position(6, 70, 90)).unwrap();
+        // 孤块中开始的拍卖在分叉点的链上不存在
+        database.store_auction(&AuctionInfo {
+            auction_id: U256::from(9u64),
+            starting_price: U256::from(1_000u64),
+            underlying_amount: U256::from(40u64),
+            original_owner: Address::from_low_u64_be(0x42),
+            token_id: U256::from(7u64),
+            triggerer: Address::from_low_u64_be(0x43),
+            reward_amount: U256::zero(),
+            start_time: 1_700_000_000,
+            epoch: 0,
+        }).unwrap();
+
+        let calls = HashMap::from([
+            (
+                short_signature("userPositions", &[ParamType::Address, ParamType::Uint(256)]),
+                web3::ethabi::encode(&[uint(100), uint(1_700_000_000), uint(3), Token::Bool(true)]),
+            ),
+            (
+                short_signature("getTokenDetails", &[ParamType::Uint(256)]),
+                web3::ethabi::encode(&[uint(1), uint(2_000), uint(0), Token::String(String::new()), Token::Bool(false)]),
+            ),
+            (short_signature("isActiveAuction", &[ParamType::Uint(256)]), web3::ethabi::encode(&[Token::Bool(false)])),
+        ]);
+        let (web3, _) = mock_rpc_with_calls(102, Vec::new(), calls).await;
+        let mut monitor = polling_monitor(web3, database.clone(), test_config()).await;
+        monitor.rollback_to_fork_point(100, 102).await.unwrap();
+
+        // 覆盖为分叉点的链上状态，之后重放规范链事件时不会叠加在孤块事件的结果上
+        let resynced = database.get_user_position(Address::from_low_u64_be(0x42), U256::from(5u64)).unwrap().unwrap();
+        assert_eq!(resynced.amount, U256::from(100u64));
+        assert_eq!(resynced.last_applied_log, Some(LogPosition { block_number: 100, log_index: u64::MAX }));
+        let untouched = database.get_user_position(Address::from_low_u64_be(0x42), U256::from(6u64)).unwrap().unwrap();
+        assert_eq!(untouched.amount, U256::from(70u64));
+        assert!(database.get_auction(U256::from(9u64)).unwrap().is_none());
+        assert_eq!(database.get_last_synced_block().unwrap(), Some(100));
+    }
+
+    #[tokio::test]
+    async fn forced_backfill_replays_events_behind_watermarks() {
+        use web3::ethabi::{short_signature, ParamType};
+
+        let database = Arc::new(Database::open_temp());
+        database.set_last_synced_block(110, None).unwrap();
+        let config = test_config();
+        let signatures = event_signature_map();
+        let user = Address::from_low_u64_be(0x42);
+        // 区块 100 的 PositionIncreased 被遗漏，之后区块 105 的事件已应用
+        database.store_user_position(&UserPosition {
+            user,
+            token_id: U256::from(5u64),
+            amount: U256::from(100u64),
+            timestamp: 1_700_000_000,
+            total_interest: U256::zero(),
+            leverage: LeverageType::Moderate,
+            mint_price: U256::from(2_000u64),
+            last_applied_log: Some(LogPosition { block_number: 105, log_index: 0 }),
+            epoch: 0,
+        }).unwrap();
+
+        let logs = vec![encoded_log(
+            &config.contracts.interest_manager,
+            vec![signatures["PositionIncreased"], H256::from(user), H256::from_low_u64_be(5)],
+            &[uint(20), uint(120), uint(6)],
+            1,
+        )];
+        let calls = HashMap::from([
+            (
+                short_signature("userPositions", &[ParamType::Address, ParamType::Uint(256)]),
+                web3::ethabi::encode(&[uint(100), uint(1_700_000_000), uint(0), Token::Bool(true)]),
+            ),
+            (
+                short_signature("getTokenDetails", &[ParamType::Uint(256)]),
+                web3::ethabi::encode(&[uint(1), uint(2_000), uint(0), Token::String(String::new()), Token::Bool(false)]),
+            ),
+        ]);
+        let (web3, _) = mock_rpc_with_calls(110, logs, calls).await;
+        let mut monitor = polling_monitor(web3, database.clone(), config).await;
+
+        // 普通回填按最后应用日志位置跳过遗漏的事件，同步进度恢复
+        monitor.backfill(100, 100, false).await.unwrap();
+        let position = database.get_user_position(user, U256::from(5u64)).unwrap().unwrap();
+        assert_eq!(position.amount, U256::from(100u64));
+        assert_eq!(database.get_last_synced_block().unwrap(), Some(110));
+
+        // 强制回填先退回区块 99 的链上状态，再重放遗漏的事件
+        monitor.backfill(100, 100, true).await.unwrap();
+        let position = database.get_user_position(user, U256::from(5u64)).unwrap().unwrap();
+        assert_eq!(position.amount, U256::from(120u64));
+        assert_eq!(position.last_applied_log, Some(LogPosition { block_number: 100, log_index: 1 }));
+        assert_eq!(database.get_last_synced_block().unwrap(), Some(100));
+    }
+
+    #[test]
+    fn cold_start_prefers_configured_from_block() {
+        let mut config = crate::config::EventMonitoringConfig {
+            cold_start_backtrace_blocks: 100,
+            ..Default::default()
+        };
+        assert_eq!(cold_start_block(&config, 1_000).unwrap(), Some(900));
+        assert_eq!(cold_start_block(&config, 50).unwrap(), Some(0));
+
+        config.cold_start_from_block = Some(10);
+        assert_eq!(cold_start_block(&config, 1_000).unwrap(), Some(10));
+        assert_eq!(cold_start_block(&config, 10).unwrap(), Some(10));
+        assert!(cold_start_block(&config, 9).is_err());
+
+        config.cold_start_from_block = None;
+        config.cold_start_backtrace_blocks = 0;
+        assert_eq!(cold_start_block(&config, 1_000).unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn auction_started_reads_original_owner_from_third_data_word() {
+        let database = Arc::new(Database::open_temp());
+        let config = test_config();
+        let signatures = event_signature_map();
+
+        let original_owner: Address = "0x5b38da6a701c568545dcfcb03fcb875f56beddc4".parse().unwrap();
+        let triggerer: Address = "0xab8483f64d9c6d1ecf9b849ae677dd3315835cb2".parse().unwrap();
+        // 非 indexed 参数按声明顺序编码：startingPrice, underlyinglAmount, originalOwner, rewardAmount
+        let log = encoded_log(
+            &config.contracts.auction_manager,
+            vec![signatures["AuctionStarted"], H256::from_low_u64_be(9), H256::from_low_u64_be(3), H256::from(triggerer)],
+            &[uint(2_500), uint(40), Token::Address(original_owner), uint(7)],
+            0,
+        );
+
+        // 开始时间取事件所在区块的时间戳，而不是处理时的当前时间
+        database.cache_block_timestamp(100, 1_700_000_000).unwrap();
+        database.cache_block_timestamp(150, 1_700_000_600).unwrap();
+        EventMonitor::dispatch_log_static(&database, &config, &signatures, &log).await;
+
+        let auction = database.get_auction(U256::from(9u64)).unwrap().expect("拍卖应被存储");
+        assert_eq!(auction.start_time, 1_700_000_000);
+        assert_eq!(auction.original_owner, original_owner);
+        assert_eq!(auction.triggerer, triggerer);
+        assert_eq!(auction.token_id, U256::from(3u64));
+        assert_eq!(auction.starting_price, U256::from(2_500u64));
+        assert_eq!(auction.underlying_amount, U256::from(40u64));
+        assert_eq!(auction.reward_amount, U256::from(7u64));
+
+        // AuctionReset 与 AuctionStarted 布局相同，更新起始价格、标的数量和奖励
+        let mut reset = encoded_log(
+            &config.contracts.auction_manager,
+            vec![signatures["AuctionReset"], H256::from_low_u64_be(9), H256::from_low_u64_be(3), H256::from(triggerer)],
+            &[uint(3_000), uint(35), Token::Address(original_owner), uint(8)],
+            1,
+        );
+        reset.block_number = Some(U64::from(150));
+        EventMonitor::dispatch_log_static(&database, &config, &signatures, &reset).await;
+        let auction = database.get_auction(U256::from(9u64)).unwrap().unwrap();
+        assert_eq!(auction.start_time, 1_700_000_600);
+        assert_eq!(auction.starting_price, U256::from(3_000u64));
+        assert_eq!(auction.underlying_amount, U256::from(35u64));
+        assert_eq!(auction.reward_amount, U256::from(8u64));
+        assert_eq!(auction.original_owner, original_owner);
+        assert_eq!(auction.triggerer, triggerer);
+
+        let removed = encoded_log(&config.contracts.auction_manager, vec![signatures["AuctionRemoved"], H256::from_low_u64_be(9)], &[], 2);
+        EventMonitor::dispatch_log_static(&database, &config, &signatures, &removed).await;
+        assert!(database.get_auction(U256::from(9u64)).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn interest_manager_events_decode_from_abi_encoding() {
+        let database = Arc::new(Database::open_temp());
+        let config = test_config();
+        let signatures = event_signature_map();
+        let user = Address::from_low_u64_be(0x42);
+        let user_topic = H256::from(user);
+        let token_topic = H256::from_low_u64_be(5);
+
+        // InterestRateChanged(uint256 oldRate, uint256 newRate)：两个参数都在 data 中
+        let rate = encoded_log(&config.contracts.interest_manager, vec![signatures["InterestRateChanged"]], &[uint(300), uint(450)], 0);
+        EventMonitor::dispatch_log_static(&database, &config, &signatures, &rate).await;
+        assert_eq!(database.get_system_params().unwrap().annual_interest_rate, U256::from(450u64));
+
+        // PositionIncreased(address indexed user, uint256 indexed tokenId, uint256 amount, uint256 totalAmount, uint256 totalInterest)
+        let increased = encoded_log(
+            &config.contracts.interest_manager,
+            vec![signatures["PositionIncreased"], user_topic, token_topic],
+            &[uint(20), uint(120), uint(6)],
+            1,
+        );
+        EventMonitor::dispatch_log_static(&database, &config, &signatures, &increased).await;
+        let position = database.get_user_position(user, U256::from(5u64)).unwrap().expect("持仓应被创建");
+        assert_eq!(position.amount, U256::from(120u64));
+        assert_eq!(position.total_interest, U256::from(6u64));
+
+        // InterestCollected(address indexed user, uint256 indexed tokenId, uint256 deductLAmountInWei, uint256 interestAmount)
+        let collected = encoded_log(
+            &config.contracts.interest_manager,
+            vec![signatures["InterestCollected"], user_topic, token_topic],
+            &[uint(30), uint(4)],
+            2,
+        );
+        EventMonitor::dispatch_log_static(&database, &config, &signatures, &collected).await;
+        let position = database.get_user_position(user, U256::from(5u64)).unwrap().unwrap();
+        assert_eq!(position.amount, U256::from(90u64));
+        assert_eq!(position.total_interest, U256::from(2u64));
+    }
+
+    #[tokio::test]
+    async fn liquidation_manager_events_decode_from_abi_encoding() {
+        let database = Arc::new(Database::open_temp());
+        let config = test_config();
+        let signatures = event_signature_map();
+        let user = Address::from_low_u64_be(0x42);
+
+        // LiquidationConfigInfo(uint256 adjustmentThreshold, uint256 liquidationThreshold, uint256 penalty, bool enabled)
+        let config_info = encoded_log(
+            &config.contracts.liquidation_manager,
+            vec![signatures["LiquidationConfigInfo"]],
+            &[uint(600), uint(250), uint(40), Token::Bool(false)],
+            0,
+        );
+        EventMonitor::dispatch_log_static(&database, &config, &signatures, &config_info).await;
+        let params = database.get_system_params().unwrap();
+        assert_eq!(params.adjustment_threshold, U256::from(600u64));
+        assert_eq!(params.liquidation_threshold, U256::from(250u64));
+        assert_eq!(params.penalty, U256::from(40u64));
+        assert!(!params.liquidation_enabled);
+
+        // NetValueAdjusted(address indexed user, uint256 indexed fromTokenId, uint256 indexed toTokenId,
+        //                  LeverageType leverage, uint256 newMintPrice, uint256 adjustAmountInWei, uint256 underlyingAmountInWei)
+        let adjusted = encoded_log(
+            &config.contracts.liquidation_manager,
+            vec![signatures["NetValueAdjusted"], H256::from(user), H256::from_low_u64_be(1), H256::from_low_u64_be(2)],
+            &[uint(2), uint(1_800), uint(75), uint(9)],
+            1,
+        );
+        EventMonitor::dispatch_log_static(&database, &config, &signatures, &adjusted).await;
+        let position = database.get_user_position(user, U256::from(2u64)).unwrap().expect("目标 token 的持仓应被创建");
+        assert!(matches!(position.leverage, LeverageType::Aggressive));
+        assert_eq!(position.mint_price, U256::from(1_800u64));
+        assert_eq!(position.amount, U256::from(75u64));
+    }
+
+    /// 模糊测试：任意长度的数据和任意数量的 topic 都不能让解码器 panic
+    #[tokio::test]
+    async fn arbitrary_log_data_never_panics() {
+        let database = Arc::new(Database::open_temp());
+        let config = test_config();
+        let signatures = event_signature_map();
+        let addresses = [
+            config.contracts.interest_manager.clone(),
+            config.contracts.liquidation_manager.clone(),
+            config.contracts.auction_manager.clone(),
+            config.contracts.custodian.clone(),
+        ];
+
+        // 固定种子的 xorshift，失败时可以复现
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut next_byte = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        };
+
+        let mut log_index = 0u64;
+        for signature in signatures.values() {
+            for address in &addresses {
+                for topic_count in 0..=4usize {
+                    for data_len in 0..=200usize {
+                        let mut topics = vec![*signature];
+                        topics.extend((1..topic_count).map(|_| H256::from_slice(&[next_byte(); 32])));
+                        topics.truncate(topic_count);
+                        let mut log = encoded_log(address, topics, &[], log_index);
+                        log.data = Bytes((0..data_len).map(|_| next_byte()).collect());
+                        log_index += 1;
+
+                        EventMonitor::dispatch_log_static(&database, &config, &signatures, &log).await;
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn only_confirmed_blocks_leave_the_buffer() {
+        let mut pending: BTreeMap<u64, &str> = [(98, "a"), (99, "b"), (100, "c"), (101, "d")].into_iter().collect();
+
+        let confirmed = take_confirmed(&mut pending, 99);
+        assert_eq!(confirmed.into_iter().collect::<Vec<_>>(), vec![(98, "a"), (99, "b")]);
+        assert_eq!(pending.keys().copied().collect::<Vec<_>>(), vec![100, 101]);
+
+        assert!(take_confirmed(&mut pending, 99).is_empty());
+        assert_eq!(take_confirmed(&mut pending, u64::MAX).len(), 2);
+        assert!(pending.is_empty());
+    }
+
+    fn event_id(block_number: u64, log_index: usize) -> EventId {
+        EventId { block_number, transaction_index: 0, log_index }
+    }
+
+    #[test]
+    fn processed_event_window_drops_whole_blocks() {
+        let mut window = ProcessedEventWindow::new(10);
+        for block_number in [100, 105, 110] {
+            window.insert(event_id(block_number, 0));
+            window.insert(event_id(block_number, 1));
+        }
+        window.insert(event_id(110, 1));
+        assert_eq!(window.len(), 6);
+
+        // 最新区块 112，保留 102 及之后的区块
+        assert_eq!(window.prune(112), 2);
+        assert!(!window.contains(&event_id(100, 0)));
+        assert!(window.contains(&event_id(105, 1)));
+
+        // 重组回滚：移除 110 及之后的区块
+        assert_eq!(window.evict_from(110), 2);
+        assert!(!window.contains(&event_id(110, 0)));
+        assert_eq!(window.len(), 2);
+        assert_eq!(window.latest_block(), Some(105));
+    }
+
+    #[test]
+    fn processed_event_window_restores_persisted_block() {
+        let mut window = ProcessedEventWindow::new(10);
+        window.insert(event_id(200, 3));
+        window.insert(event_id(200, 1));
+        let record = window.block_record(200);
+        assert_eq!(record.events, vec![(0, 1), (0, 3)]);
+
+        let mut restored = ProcessedEventWindow::new(10);
+        restored.restore(&record);
+        assert!(restored.contains(&event_id(200, 1)));
+        assert!(restored.contains(&event_id(200, 3)));
+        assert!(!restored.contains(&event_id(200, 2)));
+    }
+}
@@ -0,0 +1,209 @@
+//! RPC 节点故障切换模块
+//!
+//! `FailoverTransport` 包装主节点（`rpc_url`）和备用节点（`rpc_fallback_urls`）的 HTTP transport，
+//! 所有请求发往当前节点：
+//! - 当前节点连续出现 `rpc_failover_threshold` 次临时错误（与 RPC 重试的判断一致）后切换到下一个节点
+//! - 使用备用节点期间，按 `rpc_primary_check_interval_secs` 检查主节点，恢复后切回主节点
+//!
+//! 失败的请求本身不在 transport 内重发，由调用方的 `rpc_retry` 重试，重试时已经发往切换后的节点。
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::time::Duration;
+use futures_util::future::{BoxFuture, FutureExt};
+use jsonrpc_core::{Call, Value};
+use tokio_util::sync::CancellationToken;
+use web3::transports::Http;
+use web3::{RequestId, Transport};
+use crate::rpc::is_transient_error;
+
+/// 单个 RPC 节点
+#[derive(Debug)]
+struct Endpoint {
+    /// 日志中显示的节点名称（只保留主机名，URL 中可能带有 API key）
+    name: String,
+    transport: Http,
+}
+
+#[derive(Debug)]
+struct FailoverState {
+    endpoints: Vec<Endpoint>,
+    /// 当前使用的节点下标
+    active: AtomicUsize,
+    /// 当前节点连续出现临时错误的次数
+    consecutive_failures: AtomicU32,
+    failover_threshold: u32,
+    next_request_id: AtomicUsize,
+}
+
+/// 支持多节点故障切换的 transport
+#[derive(Debug, Clone)]
+pub struct FailoverTransport {
+    state: Arc<FailoverState>,
+}
+
+impl FailoverTransport {
+    /// 第一个 URL 为主节点，其余按顺序作为备用节点
+    pub fn new(urls: &[String], failover_threshold: u32) -> web3::Result<Self> {
+        if urls.is_empty() {
+            return Err(web3::Error::Transport(web3::error::TransportError::Message("未配置 RPC 节点".to_string())));
+        }
+        let endpoints = urls.iter()
+            .map(|url| Ok(Endpoint { name: endpoint_name(url), transport: Http::new(url)? }))
+            .collect::<web3::Result<Vec<_>>>()?;
+
+        Ok(Self {
+            state: Arc::new(FailoverState {
+                endpoints,
+                active: AtomicUsize::new(0),
+                consecutive_failures: AtomicU32::new(0),
+                failover_threshold,
+                next_request_id: AtomicUsize::new(1),
+            }),
+        })
+    }
+
+    /// 按配置创建：`rpc_url` 为主节点，`rpc_fallback_urls` 为备用节点
+    pub fn from_config(config: &crate::config::AppConfig) -> web3::Result<Self> {
+        let urls: Vec<String> = std::iter::once(config.rpc_url.clone())
+            .chain(config.rpc_fallback_urls.iter().cloned())
+            .collect();
+        let transport = Self::new(&urls, config.rpc_failover_threshold)?;
+        if urls.len() > 1 {
+            tracing::info!("RPC 故障切换已启用 - 主节点: {}, 备用节点: {} 个", transport.state.endpoints[0].name, urls.len() - 1);
+        }
+        Ok(transport)
+    }
+
+    /// 当前使用的节点下标（0 为主节点）
+    pub fn active_endpoint(&self) -> usize {
+        self.state.active.load(Ordering::Relaxed)
+    }
+
+    /// 记录一次请求结果；只统计发往当前节点的请求，切换前已发出的请求结果不影响新节点的计数
+    fn record_result(&self, endpoint: usize, result: &web3::Result<Value>) {
+        let state = &self.state;
+        if endpoint != state.active.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let error = match result {
+            Err(e) if is_transient_error(e) => e,
+            _ => {
+                state.consecutive_failures.store(0, Ordering::Relaxed);
+                return;
+            }
+        };
+
+        let failures = state.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if state.endpoints.len() < 2 || state.failover_threshold == 0 || failures < state.failover_threshold {
+            return;
+        }
+
+        let next = (endpoint + 1) % state.endpoints.len();
+        if state.active.compare_exchange(endpoint, next, Ordering::Relaxed, Ordering::Relaxed).is_ok() {
+            state.consecutive_failures.store(0, Ordering::Relaxed);
+            tracing::warn!(
+                "RPC 节点 {} 连续 {} 次请求失败，切换到 {} - 最近错误: {}",
+                state.endpoints[endpoint].name, failures, state.endpoints[next].name, error
+            );
+        }
+    }
+
+    /// 使用备用节点期间定期检查主节点，主节点恢复后切回
+    pub async fn run_primary_health_check(self, interval_secs: u64, shutdown: CancellationToken) {
+        if self.state.endpoints.len() < 2 {
+            return;
+        }
+
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => return,
+                _ = interval.tick() => {}
+            }
+
+            let active = self.active_endpoint();
+            if active == 0 {
+                continue;
+            }
+
+            let primary = &self.state.endpoints[0];
+            let (id, request) = self.prepare("eth_blockNumber", Vec::new());
+            match primary.transport.send(id, request).await {
+                Ok(_) => {
+                    if self.state.active.compare_exchange(active, 0, Ordering::Relaxed, Ordering::Relaxed).is_ok() {
+                        self.state.consecutive_failures.store(0, Ordering::Relaxed);
+                        tracing::info!("RPC 主节点 {} 已恢复，从 {} 切回主节点", primary.name, self.state.endpoints[active].name);
+                    }
+                }
+                Err(e) => tracing::debug!("RPC 主节点 {} 仍不可用: {}", primary.name, e),
+            }
+        }
+    }
+}
+
+impl Transport for FailoverTransport {
+    type Out = BoxFuture<'static, web3::Result<Value>>;
+
+    fn prepare(&self, method: &str, params: Vec<Value>) -> (RequestId, Call) {
+        let id = self.state.next_request_id.fetch_add(1, Ordering::Relaxed);
+        (id, web3::helpers::build_request(id, method, params))
+    }
+
+    fn send(&self, id: RequestId, request: Call) -> Self::Out {
+        let endpoint = self.active_endpoint();
+        let request_future = self.state.endpoints[endpoint].transport.send(id, request);
+        let transport = self.clone();
+        async move {
+            let result = request_future.await;
+            transport.record_result(endpoint, &result);
+            result
+        }
+        .boxed()
+    }
+}
+
+/// 从 URL 中取出用于日志的节点名称（主机名和端口）
+fn endpoint_name(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(parsed) => match (parsed.host_str(), parsed.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            _ => "<无效URL>".to_string(),
+        },
+        Err(_) => "<无效URL>".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn consecutive_transient_errors_rotate_to_next_endpoint() {
+        let urls = vec!["http://primary:8545".to_string(), "https://backup.example.com/v2/secret-key".to_string()];
+        let transport = FailoverTransport::new(&urls, 2).unwrap();
+        let unreachable = || Err(web3::Error::Unreachable);
+
+        // 非临时错误（如 revert）和成功请求都会清零计数
+        transport.record_result(0, &unreachable());
+        transport.record_result(0, &Err(web3::Error::Decoder("bad".to_string())));
+        transport.record_result(0, &unreachable());
+        assert_eq!(transport.active_endpoint(), 0);
+
+        transport.record_result(0, &unreachable());
+        assert_eq!(transport.active_endpoint(), 1);
+
+        // 切换前发往旧节点的请求不影响新节点
+        transport.record_result(0, &unreachable());
+        transport.record_result(1, &unreachable());
+        assert_eq!(transport.active_endpoint(), 1);
+
+        // 最后一个节点出错后回到第一个节点
+        transport.record_result(1, &unreachable());
+        assert_eq!(transport.active_endpoint(), 0);
+
+        assert_eq!(transport.state.endpoints[1].name, "backup.example.com");
+    }
+}
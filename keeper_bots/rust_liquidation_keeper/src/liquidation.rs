@@ -3,77 +3,211 @@
 //! 负责定时监控用户持仓净值，并在净值低于清算阈值时执行清算操作。
 //!
 //! ## 主要功能：
-//! - 定时获取底层资产价格
+//! - 定时获取底层资产价格（多个预言机取中位数，剔除过期报价）
+//! - 收到预言机价格推送时立即检查（可选）
 //! - 调用NAV计算所有用户持仓净值
-//! - 检查净值是否低于清算阈值
+//! - 检查净值是否低于清算阈值，按预期奖励和净值缺口的加权评分排定清算顺序
+//! - 每轮发送的 bark 数量不超过配置上限（可选），其余留到下一轮
+//! - 跳过冷却期内（已发出 bark、拍卖尚未出现）的持仓
+//! - 用 Multicall3 读取的链上净值复核清算判断（可选）
+//! - 发送 bark 前逐个与合约计算的净值交叉核对（可选）
+//! - 检查清算收益是否覆盖 gas 成本（可选）
 //! - 触发清算：调用LiquidationManager.bark函数
 //! - 处理清算退出的情况
+//! - 清算交易确认或 RPC 连续失败时发送运维通知
 
 use std::sync::Arc;
-use web3::types::{Address, U256};
-use web3::ethabi;
-use crate::{nav::NavMonitor, database::Database};
+use std::sync::atomic::{AtomicBool, Ordering};
+use web3::types::{Address, Bytes, CallRequest, U256};
+use web3::ethabi::{self, Token};
+use tokio::sync::{watch, OnceCell};
+use tokio_util::sync::CancellationToken;
+use crate::{nav::{NavCalculation, NavMonitor}, database::{Database, LiquidationRecord, SystemParams}, tx::{TransactionReverted, TransactionSender}};
+use crate::error::KeeperError;
+use crate::metrics::metrics;
+use crate::multicall::MulticallReader;
+use crate::notify::{NoopNotifier, Notification, Notifier};
+use crate::config::{LiquidationPriorityConfig, MAX_TOKEN_DECIMALS};
+use crate::oracle::{PriceOracle, PriceUpdate};
+use crate::rpc::rpc_retry;
+use crate::failover::FailoverTransport;
+use crate::status::{unix_now, KeeperStatus};
+
+/// 交叉核对时读取的合约视角持仓状态
+#[derive(Debug, Clone, PartialEq)]
+struct OnChainLiquidationView {
+    balance: U256,
+    net_nav: U256,
+    frozen: bool,
+}
 
 pub struct LiquidationMonitor {
-    web3: web3::Web3<web3::transports::Http>,
+    web3: web3::Web3<FailoverTransport>,
     nav_monitor: NavMonitor,
     database: Arc<Database>,
+    tx_sender: Arc<TransactionSender>,
     config: crate::config::AppConfig,
-    oracle_address: Address,
+    oracle: PriceOracle,
+    /// 配置了 multicall_address 时用于复核链上净值
+    multicall: Option<MulticallReader>,
     liquidation_manager_address: Address,
+    /// 交叉核对时读取合约计算的净值
+    custodian_address: Address,
+    /// 用于从 bark 回执中识别 AuctionStarted 事件
+    auction_manager_address: Address,
+    /// 奖励代币（`contracts.token`），收益检查时按其精度换算奖励和 gas 成本
+    reward_token_address: Address,
+    /// 奖励代币精度（配置覆盖，或启动时读取后缓存）
+    reward_token_decimals: OnceCell<u8>,
+    /// 上一轮检查时链上清算开关的状态，只在开关变化时记录日志
+    liquidation_enabled: AtomicBool,
+    /// 运维通知
+    notifier: Arc<dyn Notifier>,
+    /// 预言机价格推送（启用 oracle.price_subscription 时），收到更新后立即执行一轮检查
+    price_updates: Option<watch::Receiver<Option<PriceUpdate>>>,
 }
 
 impl LiquidationMonitor {
     pub fn new(
-        web3: web3::Web3<web3::transports::Http>,
+        web3: web3::Web3<FailoverTransport>,
         nav_monitor: NavMonitor,
         database: Arc<Database>,
+        tx_sender: Arc<TransactionSender>,
         config: crate::config::AppConfig,
-        oracle_address: String,
         liquidation_manager_address: String,
-        _auction_manager_address: String, // auction manager logic moved to reset.rs
+        status: Arc<KeeperStatus>,
     ) -> anyhow::Result<Self> {
-        let oracle = oracle_address.parse::<Address>()?;
+        let multicall = config.multicall_address.as_deref()
+            .map(|address| MulticallReader::new(web3.clone(), address, &config))
+            .transpose()?;
+        let oracle = PriceOracle::new(web3.clone(), &config, status)?;
         let liquidation_manager = liquidation_manager_address.parse::<Address>()?;
+        let custodian = config.contracts.custodian.parse::<Address>()?;
+        let auction_manager = config.contracts.auction_manager.parse::<Address>()?;
+        let reward_token = config.contracts.token.parse::<Address>()?;
+        let reward_token_decimals = OnceCell::new_with(config.profitability.reward_token_decimals);
 
-        tracing::info!("清算监控器初始化 - Oracle: {}, LiquidationManager: {}, 检查间隔: {}秒",
-                       oracle_address, liquidation_manager_address, config.liquidation_check_interval);
+        tracing::info!("清算监控器初始化 - LiquidationManager: {}, 检查间隔: {}秒",
+                       liquidation_manager_address, config.liquidation_check_interval);
 
         Ok(Self {
             web3,
             nav_monitor,
             database,
+            tx_sender,
             config,
-            oracle_address: oracle,
+            oracle,
+            multicall,
             liquidation_manager_address: liquidation_manager,
+            custodian_address: custodian,
+            auction_manager_address: auction_manager,
+            reward_token_address: reward_token,
+            reward_token_decimals,
+            liquidation_enabled: AtomicBool::new(true),
+            notifier: Arc::new(NoopNotifier),
+            price_updates: None,
         })
     }
 
+    /// 设置运维通知器（默认不发送通知）
+    pub fn with_notifier(mut self, notifier: Arc<dyn Notifier>) -> Self {
+        self.notifier = notifier;
+        self
+    }
+
+    /// 设置预言机价格推送通道（默认只按定时间隔检查）
+    pub fn with_price_updates(mut self, price_updates: watch::Receiver<Option<PriceUpdate>>) -> Self {
+        self.price_updates = Some(price_updates);
+        self
+    }
+
     /// 启动清算监控循环
-    pub async fn run(&mut self) -> anyhow::Result<()> {
+    ///
+    /// 按定时间隔检查；启用价格推送时，收到价格更新（防抖合并）后立即检查并重新开始计时。
+    /// 只在两轮检查之间响应关闭信号，正在进行的清算检查（包括已发出的交易）会完整执行。
+    pub async fn run(&mut self, shutdown: CancellationToken) -> anyhow::Result<()> {
         tracing::info!("清算监控器启动，监控间隔：{}秒...",
                       self.config.liquidation_check_interval);
 
         let mut interval = tokio::time::interval(
             std::time::Duration::from_secs(self.config.liquidation_check_interval)
         );
+        let rpc_failure_alert_threshold = self.config.notifier.rpc_failure_alert_threshold;
+        let mut consecutive_rpc_failures = 0u32;
+        let debounce = std::time::Duration::from_millis(self.config.oracle.price_update_debounce_ms);
+
+        if self.config.profitability.enabled {
+            match self.reward_token_decimals().await {
+                Ok(decimals) => tracing::info!("奖励代币 {:?} 精度: {} 位小数", self.reward_token_address, decimals),
+                Err(e) => tracing::warn!("读取奖励代币精度失败，将在收益检查时重试: {}", e),
+            }
+        }
 
         loop {
-            interval.tick().await;
+            let price_update = tokio::select! {
+                _ = shutdown.cancelled() => {
+                    tracing::info!("清算监控器已停止");
+                    return Ok(());
+                }
+                _ = interval.tick() => None,
+                update = next_price_update(self.price_updates.as_mut(), debounce) => Some(update),
+            };
+            if let Some(update) = &price_update {
+                tracing::info!("收到预言机价格推送 (roundId: {})，立即执行清算检查", update.round_id);
+                interval.reset();
+            }
+
+            // 每轮检查开始时从链上重新同步nonce，避免外部交易或被丢弃的交易导致nonce漂移
+            self.tx_sender.reset_nonce().await;
 
-            if let Err(e) = self.check_and_execute_liquidations().await {
-                tracing::error!("清算检查执行失败: {}", e);
-                // 继续监控，单次失败不会终止程序
+            match self.check_and_execute_liquidations(price_update.as_ref()).await {
+                Ok(()) => consecutive_rpc_failures = 0,
+                Err(e) => {
+                    tracing::error!("清算检查执行失败: {}", e);
+                    // 继续监控，单次失败不会终止程序；RPC 连续失败达到阈值时通知一次
+                    if is_rpc_failure(&e) {
+                        consecutive_rpc_failures += 1;
+                        if consecutive_rpc_failures == rpc_failure_alert_threshold {
+                            self.notifier.notify(Notification::RpcFailure {
+                                component: "清算检查",
+                                consecutive_failures: consecutive_rpc_failures,
+                                error: e.to_string(),
+                            });
+                        }
+                    } else {
+                        consecutive_rpc_failures = 0;
+                    }
+                }
             }
         }
     }
 
     /// 执行一次完整的清算检查
-    async fn check_and_execute_liquidations(&self) -> anyhow::Result<()> {
+    async fn check_and_execute_liquidations(&self, price_update: Option<&PriceUpdate>) -> anyhow::Result<()> {
         tracing::info!("开始清算检查...");
 
+        // 0. 安全保护触发后暂停清算，等待运维人员确认
+        if self.database.is_liquidation_paused()? {
+            tracing::error!("清算已因安全保护暂停，跳过本轮清算；确认无误后运行 `resume-liquidations` 恢复");
+            return Ok(());
+        }
+
+        // 链上关闭清算时跳过整轮检查，开关变化时记录一次日志
+        let system_params = self.database.get_system_params()?;
+        let liquidation_enabled = system_params.liquidation_enabled;
+        if self.liquidation_enabled.swap(liquidation_enabled, Ordering::Relaxed) != liquidation_enabled {
+            if liquidation_enabled {
+                tracing::info!("链上清算已重新启用，恢复清算");
+            } else {
+                tracing::warn!("链上清算已禁用（LiquidationConfigInfo.enabled = false），暂停清算直到重新启用");
+            }
+        }
+        if !liquidation_enabled {
+            return Ok(());
+        }
+
         // 1. 获取当前底层资产价格
-        let current_price = self.get_current_price().await?;
+        let current_price = self.current_price(price_update).await?;
         tracing::info!("当前底层资产价格: {:?}", current_price);
 
         // 2. 计算所有用户持仓的NAV
@@ -81,86 +215,388 @@ impl LiquidationMonitor {
         tracing::info!("NAV计算完成，共处理 {} 个持仓", nav_results.len());
 
         // 3. 获取清算阈值
-        let system_params = self.database.get_system_params()?;
         let liquidation_threshold = system_params.liquidation_threshold;
         tracing::debug!("清算阈值: {:?}", liquidation_threshold);
 
-        // 4. 检查需要清算的持仓
+        // 4. 检查需要清算的持仓，按清算优先级排序
         let liquidatable_positions: Vec<_> = nav_results.iter()
             .filter(|result| {
                 // 净值低于清算阈值即可触发清算，无论是否还有正净值
                 result.net_nav < liquidation_threshold
             })
             .collect();
+        let liquidatable_positions = prioritize_liquidations(
+            liquidatable_positions,
+            liquidation_threshold,
+            &system_params,
+            &self.config.liquidation_priority,
+        );
 
         tracing::info!("发现 {} 个持仓需要清算", liquidatable_positions.len());
 
-        // 5. 执行清算
-        for position_result in liquidatable_positions {
-            if let Err(e) = self.execute_liquidation(&position_result.user, &position_result.token_id).await {
-                tracing::error!("执行持仓清算失败 - 用户: {:?}, TokenID: {}, 错误: {}",
-                              position_result.user, position_result.token_id, e);
-                // 单个持仓清算失败不影响其他清算
-            } else {
-                tracing::info!("成功发起持仓清算 - 用户: {:?}, TokenID: {}",
-                             position_result.user, position_result.token_id);
+        // 5. 冷却检查：跳过最近已发出 bark、拍卖尚未出现的持仓
+        let liquidatable_positions = self.skip_positions_in_cooldown(liquidatable_positions);
+
+        // 6. 链上复核：跳过链上状态与本地缓存不一致的持仓
+        let liquidatable_positions = self.verify_on_chain(liquidatable_positions, liquidation_threshold).await;
+
+        // 7. 安全保护：单轮清算价值不超过全部跟踪价值的配置比例
+        let liquidatable_positions = self.apply_cycle_value_cap(&nav_results, liquidatable_positions)?;
+
+        // 8. 收益检查：跳过奖励不足以覆盖 gas 成本的持仓
+        let mut profitable_positions = Vec::with_capacity(liquidatable_positions.len());
+        for position in liquidatable_positions {
+            if self.is_liquidation_profitable(position, current_price, &system_params).await {
+                profitable_positions.push(position);
+            }
+        }
+        let liquidatable_positions = profitable_positions;
+
+        // 9. 执行清算；启用交叉核对时，合约视角同样判定可清算才发送 bark
+        //    每轮最多发送 max_liquidations_per_cycle 个 bark，其余持仓留到下一轮
+        let max_liquidations = self.config.max_liquidations_per_cycle;
+        let mut attempted = 0usize;
+        for (index, position_result) in liquidatable_positions.iter().copied().enumerate() {
+            if max_liquidations > 0 && attempted >= max_liquidations {
+                tracing::warn!(
+                    "本轮已发送 {} 个清算，达到每轮上限，剩余 {} 个持仓推迟到下一轮",
+                    attempted, liquidatable_positions.len() - index
+                );
+                break;
+            }
+
+            if self.config.liquidation_safety.on_chain_nav_check
+                && !self.cross_check_on_chain(position_result, liquidation_threshold).await
+            {
+                continue;
+            }
+
+            attempted += 1;
+            match self.execute_liquidation(&position_result.user, &position_result.token_id).await {
+                Err(e) if e.downcast_ref::<TransactionReverted>().is_some() => {
+                    // revert 已在 execute_liquidation 中处理，不视为 Keeper 故障
+                }
+                Err(e) => {
+                    tracing::error!("执行持仓清算失败 - 用户: {:?}, TokenID: {}, 错误: {}",
+                                  position_result.user, position_result.token_id, e);
+                    // 单个持仓清算失败不影响其他清算
+                }
+                Ok(record) => {
+                    tracing::info!("成功发起持仓清算 - 用户: {:?}, TokenID: {}",
+                                 position_result.user, position_result.token_id);
+                    if let Some(record) = record {
+                        self.notifier.notify(Notification::LiquidationSent {
+                            tx_hash: record.tx_hash,
+                            user: record.user,
+                            token_id: record.token_id,
+                            auction_id: record.auction_id,
+                            position_amount: position_result.position_amount,
+                            total_value: position_result.total_value,
+                            net_value: position_result.net_value,
+                            net_nav: position_result.net_nav,
+                        });
+                    }
+                }
             }
         }
 
         Ok(())
     }
 
-    /// 从Oracle合约获取当前价格
-    async fn get_current_price(&self) -> anyhow::Result<U256> {
-        // 创建调用数据：latestRoundData()
-        let _function_abi = r#"[
-            {
-                "name": "latestRoundData",
-                "type": "function",
-                "stateMutability": "view",
-                "inputs": [],
-                "outputs": [
-                    {"type": "uint80"},
-                    {"type": "int256"},
-                    {"type": "uint256"},
-                    {"type": "uint256"},
-                    {"type": "uint80"}
-                ]
+    /// 本轮检查使用的价格：优先使用推送的报价，无法直接使用时读取并聚合所有预言机
+    async fn current_price(&self, price_update: Option<&PriceUpdate>) -> anyhow::Result<U256> {
+        if let Some(update) = price_update {
+            match self.oracle.price_from_update(update).await {
+                Ok(Some(price)) => return Ok(price),
+                Ok(None) => {}
+                Err(e) => tracing::warn!("无法使用推送的预言机报价，改为读取 latestRoundData: {}", e),
             }
-        ]"#;
+        }
+        Ok(self.oracle.get_current_price().await?)
+    }
+
+    /// 过滤掉仍在清算冷却期内的持仓，冷却期已过的记录顺便清除
+    fn skip_positions_in_cooldown<'a>(&self, liquidatable_positions: Vec<&'a NavCalculation>) -> Vec<&'a NavCalculation> {
+        let cooldown_secs = self.config.liquidation_cooldown_secs;
+        if cooldown_secs == 0 {
+            return liquidatable_positions;
+        }
+
+        let now = unix_now();
+        liquidatable_positions.into_iter()
+            .filter(|position| {
+                let barked_at = match self.database.get_liquidation_in_flight(position.user, position.token_id) {
+                    Ok(Some(barked_at)) => barked_at,
+                    Ok(None) => return true,
+                    Err(e) => {
+                        tracing::warn!("读取清算冷却记录失败 - 用户: {:?}, TokenID: {}, 错误: {}",
+                                      position.user, position.token_id, e);
+                        return true;
+                    }
+                };
 
+                if now.saturating_sub(barked_at) < cooldown_secs {
+                    tracing::info!(
+                        "跳过冷却期内的持仓 - 用户: {:?}, TokenID: {}, {} 秒前已发出bark",
+                        position.user, position.token_id, now.saturating_sub(barked_at)
+                    );
+                    return false;
+                }
+
+                tracing::warn!(
+                    "持仓清算冷却期已过但拍卖仍未出现，允许重新清算 - 用户: {:?}, TokenID: {}",
+                    position.user, position.token_id
+                );
+                if let Err(e) = self.database.clear_liquidation_in_flight(position.user, position.token_id) {
+                    tracing::warn!("清除清算冷却记录失败: {}", e);
+                }
+                true
+            })
+            .collect()
+    }
+
+    /// 用 Multicall3 读取的链上净值复核本地清算判断（未配置 multicall_address 时原样返回）
+    ///
+    /// 只保留链上余额大于0且链上净值同样低于清算阈值的持仓，其余持仓跳过并记录警告。
+    /// 聚合读取失败时退回本地判断，不阻塞本轮清算。
+    async fn verify_on_chain<'a>(
+        &self,
+        liquidatable_positions: Vec<&'a NavCalculation>,
+        liquidation_threshold: U256,
+    ) -> Vec<&'a NavCalculation> {
+        let multicall = match &self.multicall {
+            Some(multicall) if !liquidatable_positions.is_empty() => multicall,
+            _ => return liquidatable_positions,
+        };
+
+        let keys: Vec<_> = liquidatable_positions.iter()
+            .map(|position| (position.user, position.token_id))
+            .collect();
+        let snapshot = match multicall.read(&keys).await {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                tracing::warn!("Multicall 链上复核失败，使用本地清算判断: {}", e);
+                return liquidatable_positions;
+            }
+        };
+        tracing::debug!("链上预言机价格: {:?}", snapshot.price);
+
+        liquidatable_positions.into_iter()
+            .zip(snapshot.positions)
+            .filter(|(position, on_chain)| match on_chain {
+                Some(on_chain) if !on_chain.balance.is_zero() && on_chain.net_nav < liquidation_threshold => true,
+                Some(on_chain) => {
+                    tracing::warn!(
+                        "跳过清算 - 链上状态与本地缓存不一致 - 用户: {:?}, TokenID: {}, 本地净值: {}, 链上净值: {}, 链上余额: {}, 清算阈值: {}",
+                        position.user, position.token_id, position.net_nav,
+                        on_chain.net_nav, on_chain.balance, liquidation_threshold
+                    );
+                    false
+                }
+                None => {
+                    tracing::warn!(
+                        "跳过清算 - 无法读取链上净值（持仓可能已不存在）- 用户: {:?}, TokenID: {}",
+                        position.user, position.token_id
+                    );
+                    false
+                }
+            })
+            .map(|(position, _)| position)
+            .collect()
+    }
+
+    /// 发送 bark 前用合约自己的视角核对单个持仓是否可清算
+    ///
+    /// 读取 `CustodianFixed.getSingleLeverageTokenNavV2`（bark 内部使用的同一净值）和
+    /// `LiquidationManager.checkFreezeStatus`，与本地计算不一致或读取失败时都不发送 bark，避免浪费 gas。
+    async fn cross_check_on_chain(&self, position: &NavCalculation, liquidation_threshold: U256) -> bool {
+        let view = match self.read_on_chain_view(position.user, position.token_id).await {
+            Ok(view) => view,
+            Err(e) => {
+                tracing::warn!(
+                    "跳过清算 - 读取合约净值失败，无法交叉核对 - 用户: {:?}, TokenID: {}, 错误: {}",
+                    position.user, position.token_id, e
+                );
+                return false;
+            }
+        };
+
+        match on_chain_disagreement(&view, liquidation_threshold) {
+            None => true,
+            Some(reason) => {
+                tracing::warn!(
+                    "跳过清算 - 合约视角不可清算（{}）- 用户: {:?}, TokenID: {}, 本地净值: {}, 链上净值: {}, 链上余额: {}, 清算阈值: {}",
+                    reason, position.user, position.token_id, position.net_nav,
+                    view.net_nav, view.balance, liquidation_threshold
+                );
+                false
+            }
+        }
+    }
+
+    /// 读取持仓的链上余额、除息净值和冻结状态
+    async fn read_on_chain_view(&self, user: Address, token_id: U256) -> anyhow::Result<OnChainLiquidationView> {
+        let args = [Token::Address(user), Token::Uint(token_id)];
+        let nav = self.call_view(self.custodian_address, "getSingleLeverageTokenNavV2", &args).await?;
+        let frozen = self.call_view(self.liquidation_manager_address, "checkFreezeStatus", &args).await?;
+
+        let uint_field = |index: usize, name: &str| {
+            nav.get(index).cloned()
+                .and_then(Token::into_uint)
+                .ok_or_else(|| anyhow::anyhow!("无法解析 {}", name))
+        };
+        Ok(OnChainLiquidationView {
+            balance: uint_field(0, "balance")?,
+            net_nav: uint_field(2, "netNavInWei")?,
+            frozen: frozen.into_iter().next()
+                .and_then(Token::into_bool)
+                .ok_or_else(|| anyhow::anyhow!("无法解析 isFreezed"))?,
+        })
+    }
+
+    /// 以 `eth_call` 调用只读函数并解码返回值
+    async fn call_view(&self, to: Address, function_name: &str, args: &[Token]) -> anyhow::Result<Vec<Token>> {
         let contract = get_contract()?;
-        let function = contract.function("latestRoundData")?;
-        let data = function.encode_input(&[])?;
-
-        // 执行调用
-        let result = self.web3.eth()
-            .call(
-                web3::types::CallRequest {
-                    to: Some(self.oracle_address),
-                    data: Some(web3::types::Bytes(data)),
-                    ..Default::default()
-                },
-                None,
-            )
+        let function = contract.function(function_name)?;
+        let request = CallRequest {
+            to: Some(to),
+            data: Some(Bytes(function.encode_input(args)?)),
+            ..Default::default()
+        };
+        let result = rpc_retry("eth_call", || self.web3.eth().call(request.clone(), None)).await?;
+        Ok(function.decode_output(&result.0)?)
+    }
+
+    /// 对单轮清算应用价值上限
+    ///
+    /// 待清算持仓的总价值超过上限时，只保留上限以内的持仓，发出严重告警并暂停后续清算。
+    fn apply_cycle_value_cap<'a>(
+        &self,
+        nav_results: &[NavCalculation],
+        liquidatable_positions: Vec<&'a NavCalculation>,
+    ) -> anyhow::Result<Vec<&'a NavCalculation>> {
+        let max_cycle_value_bps = self.config.liquidation_safety.max_cycle_value_bps;
+        if max_cycle_value_bps == 0 || liquidatable_positions.is_empty() {
+            return Ok(liquidatable_positions);
+        }
+
+        let total_tracked_value = nav_results.iter()
+            .fold(U256::zero(), |acc, result| acc.saturating_add(result.total_value));
+        let value_cap = total_tracked_value.saturating_mul(U256::from(max_cycle_value_bps)) / U256::from(10_000u64);
+
+        let mut capped_positions = Vec::new();
+        let mut liquidated_value = U256::zero();
+        for position in &liquidatable_positions {
+            let next_value = liquidated_value.saturating_add(position.total_value);
+            if next_value > value_cap {
+                break;
+            }
+            liquidated_value = next_value;
+            capped_positions.push(*position);
+        }
+
+        if capped_positions.len() < liquidatable_positions.len() {
+            let requested_value = liquidatable_positions.iter()
+                .fold(U256::zero(), |acc, result| acc.saturating_add(result.total_value));
+            tracing::error!(
+                "CRITICAL: 待清算价值 {} 超过单轮上限 {} (跟踪总价值 {}, 上限比例 {} 基点)，本轮只清算 {}/{} 个持仓并暂停清算，请人工确认",
+                requested_value, value_cap, total_tracked_value, max_cycle_value_bps,
+                capped_positions.len(), liquidatable_positions.len()
+            );
+            self.database.set_liquidation_paused(true)?;
+        }
+
+        Ok(capped_positions)
+    }
+
+    /// 检查清算是否有利可图（未启用收益检查时总是返回 true）
+    ///
+    /// 预期收益 = Keeper 奖励 - gas 成本。奖励由合约按奖励代币的最小单位发放，不做换算；
+    /// 只有 18 位精度的 gas 成本换算为奖励代币的最小单位。奖励代币精度或 gas 预估获取失败时同样跳过该持仓。
+    async fn is_liquidation_profitable(
+        &self,
+        position: &NavCalculation,
+        current_price: U256,
+        system_params: &SystemParams,
+    ) -> bool {
+        let profitability = &self.config.profitability;
+        if !profitability.enabled {
+            return true;
+        }
+
+        let decimals = match self.reward_token_decimals().await {
+            Ok(decimals) => decimals,
+            Err(e) => {
+                tracing::info!("跳过清算 - 用户: {:?}, TokenID: {}, 读取奖励代币精度失败: {}",
+                              position.user, position.token_id, e);
+                return false;
+            }
+        };
+        let reward = expected_keeper_reward(position.net_value, system_params);
+
+        let gas_cost_wei = match self.encode_bark(&position.user, &position.token_id).await {
+            Ok(data) => self.tx_sender.estimate_cost(self.liquidation_manager_address, &data).await,
+            Err(e) => Err(e),
+        };
+        let gas_cost_wei = match gas_cost_wei {
+            Ok(gas_cost_wei) => gas_cost_wei,
+            Err(e) => {
+                tracing::info!("跳过清算 - 用户: {:?}, TokenID: {}, gas预估失败: {}",
+                              position.user, position.token_id, e);
+                return false;
+            }
+        };
+
+        let gas_token_price = profitability.gas_token_price.map(U256::from).unwrap_or(current_price);
+        let gas_cost = gas_cost_in_token_units(gas_cost_wei, gas_token_price, decimals);
+        let min_profit = U256::from(profitability.min_profit);
+
+        if reward < gas_cost.saturating_add(min_profit) {
+            tracing::info!(
+                "跳过可清算但收益不足的持仓 - 用户: {:?}, TokenID: {}, 预期奖励: {}, gas成本: {}, 最低收益: {}",
+                position.user, position.token_id, reward, gas_cost, min_profit
+            );
+            return false;
+        }
+
+        true
+    }
+
+    /// 奖励代币精度：优先使用配置覆盖，否则读取一次 `decimals()` 并缓存
+    async fn reward_token_decimals(&self) -> anyhow::Result<u8> {
+        let decimals = self.reward_token_decimals
+            .get_or_try_init(|| self.read_reward_token_decimals())
             .await?;
+        Ok(*decimals)
+    }
 
-        // 解码结果
-        let tokens = function.decode_output(&result.0)?;
-        let price: i128 = tokens[1].clone()
-            .into_int()
-            .ok_or_else(|| anyhow::anyhow!("无法将代币转换为整数"))?
-            .try_into()
-            .map_err(|_| anyhow::anyhow!("价格转换超出i128范围"))?;
-        let price_u256 = U256::from(price.abs() as u128);
+    async fn read_reward_token_decimals(&self) -> anyhow::Result<u8> {
+        let contract = get_contract()?;
+        let function = contract.function("decimals")?;
+        let request = CallRequest {
+            to: Some(self.reward_token_address),
+            data: Some(Bytes(function.encode_input(&[])?)),
+            ..Default::default()
+        };
+        let result = rpc_retry("eth_call", || self.web3.eth().call(request.clone(), None)).await?;
 
-        Ok(price_u256)
+        let decimals = function.decode_output(&result.0)?
+            .into_iter()
+            .next()
+            .and_then(Token::into_uint)
+            .ok_or_else(|| KeeperError::Decode("无法解析奖励代币 decimals".to_string()))?;
+        if decimals > U256::from(MAX_TOKEN_DECIMALS) {
+            return Err(KeeperError::Contract(format!(
+                "奖励代币精度 {} 超出支持范围（最大 {}），请配置 profitability.reward_token_decimals", decimals, MAX_TOKEN_DECIMALS
+            )).into());
+        }
+        Ok(decimals.low_u32() as u8)
     }
 
-    /// 执行单个持仓的清算
-    async fn execute_liquidation(&self, user: &Address, token_id: &U256) -> anyhow::Result<()> {
-        // 获取Keeper地址（当前为默认地址，可根据需求修改）
-        let keeper_address = web3::types::Address::from_low_u64_be(0x123456789abcdef); // 示例地址
+    /// 编码 bark 调用数据，Keeper 地址作为奖励接收方
+    async fn encode_bark(&self, user: &Address, token_id: &U256) -> anyhow::Result<Vec<u8>> {
+        // 获取Keeper地址（由私钥推导，未配置私钥时为节点账户）
+        let keeper_address = self.tx_sender.keeper_address().await?;
 
         // 创建bark函数调用数据
         let _function_abi = r#"[
@@ -185,50 +621,253 @@ impl LiquidationMonitor {
             ethabi::Token::Address(keeper_address),
         ])?;
 
-        // 构建交易
-        let accounts = self.web3.eth().accounts().await?;
-        if accounts.is_empty() {
-            return Err(anyhow::anyhow!("No available accounts for transaction"));
+        Ok(data)
+    }
+
+    /// 执行单个持仓的清算
+    ///
+    /// 返回已确认的清算记录，模拟运行时返回 None。
+    #[tracing::instrument(name = "liquidation", skip_all, fields(user = ?user, token_id = %token_id))]
+    async fn execute_liquidation(&self, user: &Address, token_id: &U256) -> anyhow::Result<Option<LiquidationRecord>> {
+        if self.tx_sender.is_dry_run() {
+            let data = self.encode_bark(user, token_id).await?;
+            let keeper_address = self.tx_sender.keeper_address().await?;
+            self.tx_sender.log_dry_run(
+                self.liquidation_manager_address,
+                &format!("bark(user={:?}, tokenId={}, kpr={:?})", user, token_id, keeper_address),
+                &data,
+            );
+            metrics().liquidations.with_label_values(&["would_liquidate"]).inc();
+            return Ok(None);
         }
 
-        let tx = web3::types::TransactionRequest {
-            from: accounts[0],
-            to: Some(self.liquidation_manager_address),
-            data: Some(web3::types::Bytes(data)),
-            ..Default::default()
+        metrics().liquidations.with_label_values(&["attempted"]).inc();
+
+        let result = self.send_liquidation(user, token_id).await;
+        let outcome = match &result {
+            Ok(_) => "succeeded",
+            Err(e) => match e.downcast_ref::<TransactionReverted>() {
+                Some(reverted) => {
+                    self.handle_reverted_liquidation(user, token_id, reverted);
+                    "reverted"
+                }
+                None => "failed",
+            },
         };
+        metrics().liquidations.with_label_values(&[outcome]).inc();
+
+        result.map(Some)
+    }
+
+    /// 处理执行失败的 bark 交易
+    ///
+    /// revert 通常意味着持仓已被其他 Keeper 抢先清算，因此只记录警告；
+    /// 启用 `remove_position_on_revert` 时同时删除数据库中的过期持仓，避免下一轮重复发送。
+    fn handle_reverted_liquidation(&self, user: &Address, token_id: &U256, reverted: &TransactionReverted) {
+        tracing::warn!(
+            "清算交易被revert（持仓可能已被其他Keeper清算）- 用户: {:?}, TokenID: {}, {}",
+            user, token_id, reverted
+        );
+
+        // 交易已确定失败，不会再产生拍卖，无需等待冷却期
+        if let Err(e) = self.database.clear_liquidation_in_flight(*user, *token_id) {
+            tracing::warn!("清除清算冷却记录失败: {}", e);
+        }
 
-        // 发送交易
-        let tx_hash = self.web3.eth().send_transaction(tx).await?;
+        if !self.config.remove_position_on_revert {
+            return;
+        }
+        match self.database.delete_user_position(*user, *token_id) {
+            Ok(()) => tracing::info!("已删除过期持仓 - 用户: {:?}, TokenID: {}", user, token_id),
+            Err(e) => tracing::error!("删除过期持仓失败 - 用户: {:?}, TokenID: {}, 错误: {}", user, token_id, e),
+        }
+    }
+
+    /// 发送 bark 交易并检查回执
+    ///
+    /// 确认后从回执的 AuctionStarted 事件中解析 bark 产生的拍卖ID，记录持仓与拍卖的关联。
+    /// 拍卖本身仍由 events.rs 处理 AuctionStarted 事件时写入。
+    async fn send_liquidation(&self, user: &Address, token_id: &U256) -> anyhow::Result<LiquidationRecord> {
+        let data = self.encode_bark(user, token_id).await?;
+
+        // 发送前记录冷却期开始，交易确认缓慢或发送后重启时都不会重复 bark
+        self.database.set_liquidation_in_flight(*user, *token_id, unix_now())?;
+
+        // 发送交易；发送失败（如 gas 预估 revert）时交易没有广播，结束冷却期
+        let tx_hash = match self.tx_sender.send(self.liquidation_manager_address, data).await {
+            Ok(tx_hash) => tx_hash,
+            Err(e) => {
+                if let Err(clear_error) = self.database.clear_liquidation_in_flight(*user, *token_id) {
+                    tracing::warn!("清除清算冷却记录失败: {}", clear_error);
+                }
+                return Err(e);
+            }
+        };
         tracing::info!("清算交易已发送: {:?}, 稍后events.rs会自动记录auction信息", tx_hash);
 
+        let receipt = self.tx_sender.wait_for_confirmations(tx_hash).await?;
+
+        let auction_id = find_started_auction_id(&receipt.logs, self.auction_manager_address, *token_id);
+        match auction_id {
+            Some(auction_id) => tracing::info!(
+                "清算已产生拍卖 - 用户: {:?}, TokenID: {}, 拍卖ID: {}", user, token_id, auction_id
+            ),
+            None => tracing::warn!(
+                "清算交易 {:?} 已确认但回执中没有 AuctionStarted 事件 - 用户: {:?}, TokenID: {}",
+                tx_hash, user, token_id
+            ),
+        }
 
-        // 等待交易确认 - auctionId会由events.rs中的AuctionStarted事件处理
-        let receipt = self.web3.eth().transaction_receipt(tx_hash).await?;
-        match receipt {
-            Some(_) => Ok(()),
-            None => Err(anyhow::anyhow!("交易未确认")),
+        let record = LiquidationRecord {
+            user: *user,
+            token_id: *token_id,
+            auction_id,
+            tx_hash,
+            block_number: receipt.block_number.unwrap_or_default().as_u64(),
+            liquidated_at: unix_now(),
+        };
+        if let Err(e) = self.database.store_liquidation_record(&record) {
+            tracing::error!("存储清算记录失败: {}", e);
         }
+
+        Ok(record)
+    }
+}
+
+/// 清算优先级评分的归一化精度（基点）
+const PRIORITY_SCALE: u64 = 10_000;
+
+/// 按清算优先级评分从高到低排序，评分相同时总价值大的优先
+///
+/// 评分 = reward_weight × 预期奖励 / 最大预期奖励 + risk_weight × 净值缺口 / 最大净值缺口（均以基点计），
+/// 奖励和净值缺口单位不同，按本轮候选持仓中的最大值归一化后再加权。
+fn prioritize_liquidations<'a>(
+    positions: Vec<&'a NavCalculation>,
+    liquidation_threshold: U256,
+    system_params: &SystemParams,
+    priority: &LiquidationPriorityConfig,
+) -> Vec<&'a NavCalculation> {
+    let factors: Vec<_> = positions.iter()
+        .map(|position| (
+            expected_keeper_reward(position.net_value, system_params),
+            liquidation_threshold.saturating_sub(position.net_nav),
+        ))
+        .collect();
+    let max_reward = factors.iter().map(|(reward, _)| *reward).max().unwrap_or_default();
+    let max_gap = factors.iter().map(|(_, gap)| *gap).max().unwrap_or_default();
+    let normalize = |value: U256, max: U256| {
+        if max.is_zero() {
+            U256::zero()
+        } else {
+            value.saturating_mul(U256::from(PRIORITY_SCALE)) / max
+        }
+    };
+
+    let mut scored: Vec<_> = positions.into_iter()
+        .zip(factors)
+        .map(|(position, (reward, gap))| {
+            let score = normalize(reward, max_reward) * U256::from(priority.reward_weight)
+                + normalize(gap, max_gap) * U256::from(priority.risk_weight);
+            (score, position)
+        })
+        .collect();
+    scored.sort_by(|(score_a, a), (score_b, b)| {
+        score_b.cmp(score_a).then_with(|| b.total_value.cmp(&a.total_value))
+    });
+    scored.into_iter().map(|(_, position)| position).collect()
+}
+
+/// 等待下一次预言机价格推送，并在防抖时间内合并后续推送，返回最新的一次
+///
+/// 未启用推送或推送任务已结束时永远等待，清算检查只按定时间隔执行。
+async fn next_price_update(
+    price_updates: Option<&mut watch::Receiver<Option<PriceUpdate>>>,
+    debounce: std::time::Duration,
+) -> PriceUpdate {
+    let Some(price_updates) = price_updates else {
+        return std::future::pending().await;
+    };
+    loop {
+        if price_updates.changed().await.is_err() {
+            return std::future::pending().await;
+        }
+        tokio::time::sleep(debounce).await;
+        if let Some(update) = price_updates.borrow_and_update().clone() {
+            return update;
+        }
+    }
+}
+
+/// 清算检查失败是否由 RPC 错误引起（节点不可达、超时、限流等）
+fn is_rpc_failure(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause.downcast_ref::<web3::Error>().is_some()
+            || matches!(cause.downcast_ref::<KeeperError>(), Some(KeeperError::Rpc(_)))
+    })
+}
+
+/// 按 AuctionManager 的奖励公式计算预期的 Keeper 奖励
+///
+/// 拍卖价值不低于 `min_auction_amount` 时：`fixed_reward + (value - min_auction_amount) * percentage_reward / 1e18`，
+/// 否则没有奖励。
+fn expected_keeper_reward(value: U256, system_params: &SystemParams) -> U256 {
+    if value < system_params.min_auction_amount {
+        return U256::zero();
+    }
+
+    let percentage_part = (value - system_params.min_auction_amount)
+        .saturating_mul(system_params.percentage_reward)
+        / U256::exp10(18);
+    system_params.fixed_reward.saturating_add(percentage_part)
+}
+
+/// gas 成本换算为奖励代币的最小单位：gas_cost * gas代币价格 / 1e18，再按代币精度换算
+fn gas_cost_in_token_units(gas_cost_wei: U256, gas_token_price: U256, decimals: u8) -> U256 {
+    wad_to_token_units(gas_cost_wei.saturating_mul(gas_token_price) / U256::exp10(18), decimals)
+}
+
+/// 把 18 位精度的金额换算为 `decimals` 位精度代币的最小单位（精度低于 18 位时向下取整）
+fn wad_to_token_units(amount: U256, decimals: u8) -> U256 {
+    match decimals.cmp(&18) {
+        std::cmp::Ordering::Less => amount / U256::exp10(usize::from(18 - decimals)),
+        std::cmp::Ordering::Equal => amount,
+        std::cmp::Ordering::Greater => amount.saturating_mul(U256::exp10(usize::from(decimals - 18))),
+    }
+}
+
+/// 从 bark 交易回执的日志中找到对应 token 的 AuctionStarted 事件，返回拍卖ID
+///
+/// 按 `event_abi` 中的 AuctionStarted 定义解码 auctionId 和 tokenId。
+fn find_started_auction_id(logs: &[web3::types::Log], auction_manager: Address, token_id: U256) -> Option<U256> {
+    let auction_started = crate::event_abi::signature("AuctionStarted").ok()?;
+
+    logs.iter()
+        .filter(|log| log.address == auction_manager && log.topics.first() == Some(&auction_started))
+        .filter_map(|log| crate::event_abi::decode_log("AuctionStarted", log).ok())
+        .find(|event| event.uint("tokenId").is_ok_and(|id| id == token_id))
+        .and_then(|event| event.uint("auctionId").ok())
+}
+
+/// 合约视角下持仓不可清算的原因（与 bark 中的 require 条件一致），可清算时返回 None
+fn on_chain_disagreement(view: &OnChainLiquidationView, liquidation_threshold: U256) -> Option<&'static str> {
+    if view.balance.is_zero() {
+        Some("链上余额为0")
+    } else if view.frozen {
+        Some("持仓已冻结")
+    } else if view.net_nav.is_zero() {
+        Some("链上净值为0，bark 会 revert")
+    } else if view.net_nav >= liquidation_threshold {
+        Some("链上净值不低于清算阈值")
+    } else {
+        None
     }
 }
 
 /// 获取LiquidationManager合约的ABI
 fn get_contract() -> anyhow::Result<ethabi::Contract> {
-    // LiquidationManager的基本ABI，包含bark函数和latestRoundData
+    // LiquidationManager的基本ABI，包含bark函数；getSingleLeverageTokenNavV2 属于 CustodianFixed，用于清算前交叉核对；
+    // decimals 属于奖励代币，用于收益检查
     let abi = r#"[
-        {
-            "name": "latestRoundData",
-            "type": "function",
-            "stateMutability": "view",
-            "inputs": [],
-            "outputs": [
-                {"type": "uint80"},
-                {"type": "int256"},
-                {"type": "uint256"},
-                {"type": "uint256"},
-                {"type": "uint80"}
-            ]
-        },
         {
             "name": "bark",
             "type": "function",
@@ -238,10 +877,194 @@ fn get_contract() -> anyhow::Result<ethabi::Contract> {
                 {"type": "uint256", "name": "tokenId"},
                 {"type": "address", "name": "kpr"}
                 ],
-            "outputs": [{"type": "uint256"}]
+            "outputs": [{"type": "uint256", "name": ""}]
+        },
+        {
+            "name": "checkFreezeStatus",
+            "type": "function",
+            "stateMutability": "view",
+            "inputs": [
+                {"type": "address", "name": "user"},
+                {"type": "uint256", "name": "tokenId"}
+            ],
+            "outputs": [{"type": "bool", "name": "isFreezed"}]
+        },
+        {
+            "name": "getSingleLeverageTokenNavV2",
+            "type": "function",
+            "stateMutability": "view",
+            "inputs": [
+                {"type": "address", "name": "user"},
+                {"type": "uint256", "name": "tokenId"}
+            ],
+            "outputs": [
+                {"type": "uint256", "name": "balance"},
+                {"type": "uint256", "name": "grossNavInWei"},
+                {"type": "uint256", "name": "netNavInWei"},
+                {"type": "uint256", "name": "totalValueInWei"},
+                {"type": "uint256", "name": "totalNetValueInWei"},
+                {"type": "uint256", "name": "accruedInterestInWei"},
+                {"type": "uint256", "name": "currentPriceInWei"},
+                {"type": "uint256", "name": "priceTimestamp"}
+            ]
+        },
+        {
+            "name": "decimals",
+            "type": "function",
+            "stateMutability": "view",
+            "inputs": [],
+            "outputs": [{"type": "uint8", "name": ""}]
         }
     ]"#;
 
     let contract: ethabi::Contract = serde_json::from_str(abi)?;
     Ok(contract)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use web3::types::H256;
+    use crate::database::LeverageType;
+
+    fn wad(value: u64) -> U256 {
+        U256::from(value) * U256::exp10(18)
+    }
+
+    #[test]
+    fn keeper_reward_follows_auction_manager_formula() {
+        let system_params = SystemParams {
+            fixed_reward: wad(1),
+            percentage_reward: U256::exp10(16), // 1%
+            min_auction_amount: wad(100),
+            ..Default::default()
+        };
+
+        // 低于最小拍卖金额时没有奖励
+        assert_eq!(expected_keeper_reward(wad(99), &system_params), U256::zero());
+        // 恰好等于最小拍卖金额时只有固定奖励
+        assert_eq!(expected_keeper_reward(wad(100), &system_params), wad(1));
+        // 1 + (1100 - 100) * 1% = 11
+        assert_eq!(expected_keeper_reward(wad(1100), &system_params), wad(11));
+    }
+
+    #[test]
+    fn wad_amounts_convert_to_token_units() {
+        // 6 位精度（如 USDC）：1.5 -> 1_500_000，不足最小单位的部分舍去
+        assert_eq!(wad_to_token_units(U256::from(15) * U256::exp10(17), 6), U256::from(1_500_000));
+        assert_eq!(wad_to_token_units(U256::from(999_999_999_999u64), 6), U256::zero());
+        assert_eq!(wad_to_token_units(wad(3), 18), wad(3));
+        assert_eq!(wad_to_token_units(wad(3), 24), wad(3) * U256::exp10(6));
+
+        // 0.001 gas 代币 × 价格 2000 = 2 个奖励代币；6 位精度下为 2_000_000，与合约发放的原始单位奖励直接比较
+        assert_eq!(gas_cost_in_token_units(U256::exp10(15), wad(2_000), 6), U256::from(2_000_000));
+        assert_eq!(gas_cost_in_token_units(U256::exp10(15), wad(2_000), 18), wad(2));
+    }
+
+    #[test]
+    fn liquidation_priority_follows_configured_weights() {
+        let position = |token_id: u64, net_nav: U256, net_value: U256| NavCalculation {
+            user: Address::repeat_byte(0x11),
+            token_id: U256::from(token_id),
+            leverage: LeverageType::Moderate,
+            gross_nav: net_nav,
+            net_nav,
+            position_amount: wad(1),
+            total_value: net_value,
+            net_value,
+            accrued_interest: U256::zero(),
+            health_factor: U256::MAX,
+        };
+        let system_params = SystemParams {
+            fixed_reward: wad(1),
+            percentage_reward: U256::exp10(16), // 1%
+            min_auction_amount: wad(100),
+            ..Default::default()
+        };
+        let threshold = wad(1) / 2;
+        let results = [
+            position(1, wad(4) / 10, wad(10_000)),
+            position(2, wad(1) / 10, wad(500)),
+            position(3, wad(3) / 10, wad(2_000)),
+            position(4, wad(4) / 10, wad(50)),
+        ];
+        let order = |reward_weight, risk_weight| -> Vec<u64> {
+            let priority = LiquidationPriorityConfig { reward_weight, risk_weight };
+            prioritize_liquidations(results.iter().collect(), threshold, &system_params, &priority)
+                .iter()
+                .map(|position| position.token_id.low_u64())
+                .collect()
+        };
+
+        // 只看风险：净值缺口越大越优先，缺口相同时总价值大的优先
+        assert_eq!(order(0, 1), vec![2, 3, 1, 4]);
+        // 只看收益：预期奖励越高越优先
+        assert_eq!(order(1, 0), vec![1, 3, 2, 4]);
+        // 默认等权：1 = 100% + 25%，2 = 5% + 100%，3 = 20% + 50%，4 = 0% + 25%
+        assert_eq!(order(1, 1), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn only_rpc_errors_count_as_rpc_failures() {
+        let rpc_error = anyhow::Error::from(web3::Error::Unreachable).context("获取预言机价格失败");
+        assert!(is_rpc_failure(&rpc_error));
+        assert!(is_rpc_failure(&anyhow::Error::from(KeeperError::Rpc(web3::Error::Unreachable))));
+        assert!(!is_rpc_failure(&anyhow::Error::from(KeeperError::Database("写入失败".to_string()))));
+        assert!(!is_rpc_failure(&anyhow::anyhow!("Invalid mint price")));
+    }
+
+    #[test]
+    fn liquidation_manager_abi_encodes_bark() {
+        let contract = get_contract().unwrap();
+        let data = contract.function("bark").unwrap()
+            .encode_input(&[Token::Address(Address::repeat_byte(0x11)), Token::Uint(U256::from(3)), Token::Address(Address::repeat_byte(0x22))])
+            .unwrap();
+        assert_eq!(&data[..4], &web3::signing::keccak256(b"bark(address,uint256,address)")[..4]);
+    }
+
+    #[test]
+    fn cross_check_requires_contract_to_agree() {
+        let threshold = wad(1) / 2;
+        let view = OnChainLiquidationView { balance: wad(10), net_nav: wad(1) / 4, frozen: false };
+        assert_eq!(on_chain_disagreement(&view, threshold), None);
+
+        assert!(on_chain_disagreement(&OnChainLiquidationView { net_nav: threshold, ..view.clone() }, threshold).is_some());
+        assert!(on_chain_disagreement(&OnChainLiquidationView { net_nav: U256::zero(), ..view.clone() }, threshold).is_some());
+        assert!(on_chain_disagreement(&OnChainLiquidationView { frozen: true, ..view.clone() }, threshold).is_some());
+        assert!(on_chain_disagreement(&OnChainLiquidationView { balance: U256::zero(), ..view }, threshold).is_some());
+    }
+
+    #[test]
+    fn auction_id_is_found_in_bark_receipt_logs() {
+        let auction_manager = Address::repeat_byte(0xaa);
+        let topic0 = crate::event_abi::signature("AuctionStarted").unwrap();
+        let u256_topic = |value: u64| {
+            let mut bytes = [0u8; 32];
+            U256::from(value).to_big_endian(&mut bytes);
+            H256::from(bytes)
+        };
+        let log = |address: Address, auction_id: u64, token_id: u64| web3::types::Log {
+            address,
+            topics: vec![topic0, u256_topic(auction_id), u256_topic(token_id), H256::zero()],
+            // startingPrice, underlyinglAmount, originalOwner, rewardAmount
+            data: web3::types::Bytes(vec![0u8; 4 * 32]),
+            block_hash: None,
+            block_number: None,
+            transaction_hash: None,
+            transaction_index: None,
+            log_index: None,
+            transaction_log_index: None,
+            log_type: None,
+            removed: None,
+        };
+
+        let logs = vec![
+            log(Address::repeat_byte(0xbb), 1, 7), // 其他合约的同名事件
+            log(auction_manager, 2, 8),            // 其他 token 的拍卖
+            log(auction_manager, 3, 7),
+        ];
+
+        assert_eq!(find_started_auction_id(&logs, auction_manager, U256::from(7u64)), Some(U256::from(3u64)));
+        assert_eq!(find_started_auction_id(&logs, auction_manager, U256::from(9u64)), None);
+    }
+}
@@ -2,107 +2,330 @@
 //!
 //! 这个机器人用于监控杠杆代币系统的清算事件和拍卖。
 
+#[cfg(all(test, feature = "anvil-tests"))]
+mod anvil_tests;
+mod api;
+mod audit;
+mod cli;
 mod config;
 mod database;
+mod deployment;
+mod error;
+mod event_abi;
 mod events;
+mod failover;
 mod liquidation;
+mod metrics;
+mod multicall;
 mod nav;
+mod notify;
+mod oracle;
 mod reset;
+mod resync;
+mod rpc;
+mod status;
+mod tx;
+mod verify;
 
 use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+/// 收到关闭信号后等待各监控任务完成当前工作的额外时间（在交易确认超时之外）
+///
+/// 清算和拍卖重置在处理流程中等待交易确认，关闭时要等已发送的交易确认并记录结果，
+/// 总等待时间为 `confirmation_timeout_secs` 加上该值。
+const SHUTDOWN_GRACE_PERIOD_SECS: u64 = 30;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    // 解析命令行参数
+    let command = cli::parse_args(std::env::args().skip(1))?;
+
+    // 加载配置（日志格式由配置决定，因此先于日志初始化）
+    let config = config::load_config()?;
+
     // 初始化日志
-    tracing_subscriber::fmt()
-        .with_level(true)
-        .with_thread_ids(true)
-        .with_target(false)
-        .compact()
-        .init();
+    init_logging(config.log_format);
 
     tracing::info!("启动 Rust Liquidation Keeper...");
-
-    // 加载配置
-    let config = config::load_config()?;
     tracing::info!("配置加载成功");
+    rpc::configure(&config);
 
-    // 初始化数据库
-    let database = Arc::new(database::Database::new().await?);
+    // 只读命令以 secondary 实例打开数据库：不获取 LOCK、不执行迁移，Keeper 运行期间也可以执行
+    match &command {
+        cli::Command::Verify { from_block } => {
+            let database = database::Database::open_secondary(&config.db_path, &config.database)?;
+            return cli::verify(&config, &database, *from_block).await;
+        }
+        cli::Command::Export { out } => {
+            let database = database::Database::open_secondary(&config.db_path, &config.database)?;
+            return cli::export_state(&database, out);
+        }
+        cli::Command::ExportRawLogs { from_block, to_block, out } => {
+            let database = database::Database::open_secondary(&config.db_path, &config.database)?;
+            return cli::export_raw_logs(&database, *from_block, *to_block, out.as_deref());
+        }
+        _ => {}
+    }
+
+    // 初始化数据库；其余运维命令会写入数据库，需要先停止 Keeper（运行中的 Keeper 持有数据库锁）
+    let database = database::Database::new(&config.db_path, &config.database).await.map_err(|e| {
+        if command == cli::Command::Run {
+            anyhow::Error::from(e)
+        } else {
+            anyhow::anyhow!("打开数据库失败，写入数据库的运维命令需要先停止 Keeper: {}", e)
+        }
+    })?;
+    let database = Arc::new(database);
     tracing::info!("数据库初始化成功");
 
-    // 创建Web3客户端
-    let web3 = web3::Web3::new(
-        web3::transports::Http::new(&config.rpc_url)?
-    );
+    // 一次性运维命令：执行完即退出，不启动监控任务
+    match &command {
+        cli::Command::Run => {}
+        cli::Command::ResumeLiquidations => {
+            return cli::resume_liquidations(&database);
+        }
+        cli::Command::Backfill { from_block, to_block, force } => {
+            deployment::ensure_deployment_matches(&database, &config.contracts)?;
+            return cli::backfill(&config, database.clone(), *from_block, *to_block, *force).await;
+        }
+        cli::Command::Audit { tolerance, fix } => {
+            return cli::audit(&config, &database, *tolerance, *fix).await;
+        }
+        cli::Command::Import { input } => {
+            return cli::import_state(&database, input);
+        }
+        cli::Command::Verify { .. } | cli::Command::Export { .. } | cli::Command::ExportRawLogs { .. } => {
+            unreachable!("只读命令在打开数据库之前处理")
+        }
+        cli::Command::MigrateContracts { archive, from_block } => {
+            return cli::migrate_contracts(&config, &database, archive, *from_block);
+        }
+        cli::Command::ResyncAuction { auction_id } => {
+            deployment::ensure_deployment_matches(&database, &config.contracts)?;
+            return cli::resync_auction(&config, &database, *auction_id).await;
+        }
+        cli::Command::ResyncPosition { user, token_id } => {
+            deployment::ensure_deployment_matches(&database, &config.contracts)?;
+            return cli::resync_position(&config, &database, *user, *token_id).await;
+        }
+    }
+
+    // 合约重新部署后拒绝启动，避免新旧部署的状态混用
+    deployment::ensure_deployment_matches(&database, &config.contracts)?;
+
+    // 创建Web3客户端（配置了备用节点时自动故障切换）
+    let rpc_transport = failover::FailoverTransport::from_config(&config)?;
+    let web3 = web3::Web3::new(rpc_transport.clone());
     tracing::info!("Web3客户端初始化成功");
 
+    // 共享运行状态，供 /health 接口使用
+    let status = Arc::new(status::KeeperStatus::new());
+
+    // 关闭信号：各监控任务在安全点检查，收到后完成当前工作再退出
+    let shutdown = CancellationToken::new();
+
+    // 使用备用节点期间定期检查主节点是否恢复
+    tokio::spawn(rpc_transport.run_primary_health_check(config.rpc_primary_check_interval_secs, shutdown.clone()));
+
+    // 创建交易发送器（清算与拍卖重置共用）
+    let tx_sender = Arc::new(tx::TransactionSender::new(
+        web3.clone(),
+        &config,
+    )?);
+
     // 创建导航监控器对象供清算监控器使用
     let nav_for_liquidation = nav::NavMonitor::new(
         web3.clone(),
         database.clone(),
+        &config,
     )?;
 
+    // 运维通知（未配置通知目标时不发送）
+    let notifier = notify::from_config(&config.notifier)?;
+
     // 创建清算监控器
     let mut liquidation_monitor = liquidation::LiquidationMonitor::new(
         web3.clone(),
         nav_for_liquidation,
         database.clone(),
+        tx_sender.clone(),
         config.clone(),
-        config.contracts.oracle.clone(),
         config.contracts.liquidation_manager.clone(),
-        config.contracts.auction_manager.clone(),
-    )?;
+        status.clone(),
+    )?
+    .with_notifier(notifier.clone());
+
+    // 预言机价格推送：收到价格更新时立即触发清算检查
+    if config.oracle.price_subscription {
+        if let Some(ws_url) = config.ws_url.clone() {
+            let (price_sender, price_updates) = tokio::sync::watch::channel(None);
+            tokio::spawn(oracle::run_price_subscription(
+                ws_url,
+                oracle::oracle_addresses(&config)?,
+                config.event_monitoring.ws_reconnect_base_delay_secs,
+                price_sender,
+                shutdown.clone(),
+            ));
+            liquidation_monitor = liquidation_monitor.with_price_updates(price_updates);
+        }
+    }
 
     // 创建独立的NAV监控器用于单独运行
     let mut nav_monitor = nav::NavMonitor::new(
         web3.clone(),
         database.clone(),
+        &config,
     )?;
 
     let mut event_monitor = events::EventMonitor::new(
         web3.clone(),
         database.clone(),
+        tx_sender.clone(),
         config.clone(),
+        shutdown.clone(),
+        status.clone(),
+        notifier,
     ).await?;
 
+    // 创建HTTP API服务器（可选）
+    let api_server = if config.api.enabled {
+        let nav_for_api = nav::NavMonitor::new(
+            web3.clone(),
+            database.clone(),
+            &config,
+        )?;
+        Some(api::ApiServer::new(
+            &config.api,
+            database.clone(),
+            nav_for_api,
+            nav_monitor.tvl_cache(),
+            web3.clone(),
+            status.clone(),
+        )?)
+    } else {
+        None
+    };
+
     tracing::info!("所有监控器初始化完成，准备启动...");
 
     // 启动所有监控任务
-    let liquidation_handle = tokio::spawn(async move {
-        if let Err(e) = liquidation_monitor.run().await {
+    let liquidation_shutdown = shutdown.clone();
+    let mut liquidation_handle = tokio::spawn(async move {
+        if let Err(e) = liquidation_monitor.run(liquidation_shutdown).await {
             tracing::error!("清算监控器错误: {}", e);
         }
     });
 
-    let nav_handle = tokio::spawn(async move {
-        if let Err(e) = nav_monitor.run().await {
+    let nav_oracle = oracle::PriceOracle::new(web3.clone(), &config, status.clone())?;
+    let nav_recalc_interval = config.nav_recalc_interval;
+    let nav_shutdown = shutdown.clone();
+    let mut nav_handle = tokio::spawn(async move {
+        if let Err(e) = nav_monitor.run(nav_oracle, nav_recalc_interval, nav_shutdown).await {
             tracing::error!("NAV监控器错误: {}", e);
         }
     });
 
-    let events_handle = tokio::spawn(async move {
+    // 定期检查遗漏了重置任务的拍卖（重启或事件遗漏导致）
+    let auction_reset_monitor = event_monitor.auction_reset_monitor();
+    let sweep_handle = tokio::spawn(auction_reset_monitor.clone().run_orphan_sweep(config.auction_reset_sweep_interval_secs));
+
+    let mut events_handle = tokio::spawn(async move {
         if let Err(e) = event_monitor.run().await {
             tracing::error!("事件监控器错误: {}", e);
         }
     });
 
+    let metrics_config = config.metrics.clone();
+    let mut metrics_handle = tokio::spawn(async move {
+        if !metrics_config.enabled {
+            return std::future::pending().await;
+        }
+        if let Err(e) = metrics::serve(&metrics_config).await {
+            tracing::error!("指标服务错误: {}", e);
+        }
+    });
+
+    let mut api_handle = tokio::spawn(async move {
+        match api_server {
+            Some(api_server) => {
+                if let Err(e) = api_server.run().await {
+                    tracing::error!("HTTP API错误: {}", e);
+                }
+            }
+            None => std::future::pending().await,
+        }
+    });
+
     // 等待所有任务完成或者接收到关闭信号
     tokio::select! {
         _ = tokio::signal::ctrl_c() => {
             tracing::info!("收到关闭信号，正在停止...");
         }
-        _ = liquidation_handle => {
+        _ = &mut liquidation_handle => {
             tracing::info!("清算监控任务已结束");
         }
-        _ = nav_handle => {
+        _ = &mut nav_handle => {
             tracing::info!("NAV监控任务已结束");
         }
-        _ = events_handle => {
+        _ = &mut events_handle => {
             tracing::info!("事件监控任务已结束");
         }
+        _ = &mut api_handle => {
+            tracing::info!("HTTP API任务已结束");
+        }
+        _ = &mut metrics_handle => {
+            tracing::info!("指标服务任务已结束");
+        }
+    }
+
+    // 通知各监控任务停止，并在限定时间内等待它们完成当前工作
+    shutdown.cancel();
+    api_handle.abort();
+    metrics_handle.abort();
+
+    // 已结束（包括上面 select! 中已等待过）的任务不能再次等待
+    let drain = async {
+        for handle in [liquidation_handle, nav_handle, events_handle, sweep_handle] {
+            if !handle.is_finished() {
+                let _ = handle.await;
+            }
+        }
+        // 事件监控结束后不会再启动新的重置任务
+        auction_reset_monitor.wait_for_tasks().await;
+    };
+    let grace_period_secs = config.confirmation_timeout_secs.saturating_add(SHUTDOWN_GRACE_PERIOD_SECS);
+    tracing::info!("等待监控任务完成当前工作（包括确认已发送的交易），最长 {} 秒", grace_period_secs);
+    match tokio::time::timeout(std::time::Duration::from_secs(grace_period_secs), drain).await {
+        Ok(()) => tracing::info!("所有监控任务已完成当前工作"),
+        Err(_) => tracing::warn!("等待监控任务结束超时（{}秒），强制退出", grace_period_secs),
+    }
+
+    // 监控任务和拍卖重置任务都已结束时不再有写入，刷写数据库保证同步进度和最近的状态落盘；
+    // 等待超时时仍在运行的任务可能在刷写之后写入，这部分写入只在 WAL 中
+    if let Err(e) = database.flush() {
+        tracing::error!("关闭前刷写数据库失败: {}", e);
     }
 
     tracing::info!("Keeper 已停止");
     Ok(())
 }
+
+/// 按配置的格式初始化日志
+///
+/// json 格式下每条日志附带当前 span 链上的字段，日志采集系统可直接按 block_number、auction_id 等字段索引。
+fn init_logging(format: config::LogFormat) {
+    let builder = tracing_subscriber::fmt()
+        .with_level(true)
+        .with_thread_ids(true)
+        .with_target(false);
+
+    match format {
+        config::LogFormat::Compact => builder.compact().init(),
+        config::LogFormat::Json => builder
+            .json()
+            .with_current_span(false)
+            .with_span_list(true)
+            .init(),
+    }
+}
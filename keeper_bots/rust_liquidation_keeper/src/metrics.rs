@@ -0,0 +1,149 @@
+//! 监控指标模块
+//!
+//! 以 Prometheus 文本格式导出 Keeper 运行指标，供 Grafana 告警使用。
+//!
+//! ## 指标列表：
+//! - `keeper_events_processed_total{event}` 按事件类型统计的已处理事件数
+//! - `keeper_liquidations_total{result}` 清算尝试/成功/失败/revert 次数，演练模式下记为 `would_liquidate`
+//! - `keeper_auctions_total{action}` 拍卖重置任务的计划/执行次数，演练模式下记为 `would_reset`，遗漏了重置任务的拍卖记为 `orphaned`
+//! - `keeper_processed_events_cache_size` 事件去重缓存大小
+//! - `keeper_last_synced_block` 最后同步的区块号
+//! - `keeper_block_gaps_total` / `keeper_reorgs_total` 实时模式检测到的区块缺口和链重组次数
+//! - `keeper_rpc_duration_seconds{method}` RPC 调用耗时分布
+
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::OnceLock;
+use hyper::{Body, Response, Server, StatusCode};
+use hyper::service::{make_service_fn, service_fn};
+use prometheus::{Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts, Registry, TextEncoder};
+
+/// Keeper 运行指标
+pub struct Metrics {
+    registry: Registry,
+    pub events_processed: IntCounterVec,
+    pub liquidations: IntCounterVec,
+    pub auctions: IntCounterVec,
+    pub processed_events_cache_size: IntGauge,
+    pub last_synced_block: IntGauge,
+    pub block_gaps: IntCounter,
+    pub reorgs: IntCounter,
+    pub rpc_duration: HistogramVec,
+}
+
+impl Metrics {
+    fn new() -> anyhow::Result<Self> {
+        let registry = Registry::new();
+
+        let events_processed = IntCounterVec::new(
+            Opts::new("keeper_events_processed_total", "已处理的链上事件数量"),
+            &["event"],
+        )?;
+        let liquidations = IntCounterVec::new(
+            Opts::new("keeper_liquidations_total", "清算交易数量（attempted/succeeded/failed/reverted/would_liquidate）"),
+            &["result"],
+        )?;
+        let auctions = IntCounterVec::new(
+            Opts::new("keeper_auctions_total", "拍卖重置任务数量（scheduled/reset/would_reset/orphaned）"),
+            &["action"],
+        )?;
+        let processed_events_cache_size = IntGauge::new(
+            "keeper_processed_events_cache_size",
+            "事件去重缓存中的事件数量",
+        )?;
+        let last_synced_block = IntGauge::new("keeper_last_synced_block", "最后同步的区块号")?;
+        let block_gaps = IntCounter::new("keeper_block_gaps_total", "实时模式检测到的区块缺口次数")?;
+        let reorgs = IntCounter::new("keeper_reorgs_total", "实时模式检测到的链重组次数")?;
+        let rpc_duration = HistogramVec::new(
+            HistogramOpts::new("keeper_rpc_duration_seconds", "RPC 调用耗时（秒）"),
+            &["method"],
+        )?;
+
+        registry.register(Box::new(events_processed.clone()))?;
+        registry.register(Box::new(liquidations.clone()))?;
+        registry.register(Box::new(auctions.clone()))?;
+        registry.register(Box::new(processed_events_cache_size.clone()))?;
+        registry.register(Box::new(last_synced_block.clone()))?;
+        registry.register(Box::new(block_gaps.clone()))?;
+        registry.register(Box::new(reorgs.clone()))?;
+        registry.register(Box::new(rpc_duration.clone()))?;
+
+        Ok(Self {
+            registry,
+            events_processed,
+            liquidations,
+            auctions,
+            processed_events_cache_size,
+            last_synced_block,
+            block_gaps,
+            reorgs,
+            rpc_duration,
+        })
+    }
+
+    /// 以 Prometheus 文本格式编码所有指标
+    pub fn encode(&self) -> anyhow::Result<Vec<u8>> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(buffer)
+    }
+}
+
+static METRICS: OnceLock<Metrics> = OnceLock::new();
+
+/// 获取全局指标实例
+///
+/// 指标在各模块的热点路径上直接递增，无需在构造函数之间传递。
+pub fn metrics() -> &'static Metrics {
+    METRICS.get_or_init(|| Metrics::new().expect("指标注册失败"))
+}
+
+/// 记录一次 RPC 调用的耗时
+pub async fn observe_rpc<F: std::future::Future>(method: &str, future: F) -> F::Output {
+    let start = std::time::Instant::now();
+    let output = future.await;
+    metrics().rpc_duration
+        .with_label_values(&[method])
+        .observe(start.elapsed().as_secs_f64());
+    output
+}
+
+/// 启动 `/metrics` HTTP 服务
+pub async fn serve(config: &crate::config::MetricsConfig) -> anyhow::Result<()> {
+    let bind_address = config.bind_address.parse::<SocketAddr>()
+        .map_err(|e| anyhow::anyhow!("无效的指标监听地址 '{}': {}", config.bind_address, e))?;
+
+    let make_service = make_service_fn(|_conn| async {
+        Ok::<_, Infallible>(service_fn(|request: hyper::Request<Body>| async move {
+            let response = if request.uri().path() != "/metrics" {
+                Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .body(Body::empty())
+                    .unwrap_or_default()
+            } else {
+                match metrics().encode() {
+                    Ok(body) => Response::builder()
+                        .status(StatusCode::OK)
+                        .header("Content-Type", TextEncoder::new().format_type())
+                        .body(Body::from(body))
+                        .unwrap_or_default(),
+                    Err(e) => {
+                        tracing::error!("指标编码失败: {}", e);
+                        Response::builder()
+                            .status(StatusCode::INTERNAL_SERVER_ERROR)
+                            .body(Body::empty())
+                            .unwrap_or_default()
+                    }
+                }
+            };
+            Ok::<_, Infallible>(response)
+        }))
+    });
+
+    tracing::info!("指标服务启动，监听 {}/metrics", bind_address);
+    Server::try_bind(&bind_address)?
+        .serve(make_service)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,267 @@
+//! Multicall 模块
+//!
+//! 通过 Multicall3 的 `aggregate3` 在一次 `eth_call` 中读取预言机价格和一批持仓的链上净值，
+//! 供清算前复核本地缓存计算出的清算判断。
+//!
+//! ## 读取内容：
+//! - 预言机 `latestRoundData`（contracts.oracle）
+//! - 每个持仓的 `CustodianFixed.getSingleLeverageTokenNavV2(user, tokenId)`
+//!
+//! 每个子调用都允许单独失败（如持仓余额为0时合约会 revert），失败的子调用结果为 None。
+
+use web3::types::{Address, Bytes, CallRequest, U256};
+use web3::ethabi::{self, Token};
+use crate::error::{KeeperError, KeeperResult};
+use crate::oracle::{decode_latest_round_data, latest_round_data_calldata};
+use crate::failover::FailoverTransport;
+use crate::rpc::rpc_retry;
+
+/// 单次 aggregate3 调用包含的最大持仓数，避免超出节点的 eth_call gas 上限
+const MULTICALL_BATCH_SIZE: usize = 100;
+
+/// 单个持仓的链上读数
+#[derive(Debug, Clone, PartialEq)]
+pub struct OnChainPosition {
+    pub balance: U256,
+    pub net_nav: U256,
+}
+
+/// 一次聚合读取的结果
+#[derive(Debug, Clone)]
+pub struct OnChainSnapshot {
+    /// 链上预言机价格，读取失败时为 None
+    pub price: Option<U256>,
+    /// 与请求的持仓一一对应，读取失败（包括余额为0导致的 revert）时为 None
+    pub positions: Vec<Option<OnChainPosition>>,
+}
+
+/// Multicall3 聚合读取器
+pub struct MulticallReader {
+    web3: web3::Web3<FailoverTransport>,
+    multicall_address: Address,
+    custodian_address: Address,
+    oracle_address: Address,
+}
+
+impl MulticallReader {
+    pub fn new(
+        web3: web3::Web3<FailoverTransport>,
+        multicall_address: &str,
+        config: &crate::config::AppConfig,
+    ) -> KeeperResult<Self> {
+        let parse_address = |address: &str| address.parse::<Address>()
+            .map_err(|e| KeeperError::Config(format!("无效的地址 '{}': {}", address, e)));
+        let multicall_address = parse_address(multicall_address)?;
+        tracing::info!("Multicall 复核已启用 - Multicall3: {:?}", multicall_address);
+
+        Ok(Self {
+            web3,
+            multicall_address,
+            custodian_address: parse_address(&config.contracts.custodian)?,
+            oracle_address: parse_address(&config.contracts.oracle)?,
+        })
+    }
+
+    /// 读取预言机价格和给定持仓的链上净值
+    ///
+    /// 持仓较多时按 `MULTICALL_BATCH_SIZE` 分批，预言机价格只在第一批中读取。
+    pub async fn read(&self, positions: &[(Address, U256)]) -> KeeperResult<OnChainSnapshot> {
+        let mut snapshot = OnChainSnapshot {
+            price: None,
+            positions: Vec::with_capacity(positions.len()),
+        };
+
+        for (index, batch) in positions.chunks(MULTICALL_BATCH_SIZE).enumerate() {
+            let first_batch = index == 0;
+            let mut calls = Vec::with_capacity(batch.len() + 1);
+            if first_batch {
+                calls.push((self.oracle_address, latest_round_data_calldata()?));
+            }
+            for (user, token_id) in batch {
+                calls.push((self.custodian_address, encode_position_nav_call(*user, *token_id)?));
+            }
+
+            let mut results = self.aggregate3(calls).await?.into_iter();
+            if first_batch {
+                snapshot.price = results.next().flatten()
+                    .and_then(|data| decode_latest_round_data(&data).ok())
+                    .map(|answer| answer.price);
+            }
+            for result in results {
+                snapshot.positions.push(result.and_then(|data| decode_position_nav(&data).ok()));
+            }
+        }
+
+        if snapshot.positions.len() != positions.len() {
+            return Err(KeeperError::Contract(format!(
+                "Multicall 返回结果数量不匹配: 请求 {} 个持仓，返回 {} 个",
+                positions.len(), snapshot.positions.len()
+            )));
+        }
+
+        Ok(snapshot)
+    }
+
+    /// 执行 aggregate3，返回每个子调用的返回数据（失败的子调用为 None）
+    async fn aggregate3(&self, calls: Vec<(Address, Vec<u8>)>) -> KeeperResult<Vec<Option<Vec<u8>>>> {
+        let call_count = calls.len();
+        let request = CallRequest {
+            to: Some(self.multicall_address),
+            data: Some(Bytes(encode_aggregate3(calls)?)),
+            ..Default::default()
+        };
+        let result = rpc_retry("eth_call", || self.web3.eth().call(request.clone(), None)).await?;
+
+        let results = decode_aggregate3(&result.0)?;
+        if results.len() != call_count {
+            return Err(KeeperError::Contract(format!(
+                "aggregate3 返回结果数量不匹配: 请求 {} 个调用，返回 {} 个", call_count, results.len()
+            )));
+        }
+        Ok(results)
+    }
+}
+
+/// 编码 `aggregate3((address,bool,bytes)[])`，所有子调用都允许失败
+fn encode_aggregate3(calls: Vec<(Address, Vec<u8>)>) -> KeeperResult<Vec<u8>> {
+    let contract = get_contract()?;
+    let calls = calls.into_iter()
+        .map(|(target, data)| Token::Tuple(vec![
+            Token::Address(target),
+            Token::Bool(true),
+            Token::Bytes(data),
+        ]))
+        .collect();
+    Ok(contract.function("aggregate3")?.encode_input(&[Token::Array(calls)])?)
+}
+
+/// 解码 aggregate3 的返回值 `(bool success, bytes returnData)[]`
+fn decode_aggregate3(data: &[u8]) -> KeeperResult<Vec<Option<Vec<u8>>>> {
+    let contract = get_contract()?;
+    let tokens = contract.function("aggregate3")?.decode_output(data)?;
+    let results = tokens.into_iter().next()
+        .and_then(Token::into_array)
+        .ok_or_else(|| KeeperError::Decode("无法解析 aggregate3 返回值".to_string()))?;
+
+    results.into_iter()
+        .map(|result| {
+            let mut fields = result.into_tuple()
+                .ok_or_else(|| KeeperError::Decode("无法解析 aggregate3 子调用结果".to_string()))?
+                .into_iter();
+            let success = fields.next().and_then(Token::into_bool)
+                .ok_or_else(|| KeeperError::Decode("无法解析 aggregate3 子调用 success".to_string()))?;
+            let return_data = fields.next().and_then(Token::into_bytes)
+                .ok_or_else(|| KeeperError::Decode("无法解析 aggregate3 子调用 returnData".to_string()))?;
+            Ok(success.then_some(return_data))
+        })
+        .collect()
+}
+
+/// 编码 `getSingleLeverageTokenNavV2(user, tokenId)`
+fn encode_position_nav_call(user: Address, token_id: U256) -> KeeperResult<Vec<u8>> {
+    let contract = get_contract()?;
+    Ok(contract.function("getSingleLeverageTokenNavV2")?
+        .encode_input(&[Token::Address(user), Token::Uint(token_id)])?)
+}
+
+/// 解码 `getSingleLeverageTokenNavV2` 的返回值，只保留余额和除息净值
+fn decode_position_nav(data: &[u8]) -> KeeperResult<OnChainPosition> {
+    let contract = get_contract()?;
+    let tokens = contract.function("getSingleLeverageTokenNavV2")?.decode_output(data)?;
+    let uint_field = |index: usize, name: &str| {
+        tokens.get(index).cloned()
+            .and_then(Token::into_uint)
+            .ok_or_else(|| KeeperError::Decode(format!("无法解析 {}", name)))
+    };
+
+    Ok(OnChainPosition {
+        balance: uint_field(0, "balance")?,
+        net_nav: uint_field(2, "netNavInWei")?,
+    })
+}
+
+fn get_contract() -> KeeperResult<ethabi::Contract> {
+    let abi = r#"[
+        {
+            "name": "aggregate3",
+            "type": "function",
+            "stateMutability": "payable",
+            "inputs": [
+                {
+                    "name": "calls",
+                    "type": "tuple[]",
+                    "components": [
+                        {"name": "target", "type": "address"},
+                        {"name": "allowFailure", "type": "bool"},
+                        {"name": "callData", "type": "bytes"}
+                    ]
+                }
+            ],
+            "outputs": [
+                {
+                    "name": "returnData",
+                    "type": "tuple[]",
+                    "components": [
+                        {"name": "success", "type": "bool"},
+                        {"name": "returnData", "type": "bytes"}
+                    ]
+                }
+            ]
+        },
+        {
+            "name": "getSingleLeverageTokenNavV2",
+            "type": "function",
+            "stateMutability": "view",
+            "inputs": [
+                {"name": "user", "type": "address"},
+                {"name": "tokenId", "type": "uint256"}
+            ],
+            "outputs": [
+                {"name": "balance", "type": "uint256"},
+                {"name": "grossNavInWei", "type": "uint256"},
+                {"name": "netNavInWei", "type": "uint256"},
+                {"name": "totalValueInWei", "type": "uint256"},
+                {"name": "totalNetValueInWei", "type": "uint256"},
+                {"name": "accruedInterestInWei", "type": "uint256"},
+                {"name": "currentPriceInWei", "type": "uint256"},
+                {"name": "priceTimestamp", "type": "uint256"}
+            ]
+        }
+    ]"#;
+
+    let contract: ethabi::Contract = serde_json::from_str(abi)?;
+    Ok(contract)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aggregate3_results_decode_position_nav_and_failures() {
+        let nav_data = ethabi::encode(&[
+            Token::Uint(U256::from(5u64)),
+            Token::Uint(U256::exp10(18)),
+            Token::Uint(U256::exp10(17) * 7),
+            Token::Uint(U256::zero()),
+            Token::Uint(U256::zero()),
+            Token::Uint(U256::zero()),
+            Token::Uint(U256::zero()),
+            Token::Uint(U256::zero()),
+        ]);
+        let encoded = ethabi::encode(&[Token::Array(vec![
+            Token::Tuple(vec![Token::Bool(true), Token::Bytes(nav_data)]),
+            Token::Tuple(vec![Token::Bool(false), Token::Bytes(vec![0x08, 0xc3, 0x79, 0xa0])]),
+        ])]);
+
+        let results = decode_aggregate3(&encoded).unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results[1].is_none());
+
+        let position = decode_position_nav(results[0].as_ref().unwrap()).unwrap();
+        assert_eq!(position, OnChainPosition {
+            balance: U256::from(5u64),
+            net_nav: U256::exp10(17) * 7,
+        });
+    }
+}
@@ -2,38 +2,195 @@
 //!
 //! 负责计算和更新代币的净资产价值（Net Asset Value）。
 
-use std::sync::Arc;
-use web3::types::U256;
-use crate::database::{Database, LeverageType, UserPosition};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock};
+use serde::Serialize;
+use web3::ethabi::{self, Token};
+use web3::types::{Address, BlockId, Bytes, CallRequest, U256};
+use tokio_util::sync::CancellationToken;
+use crate::audit::PositionAuditor;
+use crate::database::{Database, LeverageType, NavRecord, UserPosition};
+use crate::oracle::PriceOracle;
+use crate::rpc::rpc_retry;
+use crate::failover::FailoverTransport;
+use crate::status::unix_now;
 
 /// NAV计算结果结构体
 #[derive(Debug, Clone)]
 pub struct NavCalculation {
     pub user: web3::types::Address,
     pub token_id: U256,
+    pub leverage: LeverageType,
     pub gross_nav: U256,        // 粗净值（18位精度）
     pub net_nav: U256,          // 净值（粗净值-累计利息调整后的18位精度）
     pub position_amount: U256,  // 持仓数量
     pub total_value: U256,      // 总价值（持仓量 * 粗净值）
     pub net_value: U256,        // 净价值（总价值 - 累计利息）
     pub accrued_interest: U256, // 累计利息
+    pub health_factor: U256,    // 健康度 = net_nav / liquidation_threshold（price_precision 精度）
+}
+
+impl NavCalculation {
+    /// 转换为持久化的 NAV 记录
+    pub fn to_record(&self, price: U256, calculated_at: u64) -> NavRecord {
+        NavRecord {
+            user: self.user,
+            token_id: self.token_id,
+            price,
+            gross_nav: self.gross_nav,
+            net_nav: self.net_nav,
+            position_amount: self.position_amount,
+            total_value: self.total_value,
+            net_value: self.net_value,
+            accrued_interest: self.accrued_interest,
+            health_factor: Some(self.health_factor),
+            calculated_at,
+        }
+    }
+}
+
+/// 一组持仓的价值合计
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ValueTotals {
+    pub position_count: u64,
+    pub total_value: U256,
+    pub net_value: U256,
+    pub accrued_interest: U256,
+}
+
+impl ValueTotals {
+    fn add(&mut self, calculation: &NavCalculation) {
+        self.position_count += 1;
+        self.total_value = self.total_value.saturating_add(calculation.total_value);
+        self.net_value = self.net_value.saturating_add(calculation.net_value);
+        self.accrued_interest = self.accrued_interest.saturating_add(calculation.accrued_interest);
+    }
+}
+
+/// 所有持仓的协议总敞口（TVL），按杠杆类型和 token_id 分组
+#[derive(Debug, Clone, Serialize)]
+pub struct TvlReport {
+    pub price: U256,             // 计算使用的底层资产价格（18位精度）
+    pub calculated_at: u64,      // 计算时间戳
+    pub totals: ValueTotals,
+    pub by_leverage: BTreeMap<LeverageType, ValueTotals>,
+    pub by_token_id: BTreeMap<U256, ValueTotals>,
+}
+
+impl TvlReport {
+    /// 汇总 `calculate_all_nav` 的计算结果：按杠杆类型和 token_id 合计总价值、净价值和累计利息
+    pub fn from_calculations(calculations: &[NavCalculation], price: U256, calculated_at: u64) -> Self {
+        let mut report = Self {
+            price,
+            calculated_at,
+            totals: ValueTotals::default(),
+            by_leverage: BTreeMap::new(),
+            by_token_id: BTreeMap::new(),
+        };
+        for calculation in calculations {
+            report.totals.add(calculation);
+            report.by_leverage.entry(calculation.leverage.clone()).or_default().add(calculation);
+            report.by_token_id.entry(calculation.token_id).or_default().add(calculation);
+        }
+        report
+    }
+}
+
+/// 最近一次定时计算的 TVL（以 `Arc` 在 NAV 任务与 HTTP API 之间共享，查询时不触发重新计算）
+#[derive(Debug, Default)]
+pub struct TvlCache {
+    inner: RwLock<Option<TvlReport>>,
+}
+
+impl TvlCache {
+    pub fn get(&self) -> Option<TvlReport> {
+        self.inner.read().ok().and_then(|inner| inner.clone())
+    }
+
+    fn set(&self, report: TvlReport) {
+        if let Ok(mut inner) = self.inner.write() {
+            *inner = Some(report);
+        }
+    }
+}
+
+/// 单个持仓NAV计算的完整分解（用于诊断清算决策）
+///
+/// 与 `calculate_all_nav` 使用同一套计算逻辑，但保留所有中间值及对应公式，
+/// 便于在用户对清算结果有异议时逐步核对。
+#[derive(Debug, Clone, Serialize)]
+pub struct NavExplanation {
+    pub user: Address,
+    pub token_id: U256,
+    pub leverage: LeverageType,
+    pub current_price: U256,       // 计算使用的底层资产价格（18位精度）
+    pub mint_price: U256,          // 铸币价格（18位精度）
+    pub position_amount: U256,     // 持仓数量
+    pub gross_nav: U256,           // 粗净值（18位精度）
+    pub recorded_interest: U256,   // 数据库中记录的累计利息
+    pub new_accrued_interest: U256, // 自上次更新以来新产生的利息
+    pub total_accrued_interest: U256, // 总累计利息
+    pub holding_time_seconds: u64, // 自上次更新以来的持有时间
+    pub interest_rate: U256,       // 年利率（基点）
+    pub total_value: U256,         // 总价值（持仓量 * 粗净值）
+    pub net_value: U256,           // 净价值（总价值 - 总累计利息）
+    pub net_nav: U256,             // 除息净值（18位精度）
+    pub liquidation_threshold: U256, // 清算阈值（18位精度）
+    pub health_factor: U256,       // 健康度 = net_nav / liquidation_threshold（18位精度）
+    pub liquidatable: bool,        // 是否满足清算条件（net_nav < liquidation_threshold）
+    pub formulas: Vec<String>,     // 各步骤使用的公式及代入数值
 }
 
 /// NAV 监控器
 pub struct NavMonitor {
-    web3: web3::Web3<web3::transports::Http>,
+    web3: web3::Web3<FailoverTransport>,
     database: Arc<Database>,
+    /// 只计算这些 token_id 的持仓，None 表示计算所有持仓
+    watched_token_ids: Option<Vec<u64>>,
+    /// CustodianFixed 合约地址（读取铸币价格）
+    custodian_address: Address,
+    /// mint_price 为0的持仓超过该时间（秒）后从链上读取铸币价格
+    zero_mint_price_grace_secs: u64,
+    /// mint_price 为0的持仓首次被发现的时间（只在定时计算任务中维护，重启后重新计时）
+    zero_mint_price_since: HashMap<(Address, U256), u64>,
+    /// mint_price 为0且补读失败的持仓，距最后一次更新超过该时间（秒）后删除，0 表示不删除
+    zero_mint_price_ttl_secs: u64,
+    /// 检查链上已关闭持仓的间隔（秒），0 表示不检查
+    closed_check_interval_secs: u64,
+    /// 上次检查链上已关闭持仓的时间
+    last_closed_check: Option<tokio::time::Instant>,
+    /// 读取 InterestManager 的链上持仓
+    auditor: PositionAuditor,
+    /// 定时计算任务更新的 TVL 缓存
+    tvl_cache: Arc<TvlCache>,
+    /// 价格和净值的精度（与 CustodianFixed.PRICE_PRECISION 一致）
+    price_precision: U256,
+    /// NAV 历史样本的保留时间（秒），None 表示不记录历史
+    nav_history_retention_secs: Option<u64>,
 }
 
 impl NavMonitor {
     pub fn new(
-        web3: web3::Web3<web3::transports::Http>,
+        web3: web3::Web3<FailoverTransport>,
         database: Arc<Database>,
+        config: &crate::config::AppConfig,
     ) -> anyhow::Result<Self> {
         tracing::info!("NAV监控器初始化");
+        let auditor = PositionAuditor::new(web3.clone(), &config.contracts.interest_manager)?;
         Ok(Self {
             web3,
             database,
+            watched_token_ids: config.watched_token_ids.clone(),
+            custodian_address: config.contracts.custodian.parse()?,
+            zero_mint_price_grace_secs: config.zero_mint_price_grace_secs,
+            zero_mint_price_since: HashMap::new(),
+            zero_mint_price_ttl_secs: config.position_sweep.zero_mint_price_ttl_secs,
+            closed_check_interval_secs: config.position_sweep.closed_check_interval_secs,
+            last_closed_check: None,
+            auditor,
+            tvl_cache: Arc::new(TvlCache::default()),
+            price_precision: config.price_precision(),
+            nav_history_retention_secs: config.nav_history.enabled.then_some(config.nav_history.retention_secs),
         })
     }
 
@@ -99,24 +256,47 @@ impl NavMonitor {
     /// 7. 计算除息净值（net_nav = (总价值 - 总累计利息) / 持仓量）
     /// 8. 计算净价值（net_value = 总价值 - 总累计利息）
     ///
-    /// @param current_price 当前底层资产价格(U256，18位精度)
+    /// @param current_price 当前底层资产价格(U256，price_precision 精度)
     /// @return Vec<NavCalculation> 所有持仓的NAV计算结果
     pub async fn calculate_all_nav(&self, current_price: U256) -> anyhow::Result<Vec<NavCalculation>> {
+        self.calculate_all_nav_filtered(current_price, None).await
+    }
+
+    /// 本监控器定时计算时更新的 TVL 缓存
+    pub fn tvl_cache(&self) -> Arc<TvlCache> {
+        self.tvl_cache.clone()
+    }
+
+    /// 计算持仓NAV，可按 token_id 过滤
+    ///
+    /// 指定 `token_id` 时通过数据库的 token_id 索引只加载该 token 的持仓，
+    /// 供特定 token 价格更新触发的重算使用；为 `None` 时与 `calculate_all_nav` 相同。
+    pub async fn calculate_all_nav_filtered(
+        &self,
+        current_price: U256,
+        token_id: Option<U256>,
+    ) -> anyhow::Result<Vec<NavCalculation>> {
         let mut results = Vec::new();
-        let price_precision = U256::from(1_000_000_000_000_000_000u64); // 1e18
         let current_time = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap_or_default()
             .as_secs();
 
-        // 获取系统参数，包括年利率
+        // 获取系统参数，包括年利率和计算健康度使用的清算阈值
         let system_params = self.database.get_system_params()?;
         let interest_rate = system_params.annual_interest_rate;
+        let liquidation_threshold = system_params.liquidation_threshold;
 
         tracing::info!("使用年利率: {} (基点)", interest_rate);
 
-        // 获取所有用户持仓信息
-        let all_positions = self.get_all_user_positions()?;
+        // 获取用户持仓信息（指定 token_id 时只读取索引中的持仓），配置了监控列表时忽略其他 token
+        let mut all_positions = match token_id {
+            Some(token_id) => self.database.get_positions_by_token(token_id)?,
+            None => self.get_all_user_positions()?,
+        };
+        all_positions.retain(|position| {
+            crate::config::is_token_watched(self.watched_token_ids.as_deref(), position.token_id)
+        });
 
         tracing::info!("开始计算 {} 个持仓的NAV", all_positions.len());
 
@@ -143,7 +323,13 @@ impl NavMonitor {
             };
 
             // 总累计利息 = 数据库中的累计利息 + 新产生的利息
-            let total_accrued_interest = position.total_interest + new_accrued_interest;
+            let total_accrued_interest = match position.total_interest.checked_add(new_accrued_interest) {
+                Some(interest) => interest,
+                None => {
+                    tracing::error!("累计利息溢出，跳过该持仓 - 用户: {:?}, 持仓: {:?}", position.user, position.token_id);
+                    continue;
+                }
+            };
 
             tracing::debug!(
                 "利息计算 - 持仓: {:?}, 上次更新时间: {}, 持有时间: {}秒, 新利息: {}, 总利息: {}",
@@ -151,48 +337,40 @@ impl NavMonitor {
                 new_accrued_interest, total_accrued_interest
             );
 
-            // 计算粗净值（对应Solidity中的_calculateNav）
-            let gross_nav = self.calculate_gross_nav(
-                position.leverage.clone(),
-                current_price,
-                position.mint_price
-            )?;
-
-            // 计算总价值：total_value = position.amount * gross_nav / price_precision
-            let total_value = if !gross_nav.is_zero() {
-                position.amount * gross_nav / price_precision
-            } else {
-                U256::zero()
+            // 计算粗净值（对应Solidity中的_calculateNav）和各项价值；单个持仓溢出时跳过，不影响其他持仓
+            let values = self.calculate_gross_nav(position.leverage.clone(), current_price, position.mint_price)
+                .and_then(|gross_nav| {
+                    let (total_value, net_value, net_nav) = Self::calculate_net_values(
+                        position.amount,
+                        gross_nav,
+                        total_accrued_interest,
+                        self.price_precision,
+                    )?;
+                    Ok((gross_nav, total_value, net_value, net_nav))
+                });
+            let (gross_nav, total_value, net_value, net_nav) = match values {
+                Ok(values) => values,
+                Err(e) => {
+                    tracing::error!("持仓NAV计算失败，跳过该持仓 - 用户: {:?}, 持仓: {:?}, 错误: {}", position.user, position.token_id, e);
+                    continue;
+                }
             };
-
-            // 计算除息净值和净价值
-            let (net_nav, net_value) = if total_value >= total_accrued_interest {
-                // net_value = total_value - total_accrued_interest
-                let net_value = total_value - total_accrued_interest;
-
-                // net_nav = net_value * price_precision / position_amount
-                let net_nav = if !position.amount.is_zero() {
-                    net_value * price_precision / position.amount
-                } else {
-                    U256::zero()
-                };
-
-                (net_nav, net_value)
-            } else {
+            if total_value < total_accrued_interest {
                 // 如果累计利息超过总价值，净值为0
                 tracing::warn!("持仓 {:?} 累计利息超过总价值，净值设为0", position.token_id);
-                (U256::zero(), U256::zero())
-            };
+            }
 
             results.push(NavCalculation {
                 user: position.user,
                 token_id: position.token_id,
+                leverage: position.leverage.clone(),
                 gross_nav,
                 net_nav,
                 position_amount: position.amount,
                 total_value,
                 net_value,
                 accrued_interest: total_accrued_interest,
+                health_factor: health_factor(net_nav, liquidation_threshold, self.price_precision),
             });
 
             tracing::debug!(
@@ -205,65 +383,686 @@ impl NavMonitor {
         Ok(results)
     }
 
+    /// 根据粗净值和总累计利息计算总价值、净价值和除息净值
+    ///
+    /// total_value = amount * gross_nav / price_precision
+    /// net_value = total_value - total_accrued_interest（利息超过总价值时为0）
+    /// net_nav = net_value * price_precision / amount
+    ///
+    /// 乘除法都用 `checked_mul_div` 计算，结果超出 U256 时返回错误。
+    ///
+    /// @return (total_value, net_value, net_nav)
+    fn calculate_net_values(
+        amount: U256,
+        gross_nav: U256,
+        total_accrued_interest: U256,
+        price_precision: U256,
+    ) -> anyhow::Result<(U256, U256, U256)> {
+        // 计算总价值：total_value = position.amount * gross_nav / price_precision
+        let total_value = checked_mul_div(amount, gross_nav, price_precision)
+            .ok_or_else(|| anyhow::anyhow!("总价值溢出 (amount={}, gross_nav={})", amount, gross_nav))?;
+
+        // 计算除息净值和净价值；利息超过总价值时两者都为0
+        let net_value = match total_value.checked_sub(total_accrued_interest) {
+            Some(net_value) => net_value,
+            None => return Ok((total_value, U256::zero(), U256::zero())),
+        };
+
+        // net_nav = net_value * price_precision / position_amount
+        let net_nav = if amount.is_zero() {
+            U256::zero()
+        } else {
+            checked_mul_div(net_value, price_precision, amount)
+                .ok_or_else(|| anyhow::anyhow!("除息净值溢出 (net_value={}, amount={})", net_value, amount))?
+        };
+
+        Ok((total_value, net_value, net_nav))
+    }
+
+    /// 解释单个持仓的NAV计算过程（只读诊断接口）
+    ///
+    /// 复用 `calculate_all_nav` 的计算步骤，返回每一步的中间值和代入数值后的公式，
+    /// 并附带清算阈值与健康度，用于排查清算决策。
+    ///
+    /// @param user 用户地址
+    /// @param token_id 代币ID
+    /// @param current_price 底层资产价格(U256，price_precision 精度)
+    /// @return 持仓不存在时返回 None
+    pub fn explain_position(&self, user: Address, token_id: U256, current_price: U256) -> anyhow::Result<Option<NavExplanation>> {
+        let price_precision = self.price_precision;
+        let current_time = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        let position = match self.database.get_user_position(user, token_id)? {
+            Some(position) => position,
+            None => return Ok(None),
+        };
+
+        let system_params = self.database.get_system_params()?;
+        let interest_rate = system_params.annual_interest_rate;
+        let liquidation_threshold = system_params.liquidation_threshold;
+
+        let mut formulas = Vec::new();
+
+        // 1. 粗净值
+        let gross_nav = if position.mint_price.is_zero() {
+            formulas.push("mint_price = 0，无法计算粗净值（calculate_all_nav 会跳过该持仓）".to_string());
+            U256::zero()
+        } else {
+            let gross_nav = self.calculate_gross_nav(position.leverage.clone(), current_price, position.mint_price)?;
+            let formula = match position.leverage {
+                LeverageType::Conservative => "(9*Pt - P0) / (8*P0)",
+                LeverageType::Moderate => "(5*Pt - P0) / (4*P0)",
+                LeverageType::Aggressive => "(2*Pt - P0) / (1*P0)",
+            };
+            formulas.push(format!(
+                "gross_nav = {} = {} (Pt={}, P0={})",
+                formula, gross_nav, current_price, position.mint_price
+            ));
+            gross_nav
+        };
+
+        // 2. 利息拆分：数据库记录的利息 + 自上次更新以来的新利息
+        let holding_time_seconds = current_time.saturating_sub(position.timestamp);
+        let new_accrued_interest = self.calculate_accrued_interest(
+            position.amount,
+            position.leverage.clone(),
+            interest_rate,
+            holding_time_seconds,
+        ).unwrap_or_default();
+        let total_accrued_interest = position.total_interest.checked_add(new_accrued_interest)
+            .ok_or_else(|| anyhow::anyhow!("累计利息溢出 ({} + {})", position.total_interest, new_accrued_interest))?;
+        formulas.push(format!(
+            "new_accrued_interest = amount * holding_time * rate / (10000 * 31536000) / 杠杆系数 = {} (amount={}, holding_time={}s, rate={})",
+            new_accrued_interest, position.amount, holding_time_seconds, interest_rate
+        ));
+        formulas.push(format!(
+            "total_accrued_interest = recorded_interest + new_accrued_interest = {} + {} = {}",
+            position.total_interest, new_accrued_interest, total_accrued_interest
+        ));
+
+        // 3. 总价值、净价值、除息净值
+        let (total_value, net_value, net_nav) = Self::calculate_net_values(
+            position.amount,
+            gross_nav,
+            total_accrued_interest,
+            price_precision,
+        )?;
+        formulas.push(format!(
+            "total_value = amount * gross_nav / {} = {}",
+            price_precision, total_value
+        ));
+        formulas.push(format!(
+            "net_value = max(total_value - total_accrued_interest, 0) = {}",
+            net_value
+        ));
+        formulas.push(format!(
+            "net_nav = net_value * {} / amount = {}",
+            price_precision, net_nav
+        ));
+
+        // 4. 健康度和清算判断
+        let health_factor = health_factor(net_nav, liquidation_threshold, price_precision);
+        let liquidatable = !position.mint_price.is_zero() && net_nav < liquidation_threshold;
+        formulas.push(format!(
+            "health_factor = net_nav * {} / liquidation_threshold = {} (liquidation_threshold={})",
+            price_precision, health_factor, liquidation_threshold
+        ));
+
+        Ok(Some(NavExplanation {
+            user,
+            token_id,
+            leverage: position.leverage,
+            current_price,
+            mint_price: position.mint_price,
+            position_amount: position.amount,
+            gross_nav,
+            recorded_interest: position.total_interest,
+            new_accrued_interest,
+            total_accrued_interest,
+            holding_time_seconds,
+            interest_rate,
+            total_value,
+            net_value,
+            net_nav,
+            liquidation_threshold,
+            health_factor,
+            liquidatable,
+            formulas,
+        }))
+    }
+
     /// 计算粗净值（对应CustodianFixed._calculateNav函数）
     ///
     /// CONSERVATIVE: (9*Pt - P0) / (8*P0)
     /// MODERATE: (5*Pt - P0) / (4*P0)
     /// AGGRESSIVE: (2*Pt - P0) / (1*P0)
     ///
+    /// 价格跌到 `k*Pt < P0` 时持仓已资不抵债，粗净值按0计（低于任何清算阈值），而不是在减法中溢出。
+    /// 乘法全部检查溢出，超出 U256 范围时返回错误而不是 panic。
+    ///
     /// @param leverage 杠杆类型
-    /// @param current_price 当前价格（18位精度）
-    /// @return 粗净值（18位精度）
+    /// @param current_price 当前价格（与 mint_price 相同精度）
+    /// @return 粗净值（price_precision 精度）
     pub fn calculate_gross_nav(&self, leverage: LeverageType, current_price: U256, mint_price: U256) -> anyhow::Result<U256> {
-        const PRICE_PRECISION: u64 = 1_000_000_000_000_000_000; // 1e18
-
-        match leverage {
-            LeverageType::Conservative => {
-                // NAV = (9*Pt - P0) / (8*P0)
-                let pt_scaled = U256::from(9u64) * current_price;
-                let numerator = pt_scaled - mint_price;
-                let denominator = U256::from(8u64) * mint_price;
-                if denominator.is_zero() {
-                    return Err(anyhow::anyhow!("Invalid mint price for Conservative leverage"));
+        // (价格倍数, 分母倍数)
+        let (price_multiplier, mint_multiplier) = match leverage {
+            LeverageType::Conservative => (9u64, 8u64),
+            LeverageType::Moderate => (5u64, 4u64),
+            LeverageType::Aggressive => (2u64, 1u64),
+        };
+
+        let denominator = U256::from(mint_multiplier).checked_mul(mint_price)
+            .ok_or_else(|| anyhow::anyhow!("粗净值分母溢出 - 杠杆: {:?}, 铸币价格: {}", leverage, mint_price))?;
+        if denominator.is_zero() {
+            return Err(anyhow::anyhow!("Invalid mint price for {:?} leverage", leverage));
+        }
+
+        let pt_scaled = U256::from(price_multiplier).checked_mul(current_price)
+            .ok_or_else(|| anyhow::anyhow!("粗净值分子溢出 - 杠杆: {:?}, 当前价格: {}", leverage, current_price))?;
+        let numerator = match pt_scaled.checked_sub(mint_price) {
+            Some(numerator) => numerator,
+            None => {
+                tracing::warn!(
+                    "持仓已资不抵债，粗净值按0计 - 杠杆: {:?}, 当前价格: {}, 铸币价格: {}",
+                    leverage, current_price, mint_price
+                );
+                return Ok(U256::zero());
+            }
+        };
+        checked_mul_div(numerator, self.price_precision, denominator)
+            .ok_or_else(|| anyhow::anyhow!(
+                "粗净值溢出 - 杠杆: {:?}, 当前价格: {}, 铸币价格: {}", leverage, current_price, mint_price
+            ))
+    }
+
+    /// 获取所有用户的持仓信息
+    fn get_all_user_positions(&self) -> anyhow::Result<Vec<UserPosition>> {
+        // 使用database.rs中提供的公共方法
+        Ok(self.database.get_all_user_positions()?)
+    }
+
+    /// 按 `recalc_interval_secs` 定时读取预言机价格、重新计算所有持仓NAV并持久化
+    ///
+    /// 预言机读取或计算失败只记录错误，等待下一次计算，不会终止任务。
+    pub async fn run(
+        &mut self,
+        oracle: PriceOracle,
+        recalc_interval_secs: u64,
+        shutdown: CancellationToken,
+    ) -> anyhow::Result<()> {
+        tracing::info!("NAV监控器启动，计算间隔：{}秒...", recalc_interval_secs);
+
+        let mut interval = tokio::time::interval(
+            std::time::Duration::from_secs(recalc_interval_secs)
+        );
+
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => {
+                    tracing::info!("NAV监控器已停止");
+                    return Ok(());
                 }
-                Ok(numerator * U256::from(PRICE_PRECISION) / denominator)
+                _ = interval.tick() => {}
+            }
+
+            let unpriced_pruned = self.resolve_zero_mint_prices().await.unwrap_or_else(|e| {
+                tracing::error!("检查 mint_price 为0的持仓失败: {}", e);
+                0
+            });
+            let closed_pruned = self.prune_closed_positions_if_due().await.unwrap_or_else(|e| {
+                tracing::error!("检查链上已关闭的持仓失败: {}", e);
+                0
+            });
+            if unpriced_pruned + closed_pruned > 0 {
+                tracing::info!(
+                    "清理了 {} 个废弃持仓 - mint_price 长期为0: {}, 链上已关闭: {}",
+                    unpriced_pruned + closed_pruned, unpriced_pruned, closed_pruned
+                );
+            }
+
+            if let Err(e) = self.recalculate_and_store(&oracle).await {
+                tracing::error!("NAV定时计算失败: {}", e);
             }
-            LeverageType::Moderate => {
-                // NAV = (5*Pt - P0) / (4*P0)
-                let pt_scaled = U256::from(5u64) * current_price;
-                let numerator = pt_scaled - mint_price;
-                let denominator = U256::from(4u64) * mint_price;
-                if denominator.is_zero() {
-                    return Err(anyhow::anyhow!("Invalid mint price for Moderate leverage"));
+        }
+    }
+
+    /// 为 mint_price 长时间为0的持仓从链上补读铸币价格
+    ///
+    /// mint_price 为0的持仓（PositionIncreased 早于 Mint/NetValueAdjusted 到达）在NAV计算和清算中都会被跳过。
+    /// 定价事件通常很快到达；超过宽限期仍为0时说明事件可能已丢失，通过 `getTokenDetails` 读取链上的
+    /// 铸币价格和杠杆类型，链上也无法读取时记录错误供运维排查；链上确认铸币价格为0、且距最后一次更新
+    /// 超过保留时间时删除该持仓（读取失败时不删除，下一轮重试）。返回删除的持仓数量。
+    async fn resolve_zero_mint_prices(&mut self) -> anyhow::Result<usize> {
+        let now = unix_now();
+        let zero_priced: Vec<UserPosition> = self.get_all_user_positions()?
+            .into_iter()
+            .filter(|position| position.mint_price.is_zero())
+            .filter(|position| crate::config::is_token_watched(self.watched_token_ids.as_deref(), position.token_id))
+            .collect();
+
+        // 已被事件补上价格（或已删除）的持仓不再计时
+        self.zero_mint_price_since.retain(|(user, token_id), _| {
+            zero_priced.iter().any(|position| position.user == *user && position.token_id == *token_id)
+        });
+
+        let mut pruned = 0;
+        for position in zero_priced {
+            let key = (position.user, position.token_id);
+            let since = *self.zero_mint_price_since.entry(key).or_insert(now);
+            if now.saturating_sub(since) < self.zero_mint_price_grace_secs {
+                continue;
+            }
+
+            match self.fetch_token_details(position.token_id).await {
+                Ok((leverage, mint_price)) if !mint_price.is_zero() => {
+                    // 读取期间事件可能已写入价格，重新读取持仓后再更新
+                    if let Some(mut current) = self.database.get_user_position(position.user, position.token_id)? {
+                        if current.mint_price.is_zero() {
+                            current.mint_price = mint_price;
+                            current.leverage = leverage.clone();
+                            self.database.store_user_position(&current)?;
+                            tracing::warn!(
+                                "持仓 mint_price 为0已 {} 秒，已从链上补读 - 用户: {:?}, TokenID: {}, 杠杆: {:?}, 铸币价格: {}",
+                                now - since, position.user, position.token_id, leverage, mint_price
+                            );
+                        }
+                    }
+                    self.zero_mint_price_since.remove(&key);
+                    continue;
+                }
+                Ok(_) => {
+                    tracing::error!(
+                        "持仓 mint_price 为0已 {} 秒，链上铸币价格也为0，该持仓不会参与清算 - 用户: {:?}, TokenID: {}",
+                        now - since, position.user, position.token_id
+                    );
+                }
+                Err(e) => {
+                    // RPC 故障不能作为持仓已废弃的依据
+                    tracing::error!(
+                        "持仓 mint_price 为0已 {} 秒，读取链上铸币价格失败，该持仓不会参与清算 - 用户: {:?}, TokenID: {}, 错误: {}",
+                        now - since, position.user, position.token_id, e
+                    );
+                    continue;
                 }
-                Ok(numerator * U256::from(PRICE_PRECISION) / denominator)
             }
-            LeverageType::Aggressive => {
-                // NAV = (2*Pt - P0) / (1*P0)
-                let pt_scaled = U256::from(2u64) * current_price;
-                let numerator = pt_scaled - mint_price;
-                let denominator = mint_price;
-                if denominator.is_zero() {
-                    return Err(anyhow::anyhow!("Invalid mint price for Aggressive leverage"));
+
+            if is_abandoned_unpriced(&position, self.zero_mint_price_ttl_secs, now) {
+                self.database.delete_user_position(position.user, position.token_id)?;
+                self.zero_mint_price_since.remove(&key);
+                pruned += 1;
+                tracing::warn!(
+                    "删除 mint_price 长期为0的持仓 - 用户: {:?}, TokenID: {}, 最后更新: {} 秒前",
+                    position.user, position.token_id, now.saturating_sub(position.timestamp)
+                );
+            }
+        }
+
+        Ok(pruned)
+    }
+
+    /// 按间隔读取所有持仓的链上状态，删除链上已关闭的持仓，返回删除的数量
+    async fn prune_closed_positions_if_due(&mut self) -> anyhow::Result<usize> {
+        if self.closed_check_interval_secs == 0 {
+            return Ok(0);
+        }
+        let interval = std::time::Duration::from_secs(self.closed_check_interval_secs);
+        if self.last_closed_check.is_some_and(|last| last.elapsed() < interval) {
+            return Ok(0);
+        }
+        self.last_closed_check = Some(tokio::time::Instant::now());
+
+        let mut pruned = 0;
+        for position in self.get_all_user_positions()? {
+            let chain = match self.auditor.read_position(position.user, position.token_id).await {
+                Ok(chain) => chain,
+                Err(e) => {
+                    tracing::warn!("读取链上持仓失败 - 用户: {:?}, TokenID: {}, 错误: {}", position.user, position.token_id, e);
+                    continue;
                 }
-                Ok(numerator * U256::from(PRICE_PRECISION) / denominator)
+            };
+            if chain.is_closed() {
+                self.database.delete_user_position(position.user, position.token_id)?;
+                pruned += 1;
+                tracing::warn!(
+                    "删除链上已关闭的持仓（关闭事件可能已遗漏） - 用户: {:?}, TokenID: {}, 本地数量: {}",
+                    position.user, position.token_id, position.amount
+                );
             }
         }
+        Ok(pruned)
     }
 
-    /// 获取所有用户的持仓信息
-    fn get_all_user_positions(&self) -> anyhow::Result<Vec<UserPosition>> {
-        // 使用database.rs中提供的公共方法
-        self.database.get_all_user_positions()
+    /// 调用 `CustodianFixed.getTokenDetails(tokenId)` 读取杠杆类型和铸币价格
+    async fn fetch_token_details(&self, token_id: U256) -> anyhow::Result<(LeverageType, U256)> {
+        read_token_details(&self.web3, self.custodian_address, token_id, None).await
+    }
+
+    /// 执行一次NAV计算并写入数据库
+    async fn recalculate_and_store(&self, oracle: &PriceOracle) -> anyhow::Result<()> {
+        let current_price = oracle.get_current_price().await?;
+        let nav_results = self.calculate_all_nav(current_price).await?;
+
+        let calculated_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let records: Vec<NavRecord> = nav_results.iter()
+            .map(|result| result.to_record(current_price, calculated_at))
+            .collect();
+
+        self.database.store_nav_records(&records)?;
+        if let Some(retention_secs) = self.nav_history_retention_secs {
+            self.database.append_nav_history(&records, calculated_at.saturating_sub(retention_secs))?;
+        }
+        self.tvl_cache.set(TvlReport::from_calculations(&nav_results, current_price, calculated_at));
+        tracing::info!("NAV定时计算完成 - 价格: {}, 持仓数量: {}", current_price, records.len());
+        Ok(())
+    }
+}
+
+/// 健康度 = net_nav * price_precision / liquidation_threshold
+///
+/// 低于 `price_precision`（即1.0）时满足清算条件；清算阈值为0或结果超出 U256 时返回 `U256::MAX`。
+pub fn health_factor(net_nav: U256, liquidation_threshold: U256, price_precision: U256) -> U256 {
+    if liquidation_threshold.is_zero() {
+        return U256::MAX;
+    }
+    checked_mul_div(net_nav, price_precision, liquidation_threshold).unwrap_or(U256::MAX)
+}
+
+/// mint_price 为0的持仓距最后一次更新是否已超过保留时间（`ttl_secs` 为0时永不过期）
+fn is_abandoned_unpriced(position: &UserPosition, ttl_secs: u64, now: u64) -> bool {
+    ttl_secs != 0 && position.mint_price.is_zero() && now.saturating_sub(position.timestamp) >= ttl_secs
+}
+
+/// 调用 `CustodianFixed.getTokenDetails(tokenId)` 读取杠杆类型和铸币价格（`block` 为 None 时读取最新区块）
+pub async fn read_token_details(
+    web3: &web3::Web3<FailoverTransport>,
+    custodian_address: Address,
+    token_id: U256,
+    block: Option<BlockId>,
+) -> anyhow::Result<(LeverageType, U256)> {
+    let function = get_token_details_function()?;
+    let request = CallRequest {
+        to: Some(custodian_address),
+        data: Some(Bytes(function.encode_input(&[Token::Uint(token_id)])?)),
+        ..Default::default()
+    };
+    let result = rpc_retry("eth_call", || web3.eth().call(request.clone(), block)).await?;
+    decode_token_details(&function, &result.0)
+}
+
+fn get_token_details_function() -> anyhow::Result<ethabi::Function> {
+    let abi = r#"[
+        {
+            "name": "getTokenDetails",
+            "type": "function",
+            "stateMutability": "view",
+            "inputs": [
+                {"name": "tokenId", "type": "uint256"}
+            ],
+            "outputs": [
+                {"name": "leverage", "type": "uint8"},
+                {"name": "mintPrice", "type": "uint256"},
+                {"name": "creationTime", "type": "uint256"},
+                {"name": "tokenName", "type": "string"},
+                {"name": "isStatic", "type": "bool"}
+            ]
+        }
+    ]"#;
+
+    let contract: ethabi::Contract = serde_json::from_str(abi)?;
+    Ok(contract.function("getTokenDetails")?.clone())
+}
+
+/// 计算 `a * b / denominator`（向下取整，与直接计算的结果相同）
+///
+/// 先把 `a` 拆成 `denominator` 的商和余数再分别乘以 `b`，中间值不超过 `max(a, denominator) * b`，
+/// 避免 `a * b` 先溢出；结果超出 U256 或除数为0时返回 None。
+fn checked_mul_div(a: U256, b: U256, denominator: U256) -> Option<U256> {
+    let quotient = a.checked_div(denominator)?;
+    let remainder = a % denominator;
+    quotient.checked_mul(b)?
+        .checked_add(remainder.checked_mul(b)?.checked_div(denominator)?)
+}
+
+/// 解码 `getTokenDetails` 的返回值，只保留杠杆类型和铸币价格
+fn decode_token_details(function: &ethabi::Function, data: &[u8]) -> anyhow::Result<(LeverageType, U256)> {
+    let tokens = function.decode_output(data)?;
+    let uint_field = |index: usize, name: &str| {
+        tokens.get(index).cloned()
+            .and_then(Token::into_uint)
+            .ok_or_else(|| anyhow::anyhow!("无法解析 {}", name))
+    };
+
+    let leverage = uint_field(0, "leverage")?;
+    if leverage > U256::from(u8::MAX) {
+        return Err(anyhow::anyhow!("杠杆类型超出范围: {}", leverage));
+    }
+    Ok((LeverageType::from_u8(leverage.as_u32() as u8)?, uint_field(1, "mintPrice")?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wad(value: u64) -> U256 {
+        U256::from(value) * U256::exp10(18)
+    }
+
+    fn nav_monitor() -> NavMonitor {
+        let transport = FailoverTransport::new(&["http://localhost:8545".to_string()], 3).unwrap();
+        let config = crate::config::AppConfig::default();
+        NavMonitor::new(web3::Web3::new(transport), Arc::new(Database::open_temp()), &config).unwrap()
+    }
+
+    #[test]
+    fn gross_nav_floors_underwater_positions_at_zero() {
+        let nav_monitor = nav_monitor();
+
+        // 价格不变时各杠杆类型的粗净值都是1
+        for leverage in [LeverageType::Conservative, LeverageType::Moderate, LeverageType::Aggressive] {
+            assert_eq!(nav_monitor.calculate_gross_nav(leverage, wad(100), wad(100)).unwrap(), wad(1));
+        }
+
+        // 9 * 10 < 100：保守型持仓资不抵债，粗净值为0而不是溢出
+        assert_eq!(
+            nav_monitor.calculate_gross_nav(LeverageType::Conservative, wad(10), wad(100)).unwrap(),
+            U256::zero()
+        );
+        assert_eq!(
+            nav_monitor.calculate_gross_nav(LeverageType::Aggressive, wad(49), wad(100)).unwrap(),
+            U256::zero()
+        );
+        assert!(nav_monitor.calculate_gross_nav(LeverageType::Moderate, wad(100), U256::zero()).is_err());
+    }
+
+    #[test]
+    fn nav_math_reports_overflow_instead_of_panicking() {
+        let nav_monitor = nav_monitor();
+
+        // (2 * 1e60 - 1e60) * 1e18 超出 U256，按商和余数拆分后结果仍可表示
+        let huge_price = U256::exp10(60);
+        assert_eq!(nav_monitor.calculate_gross_nav(LeverageType::Aggressive, huge_price, huge_price).unwrap(), wad(1));
+        // 结果本身超出 U256，或 k * Pt 溢出时返回错误
+        assert!(nav_monitor.calculate_gross_nav(LeverageType::Aggressive, U256::MAX / 4, U256::one()).is_err());
+        assert!(nav_monitor.calculate_gross_nav(LeverageType::Conservative, U256::MAX / 2, wad(1)).is_err());
+
+        let amount = U256::MAX / 2;
+        assert_eq!(NavMonitor::calculate_net_values(amount, wad(1), U256::zero(), wad(1)).unwrap(), (amount, amount, wad(1)));
+        assert_eq!(
+            NavMonitor::calculate_net_values(amount, wad(1), U256::MAX, wad(1)).unwrap(),
+            (amount, U256::zero(), U256::zero())
+        );
+        assert!(NavMonitor::calculate_net_values(amount, wad(3), U256::zero(), wad(1)).is_err());
+        assert_eq!(checked_mul_div(U256::MAX, U256::MAX, U256::MAX), Some(U256::MAX));
+        assert_eq!(checked_mul_div(U256::one(), U256::one(), U256::zero()), None);
+    }
+
+    #[test]
+    fn nav_scales_with_configured_price_precision() {
+        let transport = FailoverTransport::new(&["http://localhost:8545".to_string()], 3).unwrap();
+        let config = crate::config::AppConfig { price_precision_decimals: 6, ..Default::default() };
+        let monitor = NavMonitor::new(web3::Web3::new(transport), Arc::new(Database::open_temp()), &config).unwrap();
+        let unit = U256::exp10(6);
+
+        // 价格 110 / 铸币价格 100：2倍杠杆粗净值 1.2，按6位精度为 1_200_000
+        let gross_nav = monitor.calculate_gross_nav(LeverageType::Aggressive, unit * 110, unit * 100).unwrap();
+        assert_eq!(gross_nav, unit * 12 / 10);
+        // 持仓 10 个单位，利息 2：总价值 12，净价值 10，除息净值 1
+        assert_eq!(
+            NavMonitor::calculate_net_values(unit * 10, gross_nav, unit * 2, monitor.price_precision).unwrap(),
+            (unit * 12, unit * 10, unit)
+        );
+        // 同样的持仓按默认18位精度计算时，各项结果相差 1e12 倍
+        assert_eq!(
+            nav_monitor().calculate_gross_nav(LeverageType::Aggressive, wad(110), wad(100)).unwrap(),
+            gross_nav * U256::exp10(12)
+        );
+    }
+
+    #[test]
+    fn tvl_sums_values_by_leverage_and_token() {
+        let calculation = |token_id: u64, leverage: LeverageType, total_value: u64, accrued_interest: u64| NavCalculation {
+            user: Address::from_low_u64_be(token_id),
+            token_id: U256::from(token_id),
+            leverage,
+            gross_nav: wad(1),
+            net_nav: wad(1),
+            position_amount: wad(1),
+            total_value: wad(total_value),
+            net_value: wad(total_value - accrued_interest),
+            accrued_interest: wad(accrued_interest),
+            health_factor: wad(1),
+        };
+        let report = TvlReport::from_calculations(&[
+            calculation(1, LeverageType::Conservative, 100, 10),
+            calculation(1, LeverageType::Conservative, 50, 5),
+            calculation(2, LeverageType::Aggressive, 30, 0),
+        ], wad(2_000), 1_700_000_000);
+
+        assert_eq!(report.totals, ValueTotals { position_count: 3, total_value: wad(180), net_value: wad(165), accrued_interest: wad(15) });
+        assert_eq!(report.by_leverage[&LeverageType::Conservative].total_value, wad(150));
+        assert_eq!(report.by_leverage[&LeverageType::Aggressive].position_count, 1);
+        assert!(!report.by_leverage.contains_key(&LeverageType::Moderate));
+        assert_eq!(report.by_token_id[&U256::from(1)].net_value, wad(135));
+        assert_eq!(report.by_token_id[&U256::from(2)].accrued_interest, U256::zero());
+
+        // 分组键必须能序列化为 JSON 对象的键
+        let json = serde_json::to_value(&report).unwrap();
+        assert!(json["by_leverage"]["Conservative"].is_object());
+        assert_eq!(json["by_token_id"].as_object().unwrap().len(), 2);
     }
 
-    pub async fn run(&mut self) -> anyhow::Result<()> {
-        // TODO: 定期计算和监控NAV变化
-        tracing::info!("NAV监控器运行中...");
+    #[tokio::test]
+    async fn nav_calculation_reports_health_factor() {
+        let nav_monitor = nav_monitor();
+        nav_monitor.database.set_system_params(&crate::database::SystemParams {
+            annual_interest_rate: U256::zero(),
+            liquidation_threshold: U256::exp10(17) * 3,
+            ..Default::default()
+        }).unwrap();
+        nav_monitor.database.store_user_position(&UserPosition {
+            user: Address::repeat_byte(0x11),
+            token_id: U256::from(7),
+            amount: wad(100),
+            timestamp: unix_now(),
+            total_interest: U256::zero(),
+            leverage: LeverageType::Aggressive,
+            mint_price: wad(2_000),
+            last_applied_log: None,
+            epoch: 0,
+        }).unwrap();
+
+        // 价格 1500：净值 (2 × 1500 - 2000) / 2000 = 0.5，健康度 0.5 / 0.3
+        let results = nav_monitor.calculate_all_nav(wad(1_500)).await.unwrap();
+        assert_eq!(results[0].net_nav, U256::exp10(17) * 5);
+        assert_eq!(results[0].health_factor, U256::from(1_666_666_666_666_666_666u64));
+        assert_eq!(results[0].to_record(wad(1_500), 0).health_factor, Some(results[0].health_factor));
 
-        // 可以在这里实现定期从链上获取价格并计算NAV的逻辑
-        // 使用tokio::time::interval等定时器实现
+        assert_eq!(health_factor(wad(1), U256::zero(), wad(1)), U256::MAX);
+    }
+
+    #[test]
+    fn token_details_decode_leverage_and_mint_price() {
+        let function = get_token_details_function().unwrap();
+        let data = ethabi::encode(&[
+            Token::Uint(U256::from(2u64)),
+            Token::Uint(wad(2_000)),
+            Token::Uint(U256::from(1_700_000_000u64)),
+            Token::String("L-AGG-2000".to_string()),
+            Token::Bool(false),
+        ]);
+
+        let (leverage, mint_price) = decode_token_details(&function, &data).unwrap();
+        assert!(matches!(leverage, LeverageType::Aggressive));
+        assert_eq!(mint_price, wad(2_000));
+    }
+
+    #[test]
+    fn unpriced_positions_expire_after_ttl() {
+        let position = UserPosition {
+            user: Address::repeat_byte(0x11),
+            token_id: U256::from(3),
+            amount: wad(10),
+            timestamp: 1_700_000_000,
+            total_interest: U256::zero(),
+            leverage: LeverageType::Moderate,
+            mint_price: U256::zero(),
+            last_applied_log: None,
+            epoch: 0,
+        };
+
+        assert!(!is_abandoned_unpriced(&position, 3600, position.timestamp + 3599));
+        assert!(is_abandoned_unpriced(&position, 3600, position.timestamp + 3600));
+        // 保留时间为0时不删除；已有铸币价格的持仓不受影响
+        assert!(!is_abandoned_unpriced(&position, 0, position.timestamp + 1_000_000));
+        assert!(!is_abandoned_unpriced(&UserPosition { mint_price: wad(1), ..position }, 3600, 1_800_000_000));
+    }
+
+    #[tokio::test]
+    async fn unpriced_position_survives_failed_chain_read() {
+        use hyper::service::{make_service_fn, service_fn};
+
+        // 所有 eth_call 都返回 revert，getTokenDetails 读取失败
+        let make_service = make_service_fn(|_conn| async {
+            Ok::<_, std::convert::Infallible>(service_fn(|request: hyper::Request<hyper::Body>| async move {
+                let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+                let call: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                let response = serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": call["id"],
+                    "error": { "code": 3, "message": "execution reverted" },
+                });
+                Ok::<_, std::convert::Infallible>(hyper::Response::new(hyper::Body::from(response.to_string())))
+            }))
+        });
+        let server = hyper::Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let transport = FailoverTransport::new(&[format!("http://{}", server.local_addr())], 3).unwrap();
+        tokio::spawn(server);
+
+        let config = crate::config::AppConfig::default();
+        let mut monitor = NavMonitor::new(web3::Web3::new(transport), Arc::new(Database::open_temp()), &config).unwrap();
+        monitor.zero_mint_price_grace_secs = 0;
+        monitor.zero_mint_price_ttl_secs = 3600;
+        let position = UserPosition {
+            user: Address::repeat_byte(0x11),
+            token_id: U256::from(3),
+            amount: wad(10),
+            timestamp: unix_now() - 7200,
+            total_interest: U256::zero(),
+            leverage: LeverageType::Moderate,
+            mint_price: U256::zero(),
+            last_applied_log: None,
+            epoch: 0,
+        };
+        monitor.database.store_user_position(&position).unwrap();
 
-        std::future::pending().await
+        // 已超过保留时间，但链上读取失败时不能确认铸币价格为0，保留持仓等待下一轮
+        assert_eq!(monitor.resolve_zero_mint_prices().await.unwrap(), 0);
+        assert!(monitor.database.get_user_position(position.user, position.token_id).unwrap().is_some());
     }
 }
@@ -0,0 +1,239 @@
+//! 运维通知模块
+//!
+//! Keeper 执行链上操作或持续出现故障时通知运维人员：
+//! - 清算（bark）交易确认后
+//! - 拍卖重置（resetAuction）交易确认后
+//! - 清算检查因 RPC 错误连续失败达到阈值时
+//!
+//! 通知通过 `Notifier` trait 注入清算监控器和拍卖重置监控器。未配置通知目标时使用 `NoopNotifier`；
+//! `HttpNotifier` 在后台任务中发送，发送失败只记录警告，不影响 Keeper 的主流程。
+
+use std::sync::Arc;
+use std::time::Duration;
+use serde::Serialize;
+use web3::types::{Address, H256, U256};
+use crate::config::NotifierConfig;
+use crate::status::unix_now;
+
+/// 单次通知请求的超时时间（秒）
+const NOTIFY_TIMEOUT_SECS: u64 = 10;
+
+/// 通知内容
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Notification {
+    /// bark 交易已确认
+    LiquidationSent {
+        tx_hash: H256,
+        user: Address,
+        token_id: U256,
+        auction_id: Option<U256>,
+        position_amount: U256,
+        total_value: U256,
+        net_value: U256,
+        net_nav: U256,
+    },
+    /// resetAuction 交易已确认，拍卖字段为重置前数据库中的记录
+    AuctionReset {
+        tx_hash: H256,
+        auction_id: U256,
+        token_id: Option<U256>,
+        original_owner: Option<Address>,
+        starting_price: Option<U256>,
+        underlying_amount: Option<U256>,
+    },
+    /// 清算检查因 RPC 错误连续失败
+    RpcFailure {
+        component: &'static str,
+        consecutive_failures: u32,
+        error: String,
+    },
+}
+
+impl std::fmt::Display for Notification {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Notification::LiquidationSent { tx_hash, user, token_id, auction_id, position_amount, total_value, net_value, net_nav } => {
+                write!(
+                    f,
+                    "清算交易已确认\n交易: {:?}\n用户: {:?}\nTokenID: {}\n持仓数量: {}\n总价值: {}\n净价值: {}\n净值: {}",
+                    tx_hash, user, token_id, position_amount, total_value, net_value, net_nav
+                )?;
+                match auction_id {
+                    Some(auction_id) => write!(f, "\n拍卖ID: {}", auction_id),
+                    None => write!(f, "\n回执中没有 AuctionStarted 事件"),
+                }
+            }
+            Notification::AuctionReset { tx_hash, auction_id, token_id, original_owner, starting_price, underlying_amount } => {
+                write!(f, "拍卖已重置\n交易: {:?}\n拍卖ID: {}", tx_hash, auction_id)?;
+                if let Some(token_id) = token_id {
+                    write!(f, "\nTokenID: {}", token_id)?;
+                }
+                if let Some(original_owner) = original_owner {
+                    write!(f, "\n原始持有者: {:?}", original_owner)?;
+                }
+                if let Some(starting_price) = starting_price {
+                    write!(f, "\n重置前起始价格: {}", starting_price)?;
+                }
+                if let Some(underlying_amount) = underlying_amount {
+                    write!(f, "\n标的数量: {}", underlying_amount)?;
+                }
+                Ok(())
+            }
+            Notification::RpcFailure { component, consecutive_failures, error } => {
+                write!(f, "{} 因 RPC 错误已连续失败 {} 次\n最近错误: {}", component, consecutive_failures, error)
+            }
+        }
+    }
+}
+
+/// 通知发送接口
+///
+/// `notify` 不阻塞调用方，实现需要自行在后台完成发送。
+pub trait Notifier: Send + Sync {
+    fn notify(&self, notification: Notification);
+}
+
+/// 不发送任何通知（未配置通知目标时使用）
+#[derive(Debug, Default)]
+pub struct NoopNotifier;
+
+impl Notifier for NoopNotifier {
+    fn notify(&self, notification: Notification) {
+        tracing::trace!("未配置通知目标，忽略通知: {:?}", notification);
+    }
+}
+
+/// Telegram 机器人推送目标
+#[derive(Debug, Clone)]
+struct TelegramTarget {
+    bot_token: String,
+    chat_id: String,
+}
+
+/// 通过 HTTP 发送通知：webhook 接收 JSON，Telegram 接收文本消息
+pub struct HttpNotifier {
+    client: reqwest::Client,
+    webhook_url: Option<String>,
+    telegram: Option<TelegramTarget>,
+}
+
+/// webhook 请求体
+#[derive(Serialize)]
+struct WebhookPayload<'a> {
+    timestamp: u64,
+    #[serde(flatten)]
+    notification: &'a Notification,
+}
+
+impl HttpNotifier {
+    pub fn new(config: &NotifierConfig) -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(NOTIFY_TIMEOUT_SECS))
+            .build()?;
+        let telegram = match (&config.telegram_bot_token, &config.telegram_chat_id) {
+            (Some(bot_token), Some(chat_id)) => Some(TelegramTarget {
+                bot_token: bot_token.clone(),
+                chat_id: chat_id.clone(),
+            }),
+            _ => None,
+        };
+
+        Ok(Self {
+            client,
+            webhook_url: config.webhook_url.clone(),
+            telegram,
+        })
+    }
+
+    async fn send(
+        client: reqwest::Client,
+        webhook_url: Option<String>,
+        telegram: Option<TelegramTarget>,
+        notification: Notification,
+    ) {
+        if let Some(webhook_url) = webhook_url {
+            let payload = WebhookPayload { timestamp: unix_now(), notification: &notification };
+            match serde_json::to_vec(&payload) {
+                Ok(body) => {
+                    let request = client.post(&webhook_url)
+                        .header(reqwest::header::CONTENT_TYPE, "application/json")
+                        .body(body);
+                    Self::log_result("webhook", request.send().await);
+                }
+                Err(e) => tracing::warn!("序列化通知失败: {}", e),
+            }
+        }
+
+        if let Some(telegram) = telegram {
+            let url = format!("https://api.telegram.org/bot{}/sendMessage", telegram.bot_token);
+            let body = serde_json::json!({ "chat_id": telegram.chat_id, "text": notification.to_string() });
+            let request = client.post(&url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body.to_string());
+            Self::log_result("Telegram", request.send().await);
+        }
+    }
+
+    fn log_result(target: &str, result: reqwest::Result<reqwest::Response>) {
+        match result {
+            Ok(response) if response.status().is_success() => tracing::debug!("{} 通知发送成功", target),
+            Ok(response) => tracing::warn!("{} 通知发送失败: HTTP {}", target, response.status()),
+            // 错误信息中的 URL 可能包含 Telegram 机器人 token，不记录
+            Err(e) => tracing::warn!("{} 通知发送失败: {}", target, e.without_url()),
+        }
+    }
+}
+
+impl Notifier for HttpNotifier {
+    fn notify(&self, notification: Notification) {
+        tokio::spawn(Self::send(
+            self.client.clone(),
+            self.webhook_url.clone(),
+            self.telegram.clone(),
+            notification,
+        ));
+    }
+}
+
+/// 根据配置创建通知器：配置了 webhook 或 Telegram 时使用 `HttpNotifier`，否则使用 `NoopNotifier`
+pub fn from_config(config: &NotifierConfig) -> anyhow::Result<Arc<dyn Notifier>> {
+    if config.webhook_url.is_none() && config.telegram_bot_token.is_none() {
+        return Ok(Arc::new(NoopNotifier));
+    }
+    tracing::info!(
+        "运维通知已启用 - webhook: {}, Telegram: {}",
+        config.webhook_url.is_some(), config.telegram_bot_token.is_some()
+    );
+    Ok(Arc::new(HttpNotifier::new(config)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn webhook_payload_is_tagged_with_event_name() {
+        let notification = Notification::LiquidationSent {
+            tx_hash: H256::repeat_byte(0xab),
+            user: Address::repeat_byte(0x11),
+            token_id: U256::from(7),
+            auction_id: Some(U256::from(3)),
+            position_amount: U256::from(1_000),
+            total_value: U256::from(900),
+            net_value: U256::from(850),
+            net_nav: U256::from(85),
+        };
+        let json = serde_json::to_value(WebhookPayload { timestamp: 1_700_000_000, notification: &notification }).unwrap();
+
+        assert_eq!(json["event"], "liquidation_sent");
+        assert_eq!(json["timestamp"], 1_700_000_000u64);
+        assert_eq!(json["user"], format!("{:?}", Address::repeat_byte(0x11)));
+        assert_eq!(json["token_id"], "0x7");
+        assert_eq!(json["auction_id"], "0x3");
+
+        let text = notification.to_string();
+        assert!(text.contains(&format!("{:?}", H256::repeat_byte(0xab))));
+        assert!(text.contains("拍卖ID: 3"));
+    }
+}
@@ -0,0 +1,587 @@
+//! 预言机模块
+//!
+//! 读取 Chainlink 风格预言机的 `latestRoundData`，供清算检查和 NAV 定时计算共用。
+//!
+//! ## 聚合规则：
+//! - 配置多个预言机时分别读取，读取失败、未完成当前轮次、过期或非正数的报价被剔除
+//! - 报价按预言机的 `decimals()`（或配置的精度覆盖）统一换算为 `price_precision_decimals` 位精度（默认18位）
+//! - 剩余报价取中位数
+//! - 没有可用报价、或报价之间偏差超过上限时返回错误，由调用方跳过本轮计算
+//!
+//! ## 价格推送（可选）：
+//! 通过 WebSocket 订阅预言机的 `AnswerUpdated`（Chainlink 聚合器）或 `PriceUpdated`（LTCPriceOracle）事件，
+//! 最新的更新通过 watch 通道交给清算监控器立即触发检查。只配置一个预言机时直接使用推送的报价，
+//! 省去一次 `latestRoundData` 调用；配置多个预言机时推送只触发检查，价格仍按上述规则聚合。
+//!
+//! ## 手动价格（测试用）：
+//! 配置 `oracle.manual_price` 后不读取预言机，始终返回该价格，便于在没有预言机的本地链上完整执行清算流程。
+
+use std::sync::Arc;
+use futures_util::StreamExt;
+use tokio::sync::{watch, OnceCell};
+use tokio_util::sync::CancellationToken;
+use web3::types::{Address, FilterBuilder, Log, U256};
+use web3::ethabi;
+use crate::config::OracleConfig;
+use crate::error::{KeeperError, KeeperResult};
+use crate::event_abi;
+use crate::rpc::rpc_retry;
+use crate::failover::FailoverTransport;
+use crate::status::KeeperStatus;
+
+/// 价格预言机（支持多源聚合）
+pub struct PriceOracle {
+    web3: web3::Web3<FailoverTransport>,
+    addresses: Vec<Address>,
+    /// 各预言机报价的小数位数（与 `addresses` 一一对应），首次读取成功后缓存
+    decimals: Vec<OnceCell<u8>>,
+    /// 配置的手动价格（price_precision_decimals 位精度），设置时不读取预言机
+    manual_price: Option<U256>,
+    /// 报价换算的目标精度（小数位数），与 NAV 计算使用的价格精度一致
+    price_decimals: u8,
+    config: OracleConfig,
+    status: Arc<KeeperStatus>,
+}
+
+impl PriceOracle {
+    pub fn new(
+        web3: web3::Web3<FailoverTransport>,
+        config: &crate::config::AppConfig,
+        status: Arc<KeeperStatus>,
+    ) -> KeeperResult<Self> {
+        let addresses = oracle_addresses(config)?;
+
+        let manual_price = match &config.oracle.manual_price {
+            Some(price) => Some(U256::from_dec_str(price)
+                .map_err(|e| KeeperError::Config(format!("无效的手动价格 '{}': {}", price, e)))?),
+            None => None,
+        };
+        match manual_price {
+            Some(price) => tracing::warn!("预言机使用手动价格 {}，不读取链上报价（仅用于测试）", price),
+            None => tracing::info!("预言机初始化 - 地址: {:?}", addresses),
+        }
+
+        let decimals = addresses.iter()
+            .map(|_| match config.oracle.decimals {
+                Some(decimals) => OnceCell::new_with(Some(decimals)),
+                None => OnceCell::new(),
+            })
+            .collect();
+
+        Ok(Self {
+            web3,
+            addresses,
+            decimals,
+            manual_price,
+            price_decimals: config.price_precision_decimals,
+            config: config.oracle.clone(),
+            status,
+        })
+    }
+
+    /// 从所有配置的预言机获取当前价格并聚合
+    ///
+    /// 读取失败、轮次未完成或沿用旧轮次、以及过期的报价被剔除，剩余报价取中位数；没有可用报价或偏差过大时返回错误，
+    /// 由调用方跳过本轮计算。
+    pub async fn get_current_price(&self) -> KeeperResult<U256> {
+        if let Some(price) = self.manual_price {
+            return Ok(price);
+        }
+
+        let max_staleness = self.config.max_price_staleness_secs;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        let mut prices = Vec::with_capacity(self.addresses.len());
+        for (oracle_address, decimals) in self.addresses.iter().zip(&self.decimals) {
+            let decimals = match decimals.get_or_try_init(|| self.read_decimals(*oracle_address)).await {
+                Ok(decimals) => *decimals,
+                Err(e) => {
+                    tracing::warn!("读取预言机 {:?} 的 decimals 失败（可通过 oracle.decimals 配置覆盖）: {}", oracle_address, e);
+                    continue;
+                }
+            };
+
+            let answer = match self.read_oracle(*oracle_address).await {
+                Ok(answer) => answer,
+                Err(e) if e.is_transient() => {
+                    tracing::warn!("读取预言机 {:?} 失败（重试后仍为临时错误）: {}", oracle_address, e);
+                    continue;
+                }
+                Err(e) => {
+                    // 解码失败或合约返回异常，重试无意义，需要检查预言机地址和接口
+                    tracing::error!("读取预言机 {:?} 失败: {}", oracle_address, e);
+                    continue;
+                }
+            };
+
+            if let Err(e) = validate_round(&answer, now, max_staleness) {
+                tracing::warn!("预言机 {:?} {}，已剔除", oracle_address, e);
+                continue;
+            }
+
+            match normalize_price(answer.price, decimals, self.price_decimals) {
+                Ok(price) => prices.push(price),
+                Err(e) => tracing::warn!("预言机 {:?} 报价无法换算为{}位精度，已剔除: {}", oracle_address, self.price_decimals, e),
+            }
+        }
+
+        let price = aggregate_prices(prices, self.config.max_price_deviation_bps)?;
+        self.status.record_oracle_read();
+        Ok(price)
+    }
+
+    /// 将推送的价格更新换算为清算检查使用的价格
+    ///
+    /// 只配置一个预言机时返回换算为价格精度的推送报价；配置多个预言机时单个推送不足以聚合，返回 None，
+    /// 由调用方改用 `get_current_price`。
+    pub async fn price_from_update(&self, update: &PriceUpdate) -> KeeperResult<Option<U256>> {
+        if self.manual_price.is_some() {
+            return Ok(self.manual_price);
+        }
+        if self.addresses.len() != 1 || self.addresses[0] != update.oracle {
+            return Ok(None);
+        }
+
+        let decimals = *self.decimals[0].get_or_try_init(|| self.read_decimals(update.oracle)).await?;
+        let price = normalize_price(update.answer, decimals, self.price_decimals)?;
+        self.status.record_oracle_read();
+        Ok(Some(price))
+    }
+
+    /// 读取单个预言机报价的小数位数
+    async fn read_decimals(&self, oracle_address: Address) -> KeeperResult<u8> {
+        let function = get_contract()?.function("decimals")?.clone();
+        let request = web3::types::CallRequest {
+            to: Some(oracle_address),
+            data: Some(web3::types::Bytes(function.encode_input(&[])?)),
+            ..Default::default()
+        };
+        let result = rpc_retry("eth_call", || self.web3.eth().call(request.clone(), None)).await?;
+
+        let decimals = function.decode_output(&result.0)?
+            .into_iter()
+            .next()
+            .and_then(|token| token.into_uint())
+            .ok_or_else(|| KeeperError::Decode("无法解析 decimals".to_string()))?;
+        let decimals = u8::try_from(decimals).map_err(|_| KeeperError::Contract(format!("decimals 超出范围: {}", decimals)))?;
+
+        tracing::info!("预言机 {:?} 报价精度: {} 位小数", oracle_address, decimals);
+        Ok(decimals)
+    }
+
+    /// 读取单个预言机的 latestRoundData
+    async fn read_oracle(&self, oracle_address: Address) -> KeeperResult<OracleAnswer> {
+        let request = web3::types::CallRequest {
+            to: Some(oracle_address),
+            data: Some(web3::types::Bytes(latest_round_data_calldata()?)),
+            ..Default::default()
+        };
+        let result = rpc_retry("eth_call", || self.web3.eth().call(request.clone(), None)).await?;
+        decode_latest_round_data(&result.0)
+    }
+}
+
+/// `latestRoundData()` 的调用数据
+pub fn latest_round_data_calldata() -> KeeperResult<Vec<u8>> {
+    let contract = get_contract()?;
+    Ok(contract.function("latestRoundData")?.encode_input(&[])?)
+}
+
+/// 解码 `latestRoundData()` 的返回数据：(roundId, answer, startedAt, updatedAt, answeredInRound)
+pub fn decode_latest_round_data(data: &[u8]) -> KeeperResult<OracleAnswer> {
+    let contract = get_contract()?;
+    let function = contract.function("latestRoundData")?;
+    let tokens = function.decode_output(data)?;
+    if tokens.len() < 5 {
+        return Err(KeeperError::Decode(format!("latestRoundData 返回字段数量不足: {}", tokens.len())));
+    }
+    let answer = tokens[1].clone()
+        .into_int()
+        .ok_or_else(|| KeeperError::Decode("无法将代币转换为整数".to_string()))?;
+
+    let uint_field = |index: usize, name: &str| {
+        tokens[index].clone()
+            .into_uint()
+            .ok_or_else(|| KeeperError::Decode(format!("无法解析 {}", name)))
+    };
+
+    let round_id = uint_field(0, "roundId")?;
+    Ok(OracleAnswer {
+        round_id,
+        price: positive_answer(answer, round_id)?,
+        updated_at: uint_field(3, "updatedAt")?.low_u64(),
+        answered_in_round: uint_field(4, "answeredInRound")?,
+    })
+}
+
+/// 配置的预言机地址：配置了 `oracle.addresses` 时使用列表，否则只使用 contracts.oracle
+pub fn oracle_addresses(config: &crate::config::AppConfig) -> KeeperResult<Vec<Address>> {
+    let parse_address = |address: &String| address.parse::<Address>()
+        .map_err(|e| KeeperError::Config(format!("无效的预言机地址 '{}': {}", address, e)));
+    if config.oracle.addresses.is_empty() {
+        Ok(vec![parse_address(&config.contracts.oracle)?])
+    } else {
+        config.oracle.addresses.iter()
+            .map(parse_address)
+            .collect()
+    }
+}
+
+/// 预言机推送的价格更新
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriceUpdate {
+    pub oracle: Address,
+    /// 预言机原始精度的报价
+    pub answer: U256,
+    pub round_id: U256,
+    pub updated_at: u64,
+}
+
+/// 解码 `AnswerUpdated` 或 `PriceUpdated` 日志，非正价格视为无效报价
+pub fn decode_price_update(log: &Log) -> KeeperResult<PriceUpdate> {
+    let topic0 = log.topics.first().copied().unwrap_or_default();
+    let (answer, round_id, updated_at) = if topic0 == event_abi::signature("AnswerUpdated")? {
+        let event = event_abi::decode_log("AnswerUpdated", log)?;
+        (event.int("current")?, event.uint("roundId")?, event.uint("updatedAt")?)
+    } else {
+        let event = event_abi::decode_log("PriceUpdated", log)?;
+        (event.int("price")?, event.uint("roundId")?, event.uint("timestamp")?)
+    };
+
+    Ok(PriceUpdate {
+        oracle: log.address,
+        answer: positive_answer(answer, round_id)?,
+        round_id,
+        updated_at: updated_at.low_u64(),
+    })
+}
+
+/// 订阅预言机价格更新事件，最新的更新写入 `updates`，直到收到关闭信号
+///
+/// WebSocket 断开后按指数退避重连；订阅不可用期间清算检查仍按定时间隔执行。
+pub async fn run_price_subscription(
+    ws_url: String,
+    oracles: Vec<Address>,
+    reconnect_base_delay_secs: u64,
+    updates: watch::Sender<Option<PriceUpdate>>,
+    shutdown: CancellationToken,
+) {
+    let topics = match ["AnswerUpdated", "PriceUpdated"].into_iter().map(event_abi::signature).collect::<KeeperResult<Vec<_>>>() {
+        Ok(topics) => topics,
+        Err(e) => {
+            tracing::error!("无法生成预言机事件签名，价格订阅未启动: {}", e);
+            return;
+        }
+    };
+    let filter = FilterBuilder::default()
+        .address(oracles.clone())
+        .topics(Some(topics), None, None, None)
+        .build();
+
+    let mut attempt = 0u32;
+    loop {
+        if attempt > 0 {
+            let delay = crate::events::reconnect_delay(reconnect_base_delay_secs, attempt);
+            tracing::info!("{:.1}秒后重新订阅预言机价格更新...", delay.as_secs_f64());
+            tokio::select! {
+                _ = shutdown.cancelled() => return,
+                _ = tokio::time::sleep(delay) => {}
+            }
+        }
+        attempt += 1;
+
+        let subscription = match web3::transports::WebSocket::new(&ws_url).await {
+            Ok(transport) => web3::Web3::new(transport).eth_subscribe().subscribe_logs(filter.clone()).await,
+            Err(e) => Err(e),
+        };
+        let mut logs = match subscription {
+            Ok(logs) => logs,
+            Err(e) => {
+                tracing::warn!("订阅预言机价格更新失败: {}", e);
+                continue;
+            }
+        };
+        tracing::info!("已订阅预言机价格更新 - 预言机: {:?}", oracles);
+        attempt = 1;
+
+        loop {
+            let log = tokio::select! {
+                _ = shutdown.cancelled() => return,
+                log = logs.next() => log,
+            };
+            match log {
+                // 链重组撤销的更新不触发检查
+                Some(Ok(log)) if log.removed == Some(true) => {}
+                Some(Ok(log)) => match decode_price_update(&log) {
+                    Ok(update) => {
+                        tracing::debug!("收到预言机价格推送 - 预言机: {:?}, 报价: {}, roundId: {}", update.oracle, update.answer, update.round_id);
+                        updates.send_replace(Some(update));
+                    }
+                    Err(e) => tracing::warn!("无法解码预言机价格推送: {}", e),
+                },
+                Some(Err(e)) => {
+                    tracing::warn!("预言机价格订阅错误: {}", e);
+                    break;
+                }
+                None => break,
+            }
+        }
+        tracing::warn!("预言机价格订阅已断开");
+    }
+}
+
+/// 单个预言机的 latestRoundData 读数
+pub struct OracleAnswer {
+    pub round_id: U256,
+    pub price: U256,
+    pub updated_at: u64,
+    pub answered_in_round: U256,
+}
+
+/// 按 Chainlink 的建议检查报价所在轮次
+///
+/// - `updatedAt` 为0：轮次尚未完成
+/// - `answeredInRound < roundId`：报价沿用自之前的轮次
+/// - 距 `updatedAt` 超过 `max_staleness` 秒（为0时不检查）：报价已过期
+fn validate_round(answer: &OracleAnswer, now: u64, max_staleness: u64) -> KeeperResult<()> {
+    if answer.updated_at == 0 {
+        return Err(KeeperError::Contract(format!("报价所在轮次尚未完成 (roundId: {}, updatedAt: 0)", answer.round_id)));
+    }
+    if answer.answered_in_round < answer.round_id {
+        return Err(KeeperError::Contract(format!(
+            "报价沿用自之前的轮次 (roundId: {}, answeredInRound: {})",
+            answer.round_id, answer.answered_in_round
+        )));
+    }
+    let age = now.saturating_sub(answer.updated_at);
+    if max_staleness > 0 && age > max_staleness {
+        return Err(KeeperError::Contract(format!(
+            "报价已过期 {} 秒（上限 {} 秒, roundId: {}）", age, max_staleness, answer.round_id
+        )));
+    }
+    Ok(())
+}
+
+/// 检查 int256 报价（补码表示）为正数
+///
+/// 负数或零报价说明预言机异常，不能取绝对值继续计算，否则会得到错误的净值并触发误清算。
+fn positive_answer(answer: U256, round_id: U256) -> KeeperResult<U256> {
+    // 补码的最高位为符号位
+    if answer.bit(255) {
+        return Err(KeeperError::Contract(format!("预言机返回了负价格 (roundId: {})", round_id)));
+    }
+    if answer.is_zero() {
+        return Err(KeeperError::Contract(format!("预言机返回了零价格 (roundId: {})", round_id)));
+    }
+    Ok(answer)
+}
+
+/// 将 `decimals` 位小数的报价换算为 NAV 计算使用的 `price_decimals` 位精度
+fn normalize_price(price: U256, decimals: u8, price_decimals: u8) -> KeeperResult<U256> {
+    if decimals <= price_decimals {
+        price.checked_mul(U256::exp10((price_decimals - decimals) as usize))
+            .ok_or_else(|| KeeperError::Contract(format!("报价 {} 换算为{}位精度时溢出", price, price_decimals)))
+    } else {
+        Ok(price / U256::exp10((decimals - price_decimals) as usize))
+    }
+}
+
+/// 聚合多个预言机报价：取中位数，并检查报价之间的偏差
+///
+/// 偏差 = (最高价 - 最低价) / 中位数，以基点计；`max_deviation_bps` 为0时不检查。
+fn aggregate_prices(mut prices: Vec<U256>, max_deviation_bps: u64) -> KeeperResult<U256> {
+    if prices.is_empty() {
+        return Err(KeeperError::Contract("没有可用的预言机报价（全部读取失败或已过期），跳过本轮计算".to_string()));
+    }
+
+    prices.sort();
+    let middle = prices.len() / 2;
+    let median = if prices.len() % 2 == 1 {
+        prices[middle]
+    } else {
+        (prices[middle - 1] + prices[middle]) / 2
+    };
+
+    if max_deviation_bps > 0 && prices.len() > 1 && !median.is_zero() {
+        let spread = prices[prices.len() - 1] - prices[0];
+        let deviation_bps = spread.saturating_mul(U256::from(10_000u64)) / median;
+        if deviation_bps > U256::from(max_deviation_bps) {
+            return Err(KeeperError::Contract(format!(
+                "预言机报价偏差 {} 基点超过上限 {} 基点 (报价: {:?})，跳过本轮计算",
+                deviation_bps, max_deviation_bps, prices
+            )));
+        }
+    }
+
+    Ok(median)
+}
+
+/// 预言机合约的ABI
+fn get_contract() -> KeeperResult<ethabi::Contract> {
+    let abi = r#"[
+        {
+            "name": "decimals",
+            "type": "function",
+            "stateMutability": "view",
+            "inputs": [],
+            "outputs": [{"type": "uint8", "name": ""}]
+        },
+        {
+            "name": "latestRoundData",
+            "type": "function",
+            "stateMutability": "view",
+            "inputs": [],
+            "outputs": [
+                {"type": "uint80", "name": "roundId"},
+                {"type": "int256", "name": "answer"},
+                {"type": "uint256", "name": "startedAt"},
+                {"type": "uint256", "name": "updatedAt"},
+                {"type": "uint80", "name": "answeredInRound"}
+            ]
+        }
+    ]"#;
+
+    let contract: ethabi::Contract = serde_json::from_str(abi)?;
+    Ok(contract)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wad(value: u64) -> U256 {
+        U256::from(value) * U256::exp10(18)
+    }
+
+    #[tokio::test]
+    async fn manual_price_bypasses_oracle_reads() {
+        let mut config = crate::config::AppConfig::default();
+        config.oracle.manual_price = Some(wad(75).to_string());
+        // 节点不可达：读取预言机必然失败，手动价格不应发出任何请求
+        let transport = FailoverTransport::new(&["http://127.0.0.1:1".to_string()], 3).unwrap();
+        let oracle = PriceOracle::new(web3::Web3::new(transport), &config, Arc::new(KeeperStatus::new())).unwrap();
+
+        assert_eq!(oracle.get_current_price().await.unwrap(), wad(75));
+        let update = PriceUpdate { oracle: Address::zero(), answer: U256::one(), round_id: U256::one(), updated_at: 0 };
+        assert_eq!(oracle.price_from_update(&update).await.unwrap(), Some(wad(75)));
+    }
+
+    #[test]
+    fn oracle_prices_aggregate_to_median() {
+        assert_eq!(aggregate_prices(vec![wad(3), wad(1), wad(2)], 0).unwrap(), wad(2));
+        assert_eq!(aggregate_prices(vec![wad(1), wad(3)], 0).unwrap(), wad(2));
+        assert!(aggregate_prices(vec![], 0).is_err());
+    }
+
+    #[test]
+    fn oracle_prices_are_normalized_to_18_decimals() {
+        // Chainlink 常见的8位小数报价：2000.5 美元
+        assert_eq!(normalize_price(U256::from(200_050_000_000u64), 8, 18).unwrap(), wad(20_005) / 10);
+        assert_eq!(normalize_price(wad(3), 18, 18).unwrap(), wad(3));
+        assert_eq!(normalize_price(wad(3) * 100, 20, 18).unwrap(), wad(3));
+        assert!(normalize_price(U256::MAX, 8, 18).is_err());
+        // 合约使用8位价格精度时，8位小数的报价不需要换算
+        assert_eq!(normalize_price(U256::from(200_050_000_000u64), 8, 8).unwrap(), U256::from(200_050_000_000u64));
+    }
+
+    #[test]
+    fn non_positive_answers_skip_the_cycle() {
+        let round_data = |answer: U256| ethabi::encode(&[
+            ethabi::Token::Uint(U256::from(5)),
+            ethabi::Token::Int(answer),
+            ethabi::Token::Uint(U256::from(1_700_000_000u64)),
+            ethabi::Token::Uint(U256::from(1_700_000_000u64)),
+            ethabi::Token::Uint(U256::from(5)),
+        ]);
+        assert_eq!(decode_latest_round_data(&round_data(wad(75))).unwrap().price, wad(75));
+
+        // -1（补码）不能被当作正价格使用
+        let negative = U256::MAX;
+        assert!(matches!(decode_latest_round_data(&round_data(negative)), Err(KeeperError::Contract(_))));
+        assert!(decode_latest_round_data(&round_data(U256::zero())).is_err());
+
+        // 唯一的预言机报价被剔除后没有可用报价，本轮检查被跳过
+        assert!(aggregate_prices(Vec::new(), 0).is_err());
+    }
+
+    #[test]
+    fn incomplete_carried_over_and_stale_rounds_are_rejected() {
+        let now = 1_700_000_000;
+        let answer = |round_id: u64, updated_at: u64, answered_in_round: u64| OracleAnswer {
+            round_id: U256::from(round_id),
+            price: wad(75),
+            updated_at,
+            answered_in_round: U256::from(answered_in_round),
+        };
+        let rejection = |answer: OracleAnswer, max_staleness: u64| match validate_round(&answer, now, max_staleness) {
+            Err(KeeperError::Contract(message)) => message,
+            other => panic!("报价应被剔除: {:?}", other.map(|_| ())),
+        };
+
+        assert!(validate_round(&answer(5, now - 60, 5), now, 3600).is_ok());
+        assert!(validate_round(&answer(5, now - 60, 6), now, 3600).is_ok());
+
+        assert!(rejection(answer(5, 0, 5), 0).contains("尚未完成"));
+        assert!(rejection(answer(5, now, 4), 3600).contains("之前的轮次"));
+        assert!(rejection(answer(5, now - 3601, 5), 3600).contains("已过期"));
+        // 上限为0时不检查过期
+        assert!(validate_round(&answer(5, now - 3601, 5), now, 0).is_ok());
+    }
+
+    #[test]
+    fn price_updates_are_decoded_from_both_event_layouts() {
+        let oracle = Address::repeat_byte(0x0c);
+        let log = |topics: Vec<web3::types::H256>, data: Vec<u8>| Log {
+            address: oracle,
+            topics,
+            data: web3::types::Bytes(data),
+            block_hash: None,
+            block_number: None,
+            transaction_hash: None,
+            transaction_index: None,
+            log_index: None,
+            transaction_log_index: None,
+            log_type: None,
+            removed: None,
+        };
+        let word = |value: U256| {
+            let mut bytes = [0u8; 32];
+            value.to_big_endian(&mut bytes);
+            web3::types::H256::from(bytes)
+        };
+        let encode = |tokens: &[ethabi::Token]| ethabi::encode(tokens);
+
+        // AnswerUpdated(int256 indexed current, uint256 indexed roundId, uint256 updatedAt)
+        let answer_updated = log(
+            vec![event_abi::signature("AnswerUpdated").unwrap(), word(U256::from(200_050_000_000u64)), word(U256::from(7))],
+            encode(&[ethabi::Token::Uint(U256::from(1_700_000_000u64))]),
+        );
+        assert_eq!(decode_price_update(&answer_updated).unwrap(), PriceUpdate {
+            oracle,
+            answer: U256::from(200_050_000_000u64),
+            round_id: U256::from(7),
+            updated_at: 1_700_000_000,
+        });
+
+        // PriceUpdated(uint80 indexed roundId, int256 price, uint256 timestamp, address indexed updater)
+        let price_updated = |price: U256| log(
+            vec![event_abi::signature("PriceUpdated").unwrap(), word(U256::from(8)), web3::types::H256::from(Address::repeat_byte(0x22))],
+            encode(&[ethabi::Token::Int(price), ethabi::Token::Uint(U256::from(1_700_000_060u64))]),
+        );
+        let update = decode_price_update(&price_updated(wad(75))).unwrap();
+        assert_eq!((update.answer, update.round_id, update.updated_at), (wad(75), U256::from(8), 1_700_000_060));
+
+        // 负价格（补码）被拒绝
+        assert!(decode_price_update(&price_updated(U256::MAX)).is_err());
+    }
+
+    #[test]
+    fn oracle_prices_rejected_when_deviation_too_large() {
+        // (102 - 100) / 101 ≈ 198 基点
+        assert!(aggregate_prices(vec![wad(100), wad(101), wad(102)], 200).is_ok());
+        assert!(aggregate_prices(vec![wad(100), wad(101), wad(102)], 100).is_err());
+    }
+}
@@ -1,26 +1,47 @@
 //! 拍卖重置监控模块
 //!
-//! 精准监控拍卖生命周期，根据价格计算公式，在拍卖价格降至下界时自动触发重置。
+//! 精准监控拍卖生命周期，按 AuctionManager 的重置条件（进行时间超过 `resetTime`，或按价格曲线计算的价格
+//! 与起始价格之比低于 `priceDropThreshold`）在满足条件时自动触发重置。价格曲线（abacus）按配置选择。
 //!
 //! ## 核心机制：
 //! - 监听新拍卖创建事件
-//! - 根据起始价格和价格下界计算重置时刻
+//! - 根据起始价格、重置条件和拍卖已进行的时间计算重置时刻（重启后或处理较早开始的拍卖时只等待剩余时间）
 //! - 精确定时触发拍卖重置
-//! - 如果拍卖提前结束，自动取消重置任务
+//! - 如果拍卖提前结束，取消重置任务（通过任务自身的取消令牌中止等待中的任务）
+//! - 同一拍卖重新计划重置时（AuctionReset），取消之前的任务
+//! - Keeper 关闭时放弃尚未执行的重置任务，不在关闭过程中发送交易；已发送的重置交易等待确认后结束（`wait_for_tasks`）
+//! - 拍卖断路器触发期间不计划也不执行重置，解除后为仍在进行的拍卖重新计划
+//! - 重置交易确认后发送运维通知
+//! - 拍卖价值低于 `minAuctionAmount` 时（重置不发放奖励，只消耗 gas）不计划也不执行重置
+//! - 定期检查所有拍卖，已满足重置条件、却没有待处理重置任务的拍卖
+//!   （重启后内存中的任务丢失，或遗漏了事件）记录告警并立即重置
+//! - 重置交易发出后到 AuctionReset 事件更新拍卖记录之前，拍卖标记为重置中，检查时跳过，不重复发送
 
 use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 use web3::types::{Address, U256};
 use web3::ethabi;
+use tokio::task::JoinSet;
 use tokio::time::{Duration, Instant};
-use crate::database::Database;
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
+use crate::config::PriceCurve;
+use crate::database::{AuctionInfo, Database, SystemParams};
+use crate::notify::{Notification, Notifier};
+use crate::tx::TransactionSender;
+use crate::metrics::metrics;
+use crate::status::unix_now;
+
+/// 已发送、尚未生效的重置交易（拍卖ID -> 发送时拍卖记录的开始时间）
+type ResetsInFlight = Arc<RwLock<HashMap<U256, u64>>>;
 
 /// 拍卖重置任务
 #[derive(Debug, Clone)]
 struct AuctionResetTask {
     auction_id: U256,
     reset_time: Instant,
-    _handle_abort: tokio_util::sync::CancellationToken,
+    /// 取消令牌：拍卖结束或被重新计划时触发，中止等待中的任务
+    cancel: CancellationToken,
 }
 
 impl AuctionResetTask {
@@ -28,81 +49,112 @@ impl AuctionResetTask {
         Self {
             auction_id,
             reset_time,
-            _handle_abort: tokio_util::sync::CancellationToken::new(),
+            cancel: CancellationToken::new(),
         }
     }
 }
 
-/// 拍卖重置监控器
+/// 拍卖重置监控器（克隆后共享待处理的重置任务）
+#[derive(Clone)]
 pub struct AuctionResetMonitor {
-    web3: web3::Web3<web3::transports::Http>,
     database: Arc<Database>,
+    tx_sender: Arc<TransactionSender>,
     auction_manager_address: Address,
+    /// AuctionManager 使用的价格曲线
+    price_curve: PriceCurve,
     pending_resets: Arc<RwLock<HashMap<U256, AuctionResetTask>>>,
+    /// 重置中的拍卖：AuctionReset 事件更新拍卖的开始时间后标记失效，交易失败时立即移除
+    resets_in_flight: ResetsInFlight,
+    /// 已启动的重置任务，关闭时等待执行中的任务结束
+    tasks: Arc<Mutex<JoinSet<()>>>,
+    /// Keeper 关闭信号
+    shutdown: CancellationToken,
+    /// 运维通知
+    notifier: Arc<dyn Notifier>,
 }
 
 impl AuctionResetMonitor {
     pub fn new(
-        web3: web3::Web3<web3::transports::Http>,
         database: Arc<Database>,
+        tx_sender: Arc<TransactionSender>,
         auction_manager_address: String,
+        price_curve: PriceCurve,
+        shutdown: CancellationToken,
+        notifier: Arc<dyn Notifier>,
     ) -> anyhow::Result<Self> {
         let auction_manager = auction_manager_address.parse::<Address>()?;
 
         Ok(Self {
-            web3,
             database,
+            tx_sender,
             auction_manager_address: auction_manager,
+            price_curve,
             pending_resets: Arc::new(RwLock::new(HashMap::new())),
+            resets_in_flight: Arc::new(RwLock::new(HashMap::new())),
+            tasks: Arc::new(Mutex::new(JoinSet::new())),
+            shutdown,
+            notifier,
         })
     }
 
-    /// 添加新拍卖的重置任务
+    /// 添加新拍卖的重置任务，按拍卖（最近一次重置后）的起始价格和开始时间计算重置时刻；
+    /// 拍卖价值低于 minAuctionAmount 时不计划，并取消之前计划的任务
     /// 注意：此函数在AuctionStarted事件立即调用，此时拍卖记录必定存在，无需检查
-    pub async fn schedule_auction_reset(
-        &self,
-        auction_id: U256,
-        starting_price: U256,
-    ) -> anyhow::Result<()> {
+    pub async fn schedule_auction_reset(&self, auction: &AuctionInfo) -> anyhow::Result<()> {
+        let auction_id = auction.auction_id;
+        let starting_price = auction.starting_price;
+
         // 从数据库获取系统参数
         let system_params = self.database.get_system_params()?;
+        if system_params.circuit_breaker {
+            tracing::info!("拍卖断路器已触发，暂不为拍卖 {} 计划重置", auction_id);
+            return Ok(());
+        }
+        if is_dust_auction(auction, &system_params) {
+            tracing::debug!("拍卖 {} 价值低于 minAuctionAmount，不计划重置 (标的数量: {})", auction_id, auction.underlying_amount);
+            // 重置后标的数量可能减少，之前计划的任务已不再适用
+            self.cancel_auction_reset(&auction_id);
+            return Ok(());
+        }
         let price_drop_threshold = system_params.price_drop_threshold;
-        let reset_time = system_params.reset_time;
 
-        // 计算达到价格下界所需的时间
+        // 计算满足重置条件还需要的时间（扣除拍卖已进行的时间）
+        let elapsed_secs = unix_now().saturating_sub(auction.start_time);
         let reset_duration_secs = calculate_reset_duration(
+            self.price_curve,
             starting_price,
-            price_drop_threshold,
-            reset_time
+            &system_params,
+            elapsed_secs,
         );
 
-        if reset_duration_secs == 0 {
-            tracing::info!("拍卖 {} 已经达到价格下界，需要立即重置", auction_id);
-            // 立即执行重置
-            if let Err(e) = self.execute_auction_reset(auction_id).await {
-                tracing::error!("立即重置拍卖 {} 失败: {}", auction_id, e);
-            }
+        if reset_duration_secs == 0 && reset_in_flight(&self.resets_in_flight, auction) {
+            tracing::debug!("拍卖 {} 的重置交易已发送，等待 AuctionReset 事件", auction_id);
             return Ok(());
         }
 
+        // 已满足重置条件时重置时刻即为现在，同样由任务执行，不阻塞事件处理
         let reset_instant = Instant::now() + Duration::from_secs(reset_duration_secs);
 
         tracing::info!(
-            "为拍卖 {} 计划重置任务 - {} 秒后重置 (起始价格: {}, 阈值: {})",
-            auction_id, reset_duration_secs, starting_price, price_drop_threshold
+            "为拍卖 {} 计划重置任务 - {} 秒后重置 (起始价格: {}, 阈值: {}, 已进行: {} 秒)",
+            auction_id, reset_duration_secs, starting_price, price_drop_threshold, elapsed_secs
         );
 
         // 创建重置任务并记录到pending_reset映射中
         let task = AuctionResetTask::new(auction_id, reset_instant);
 
-        // 添加到待处理任务映射，以便将来可以取消
+        // 添加到待处理任务映射，以便将来可以取消；同一拍卖之前的任务已过时，一并取消
         if let Ok(mut pending_resets) = self.pending_resets.write() {
-            pending_resets.insert(auction_id, task.clone());
+            if let Some(previous) = pending_resets.insert(auction_id, task.clone()) {
+                previous.cancel.cancel();
+                tracing::debug!("拍卖 {} 重新计划重置，取消之前的任务", auction_id);
+            }
             tracing::debug!("拍卖 {} 重置任务已添加到待处理映射", auction_id);
         }
 
         // 启动异步任务执行重置
         self.start_reset_task(task);
+        metrics().auctions.with_label_values(&["scheduled"]).inc();
 
         Ok(())
     }
@@ -111,30 +163,45 @@ impl AuctionResetMonitor {
     fn start_reset_task(&self, task: AuctionResetTask) {
         let auction_id = task.auction_id;
         let reset_time = task.reset_time;
-        let web3 = self.web3.clone();
+        let cancel = task.cancel;
+        let pending_resets = self.pending_resets.clone();
         let database = self.database.clone();
+        let tx_sender = self.tx_sender.clone();
         let auction_manager_address = self.auction_manager_address;
+        let shutdown = self.shutdown.clone();
+        let notifier = self.notifier.clone();
+        let resets_in_flight = self.resets_in_flight.clone();
+
+        let reset_task = async move {
+            tokio::select! {
+                _ = shutdown.cancelled() => {
+                    tracing::info!("Keeper 正在关闭，放弃拍卖 {} 的重置任务", auction_id);
+                    return;
+                }
+                _ = cancel.cancelled() => {
+                    tracing::debug!("拍卖 {} 的重置任务已取消", auction_id);
+                    return;
+                }
+                _ = tokio::time::sleep_until(reset_time) => {}
+            }
 
-        tokio::spawn(async move {
-            let now = Instant::now();
-            if now < reset_time {
-                tokio::time::sleep_until(reset_time).await;
+            // 重置时刻已到，从待处理映射中移除本任务（映射中已是更新的任务时保留）
+            if let Ok(mut pending) = pending_resets.write() {
+                if pending.get(&auction_id).is_some_and(|task| task.reset_time == reset_time) {
+                    pending.remove(&auction_id);
+                }
             }
 
-            // 重置时刻已到，检查拍卖记录是否还存在
+            // 取消事件和重置时刻同时到达时，数据库中的记录作为兜底检查
             match database.auction_exists(auction_id) {
+                Ok(true) if database.get_system_params().is_ok_and(|params| params.circuit_breaker) => {
+                    tracing::info!("拍卖断路器已触发，跳过拍卖 {} 的重置，解除后重新计划", auction_id);
+                }
                 Ok(true) => {
                     // 拍卖还存在，执行重置
                     tracing::info!("拍卖 {} 重置时刻已到，执行重置", auction_id);
 
-                    let reset_monitor = AuctionResetMonitor {
-                        web3,
-                        database,
-                        auction_manager_address,
-                        pending_resets: Arc::new(RwLock::new(HashMap::new())),
-                    };
-
-                    if let Err(e) = reset_monitor.execute_auction_reset(auction_id).await {
+                    if let Err(e) = execute_auction_reset(&database, &tx_sender, auction_manager_address, &shutdown, notifier.as_ref(), &resets_in_flight, auction_id).await {
                         tracing::error!("重置拍卖 {} 失败: {}", auction_id, e);
                     }
                 }
@@ -146,133 +213,533 @@ impl AuctionResetMonitor {
                     tracing::error!("检查拍卖 {} 状态失败: {}", auction_id, e);
                 }
             }
-        });
+        }.instrument(tracing::info_span!("auction_reset_task", auction_id = %auction_id));
+
+        match self.tasks.lock() {
+            Ok(mut tasks) => {
+                // 顺便回收已结束的任务
+                while tasks.try_join_next().is_some() {}
+                tasks.spawn(reset_task);
+            }
+            Err(_) => {
+                tokio::spawn(reset_task);
+            }
+        }
+    }
+
+    /// 等待已启动的重置任务结束（关闭时调用，应先触发关闭信号）
+    ///
+    /// 等待中的任务收到关闭信号后立即放弃；已发送交易的任务等待确认并记录结果后结束。
+    pub async fn wait_for_tasks(&self) {
+        let mut tasks = self.tasks.lock()
+            .map(|mut tasks| std::mem::take(&mut *tasks))
+            .unwrap_or_default();
+        while tasks.join_next().await.is_some() {}
     }
 
-    /// 执行拍卖重置
-    async fn execute_auction_reset(&self, auction_id: U256) -> anyhow::Result<()> {
-        // 获取Keeper地址
-        let keeper_address = web3::types::Address::from_low_u64_be(0x123456789abcdef);
-
-        // 创建resetAuction函数调用数据
-        let function_abi = r#"
-            {
-                "name": "resetAuction",
-                "type": "function",
-                "stateMutability": "nonpayable",
-                "inputs": [
-                    {"type": "uint256", "name": "auctionId"},
-                    {"type": "address", "name": "triggerer"}
-                ],
-                "outputs": []
+    /// 为数据库中没有待处理重置任务的拍卖重新计划重置（拍卖断路器解除后调用）
+    pub async fn resume_pending_resets(&self) -> anyhow::Result<usize> {
+        let mut resumed = 0;
+        for auction in self.database.get_all_auctions()? {
+            let pending = self.pending_resets.read()
+                .map(|pending_resets| pending_resets.contains_key(&auction.auction_id))
+                .unwrap_or(false);
+            if pending || reset_in_flight(&self.resets_in_flight, &auction) {
+                continue;
             }
-        "#;
-
-        let contract: ethabi::Contract = serde_json::from_str(&format!(r#"[{}]"#, function_abi))?;
-        let function = contract.function("resetAuction")?;
-        let data = function.encode_input(&[
-            ethabi::Token::Uint(auction_id),
-            ethabi::Token::Address(keeper_address),
-        ])?;
-
-        // 构建交易
-        let accounts = self.web3.eth().accounts().await?;
-        if accounts.is_empty() {
-            return Err(anyhow::anyhow!("No available accounts for transaction"));
+            self.schedule_auction_reset(&auction).await?;
+            resumed += 1;
         }
+        Ok(resumed)
+    }
 
-        let tx = web3::types::TransactionRequest {
-            from: accounts[0],
-            to: Some(self.auction_manager_address),
-            data: Some(web3::types::Bytes(data)),
-            ..Default::default()
-        };
+    /// 定期检查遗漏了重置任务的拍卖，`interval_secs` 为0时不检查
+    ///
+    /// 第一次检查推迟一个间隔，留出启动时历史同步的时间。
+    pub async fn run_orphan_sweep(self, interval_secs: u64) {
+        if interval_secs == 0 {
+            return;
+        }
 
-        // 发送交易
-        let tx_hash = self.web3.eth().send_transaction(tx).await?;
-        tracing::info!("拍卖重置交易已发送: {:?}, 拍卖ID: {}", tx_hash, auction_id);
+        let period = Duration::from_secs(interval_secs);
+        let mut interval = tokio::time::interval_at(Instant::now() + period, period);
+        loop {
+            tokio::select! {
+                _ = self.shutdown.cancelled() => return,
+                _ = interval.tick() => {}
+            }
 
-        // 等待交易确认 - 新的auction信息会由events.rs处理
-        let receipt = self.web3.eth().transaction_receipt(tx_hash).await?;
-        match receipt {
-            Some(_) => Ok(()),
-            None => Err(anyhow::anyhow!("拍卖重置交易未确认")),
+            match self.sweep_orphaned_auctions().await {
+                Ok(0) => tracing::debug!("没有遗漏重置任务的拍卖"),
+                Ok(count) => tracing::warn!("发现并重置了 {} 个遗漏重置任务的拍卖", count),
+                Err(e) => tracing::error!("检查遗漏的拍卖重置失败: {}", e),
+            }
+        }
+    }
+
+    /// 找出已满足重置条件、但没有待处理重置任务的拍卖并立即重置，返回发现的数量
+    pub async fn sweep_orphaned_auctions(&self) -> anyhow::Result<usize> {
+        let system_params = self.database.get_system_params()?;
+        if system_params.circuit_breaker {
+            tracing::debug!("拍卖断路器已触发，跳过遗漏重置检查");
+            return Ok(0);
+        }
+
+        let now = unix_now();
+        let mut orphaned = 0;
+        for auction in self.database.get_all_auctions()? {
+            let pending = self.pending_resets.read()
+                .map(|pending_resets| pending_resets.contains_key(&auction.auction_id))
+                .unwrap_or(false);
+            if pending || !is_past_reset_threshold(&auction, &system_params, self.price_curve, now) {
+                continue;
+            }
+            if reset_in_flight(&self.resets_in_flight, &auction) {
+                tracing::debug!("拍卖 {} 的重置交易已发送，等待 AuctionReset 事件", auction.auction_id);
+                continue;
+            }
+            if is_dust_auction(&auction, &system_params) {
+                tracing::debug!("拍卖 {} 价值低于 minAuctionAmount，跳过重置", auction.auction_id);
+                continue;
+            }
+
+            orphaned += 1;
+            metrics().auctions.with_label_values(&["orphaned"]).inc();
+            tracing::warn!(
+                "拍卖 {} 已满足重置条件但没有待处理的重置任务，立即重置 - 起始价格: {}, 当前价格: {}, 已进行 {} 秒",
+                auction.auction_id,
+                auction.starting_price,
+                curve_price(self.price_curve, auction.starting_price, now.saturating_sub(auction.start_time)),
+                now.saturating_sub(auction.start_time)
+            );
+            if let Err(e) = execute_auction_reset(&self.database, &self.tx_sender, self.auction_manager_address, &self.shutdown, self.notifier.as_ref(), &self.resets_in_flight, auction.auction_id).await {
+                tracing::error!("重置遗漏的拍卖 {} 失败: {}", auction.auction_id, e);
+            }
         }
+        Ok(orphaned)
     }
 
     /// 取消拍卖重置任务（当拍卖被移除时调用）
     pub fn cancel_auction_reset(&self, auction_id: &U256) {
         if let Ok(mut pending_resets) = self.pending_resets.write() {
-            if pending_resets.remove(auction_id).is_some() {
+            if let Some(task) = pending_resets.remove(auction_id) {
+                task.cancel.cancel();
                 tracing::debug!("取消了拍卖 {} 的重置任务", auction_id);
             }
         }
     }
 }
 
-/// 计算从起始价格降至价格下界所需的时间（秒）
-/// 精确模拟Solidity LinearDecrease合约的price函数：
-/// price(current_time) = starting_price * (tau - elapsed) / tau
-/// 当price <= starting_price * price_drop_threshold时需要重置
+/// 拍卖是否有已发送、尚未生效的重置交易；AuctionReset 事件已更新开始时间的标记顺便清除
+fn reset_in_flight(resets_in_flight: &ResetsInFlight, auction: &AuctionInfo) -> bool {
+    let Ok(mut in_flight) = resets_in_flight.write() else {
+        return false;
+    };
+    match in_flight.get(&auction.auction_id) {
+        Some(start_time) if *start_time == auction.start_time => true,
+        Some(_) => {
+            in_flight.remove(&auction.auction_id);
+            false
+        }
+        None => false,
+    }
+}
+
+/// 执行拍卖重置：发送 resetAuction 交易，确认后发送运维通知
 ///
-/// 参数：
-/// - starting_price: 拍卖起始价格 (WAD精度)
-/// - price_drop_threshold: 价格下界比例 (WAD精度, 如0.5 * 1e18表示50%)
-/// - reset_time(tau): 从开始到价格为0所需的总时间 (秒)
+/// 遗漏重置检查和计划任务共用，计划任务中直接调用，不需要构造监控器实例。
+/// 发送前把拍卖标记为重置中，交易失败或确认超时时移除标记。
+#[tracing::instrument(name = "auction_reset", skip_all, fields(auction_id = %auction_id))]
+async fn execute_auction_reset(
+    database: &Database,
+    tx_sender: &TransactionSender,
+    auction_manager_address: Address,
+    shutdown: &CancellationToken,
+    notifier: &dyn Notifier,
+    resets_in_flight: &ResetsInFlight,
+    auction_id: U256,
+) -> anyhow::Result<()> {
+    if shutdown.is_cancelled() {
+        tracing::info!("Keeper 正在关闭，跳过拍卖 {} 的重置", auction_id);
+        return Ok(());
+    }
+
+    // 重新检查当前记录：计划之后拍卖可能已被部分成交，剩余价值不足时重置不发放奖励
+    let current = database.get_auction(auction_id)?;
+    if let Some(auction) = &current {
+        if is_dust_auction(auction, &database.get_system_params()?) {
+            tracing::debug!("拍卖 {} 剩余价值低于 minAuctionAmount，跳过重置 (标的数量: {})", auction_id, auction.underlying_amount);
+            return Ok(());
+        }
+    }
+
+    // 获取Keeper地址（由私钥推导，未配置私钥时为节点账户）
+    let keeper_address = tx_sender.keeper_address().await?;
+
+    // 创建resetAuction函数调用数据
+    let function_abi = r#"
+        {
+            "name": "resetAuction",
+            "type": "function",
+            "stateMutability": "nonpayable",
+            "inputs": [
+                {"type": "uint256", "name": "auctionId"},
+                {"type": "address", "name": "triggerer"}
+            ],
+            "outputs": []
+        }
+    "#;
+
+    let contract: ethabi::Contract = serde_json::from_str(&format!(r#"[{}]"#, function_abi))?;
+    let function = contract.function("resetAuction")?;
+    let data = function.encode_input(&[
+        ethabi::Token::Uint(auction_id),
+        ethabi::Token::Address(keeper_address),
+    ])?;
+
+    if tx_sender.is_dry_run() {
+        tx_sender.log_dry_run(
+            auction_manager_address,
+            &format!("resetAuction(auctionId={}, triggerer={:?})", auction_id, keeper_address),
+            &data,
+        );
+        metrics().auctions.with_label_values(&["would_reset"]).inc();
+        return Ok(());
+    }
+
+    // 交易生效前（AuctionReset 事件更新拍卖的开始时间前）遗漏重置检查跳过该拍卖
+    if let (Some(auction), Ok(mut in_flight)) = (&current, resets_in_flight.write()) {
+        in_flight.insert(auction_id, auction.start_time);
+    }
+
+    let sent = async {
+        // 发送交易
+        let tx_hash = tx_sender.send(auction_manager_address, data).await?;
+        tracing::info!("拍卖重置交易已发送: {:?}, 拍卖ID: {}", tx_hash, auction_id);
+
+        // 等待交易确认 - 新的auction信息会由events.rs处理
+        tx_sender.wait_for_confirmations(tx_hash).await?;
+        anyhow::Ok(tx_hash)
+    }.await;
+    let tx_hash = match sent {
+        Ok(tx_hash) => tx_hash,
+        Err(e) => {
+            // 交易没有生效（或无法确认），允许之后重新重置
+            if let Ok(mut in_flight) = resets_in_flight.write() {
+                in_flight.remove(&auction_id);
+            }
+            return Err(e);
+        }
+    };
+    metrics().auctions.with_label_values(&["reset"]).inc();
+
+    // 通知中附带重置前的拍卖记录（AuctionReset 事件可能已先更新了起始价格）
+    let auction = database.get_auction(auction_id).unwrap_or_else(|e| {
+        tracing::warn!("读取拍卖 {} 记录失败: {}", auction_id, e);
+        None
+    });
+    notifier.notify(Notification::AuctionReset {
+        tx_hash,
+        auction_id,
+        token_id: auction.as_ref().map(|auction| auction.token_id),
+        original_owner: auction.as_ref().map(|auction| auction.original_owner),
+        starting_price: auction.as_ref().map(|auction| auction.starting_price),
+        underlying_amount: auction.as_ref().map(|auction| auction.underlying_amount),
+    });
+    Ok(())
+}
+
+fn wad() -> U256 {
+    U256::exp10(18)
+}
+
+/// 按配置的价格曲线（AuctionManager 的 priceCalculator）计算拍卖进行 `elapsed` 秒后的价格，与合约的整数运算一致
+fn curve_price(curve: PriceCurve, starting_price: U256, elapsed: u64) -> U256 {
+    match curve {
+        // LinearDecrease.price：wmul(top, mul(tau - dur, WAD) / tau)，到达 tau 后为0
+        PriceCurve::Linear { tau } => {
+            if elapsed >= tau {
+                return U256::zero();
+            }
+            let ratio = U256::from(tau - elapsed) * wad() / U256::from(tau);
+            starting_price.saturating_mul(ratio) / wad()
+        }
+        // StairstepExponentialDecrease.price：wmul(top, wpow(cut, dur / step, WAD))
+        PriceCurve::StairstepExponential { step, cut } => {
+            starting_price.saturating_mul(wpow(U256::from(cut), elapsed / step.max(1))) / wad()
+        }
+        // ExponentialDecrease.price：wmul(top, wpow(cut, dur, WAD))
+        PriceCurve::Exponential { cut } => starting_price.saturating_mul(wpow(U256::from(cut), elapsed)) / wad(),
+    }
+}
+
+/// abaci.sol 的 wpow：`x^n`（18位精度），每次乘法后四舍五入，与合约的舍入一致
+fn wpow(x: U256, n: u64) -> U256 {
+    if n == 0 {
+        return wad();
+    }
+    if x.is_zero() {
+        return U256::zero();
+    }
+
+    let half = wad() / 2;
+    let (mut x, mut n) = (x, n);
+    let mut z = if n % 2 == 0 { wad() } else { x };
+    n /= 2;
+    while n > 0 {
+        x = (x.saturating_mul(x) + half) / wad();
+        if n % 2 == 1 {
+            z = (z.saturating_mul(x) + half) / wad();
+        }
+        n /= 2;
+    }
+    z
+}
+
+/// AuctionManager.checkAuctionStatus 的重置条件：
+/// 进行时间超过 `resetTime`，或当前价格与起始价格之比（wdiv）低于 `priceDropThreshold`
+fn needs_reset(curve: PriceCurve, starting_price: U256, system_params: &SystemParams, elapsed: u64) -> bool {
+    if starting_price.is_zero() {
+        return true; // 无效起始价格，立即重置
+    }
+    if U256::from(elapsed) > system_params.reset_time {
+        return true;
+    }
+    let price = curve_price(curve, starting_price, elapsed);
+    price.saturating_mul(wad()) / starting_price < system_params.price_drop_threshold
+}
+
+/// 拍卖价值是否低于 `minAuctionAmount`（AuctionManager 只在 `wmul(underlyingAmount, price) >= minAuctionAmount` 时发放重置奖励）
+///
+/// 价格按起始价格反推：`startingPrice = wmul(price, priceMultiplier)`；priceMultiplier 为0时无法估算，不视为小额拍卖。
+fn is_dust_auction(auction: &AuctionInfo, system_params: &SystemParams) -> bool {
+    if system_params.min_auction_amount.is_zero() || system_params.price_multiplier.is_zero() {
+        return false;
+    }
+    let price = auction.starting_price.saturating_mul(wad()) / system_params.price_multiplier;
+    auction.underlying_amount.saturating_mul(price) / wad() < system_params.min_auction_amount
+}
+
+/// 拍卖在 `now` 时是否已满足重置条件
+fn is_past_reset_threshold(auction: &AuctionInfo, system_params: &SystemParams, curve: PriceCurve, now: u64) -> bool {
+    needs_reset(curve, auction.starting_price, system_params, now.saturating_sub(auction.start_time))
+}
+
+/// 计算拍卖已进行 `elapsed_secs` 秒后，距离满足重置条件还需要的时间（秒）
+///
+/// 所有价格曲线都随时间单调不增，重置条件一旦满足就一直满足，因此对各曲线都用合约的整数运算二分查找
+/// 最早满足条件的时刻（与链上判断完全一致，不受对数等近似计算的误差影响）；最迟在 `resetTime + 1` 秒时满足。
 ///
-/// 返回：需要等待的时间（秒）, 0表示立即重置
+/// 返回：还需要等待的时间（秒）, 0表示立即重置（包括已经越过价格下界）
 fn calculate_reset_duration(
+    curve: PriceCurve,
     starting_price: U256,
-    price_drop_threshold: U256,
-    reset_time: U256,  // tau in solidity contract
+    system_params: &SystemParams,
+    elapsed_secs: u64,
 ) -> u64 {
-    if starting_price == U256::zero() {
-        return 0; // 无效起始价格，立即重置
+    let deadline = if system_params.reset_time >= U256::from(u64::MAX) {
+        u64::MAX
+    } else {
+        system_params.reset_time.as_u64() + 1
+    };
+
+    // 最早满足重置条件的时刻在 [low, high] 之间
+    let (mut low, mut high) = (0u64, deadline);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if needs_reset(curve, starting_price, system_params, mid) {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
     }
 
-    let reset_time_u64 = reset_time.as_u64();
-    if reset_time_u64 == 0 {
-        return 0; // 无效重置时间，立即重置
+    low.saturating_sub(elapsed_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reset_follows_auction_manager_conditions() {
+        let wad = U256::exp10(18);
+        let curve = PriceCurve::Linear { tau: 1_000 };
+        let system_params = SystemParams {
+            reset_time: U256::from(600),
+            price_drop_threshold: wad / 2, // 50%
+            ..Default::default()
+        };
+        let auction = AuctionInfo {
+            auction_id: U256::from(1),
+            starting_price: U256::from(2_000) * wad,
+            underlying_amount: wad,
+            original_owner: Address::repeat_byte(0x11),
+            token_id: U256::from(7),
+            triggerer: Address::repeat_byte(0x22),
+            reward_amount: U256::zero(),
+            start_time: 1_700_000_000,
+            epoch: 0,
+        };
+
+        assert_eq!(curve_price(curve, auction.starting_price, 250), U256::from(1_500) * wad);
+        assert_eq!(curve_price(curve, auction.starting_price, 1_000), U256::zero());
+
+        // 价格比例在 500 秒时等于 50%，严格低于阈值要到 501 秒；已进行的时间从等待时间中扣除
+        let duration = |elapsed| calculate_reset_duration(curve, auction.starting_price, &system_params, elapsed);
+        assert_eq!(duration(0), 501);
+        assert_eq!(duration(200), 301);
+        assert_eq!(duration(501), 0);
+        assert!(!is_past_reset_threshold(&auction, &system_params, curve, auction.start_time + 500));
+        assert!(is_past_reset_threshold(&auction, &system_params, curve, auction.start_time + 501));
+
+        // 价格曲线下降较慢时，进行时间超过 resetTime 后重置
+        let slow = PriceCurve::Linear { tau: 10_000 };
+        assert_eq!(calculate_reset_duration(slow, auction.starting_price, &system_params, 0), 601);
     }
 
-    // 价格下界 = 起始价格 * 阈值比例
-    // 由于都是WAD精度(1e18)，直接相乘
-    let precision = U256::from(1_000_000_000_000_000_000_u64); // WAD = 1e18
-    let threshold_price = starting_price
-        .saturating_mul(price_drop_threshold)
-        .checked_div(precision)
-        .unwrap_or(U256::zero());
-
-    // 如果阈值价格 >= 起始价格（异常情况），立即重置
-    if threshold_price >= starting_price {
-        return 0;
+    #[test]
+    fn exponential_curves_match_abaci_reference_prices() {
+        let wad = U256::exp10(18);
+        let top = U256::from(1_000) * wad;
+        let price = |curve, elapsed| curve_price(curve, top, elapsed);
+
+        // 参考值按 abaci.sol 的 wmul/wpow 整数运算计算
+        let stairstep = PriceCurve::StairstepExponential { step: 60, cut: 990_000_000_000_000_000 };
+        assert_eq!(price(stairstep, 59), top);
+        assert_eq!(price(stairstep, 60), U256::from(990) * wad);
+        assert_eq!(price(stairstep, 120), U256::from_dec_str("980100000000000000000").unwrap());
+        assert_eq!(price(stairstep, 600), U256::from_dec_str("904382075008804490000").unwrap());
+
+        let exponential = PriceCurve::Exponential { cut: 999_000_000_000_000_000 };
+        assert_eq!(price(exponential, 0), top);
+        assert_eq!(price(exponential, 1), U256::from(999) * wad);
+        assert_eq!(price(exponential, 10), U256::from_dec_str("990044880209748210000").unwrap());
+        assert_eq!(price(exponential, 100), U256::from_dec_str("904792147113709043000").unwrap());
+        assert_eq!(price(exponential, 693), U256::from_dec_str("499900234647727981000").unwrap());
+        assert_eq!(price(exponential, 3600), U256::from_dec_str("27274551230723194000").unwrap());
+
+        let system_params = SystemParams {
+            reset_time: U256::from(7200),
+            price_drop_threshold: wad / 2, // 50%
+            ..Default::default()
+        };
+        // 0.999^693 ≈ 0.49990，第一次低于 50% 的时刻
+        assert_eq!(calculate_reset_duration(exponential, top, &system_params, 0), 693);
+        // 0.99^69 ≈ 0.4998，在第 69 步（4140 秒）时低于 50%
+        assert_eq!(calculate_reset_duration(stairstep, top, &system_params, 0), 4140);
     }
 
-    // 解Linear Decrease公式：
-    // threshold_price = starting_price * (reset_time - elapsed) / reset_time
-    // threshold_price / starting_price = (reset_time - elapsed) / reset_time
-    // threshold_price * reset_time / starting_price = reset_time - elapsed
-    // elapsed = reset_time - (threshold_price * reset_time / starting_price)
+    #[test]
+    fn dust_auctions_are_valued_at_the_implied_oracle_price() {
+        let wad = U256::exp10(18);
+        let system_params = SystemParams {
+            price_multiplier: wad * 12 / 10, // 起始价格为预言机价格的 1.2 倍
+            min_auction_amount: U256::from(100) * wad,
+            ..Default::default()
+        };
+        let auction = |underlying_amount: U256| AuctionInfo {
+            auction_id: U256::from(1),
+            starting_price: U256::from(120) * wad, // 预言机价格 100
+            underlying_amount,
+            original_owner: Address::repeat_byte(0x11),
+            token_id: U256::from(7),
+            triggerer: Address::repeat_byte(0x22),
+            reward_amount: U256::zero(),
+            start_time: 1_700_000_000,
+            epoch: 0,
+        };
 
-    let numerator = threshold_price.saturating_mul(reset_time);
-    let denominator = starting_price;
+        assert!(is_dust_auction(&auction(wad / 2), &system_params));
+        assert!(!is_dust_auction(&auction(wad), &system_params));
+        assert!(!is_dust_auction(&auction(wad / 2), &SystemParams { min_auction_amount: U256::zero(), ..system_params }));
+    }
 
-    if denominator == U256::zero() {
-        return reset_time_u64;
+    #[tokio::test]
+    async fn shutdown_waits_for_started_reset_tasks() {
+        let transport = crate::failover::FailoverTransport::new(&["http://localhost:8545".to_string()], 3).unwrap();
+        let config = crate::config::AppConfig::default();
+        let tx_sender = Arc::new(TransactionSender::new(web3::Web3::new(transport), &config).unwrap());
+        let shutdown = CancellationToken::new();
+        let monitor = AuctionResetMonitor::new(
+            Arc::new(Database::open_temp()),
+            tx_sender,
+            format!("{:?}", Address::repeat_byte(0x03)),
+            PriceCurve::Linear { tau: 1_000 },
+            shutdown.clone(),
+            Arc::new(crate::notify::NoopNotifier),
+        ).unwrap();
+
+        monitor.start_reset_task(AuctionResetTask::new(U256::from(1), Instant::now() + Duration::from_secs(3600)));
+        assert_eq!(monitor.tasks.lock().unwrap().len(), 1);
+
+        // 等待中的任务收到关闭信号后放弃，等待立即结束
+        shutdown.cancel();
+        tokio::time::timeout(Duration::from_secs(5), monitor.wait_for_tasks()).await.unwrap();
+        assert!(monitor.tasks.lock().unwrap().is_empty());
     }
 
-    let remaining_ratio = numerator.checked_div(denominator).unwrap_or(U256::zero());
-    let elapsed_time = if remaining_ratio >= reset_time {
-        // 价格下界 > 当前起始价格，不可能，立即重置
-        0u64
-    } else {
-        let elapsed_u256 = reset_time.saturating_sub(remaining_ratio);
-        if elapsed_u256 > U256::from(u64::MAX) {
-            reset_time_u64 // 溢出，设置为最大值
-        } else {
-            elapsed_u256.as_u64()
-        }
-    };
+    #[tokio::test]
+    async fn due_reset_replaces_pending_task_without_blocking() {
+        let transport = crate::failover::FailoverTransport::new(&["http://localhost:8545".to_string()], 3).unwrap();
+        let config = crate::config::AppConfig::default();
+        let tx_sender = Arc::new(TransactionSender::new(web3::Web3::new(transport), &config).unwrap());
+        let monitor = AuctionResetMonitor::new(
+            Arc::new(Database::open_temp()),
+            tx_sender,
+            format!("{:?}", Address::repeat_byte(0x03)),
+            PriceCurve::Linear { tau: 1_000 },
+            CancellationToken::new(),
+            Arc::new(crate::notify::NoopNotifier),
+        ).unwrap();
+        let auction = AuctionInfo {
+            auction_id: U256::from(1),
+            starting_price: U256::exp10(18),
+            underlying_amount: U256::exp10(18),
+            original_owner: Address::repeat_byte(0x11),
+            token_id: U256::from(7),
+            triggerer: Address::repeat_byte(0x22),
+            reward_amount: U256::zero(),
+            start_time: 1_700_000_000,
+            epoch: 0,
+        };
+
+        let previous = AuctionResetTask::new(auction.auction_id, Instant::now() + Duration::from_secs(3600));
+        monitor.pending_resets.write().unwrap().insert(auction.auction_id, previous.clone());
+        monitor.start_reset_task(previous.clone());
 
-    elapsed_time
+        // 拍卖早已超过重置条件：立即返回，由新任务执行重置，之前的任务被取消
+        tokio::time::timeout(Duration::from_secs(1), monitor.schedule_auction_reset(&auction)).await.unwrap().unwrap();
+        assert!(previous.cancel.is_cancelled());
+
+        // 两个任务都在受跟踪的任务集中，拍卖记录不存在时新任务不发送交易即结束
+        tokio::time::timeout(Duration::from_secs(5), monitor.wait_for_tasks()).await.unwrap();
+    }
+
+    #[test]
+    fn in_flight_reset_expires_once_the_reset_is_applied() {
+        let mut auction = AuctionInfo {
+            auction_id: U256::from(1),
+            starting_price: U256::exp10(18),
+            underlying_amount: U256::exp10(18),
+            original_owner: Address::repeat_byte(0x11),
+            token_id: U256::from(7),
+            triggerer: Address::repeat_byte(0x22),
+            reward_amount: U256::zero(),
+            start_time: 1_700_000_000,
+            epoch: 0,
+        };
+        let resets_in_flight: ResetsInFlight = Arc::default();
+        assert!(!reset_in_flight(&resets_in_flight, &auction));
+
+        // 交易已发送、AuctionReset 事件尚未处理：一直视为重置中
+        resets_in_flight.write().unwrap().insert(auction.auction_id, auction.start_time);
+        assert!(reset_in_flight(&resets_in_flight, &auction));
+        assert!(reset_in_flight(&resets_in_flight, &auction));
+
+        // 事件更新了开始时间后标记失效并被清除
+        auction.start_time += 600;
+        assert!(!reset_in_flight(&resets_in_flight, &auction));
+        assert!(resets_in_flight.read().unwrap().is_empty());
+    }
 }
@@ -0,0 +1,300 @@
+//! 单个拍卖或持仓的链上重新同步模块
+//!
+//! 怀疑某个拍卖或持仓的本地记录过期时，不需要回填整个区块范围：以 `eth_call` 读取合约在最新区块的状态，
+//! 直接覆盖本地记录。
+//! - 拍卖：读取 AuctionManager 的 `isActiveAuction(auctionId)` 和 `auctions(auctionId)`，拍卖已结束时删除本地记录
+//! - 持仓：读取 InterestManager 的 `userPositions(user, tokenId)` 和 CustodianFixed 的 `getTokenDetails(tokenId)`，
+//!   链上已关闭时删除本地记录
+//!
+//! 两次读取固定在同一区块。持仓的最后应用日志位置推进到该区块末尾，之后重放不晚于该区块的事件时跳过，
+//! 不会重复计入已经反映在链上状态中的变化。
+//!
+//! 链重组回滚时按分叉点区块重新同步（`*_at`），之后从分叉点重放规范链事件。
+
+use web3::ethabi::{self, Token};
+use web3::types::{Address, BlockId, BlockNumber, Bytes, CallRequest, U256, U64};
+use crate::audit::{ChainPosition, PositionAuditor};
+use crate::database::{AuctionInfo, Database, LeverageType, LogPosition, UserPosition};
+use crate::error::{KeeperError, KeeperResult};
+use crate::failover::FailoverTransport;
+use crate::rpc::rpc_retry;
+
+/// AuctionManager 中记录的拍卖
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainAuction {
+    pub underlying_amount: U256,
+    pub original_owner: Address,
+    pub token_id: U256,
+    pub start_time: u64,
+    pub starting_price: U256,
+}
+
+/// 重新同步的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResyncOutcome {
+    /// 用链上状态覆盖（或新建）了本地记录
+    Updated,
+    /// 链上已结束或关闭，删除了本地记录
+    Removed,
+    /// 链上已结束或关闭，本地也没有记录
+    Absent,
+}
+
+/// 单个拍卖或持仓的重新同步器
+pub struct Resyncer {
+    web3: web3::Web3<FailoverTransport>,
+    auction_manager_address: Address,
+    custodian_address: Address,
+    auditor: PositionAuditor,
+}
+
+impl Resyncer {
+    pub fn new(web3: web3::Web3<FailoverTransport>, config: &crate::config::AppConfig) -> KeeperResult<Self> {
+        let parse = |name: &str, address: &str| address.parse::<Address>()
+            .map_err(|e| KeeperError::Config(format!("无效的 {} 地址 '{}': {}", name, address, e)));
+        Ok(Self {
+            auction_manager_address: parse("AuctionManager", &config.contracts.auction_manager)?,
+            custodian_address: parse("CustodianFixed", &config.contracts.custodian)?,
+            auditor: PositionAuditor::new(web3.clone(), &config.contracts.interest_manager)?,
+            web3,
+        })
+    }
+
+    /// 用链上状态覆盖拍卖的本地记录
+    pub async fn resync_auction(&self, database: &Database, auction_id: U256) -> anyhow::Result<ResyncOutcome> {
+        let block = self.latest_block().await?;
+        self.resync_auction_at(database, auction_id, block).await
+    }
+
+    /// 用 `block` 的链上状态覆盖拍卖的本地记录
+    pub async fn resync_auction_at(&self, database: &Database, auction_id: U256, block: U64) -> anyhow::Result<ResyncOutcome> {
+        let local = database.get_auction(auction_id)?;
+
+        let chain = self.read_auction(auction_id, block).await?;
+        let Some(chain) = chain else {
+            if local.is_none() {
+                return Ok(ResyncOutcome::Absent);
+            }
+            database.delete_auction(auction_id)?;
+            tracing::info!("拍卖 {} 在链上已结束，已删除本地记录", auction_id);
+            return Ok(ResyncOutcome::Removed);
+        };
+
+        let auction = merge_chain_auction(auction_id, &chain, local.as_ref(), database.contract_epoch());
+        database.store_auction(&auction)?;
+        tracing::info!(
+            "拍卖 {} 已按区块 {} 的链上状态更新 - 起始价格: {}, 标的数量: {}, 开始时间: {}",
+            auction_id, block.as_u64(), auction.starting_price, auction.underlying_amount, auction.start_time
+        );
+        Ok(ResyncOutcome::Updated)
+    }
+
+    /// 用链上状态覆盖持仓的本地记录
+    pub async fn resync_position(&self, database: &Database, user: Address, token_id: U256) -> anyhow::Result<ResyncOutcome> {
+        let block = self.latest_block().await?;
+        self.resync_position_at(database, user, token_id, block).await
+    }
+
+    /// 用 `block` 的链上状态覆盖持仓的本地记录
+    pub async fn resync_position_at(&self, database: &Database, user: Address, token_id: U256, block: U64) -> anyhow::Result<ResyncOutcome> {
+        let local = database.get_user_position(user, token_id)?;
+
+        let block_id = Some(BlockId::Number(BlockNumber::Number(block)));
+        let chain = self.auditor.read_position_at(user, token_id, block_id).await?;
+        if chain.is_closed() {
+            if local.is_none() {
+                return Ok(ResyncOutcome::Absent);
+            }
+            database.delete_user_position(user, token_id)?;
+            tracing::info!("持仓在链上已关闭，已删除本地记录 - 用户: {:?}, TokenID: {}", user, token_id);
+            return Ok(ResyncOutcome::Removed);
+        }
+
+        let details = crate::nav::read_token_details(&self.web3, self.custodian_address, token_id, block_id).await?;
+        let position = merge_chain_position(user, token_id, &chain, details, local.as_ref(), block.as_u64(), database.contract_epoch());
+        database.store_user_position(&position)?;
+        tracing::info!(
+            "持仓已按区块 {} 的链上状态更新 - 用户: {:?}, TokenID: {}, 数量: {}, 累计利息: {}, 杠杆: {}, 铸币价格: {}",
+            block.as_u64(), user, token_id, position.amount, position.total_interest, position.leverage, position.mint_price
+        );
+        Ok(ResyncOutcome::Updated)
+    }
+
+    async fn latest_block(&self) -> KeeperResult<U64> {
+        Ok(rpc_retry("eth_blockNumber", || self.web3.eth().block_number()).await?)
+    }
+
+    /// 读取指定区块的拍卖，拍卖不活跃时返回 None
+    async fn read_auction(&self, auction_id: U256, block: U64) -> KeeperResult<Option<ChainAuction>> {
+        let block = Some(BlockId::Number(BlockNumber::Number(block)));
+        let active = self.call(self.auction_manager_address, "isActiveAuction", auction_id, block).await?;
+        let active = active.into_iter().next()
+            .and_then(Token::into_bool)
+            .ok_or_else(|| KeeperError::Decode("无法解析 isActiveAuction".to_string()))?;
+        if !active {
+            return Ok(None);
+        }
+
+        let tokens = self.call(self.auction_manager_address, "auctions", auction_id, block).await?;
+        decode_auction(tokens).map(Some)
+    }
+
+    async fn call(&self, to: Address, function_name: &str, id: U256, block: Option<BlockId>) -> KeeperResult<Vec<Token>> {
+        let contract = get_contract()?;
+        let function = contract.function(function_name)?;
+        let request = CallRequest {
+            to: Some(to),
+            data: Some(Bytes(function.encode_input(&[Token::Uint(id)])?)),
+            ..Default::default()
+        };
+        let result = rpc_retry("eth_call", || self.web3.eth().call(request.clone(), block)).await?;
+        Ok(function.decode_output(&result.0)?)
+    }
+}
+
+/// 解码 `auctions(auctionId)` 的返回值
+fn decode_auction(tokens: Vec<Token>) -> KeeperResult<ChainAuction> {
+    let uint_field = |index: usize, name: &str| {
+        tokens.get(index).cloned()
+            .and_then(Token::into_uint)
+            .ok_or_else(|| KeeperError::Decode(format!("无法解析 {}", name)))
+    };
+    Ok(ChainAuction {
+        underlying_amount: uint_field(1, "underlyingAmount")?,
+        original_owner: tokens.get(2).cloned()
+            .and_then(Token::into_address)
+            .ok_or_else(|| KeeperError::Decode("无法解析 originalOwner".to_string()))?,
+        token_id: uint_field(3, "tokenId")?,
+        start_time: uint_field(4, "startTime")?.low_u64(),
+        starting_price: uint_field(5, "startingPrice")?,
+    })
+}
+
+/// 用链上拍卖覆盖本地记录；链上不保存的触发者和奖励沿用本地记录（没有本地记录时为0）
+fn merge_chain_auction(auction_id: U256, chain: &ChainAuction, local: Option<&AuctionInfo>, epoch: u64) -> AuctionInfo {
+    AuctionInfo {
+        auction_id,
+        starting_price: chain.starting_price,
+        underlying_amount: chain.underlying_amount,
+        original_owner: chain.original_owner,
+        token_id: chain.token_id,
+        triggerer: local.map(|local| local.triggerer).unwrap_or_default(),
+        reward_amount: local.map(|local| local.reward_amount).unwrap_or_default(),
+        start_time: chain.start_time,
+        epoch: local.map_or(epoch, |local| local.epoch),
+    }
+}
+
+/// 用 `block` 的链上持仓和代币信息覆盖本地记录，最后应用日志位置推进到该区块末尾
+fn merge_chain_position(
+    user: Address,
+    token_id: U256,
+    chain: &ChainPosition,
+    (leverage, mint_price): (LeverageType, U256),
+    local: Option<&UserPosition>,
+    block: u64,
+    epoch: u64,
+) -> UserPosition {
+    UserPosition {
+        user,
+        token_id,
+        amount: chain.amount,
+        timestamp: chain.timestamp,
+        total_interest: chain.accrued_interest,
+        leverage,
+        mint_price,
+        last_applied_log: Some(LogPosition { block_number: block, log_index: u64::MAX }),
+        epoch: local.map_or(epoch, |local| local.epoch),
+    }
+}
+
+/// AuctionManager 的只读 ABI（`auctions` 和 `isActiveAuction` 为公开 mapping 的 getter）
+fn get_contract() -> KeeperResult<ethabi::Contract> {
+    let abi = r#"[
+        {
+            "name": "isActiveAuction",
+            "type": "function",
+            "stateMutability": "view",
+            "inputs": [{"type": "uint256", "name": "auctionId"}],
+            "outputs": [{"type": "bool", "name": ""}]
+        },
+        {
+            "name": "auctions",
+            "type": "function",
+            "stateMutability": "view",
+            "inputs": [{"type": "uint256", "name": "auctionId"}],
+            "outputs": [
+                {"type": "uint256", "name": "arrayIndex"},
+                {"type": "uint256", "name": "underlyingAmount"},
+                {"type": "address", "name": "originalOwner"},
+                {"type": "uint256", "name": "tokenId"},
+                {"type": "uint96", "name": "startTime"},
+                {"type": "uint256", "name": "startingPrice"},
+                {"type": "uint256", "name": "currentPrice"},
+                {"type": "uint256", "name": "totalPayment"}
+            ]
+        }
+    ]"#;
+
+    Ok(ethabi::Contract::load(abi.as_bytes())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chain_state_overwrites_local_records() {
+        let owner = Address::repeat_byte(0x22);
+        let data = ethabi::encode(&[
+            Token::Uint(U256::from(4)),
+            Token::Uint(U256::from(900)),
+            Token::Address(owner),
+            Token::Uint(U256::from(7)),
+            Token::Uint(U256::from(1_700_000_000u64)),
+            Token::Uint(U256::from(1_500)),
+            Token::Uint(U256::from(1_200)),
+            Token::Uint(U256::from(50)),
+        ]);
+        let tokens = get_contract().unwrap().function("auctions").unwrap().decode_output(&data).unwrap();
+        let chain = decode_auction(tokens).unwrap();
+        assert_eq!(chain, ChainAuction {
+            underlying_amount: U256::from(900),
+            original_owner: owner,
+            token_id: U256::from(7),
+            start_time: 1_700_000_000,
+            starting_price: U256::from(1_500),
+        });
+
+        // 触发者、奖励和部署纪元沿用本地记录
+        let local = AuctionInfo {
+            auction_id: U256::from(3),
+            starting_price: U256::from(2_000),
+            underlying_amount: U256::from(1_000),
+            original_owner: owner,
+            token_id: U256::from(7),
+            triggerer: Address::repeat_byte(0x33),
+            reward_amount: U256::from(10),
+            start_time: 1_600_000_000,
+            epoch: 1,
+        };
+        let auction = merge_chain_auction(U256::from(3), &chain, Some(&local), 2);
+        assert_eq!((auction.starting_price, auction.underlying_amount, auction.start_time), (U256::from(1_500), U256::from(900), 1_700_000_000));
+        assert_eq!((auction.triggerer, auction.reward_amount, auction.epoch), (local.triggerer, local.reward_amount, 1));
+        assert_eq!(merge_chain_auction(U256::from(3), &chain, None, 2).epoch, 2);
+
+        let chain_position = ChainPosition {
+            amount: U256::from(500),
+            timestamp: 1_700_000_100,
+            accrued_interest: U256::from(12),
+            active: true,
+        };
+        let position = merge_chain_position(
+            owner, U256::from(7), &chain_position, (LeverageType::Moderate, U256::from(2_000)), None, 120, 2,
+        );
+        assert_eq!((position.amount, position.total_interest, position.timestamp), (U256::from(500), U256::from(12), 1_700_000_100));
+        assert_eq!((position.leverage, position.mint_price, position.epoch), (LeverageType::Moderate, U256::from(2_000), 2));
+        // 不晚于重新同步区块的事件已反映在链上状态中，重放时应跳过
+        assert_eq!(position.last_applied_log, Some(LogPosition { block_number: 120, log_index: u64::MAX }));
+    }
+}
@@ -0,0 +1,186 @@
+//! RPC 重试模块
+//!
+//! 为链上 RPC 调用提供带指数退避的重试，应对节点超时、限流（429）和 5xx 等临时错误。
+//!
+//! ## 重试策略：
+//! - 调用可以返回 `web3::Error` 或 `KeeperError`，后者只有 `KeeperError::Rpc` 中的临时错误会重试
+//! - 临时错误：节点不可达、连接/超时错误、HTTP 429 和 5xx、提供方的限流提示
+//! - 确定性错误（如 `execution reverted`、日志数量超限、参数错误）立即返回，不重复请求
+//! - 第 n 次重试前等待 `rpc_backoff_base_ms × 2^(n-1)` 毫秒，最多重试 `rpc_max_retries` 次
+
+use std::future::Future;
+use std::sync::OnceLock;
+use std::time::Duration;
+use crate::error::KeeperError;
+use crate::metrics::observe_rpc;
+
+/// 单次退避等待的上限（毫秒）
+const RPC_BACKOFF_MAX_MS: u64 = 10_000;
+
+/// RPC 重试策略
+#[derive(Debug, Clone, Copy)]
+struct RetryPolicy {
+    max_retries: u32,
+    backoff_base_ms: u64,
+}
+
+static RETRY_POLICY: OnceLock<RetryPolicy> = OnceLock::new();
+
+/// 在启动时根据配置设置全局重试策略（未设置时使用配置默认值）
+pub fn configure(config: &crate::config::AppConfig) {
+    let policy = RetryPolicy {
+        max_retries: config.rpc_max_retries,
+        backoff_base_ms: config.rpc_backoff_base_ms,
+    };
+    if RETRY_POLICY.set(policy).is_err() {
+        tracing::warn!("RPC重试策略已设置，忽略重复配置");
+    }
+}
+
+fn policy() -> RetryPolicy {
+    *RETRY_POLICY.get_or_init(|| {
+        let defaults = crate::config::AppConfig::default();
+        RetryPolicy {
+            max_retries: defaults.rpc_max_retries,
+            backoff_base_ms: defaults.rpc_backoff_base_ms,
+        }
+    })
+}
+
+/// 可按临时/确定性分类的错误，`rpc_retry` 据此决定是否重试
+pub trait RetryableError: std::fmt::Display {
+    /// 是否为值得重试的临时错误
+    fn is_transient(&self) -> bool;
+}
+
+impl RetryableError for web3::Error {
+    fn is_transient(&self) -> bool {
+        is_transient_error(self)
+    }
+}
+
+impl RetryableError for KeeperError {
+    fn is_transient(&self) -> bool {
+        KeeperError::is_transient(self)
+    }
+}
+
+/// 执行 RPC 调用，遇到临时错误时按指数退避重试
+///
+/// `call` 每次重试都会被重新调用以构造新的请求；每次尝试的耗时都计入 `method` 对应的 RPC 指标。
+pub async fn rpc_retry<T, E, F, Fut>(method: &str, mut call: F) -> Result<T, E>
+where
+    E: RetryableError,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let policy = policy();
+    let mut attempt = 0u32;
+
+    loop {
+        match observe_rpc(method, call()).await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < policy.max_retries && e.is_transient() => {
+                attempt += 1;
+                let delay = backoff_delay(policy.backoff_base_ms, attempt);
+                tracing::warn!(
+                    "RPC调用 {} 临时失败，{}毫秒后进行第 {}/{} 次重试: {}",
+                    method, delay.as_millis(), attempt, policy.max_retries, e
+                );
+                tokio::time::sleep(delay).await;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// 第 `attempt` 次重试前的等待时间
+fn backoff_delay(base_ms: u64, attempt: u32) -> Duration {
+    let factor = 1u64 << attempt.saturating_sub(1).min(16);
+    Duration::from_millis(base_ms.saturating_mul(factor).min(RPC_BACKOFF_MAX_MS))
+}
+
+/// 判断 RPC 错误是否为值得重试的临时错误
+pub fn is_transient_error(error: &web3::Error) -> bool {
+    match error {
+        web3::Error::Unreachable | web3::Error::Io(_) => true,
+        web3::Error::Transport(web3::error::TransportError::Code(code)) => *code == 429 || *code >= 500,
+        web3::Error::Transport(web3::error::TransportError::Message(_)) | web3::Error::Rpc(_) => {
+            let message = error.to_string().to_lowercase();
+
+            // 交易会 revert、nonce 冲突等确定性错误，重试也不会成功
+            if ["revert", "nonce too low", "already known", "insufficient funds", "invalid"]
+                .iter()
+                .any(|pattern| message.contains(pattern))
+            {
+                return false;
+            }
+
+            ["timeout", "timed out", "too many requests", "rate limit", "429", "502", "503", "504",
+                "connection", "temporarily unavailable", "header not found", "internal error"]
+                .iter()
+                .any(|pattern| message.contains(pattern))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use web3::error::TransportError;
+
+    fn message_error(message: &str) -> web3::Error {
+        web3::Error::Transport(TransportError::Message(message.to_string()))
+    }
+
+    #[test]
+    fn transient_errors_are_retried() {
+        assert!(is_transient_error(&web3::Error::Unreachable));
+        assert!(is_transient_error(&web3::Error::Transport(TransportError::Code(429))));
+        assert!(is_transient_error(&web3::Error::Transport(TransportError::Code(503))));
+        assert!(is_transient_error(&message_error("request timed out")));
+        assert!(is_transient_error(&message_error("rate limit exceeded, please retry")));
+    }
+
+    #[test]
+    fn deterministic_errors_are_not_retried() {
+        assert!(!is_transient_error(&web3::Error::Transport(TransportError::Code(400))));
+        assert!(!is_transient_error(&message_error("execution reverted: position not liquidatable")));
+        assert!(!is_transient_error(&message_error("query returned more than 10000 results")));
+        assert!(!is_transient_error(&web3::Error::Decoder("bad abi".to_string())));
+    }
+
+    #[tokio::test]
+    async fn wrapped_keeper_errors_are_classified() {
+        let mut attempts = 0;
+        let result = rpc_retry("test", || {
+            attempts += 1;
+            let attempt = attempts;
+            async move {
+                if attempt == 1 {
+                    Err(KeeperError::Rpc(web3::Error::Unreachable))
+                } else {
+                    Ok(attempt)
+                }
+            }
+        }).await;
+        assert_eq!(result.unwrap(), 2);
+
+        let mut attempts = 0;
+        let result: Result<(), _> = rpc_retry("test", || {
+            attempts += 1;
+            async { Err(KeeperError::Decode("bad abi".to_string())) }
+        }).await;
+        assert!(matches!(result, Err(KeeperError::Decode(_))));
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn backoff_grows_exponentially_up_to_cap() {
+        assert_eq!(backoff_delay(500, 1), Duration::from_millis(500));
+        assert_eq!(backoff_delay(500, 2), Duration::from_millis(1000));
+        assert_eq!(backoff_delay(500, 3), Duration::from_millis(2000));
+        assert_eq!(backoff_delay(500, 10), Duration::from_millis(RPC_BACKOFF_MAX_MS));
+    }
+}
@@ -0,0 +1,69 @@
+//! 运行状态模块
+//!
+//! 各监控任务在每轮循环中更新的共享运行状态，供 HTTP API 的 `/health` 接口判断 Keeper 是否健康。
+
+use std::sync::RwLock;
+use serde::Serialize;
+
+/// Keeper 运行状态（以 `Arc` 在监控任务与 HTTP API 之间共享）
+#[derive(Debug, Default)]
+pub struct KeeperStatus {
+    inner: RwLock<StatusSnapshot>,
+}
+
+/// 某一时刻的运行状态快照（时间均为 Unix 时间戳，秒）
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StatusSnapshot {
+    /// 事件监控模式（realtime/polling），事件监控启动前为 None
+    pub monitor_mode: Option<&'static str>,
+    /// WebSocket 订阅是否处于连接状态
+    pub websocket_connected: bool,
+    /// 最近一次成功处理区块的时间
+    pub last_block_processed_at: Option<u64>,
+    /// 最近一次成功读取预言机价格的时间
+    pub last_oracle_read_at: Option<u64>,
+}
+
+impl KeeperStatus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 获取当前状态快照
+    pub fn snapshot(&self) -> StatusSnapshot {
+        self.inner.read().map(|inner| inner.clone()).unwrap_or_default()
+    }
+
+    pub fn set_monitor_mode(&self, mode: &'static str) {
+        self.update(|inner| inner.monitor_mode = Some(mode));
+    }
+
+    pub fn set_websocket_connected(&self, connected: bool) {
+        self.update(|inner| inner.websocket_connected = connected);
+    }
+
+    /// 记录成功处理了一个区块（或一轮轮询）
+    pub fn record_block_processed(&self) {
+        let now = unix_now();
+        self.update(|inner| inner.last_block_processed_at = Some(now));
+    }
+
+    /// 记录成功读取了预言机价格
+    pub fn record_oracle_read(&self) {
+        let now = unix_now();
+        self.update(|inner| inner.last_oracle_read_at = Some(now));
+    }
+
+    fn update(&self, f: impl FnOnce(&mut StatusSnapshot)) {
+        if let Ok(mut inner) = self.inner.write() {
+            f(&mut inner);
+        }
+    }
+}
+
+pub fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
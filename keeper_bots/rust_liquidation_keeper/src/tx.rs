@@ -0,0 +1,533 @@
+//! 交易发送模块
+//!
+//! 清算（bark）和拍卖重置（resetAuction）共用的交易发送逻辑。
+//!
+//! ## 签名方式：
+//! - 配置了 `private_key` 时，本地签名原始交易并通过 `eth_sendRawTransaction` 发送
+//!   （适用于 Infura/Alchemy 等不托管账户的节点）
+//! - 未配置私钥时，回退到节点托管账户（`eth_accounts` + `eth_sendTransaction`），仅适用于已解锁的本地节点
+//!
+//! ## 演练模式：
+//! - 配置 `dry_run = true` 时，调用方在发送前通过 `is_dry_run` 判断，只记录目标合约、调用参数和 calldata
+//!
+//! ## Gas 设置：
+//! - gas limit = `eth_estimateGas` 预估值 × `gas_multiplier`，不超过 `max_gas_limit`
+//! - gas price = `eth_gasPrice`，不超过 `max_gas_price`
+//! - `tx_type = "eip1559"` 时发送 type-2 交易：`max_fee_per_gas` = pending 区块 base fee × `base_fee_multiplier`
+//!   + `max_priority_fee_per_gas`，不超过 `max_gas_price`；节点不返回 base fee 时回退到 legacy 交易
+//! - 预估失败（通常是调用会 revert）时记录原因并放弃发送，避免浪费 gas
+//!
+//! ## 交易确认：
+//! - `wait_for_confirmations` 轮询回执，直到交易所在区块之上累积 `confirmation_blocks` 个区块
+//! - 回执消失（交易所在区块被重组掉）时继续等待交易重新上链；交易从节点消失时视为被丢弃
+//! - 交易执行失败（`status = 0`）或超过 `confirmation_timeout_secs` 时返回错误
+//! - 交易执行失败时在所在区块用 `eth_call` 重放交易以获取 revert 原因，以 `TransactionReverted` 错误返回
+//!
+//! ## Nonce 管理：
+//! - 首次发送时从链上读取账户的 pending 交易数作为起始 nonce，之后每发送一笔本地递增
+//! - 清算和拍卖重置共用同一个发送器，因此共用同一个 nonce 来源，不会互相冲突
+//! - 每轮清算检查开始时或发送失败时丢弃本地 nonce，下次发送重新从链上同步
+
+use web3::signing::{Key, SecretKey, SecretKeyRef};
+use tokio::sync::Mutex;
+use web3::types::{
+    Address, BlockId, BlockNumber, Bytes, CallRequest, TransactionId, TransactionParameters, TransactionReceipt,
+    TransactionRequest, H256, U256, U64,
+};
+use crate::config::{GasConfig, TxType};
+use crate::failover::FailoverTransport;
+use crate::rpc::rpc_retry;
+
+/// 等待确认时轮询回执的间隔（秒）
+const CONFIRMATION_POLL_INTERVAL_SECS: u64 = 3;
+
+/// 交易已上链但执行失败（receipt.status = 0）
+///
+/// 调用方可以通过 `anyhow::Error::downcast_ref` 区分执行失败和其他发送错误。
+#[derive(Debug, Clone)]
+pub struct TransactionReverted {
+    pub tx_hash: H256,
+    pub block_number: U64,
+    /// 通过 eth_call 重放得到的 revert 原因（获取失败时为 None）
+    pub reason: Option<String>,
+}
+
+impl std::fmt::Display for TransactionReverted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "交易 {:?} 在区块 {} 执行失败（revert）: {}",
+            self.tx_hash,
+            self.block_number,
+            self.reason.as_deref().unwrap_or("未知原因")
+        )
+    }
+}
+
+impl std::error::Error for TransactionReverted {}
+
+/// EIP-1559 交易类型
+const EIP1559_TX_TYPE: u64 = 2;
+
+/// 交易的 gas 定价
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GasPricing {
+    Legacy { gas_price: U256 },
+    Eip1559 { max_fee_per_gas: U256, max_priority_fee_per_gas: U256 },
+}
+
+impl GasPricing {
+    /// 每单位 gas 最多支付的价格（用于估算交易成本）
+    fn max_price_per_gas(&self) -> U256 {
+        match self {
+            GasPricing::Legacy { gas_price } => *gas_price,
+            GasPricing::Eip1559 { max_fee_per_gas, .. } => *max_fee_per_gas,
+        }
+    }
+}
+
+/// Keeper 交易发送器
+pub struct TransactionSender {
+    web3: web3::Web3<FailoverTransport>,
+    /// 本地签名私钥（未配置时使用节点托管账户）
+    signing_key: Option<SecretKey>,
+    /// 配置的 Keeper 地址（已在初始化时与私钥核对）
+    keeper_address: Option<Address>,
+    gas_config: GasConfig,
+    /// 演练模式（不发送交易）
+    dry_run: bool,
+    /// 视为成功所需的确认区块数
+    confirmation_blocks: u64,
+    /// 等待确认的最长时间（秒）
+    confirmation_timeout_secs: u64,
+    /// 下一笔交易的 nonce（None 表示需要从链上重新同步）
+    next_nonce: Mutex<Option<U256>>,
+}
+
+impl TransactionSender {
+    pub fn new(
+        web3: web3::Web3<FailoverTransport>,
+        config: &crate::config::AppConfig,
+    ) -> anyhow::Result<Self> {
+        let signing_key = match config.private_key.as_deref() {
+            Some(key) if !key.trim().is_empty() => {
+                let key = key.trim().trim_start_matches("0x")
+                    .parse::<SecretKey>()
+                    .map_err(|e| anyhow::anyhow!("无效的私钥: {}", e))?;
+                tracing::info!("交易发送器初始化 - 使用本地私钥签名, Keeper地址: {:?}", SecretKeyRef::new(&key).address());
+                Some(key)
+            }
+            _ => {
+                tracing::warn!("未配置私钥，交易将通过节点托管账户发送（仅适用于已解锁的本地节点）");
+                None
+            }
+        };
+
+        let keeper_address = config.keeper_address.as_deref()
+            .map(|address| address.trim().parse::<Address>()
+                .map_err(|e| anyhow::anyhow!("无效的 keeper_address '{}': {}", address, e)))
+            .transpose()?;
+        if let (Some(key), Some(keeper_address)) = (&signing_key, keeper_address) {
+            let derived = SecretKeyRef::new(key).address();
+            if derived != keeper_address {
+                return Err(anyhow::anyhow!(
+                    "keeper_address {:?} 与私钥推导的地址 {:?} 不一致", keeper_address, derived
+                ));
+            }
+        }
+
+        if config.dry_run {
+            tracing::warn!("演练模式已启用：清算和拍卖重置交易只记录日志，不会发送");
+        }
+
+        Ok(Self {
+            web3,
+            signing_key,
+            keeper_address,
+            gas_config: config.gas.clone(),
+            dry_run: config.dry_run,
+            confirmation_blocks: config.confirmation_blocks.max(1),
+            confirmation_timeout_secs: config.confirmation_timeout_secs,
+            next_nonce: Mutex::new(None),
+        })
+    }
+
+    /// 获取 Keeper 地址（作为 bark 的 kpr 和 resetAuction 的 triggerer 参数）
+    ///
+    /// 配置私钥时由私钥推导；否则使用配置的 `keeper_address`，都未配置时使用节点托管的第一个账户。
+    /// 清算和拍卖重置都通过这里取得同一个地址。
+    pub async fn keeper_address(&self) -> anyhow::Result<Address> {
+        match (&self.signing_key, self.keeper_address) {
+            (Some(key), _) => Ok(SecretKeyRef::new(key).address()),
+            (None, Some(keeper_address)) => Ok(keeper_address),
+            (None, None) => self.node_account().await,
+        }
+    }
+
+    /// 是否处于演练模式
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// 演练模式下记录本应发送的交易
+    pub fn log_dry_run(&self, to: Address, call: &str, data: &[u8]) {
+        let calldata: String = data.iter().map(|byte| format!("{:02x}", byte)).collect();
+        tracing::info!("[演练模式] 跳过发送交易 - 目标合约: {:?}, 调用: {}, calldata: 0x{}", to, call, calldata);
+    }
+
+    /// 丢弃本地 nonce，下次发送时从链上重新同步
+    pub async fn reset_nonce(&self) {
+        *self.next_nonce.lock().await = None;
+    }
+
+    /// 发送合约调用交易，返回交易哈希
+    ///
+    /// 持有 nonce 锁直到交易提交完成，保证并发发送的交易按顺序分配 nonce。
+    pub async fn send(&self, to: Address, data: Vec<u8>) -> anyhow::Result<H256> {
+        // 先预估gas，预估失败时不占用nonce
+        let (gas, pricing) = self.estimate_gas(to, &data).await?;
+
+        let mut next_nonce = self.next_nonce.lock().await;
+
+        let nonce = match *next_nonce {
+            Some(nonce) => nonce,
+            None => {
+                let keeper_address = self.keeper_address().await?;
+                let nonce = rpc_retry("eth_getTransactionCount", || {
+                    self.web3.eth().transaction_count(keeper_address, Some(BlockNumber::Pending))
+                }).await?;
+                tracing::debug!("从链上同步nonce: {} (账户: {:?})", nonce, keeper_address);
+                nonce
+            }
+        };
+
+        match self.send_with_nonce(to, data, nonce, gas, pricing).await {
+            Ok(tx_hash) => {
+                *next_nonce = Some(nonce + 1);
+                Ok(tx_hash)
+            }
+            Err(e) => {
+                // 发送失败时无法确定 nonce 是否已被占用，下次重新同步
+                *next_nonce = None;
+                Err(e)
+            }
+        }
+    }
+
+    async fn send_with_nonce(
+        &self,
+        to: Address,
+        data: Vec<u8>,
+        nonce: U256,
+        gas: U256,
+        pricing: GasPricing,
+    ) -> anyhow::Result<H256> {
+        let (transaction_type, gas_price, max_fee_per_gas, max_priority_fee_per_gas) = match pricing {
+            GasPricing::Legacy { gas_price } => (None, Some(gas_price), None, None),
+            GasPricing::Eip1559 { max_fee_per_gas, max_priority_fee_per_gas } => (
+                Some(U64::from(EIP1559_TX_TYPE)),
+                None,
+                Some(max_fee_per_gas),
+                Some(max_priority_fee_per_gas),
+            ),
+        };
+
+        match &self.signing_key {
+            Some(key) => {
+                let tx = TransactionParameters {
+                    nonce: Some(nonce),
+                    to: Some(to),
+                    gas,
+                    gas_price,
+                    data: Bytes(data),
+                    transaction_type,
+                    max_fee_per_gas,
+                    max_priority_fee_per_gas,
+                    ..Default::default()
+                };
+
+                // chain_id 未指定时由 sign_transaction 从节点查询
+                let signed = self.web3.accounts()
+                    .sign_transaction(tx, SecretKeyRef::new(key))
+                    .await?;
+                // 重试时重复提交同一笔已签名交易是安全的；前一次请求实际已送达时节点返回 already known
+                match rpc_retry("eth_sendRawTransaction", || {
+                    self.web3.eth().send_raw_transaction(signed.raw_transaction.clone())
+                }).await {
+                    Ok(tx_hash) => Ok(tx_hash),
+                    Err(e) if e.to_string().to_lowercase().contains("already known") => {
+                        tracing::debug!("交易已在节点交易池中: {:?}", signed.transaction_hash);
+                        Ok(signed.transaction_hash)
+                    }
+                    Err(e) => Err(e.into()),
+                }
+            }
+            None => {
+                // 与 nonce 查询和 bark/resetAuction 参数使用同一个地址（配置的 keeper_address 或节点的第一个账户）
+                let tx = TransactionRequest {
+                    from: self.keeper_address().await?,
+                    to: Some(to),
+                    gas: Some(gas),
+                    gas_price,
+                    data: Some(Bytes(data)),
+                    nonce: Some(nonce),
+                    transaction_type,
+                    max_fee_per_gas,
+                    max_priority_fee_per_gas,
+                    ..Default::default()
+                };
+                let tx_hash = rpc_retry("eth_sendTransaction", || self.web3.eth().send_transaction(tx.clone())).await?;
+                Ok(tx_hash)
+            }
+        }
+    }
+
+    /// 等待交易被 `confirmation_blocks` 个区块确认，返回最终的回执
+    ///
+    /// 交易执行失败、被丢弃或等待超时时返回错误。
+    pub async fn wait_for_confirmations(&self, tx_hash: H256) -> anyhow::Result<TransactionReceipt> {
+        let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(self.confirmation_timeout_secs);
+        let poll_interval = std::time::Duration::from_secs(CONFIRMATION_POLL_INTERVAL_SECS);
+        let mut seen_in_block: Option<U64> = None;
+
+        loop {
+            let receipt = rpc_retry("eth_getTransactionReceipt", || self.web3.eth().transaction_receipt(tx_hash)).await?;
+
+            match receipt {
+                Some(receipt) => {
+                    let block_number = receipt.block_number.unwrap_or_default();
+                    if receipt.status == Some(U64::zero()) {
+                        let reason = self.fetch_revert_reason(tx_hash, block_number).await;
+                        return Err(TransactionReverted { tx_hash, block_number, reason }.into());
+                    }
+
+                    if seen_in_block.is_some_and(|seen| seen != block_number) {
+                        tracing::warn!("交易 {:?} 因链重组被重新打包到区块 {}", tx_hash, block_number);
+                    }
+                    seen_in_block = Some(block_number);
+
+                    let latest_block = rpc_retry("eth_blockNumber", || self.web3.eth().block_number()).await?;
+                    let confirmations = (latest_block + 1).saturating_sub(block_number).as_u64();
+                    if confirmations >= self.confirmation_blocks {
+                        tracing::debug!("交易 {:?} 已确认 - 区块: {}, 确认数: {}", tx_hash, block_number, confirmations);
+                        return Ok(receipt);
+                    }
+                    tracing::debug!(
+                        "等待交易 {:?} 确认 - 区块: {}, 确认数: {}/{}",
+                        tx_hash, block_number, confirmations, self.confirmation_blocks
+                    );
+                }
+                None => {
+                    if let Some(block_number) = seen_in_block.take() {
+                        tracing::warn!("交易 {:?} 所在区块 {} 已被重组，等待重新打包", tx_hash, block_number);
+                    }
+
+                    let transaction = rpc_retry("eth_getTransactionByHash", || {
+                        self.web3.eth().transaction(TransactionId::Hash(tx_hash))
+                    }).await?;
+                    if transaction.is_none() {
+                        return Err(anyhow::anyhow!("交易 {:?} 已被节点丢弃", tx_hash));
+                    }
+                }
+            }
+
+            if tokio::time::Instant::now() + poll_interval > deadline {
+                return Err(anyhow::anyhow!(
+                    "等待交易 {:?} 确认超时（{}秒）", tx_hash, self.confirmation_timeout_secs
+                ));
+            }
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    /// 在交易所在区块用 eth_call 重放交易，获取 revert 原因
+    async fn fetch_revert_reason(&self, tx_hash: H256, block_number: U64) -> Option<String> {
+        let transaction = match rpc_retry("eth_getTransactionByHash", || {
+            self.web3.eth().transaction(TransactionId::Hash(tx_hash))
+        }).await {
+            Ok(Some(transaction)) => transaction,
+            Ok(None) => return None,
+            Err(e) => {
+                tracing::debug!("获取交易 {:?} 详情失败，无法重放: {}", tx_hash, e);
+                return None;
+            }
+        };
+
+        let request = CallRequest {
+            from: transaction.from,
+            to: transaction.to,
+            gas: Some(transaction.gas),
+            value: Some(transaction.value),
+            data: Some(transaction.input),
+            ..Default::default()
+        };
+        let block = BlockId::Number(BlockNumber::Number(block_number));
+
+        match rpc_retry("eth_call", || self.web3.eth().call(request.clone(), Some(block))).await {
+            // 节点通常以 RPC 错误返回 revert 原因（如 "execution reverted: ..."）
+            Err(e) => Some(e.to_string()),
+            // 部分节点直接返回 Error(string) 编码的数据
+            Ok(output) => decode_revert_reason(&output.0),
+        }
+    }
+
+    /// 预估交易的 gas 成本（gas limit × 每单位 gas 最高价格，单位 wei）
+    pub async fn estimate_cost(&self, to: Address, data: &[u8]) -> anyhow::Result<U256> {
+        let (gas, pricing) = self.estimate_gas(to, data).await?;
+        Ok(gas.saturating_mul(pricing.max_price_per_gas()))
+    }
+
+    /// 预估交易的 gas limit 和 gas 定价
+    async fn estimate_gas(&self, to: Address, data: &[u8]) -> anyhow::Result<(U256, GasPricing)> {
+        let request = CallRequest {
+            from: Some(self.keeper_address().await?),
+            to: Some(to),
+            data: Some(Bytes(data.to_vec())),
+            ..Default::default()
+        };
+
+        let estimated = match rpc_retry("eth_estimateGas", || self.web3.eth().estimate_gas(request.clone(), None)).await {
+            Ok(estimated) => estimated,
+            Err(e) => {
+                tracing::warn!("gas预估失败，交易可能会revert，放弃发送 - 目标合约: {:?}, 原因: {}", to, e);
+                return Err(anyhow::anyhow!("gas预估失败: {}", e));
+            }
+        };
+
+        let gas = apply_gas_limit(estimated, &self.gas_config)?;
+        let pricing = self.gas_pricing().await?;
+
+        tracing::debug!("gas设置 - 预估: {}, gas limit: {}, 定价: {:?}", estimated, gas, pricing);
+        Ok((gas, pricing))
+    }
+
+    /// 按配置的交易类型计算 gas 定价
+    async fn gas_pricing(&self) -> anyhow::Result<GasPricing> {
+        if self.gas_config.tx_type == TxType::Eip1559 {
+            let pending = BlockId::Number(BlockNumber::Pending);
+            let base_fee = rpc_retry("eth_getBlockByNumber", || self.web3.eth().block(pending)).await?
+                .and_then(|block| block.base_fee_per_gas);
+            match base_fee {
+                Some(base_fee) => return Ok(eip1559_pricing(base_fee, &self.gas_config)),
+                None => tracing::warn!("节点未返回 pending 区块的 base fee，回退到 legacy 交易"),
+            }
+        }
+
+        let mut gas_price = rpc_retry("eth_gasPrice", || self.web3.eth().gas_price()).await?;
+        if let Some(max_gas_price) = self.gas_config.max_gas_price {
+            gas_price = gas_price.min(U256::from(max_gas_price));
+        }
+        Ok(GasPricing::Legacy { gas_price })
+    }
+
+    /// 获取节点托管的第一个账户
+    async fn node_account(&self) -> anyhow::Result<Address> {
+        let accounts = rpc_retry("eth_accounts", || self.web3.eth().accounts()).await?;
+        accounts.first()
+            .copied()
+            .ok_or_else(|| anyhow::anyhow!("No available accounts for transaction"))
+    }
+}
+
+/// 解码 Solidity `Error(string)` 格式的 revert 数据
+fn decode_revert_reason(data: &[u8]) -> Option<String> {
+    const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+    let encoded = data.strip_prefix(&ERROR_SELECTOR)?;
+    match web3::ethabi::decode(&[web3::ethabi::ParamType::String], encoded).ok()?.pop()? {
+        web3::ethabi::Token::String(reason) => Some(reason),
+        _ => None,
+    }
+}
+
+/// 根据 base fee 计算 EIP-1559 定价
+///
+/// `max_fee_per_gas = base_fee × base_fee_multiplier + max_priority_fee_per_gas`，不超过 `max_gas_price`；
+/// 小费不超过 `max_fee_per_gas`（节点会拒绝小费高于总费用上限的交易）。
+fn eip1559_pricing(base_fee: U256, gas_config: &GasConfig) -> GasPricing {
+    // 倍数按千分比计算，避免浮点数与 U256 混合运算
+    let multiplier_permille = (gas_config.base_fee_multiplier.max(1.0) * 1000.0) as u64;
+    let priority_fee = U256::from(gas_config.max_priority_fee_per_gas);
+    let mut max_fee_per_gas = base_fee.saturating_mul(U256::from(multiplier_permille)) / U256::from(1000u64);
+    max_fee_per_gas = max_fee_per_gas.saturating_add(priority_fee);
+    if let Some(max_gas_price) = gas_config.max_gas_price {
+        max_fee_per_gas = max_fee_per_gas.min(U256::from(max_gas_price));
+    }
+
+    GasPricing::Eip1559 {
+        max_fee_per_gas,
+        max_priority_fee_per_gas: priority_fee.min(max_fee_per_gas),
+    }
+}
+
+/// 对 gas 预估值应用安全系数和上限
+///
+/// 预估值本身已超过上限时返回错误（按上限发送必然 out of gas）。
+fn apply_gas_limit(estimated: U256, gas_config: &GasConfig) -> anyhow::Result<U256> {
+    // 系数按千分比计算，避免浮点数与 U256 混合运算
+    let multiplier_permille = (gas_config.gas_multiplier.max(1.0) * 1000.0) as u64;
+    let gas = estimated.saturating_mul(U256::from(multiplier_permille)) / U256::from(1000u64);
+
+    match gas_config.max_gas_limit.map(U256::from) {
+        Some(max_gas_limit) if estimated > max_gas_limit => Err(anyhow::anyhow!(
+            "gas预估值 {} 超过上限 {}", estimated, max_gas_limit
+        )),
+        Some(max_gas_limit) => Ok(gas.min(max_gas_limit)),
+        None => Ok(gas),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeper_address_must_match_private_key() {
+        let web3 = web3::Web3::new(FailoverTransport::new(&["http://localhost:8545".to_string()], 3).unwrap());
+        let private_key = "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+        let derived = SecretKeyRef::new(&private_key[2..].parse::<SecretKey>().unwrap()).address();
+
+        let mut config = crate::config::AppConfig {
+            private_key: Some(private_key.to_string()),
+            keeper_address: Some(format!("{:?}", derived)),
+            ..Default::default()
+        };
+        assert!(TransactionSender::new(web3.clone(), &config).is_ok());
+
+        config.keeper_address = Some("0x1111111111111111111111111111111111111111".to_string());
+        assert!(TransactionSender::new(web3, &config).is_err());
+    }
+
+    #[test]
+    fn revert_reason_is_decoded_from_error_string() {
+        let mut data = vec![0x08, 0xc3, 0x79, 0xa0];
+        data.extend(web3::ethabi::encode(&[web3::ethabi::Token::String("Position not liquidatable".to_string())]));
+
+        assert_eq!(decode_revert_reason(&data).as_deref(), Some("Position not liquidatable"));
+        assert_eq!(decode_revert_reason(&[]), None);
+        assert_eq!(decode_revert_reason(&[0xde, 0xad, 0xbe, 0xef]), None);
+    }
+
+    #[test]
+    fn eip1559_pricing_adds_priority_fee_and_respects_cap() {
+        let gwei = |value: u64| U256::from(value) * U256::exp10(9);
+        let mut gas_config = GasConfig {
+            tx_type: TxType::Eip1559,
+            max_priority_fee_per_gas: 2_000_000_000,
+            base_fee_multiplier: 2.0,
+            ..GasConfig::default()
+        };
+
+        // 10 gwei × 2 + 2 gwei
+        assert_eq!(
+            eip1559_pricing(gwei(10), &gas_config),
+            GasPricing::Eip1559 { max_fee_per_gas: gwei(22), max_priority_fee_per_gas: gwei(2) }
+        );
+
+        gas_config.max_gas_price = Some(1_000_000_000);
+        assert_eq!(
+            eip1559_pricing(gwei(10), &gas_config),
+            GasPricing::Eip1559 { max_fee_per_gas: gwei(1), max_priority_fee_per_gas: gwei(1) }
+        );
+    }
+}
@@ -0,0 +1,197 @@
+//! 事件重放校验模块
+//!
+//! `verify` 子命令在临时数据库中用历史同步的事件处理逻辑重放指定区块范围的事件，再与生产数据库逐项比较，
+//! 找出链重组、事件乱序或处理逻辑缺陷累积下来的偏差。重放只写入临时数据库，不发送任何交易；
+//! 生产数据库以 secondary 实例只读打开。
+//!
+//! 比较持仓、拍卖和系统参数。以下字段取决于事件被处理的时刻而不是事件本身，不参与比较：
+//! - 持仓的 `timestamp` 和拍卖的 `start_time`
+//! - 持仓和系统参数的 `last_applied_log`
+
+use std::collections::BTreeMap;
+use web3::types::{Address, U256};
+use crate::database::{AuctionInfo, DatabaseExport, UserPosition};
+
+/// 重放结果与生产数据库之间的一处不一致
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mismatch {
+    /// 重放得到了该持仓，生产数据库中没有
+    MissingPosition { user: Address, token_id: U256 },
+    /// 生产数据库中的持仓在重放结果中不存在
+    UnexpectedPosition { user: Address, token_id: U256 },
+    /// 同一持仓的字段取值不同
+    Position { user: Address, token_id: U256, field: &'static str, live: String, replayed: String },
+    /// 重放得到了该拍卖，生产数据库中没有
+    MissingAuction { auction_id: U256 },
+    /// 生产数据库中的拍卖在重放结果中不存在
+    UnexpectedAuction { auction_id: U256 },
+    /// 同一拍卖的字段取值不同
+    Auction { auction_id: U256, field: &'static str, live: String, replayed: String },
+    /// 系统参数取值不同
+    Param { field: String, live: String, replayed: String },
+}
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Mismatch::MissingPosition { user, token_id } => {
+                write!(f, "生产数据库缺少持仓 - 用户: {:?}, TokenID: {}", user, token_id)
+            }
+            Mismatch::UnexpectedPosition { user, token_id } => {
+                write!(f, "生产数据库多出持仓 - 用户: {:?}, TokenID: {}", user, token_id)
+            }
+            Mismatch::Position { user, token_id, field, live, replayed } => {
+                write!(f, "持仓字段不一致 - 用户: {:?}, TokenID: {}, {}: 生产 {} / 重放 {}", user, token_id, field, live, replayed)
+            }
+            Mismatch::MissingAuction { auction_id } => write!(f, "生产数据库缺少拍卖 {}", auction_id),
+            Mismatch::UnexpectedAuction { auction_id } => write!(f, "生产数据库多出拍卖 {}", auction_id),
+            Mismatch::Auction { auction_id, field, live, replayed } => {
+                write!(f, "拍卖 {} 字段不一致 - {}: 生产 {} / 重放 {}", auction_id, field, live, replayed)
+            }
+            Mismatch::Param { field, live, replayed } => {
+                write!(f, "系统参数不一致 - {}: 生产 {} / 重放 {}", field, live, replayed)
+            }
+        }
+    }
+}
+
+/// 参与比较的持仓字段
+fn position_fields(position: &UserPosition) -> [(&'static str, String); 4] {
+    [
+        ("amount", position.amount.to_string()),
+        ("total_interest", position.total_interest.to_string()),
+        // 附带合约的枚举值，便于与 export-raw-logs 导出的原始事件数据对照
+        ("leverage", format!("{} ({})", position.leverage, position.leverage.to_u8())),
+        ("mint_price", position.mint_price.to_string()),
+    ]
+}
+
+/// 参与比较的拍卖字段
+fn auction_fields(auction: &AuctionInfo) -> [(&'static str, String); 6] {
+    [
+        ("starting_price", auction.starting_price.to_string()),
+        ("underlying_amount", auction.underlying_amount.to_string()),
+        ("original_owner", format!("{:?}", auction.original_owner)),
+        ("token_id", auction.token_id.to_string()),
+        ("triggerer", format!("{:?}", auction.triggerer)),
+        ("reward_amount", auction.reward_amount.to_string()),
+    ]
+}
+
+/// 比较生产数据库（`live`）和重放结果（`replayed`），按持仓、拍卖、系统参数的顺序返回所有不一致
+pub fn diff_states(live: &DatabaseExport, replayed: &DatabaseExport) -> Vec<Mismatch> {
+    let mut mismatches = Vec::new();
+
+    let live_positions: BTreeMap<_, _> = live.positions.iter().map(|p| ((p.user, p.token_id), p)).collect();
+    let replayed_positions: BTreeMap<_, _> = replayed.positions.iter().map(|p| ((p.user, p.token_id), p)).collect();
+    for (&(user, token_id), replayed_position) in &replayed_positions {
+        let Some(live_position) = live_positions.get(&(user, token_id)) else {
+            mismatches.push(Mismatch::MissingPosition { user, token_id });
+            continue;
+        };
+        for ((field, live), (_, replayed)) in position_fields(live_position).into_iter().zip(position_fields(replayed_position)) {
+            if live != replayed {
+                mismatches.push(Mismatch::Position { user, token_id, field, live, replayed });
+            }
+        }
+    }
+    for &(user, token_id) in live_positions.keys() {
+        if !replayed_positions.contains_key(&(user, token_id)) {
+            mismatches.push(Mismatch::UnexpectedPosition { user, token_id });
+        }
+    }
+
+    let live_auctions: BTreeMap<_, _> = live.auctions.iter().map(|a| (a.auction_id, a)).collect();
+    let replayed_auctions: BTreeMap<_, _> = replayed.auctions.iter().map(|a| (a.auction_id, a)).collect();
+    for (&auction_id, replayed_auction) in &replayed_auctions {
+        let Some(live_auction) = live_auctions.get(&auction_id) else {
+            mismatches.push(Mismatch::MissingAuction { auction_id });
+            continue;
+        };
+        for ((field, live), (_, replayed)) in auction_fields(live_auction).into_iter().zip(auction_fields(replayed_auction)) {
+            if live != replayed {
+                mismatches.push(Mismatch::Auction { auction_id, field, live, replayed });
+            }
+        }
+    }
+    for &auction_id in live_auctions.keys() {
+        if !replayed_auctions.contains_key(&auction_id) {
+            mismatches.push(Mismatch::UnexpectedAuction { auction_id });
+        }
+    }
+
+    // 系统参数逐个字段比较（序列化后比较，新增参数字段时无需修改这里）
+    let live_params = serde_json::to_value(&live.params).unwrap_or_default();
+    let replayed_params = serde_json::to_value(&replayed.params).unwrap_or_default();
+    if let (Some(live_params), Some(replayed_params)) = (live_params.as_object(), replayed_params.as_object()) {
+        for (field, live_value) in live_params {
+            if field == "last_applied_log" {
+                continue;
+            }
+            let replayed_value = replayed_params.get(field).cloned().unwrap_or_default();
+            if *live_value != replayed_value {
+                mismatches.push(Mismatch::Param {
+                    field: field.clone(),
+                    live: live_value.to_string(),
+                    replayed: replayed_value.to_string(),
+                });
+            }
+        }
+    }
+
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{LeverageType, LogPosition, SystemParams};
+
+    fn position(user: u8, amount: u64) -> UserPosition {
+        UserPosition {
+            user: Address::repeat_byte(user),
+            token_id: U256::from(7),
+            amount: U256::from(amount),
+            timestamp: 1_700_000_000,
+            total_interest: U256::zero(),
+            leverage: LeverageType::Moderate,
+            mint_price: U256::exp10(18),
+            last_applied_log: None,
+            epoch: 0,
+        }
+    }
+
+    #[test]
+    fn replay_differences_are_reported_per_field() {
+        let live = DatabaseExport {
+            last_synced_block: Some(100),
+            params: SystemParams::default(),
+            positions: vec![position(0x01, 100), position(0x02, 50)],
+            auctions: Vec::new(),
+        };
+        let mut replayed = live.clone();
+        // 处理时刻相关的字段不参与比较
+        replayed.positions[0].timestamp = 1_800_000_000;
+        replayed.positions[0].last_applied_log = Some(LogPosition { block_number: 90, log_index: 1 });
+        replayed.params.last_applied_log = Some(LogPosition { block_number: 90, log_index: 2 });
+        assert!(diff_states(&live, &replayed).is_empty());
+
+        replayed.positions[0].amount = U256::from(120);
+        replayed.positions.truncate(1);
+        replayed.positions.push(position(0x03, 10));
+        replayed.params.penalty = U256::zero();
+
+        let mismatches = diff_states(&live, &replayed);
+        assert_eq!(mismatches.len(), 4);
+        assert!(mismatches.contains(&Mismatch::Position {
+            user: Address::repeat_byte(0x01),
+            token_id: U256::from(7),
+            field: "amount",
+            live: "100".to_string(),
+            replayed: "120".to_string(),
+        }));
+        assert!(mismatches.contains(&Mismatch::MissingPosition { user: Address::repeat_byte(0x03), token_id: U256::from(7) }));
+        assert!(mismatches.contains(&Mismatch::UnexpectedPosition { user: Address::repeat_byte(0x02), token_id: U256::from(7) }));
+        assert!(matches!(&mismatches[3], Mismatch::Param { field, .. } if field == "penalty"));
+    }
+}